@@ -20,9 +20,10 @@ use tokio::sync::Mutex;
 use tracing::{info, instrument, warn};
 
 use crate::build;
-use crate::chain;
+use crate::chain::{self, ChainOptions};
 use crate::run_tests::cleanup::{cleanup, cleanup_on_signal};
 use crate::run_tests::types::*;
+use crate::start_package;
 use crate::KIT_CACHE;
 
 const KINODE_RELEASE_BASE_URL: &str = "https://github.com/kinode-dao/kinode/releases/download";
@@ -30,6 +31,7 @@ pub const KINODE_OWNER: &str = "kinode-dao";
 const KINODE_REPO: &str = "kinode";
 const LOCAL_PREFIX: &str = "kinode-";
 pub const CACHE_EXPIRY_SECONDS: u64 = 300;
+const MAX_HEALTH_CHECKS: u16 = 120;
 
 #[derive(Deserialize, Debug)]
 pub struct Release {
@@ -367,6 +369,7 @@ pub fn run_runtime(
     verbose: bool,
     detached: bool,
     verbosity: u8,
+    wrapper: Option<Vec<String>>,
 ) -> Result<(Child, OwnedFd)> {
     let mut full_args = vec![
         home.to_str().unwrap().into(),
@@ -382,7 +385,16 @@ pub fn run_runtime(
 
     let fds = nix::pty::openpty(None, None)?;
 
-    let process = TCommand::new(path)
+    // run the runtime as an argument of `wrapper`, if given
+    let mut command = match wrapper {
+        None => TCommand::new(path),
+        Some(wrapper) => {
+            let mut command = TCommand::new(&wrapper[0]);
+            command.args(&wrapper[1..]).arg(path);
+            command
+        }
+    };
+    let process = command
         .args(&full_args)
         .stdin(if !detached {
             Stdio::inherit()
@@ -405,12 +417,54 @@ pub fn run_runtime(
     Ok((process, fds.master))
 }
 
+/// Name, home & port of node `index` of a `--nodes` network: `fake.dev`
+/// at `/tmp/kinode-fake-node` on `8080` gives `fake0.dev` at
+/// `/tmp/kinode-fake-node-0` on `8080`, `fake1.dev` at `...-1` on `8081`, ...
+fn make_network_node(
+    fake_node_name: &str,
+    node_home: &Path,
+    node_port: u16,
+    index: u16,
+) -> Result<(String, PathBuf, u16)> {
+    let name = match fake_node_name.split_once('.') {
+        Some((stem, suffix)) => format!("{stem}{index}.{suffix}"),
+        None => format!("{fake_node_name}{index}"),
+    };
+    let home = PathBuf::from(format!("{}-{index}", node_home.display()));
+    let Some(port) = node_port.checked_add(index) else {
+        return Err(eyre!("--port {node_port} leaves no port for node {index}")
+            .with_suggestion(|| "Use a lower --port or fewer --nodes."));
+    };
+    Ok((name, home, port))
+}
+
+/// Wait until the node on `port` serves HTTP
+#[instrument(level = "trace", skip_all)]
+async fn wait_until_healthy(port: u16, mut recv_kill: BroadcastRecvBool) -> Result<()> {
+    let url = format!("http://localhost:{port}/");
+    for _ in 0..MAX_HEALTH_CHECKS {
+        if reqwest::get(&url).await.is_ok() {
+            return Ok(());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+            _ = recv_kill.recv() => {
+                return Err(eyre!("received exit"));
+            }
+        };
+    }
+    Err(eyre!(
+        "node on port {port} not healthy after {MAX_HEALTH_CHECKS}s"
+    ))
+}
+
 #[instrument(level = "trace", skip_all)]
 pub async fn execute(
     runtime_path: Option<PathBuf>,
     version: String,
     node_home: PathBuf,
     node_port: u16,
+    num_nodes: u16,
     fakechain_port: u16,
     rpc: Option<&str>,
     mut fake_node_name: String,
@@ -418,6 +472,8 @@ pub async fn execute(
     is_persist: bool,
     release: bool,
     verbosity: u8,
+    install: &[PathBuf],
+    install_retries: u32,
     mut args: Vec<String>,
 ) -> Result<()> {
     let detached = false; // TODO: to argument?
@@ -467,6 +523,7 @@ pub async fn execute(
     let (send_to_kill, _recv_kill) = tokio::sync::broadcast::channel(1);
     let recv_kill_in_cos = send_to_kill.subscribe();
     let recv_kill_in_start_chain = send_to_kill.subscribe();
+    let recv_kill_in_wait = send_to_kill.subscribe();
 
     let node_cleanup_infos_for_cleanup = Arc::clone(&node_cleanup_infos);
     let handle = tokio::spawn(cleanup(
@@ -497,44 +554,78 @@ pub async fn execute(
         fakechain_port,
         recv_kill_in_start_chain,
         Some(version),
-        false,
+        &ChainOptions::default(),
     )
     .await?;
 
     if let Some(rpc) = rpc {
         args.extend_from_slice(&["--rpc".into(), rpc.into()]);
     };
+    args.extend_from_slice(&["--password".into(), password.into()]);
 
-    args.extend_from_slice(&[
-        "--password".into(),
-        password.into(),
-        "--fake-node-name".into(),
-        fake_node_name,
-        "--fakechain-port".into(),
-        format!("{fakechain_port}"),
-    ]);
-
-    let (mut runtime_process, master_fd) = run_runtime(
-        &runtime_path,
-        &node_home,
-        node_port,
-        &args[..],
-        true,
-        detached,
-        verbosity,
-    )?;
-
-    let mut node_cleanup_infos = node_cleanup_infos.lock().await;
-    node_cleanup_infos.push(NodeCleanupInfo {
-        master_fd,
-        process_id: runtime_process.id().unwrap() as i32,
-        home: node_home.clone(),
-        anvil_process: anvil_process.map(|ap| ap.id() as i32),
-        other_processes: vec![],
-    });
-    drop(node_cleanup_infos);
-
-    runtime_process.wait().await.unwrap();
+    let nodes = if num_nodes <= 1 {
+        vec![(fake_node_name, node_home, node_port)]
+    } else {
+        (0..num_nodes)
+            .map(|index| make_network_node(&fake_node_name, &node_home, node_port, index))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    let mut anvil_process = anvil_process.map(|ap| ap.id() as i32);
+    let mut runtime_processes = vec![];
+    for (fake_node_name, node_home, node_port) in &nodes {
+        let mut args = args.clone();
+        args.extend_from_slice(&[
+            "--fake-node-name".into(),
+            fake_node_name.clone(),
+            "--fakechain-port".into(),
+            format!("{fakechain_port}"),
+        ]);
+
+        // only a lone node can take the terminal's input
+        let (runtime_process, master_fd) = run_runtime(
+            &runtime_path,
+            node_home,
+            *node_port,
+            &args[..],
+            true,
+            detached || num_nodes > 1,
+            verbosity,
+            None,
+        )?;
+
+        let mut node_cleanup_infos = node_cleanup_infos.lock().await;
+        node_cleanup_infos.push(NodeCleanupInfo {
+            master_fd,
+            process_id: runtime_process.id().unwrap() as i32,
+            home: node_home.clone(),
+            anvil_process: anvil_process.take(),
+            other_processes: vec![],
+        });
+        drop(node_cleanup_infos);
+        runtime_processes.push(runtime_process);
+    }
+
+    if num_nodes > 1 || !install.is_empty() {
+        for (fake_node_name, _, node_port) in &nodes {
+            wait_until_healthy(*node_port, recv_kill_in_wait.resubscribe()).await?;
+            let url = format!("http://localhost:{node_port}");
+            for zip_path in install {
+                info!("Installing {zip_path:?} on {fake_node_name}...");
+                start_package::install_zip(zip_path, &url, install_retries).await?;
+            }
+            if num_nodes > 1 {
+                info!("{fake_node_name} is up at {url}");
+            }
+        }
+        if num_nodes > 1 {
+            info!("All {num_nodes} nodes are up; Ctrl-C to stop them.");
+        }
+    }
+
+    for mut runtime_process in runtime_processes {
+        runtime_process.wait().await.unwrap();
+    }
     let _ = send_to_cleanup.send(true);
     for handle in task_handles {
         handle.await.unwrap();
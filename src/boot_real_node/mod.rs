@@ -86,6 +86,7 @@ pub async fn execute(
         true,
         detached,
         verbosity,
+        None,
     )?;
 
     let mut node_cleanup_infos = node_cleanup_infos.lock().await;
@@ -0,0 +1,249 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tracing::{info, instrument, warn};
+use zip::read::ZipArchive;
+
+use super::docs::{parse_interfaces, WitItem};
+
+/// Interface name -> its items, by name
+type Api = BTreeMap<String, BTreeMap<String, WitItem>>;
+
+#[derive(Debug, Default)]
+struct Changes {
+    breaking: Vec<String>,
+    additive: Vec<String>,
+}
+
+/// The WIT files of `pkg/api.zip`, if the package has been built before
+#[instrument(level = "trace", skip_all)]
+pub fn read_api_zip(package_dir: &Path) -> Result<Option<Vec<String>>> {
+    let zip_path = package_dir.join("pkg").join("api.zip");
+    if !zip_path.exists() {
+        return Ok(None);
+    }
+    let mut archive = ZipArchive::new(fs::File::open(&zip_path)?)?;
+    let mut wits = vec![];
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if !file.name().ends_with(".wit") {
+            continue;
+        }
+        let mut wit = String::new();
+        file.read_to_string(&mut wit)?;
+        wits.push(wit);
+    }
+    Ok(Some(wits))
+}
+
+fn parse_api(wits: Vec<String>) -> Api {
+    wits.iter()
+        .flat_map(|wit| parse_interfaces(wit))
+        .map(|interface| {
+            let items = interface
+                .items
+                .into_iter()
+                .map(|item| (item.name.clone(), item))
+                .collect();
+            (interface.name, items)
+        })
+        .collect()
+}
+
+/// A definition without comments or formatting, so that only its meaning is compared
+fn normalize(definition: &str) -> String {
+    definition
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Field or case name -> its normalized definition, for the `{ ... }` body of a
+/// record, variant, enum or flags
+fn parse_members(definition: &str) -> BTreeMap<String, String> {
+    let normalized = normalize(definition);
+    let (Some(start), Some(end)) = (normalized.find('{'), normalized.rfind('}')) else {
+        return BTreeMap::new();
+    };
+    let mut members = vec![];
+    let mut member = String::new();
+    let mut depth = 0;
+    for c in normalized[start + 1..end].chars() {
+        match c {
+            '<' | '(' => depth += 1,
+            '>' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                members.push(std::mem::take(&mut member));
+                continue;
+            }
+            _ => {}
+        }
+        member.push(c);
+    }
+    members.push(member);
+    members
+        .into_iter()
+        .map(|m| m.trim().to_string())
+        .filter(|m| !m.is_empty())
+        .map(|m| {
+            let name = m
+                .split([':', '('])
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .trim_start_matches('%')
+                .to_string();
+            (name, m)
+        })
+        .collect()
+}
+
+fn compare_members(interface: &str, old: &WitItem, new: &WitItem, changes: &mut Changes) {
+    let member_kind = if old.kind == "record" {
+        "field"
+    } else {
+        "case"
+    };
+    let item = format!("{} {interface}/{}", old.kind, old.name);
+    let old_members = parse_members(&old.definition);
+    let new_members = parse_members(&new.definition);
+    for (name, old_member) in &old_members {
+        match new_members.get(name) {
+            None => changes
+                .breaking
+                .push(format!("removed {member_kind} {name} of {item}")),
+            Some(new_member) if new_member != old_member => changes.breaking.push(format!(
+                "changed {member_kind} {name} of {item}: `{old_member}` -> `{new_member}`"
+            )),
+            Some(_) => {}
+        }
+    }
+    for (name, new_member) in &new_members {
+        if old_members.contains_key(name) {
+            continue;
+        }
+        let is_optional = new_member
+            .split_once(':')
+            .is_some_and(|(_, ty)| ty.trim().starts_with("option<"));
+        if old.kind == "record" && !is_optional {
+            changes
+                .breaking
+                .push(format!("added required field {name} to {item}"));
+        } else if old.kind == "record" {
+            changes
+                .additive
+                .push(format!("added optional field {name} to {item}"));
+        } else {
+            changes
+                .additive
+                .push(format!("added case {name} to {item}"));
+        }
+    }
+}
+
+/// The definition with its name blanked, to recognize a renamed type
+fn anonymize(item: &WitItem) -> String {
+    normalize(&item.definition).replacen(&item.name, "", 1)
+}
+
+fn compare_apis(old: &Api, new: &Api) -> Changes {
+    let mut changes = Changes::default();
+    for (interface, old_items) in old {
+        let Some(new_items) = new.get(interface) else {
+            changes
+                .breaking
+                .push(format!("removed interface {interface}"));
+            continue;
+        };
+        let mut added: Vec<&WitItem> = new_items
+            .values()
+            .filter(|item| !old_items.contains_key(&item.name))
+            .collect();
+        for old_item in old_items.values() {
+            let Some(new_item) = new_items.get(&old_item.name) else {
+                let renamed_to = added
+                    .iter()
+                    .position(|a| a.kind == old_item.kind && anonymize(a) == anonymize(old_item));
+                match renamed_to {
+                    Some(index) => {
+                        let new_item = added.remove(index);
+                        changes.breaking.push(format!(
+                            "renamed {} {interface}/{} to {}",
+                            old_item.kind, old_item.name, new_item.name,
+                        ));
+                    }
+                    None => changes.breaking.push(format!(
+                        "removed {} {interface}/{}",
+                        old_item.kind, old_item.name,
+                    )),
+                }
+                continue;
+            };
+            if new_item.kind != old_item.kind {
+                changes.breaking.push(format!(
+                    "changed {interface}/{} from {} to {}",
+                    old_item.name, old_item.kind, new_item.kind,
+                ));
+            } else if ["record", "variant", "enum", "flags"].contains(&old_item.kind) {
+                compare_members(interface, old_item, new_item, &mut changes);
+            } else if normalize(&old_item.definition) != normalize(&new_item.definition) {
+                changes.breaking.push(format!(
+                    "changed signature of {} {interface}/{}: `{}` -> `{}`",
+                    old_item.kind,
+                    old_item.name,
+                    normalize(&old_item.definition),
+                    normalize(&new_item.definition),
+                ));
+            }
+        }
+        for item in added {
+            changes
+                .additive
+                .push(format!("added {} {interface}/{}", item.kind, item.name));
+        }
+    }
+    for interface in new.keys().filter(|i| !old.contains_key(*i)) {
+        changes
+            .additive
+            .push(format!("added interface {interface}"));
+    }
+    changes
+}
+
+/// Compare the WIT API just built into `pkg/api.zip` with that of the previous
+/// build: warn of additive changes & fail on breaking ones
+#[instrument(level = "trace", skip_all)]
+pub fn check_breaking_changes(package_dir: &Path, previous_api: Option<Vec<String>>) -> Result<()> {
+    let Some(previous_api) = previous_api else {
+        info!("No previous pkg/api.zip in {package_dir:?}: nothing to check for breaking changes.");
+        return Ok(());
+    };
+    let api = read_api_zip(package_dir)?.unwrap_or_default();
+    let changes = compare_apis(&parse_api(previous_api), &parse_api(api));
+
+    for change in &changes.additive {
+        warn!("Additive API change: {change}");
+    }
+    if changes.breaking.is_empty() {
+        info!(
+            "No breaking API changes ({} additive).",
+            changes.additive.len()
+        );
+        return Ok(());
+    }
+    Err(eyre!(
+        "{} breaking API change(s) to {package_dir:?}:\n{}",
+        changes.breaking.len(),
+        changes.breaking.join("\n"),
+    )
+    .with_suggestion(|| {
+        "Bump the package's major version so that downstream consumers know to update."
+    }))
+}
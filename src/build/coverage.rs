@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::Result;
+use fs_err as fs;
+use tracing::{instrument, warn};
+
+/// `-Zno-profiler-runtime`: wasm32-wasip1 has no profiler runtime, so the
+/// process writes its own `.profraw`, e.g. with `minicov`; processes can gate
+/// that on `#[cfg(kinode_coverage)]`
+const COVERAGE_RUSTFLAGS: &str =
+    "-C instrument-coverage -Zno-profiler-runtime --cfg kinode_coverage";
+
+/// `target/coverage/`, where instrumented builds & reports are placed
+pub fn get_coverage_dir(package_dir: &Path) -> PathBuf {
+    package_dir.join("target").join("coverage")
+}
+
+/// Instrumented core modules: their coverage mappings are needed to
+/// interpret `.profraw`s, and do not survive `wasm-tools component new`
+pub fn get_coverage_objects_dir(package_dir: &Path) -> PathBuf {
+    get_coverage_dir(package_dir).join("objects")
+}
+
+/// `RUSTFLAGS` for an instrumented build, keeping any already set
+pub fn get_coverage_rustflags() -> String {
+    match std::env::var("RUSTFLAGS") {
+        Ok(rustflags) if !rustflags.is_empty() => format!("{rustflags} {COVERAGE_RUSTFLAGS}"),
+        _ => COVERAGE_RUSTFLAGS.to_string(),
+    }
+}
+
+/// Without a way to write its counters, an instrumented process
+/// produces no `.profraw`
+pub fn check_coverage_writer(process_dir: &Path) -> Result<()> {
+    let cargo_toml = fs::read_to_string(process_dir.join("Cargo.toml"))?;
+    let cargo_toml: toml::Value = cargo_toml.parse()?;
+    let has_minicov = cargo_toml
+        .get("dependencies")
+        .and_then(|d| d.get("minicov"))
+        .is_some();
+    if !has_minicov {
+        warn!(
+            "{process_dir:?} does not depend on `minicov`: it will not write a `.profraw`.\nAdd `minicov` as a dependency and, under `#[cfg(kinode_coverage)]`, write the output of `minicov::capture_coverage()` to a VFS file ending in `.profraw` before the process exits."
+        );
+    }
+    Ok(())
+}
+
+/// Keep the instrumented core module of the process for `kit coverage-report`
+#[instrument(level = "trace", skip_all)]
+pub fn save_instrumented_module(process_dir: &Path, wasm_file: &Path) -> Result<()> {
+    let package_dir = process_dir.parent().unwrap();
+    let objects_dir = get_coverage_objects_dir(package_dir);
+    fs::create_dir_all(&objects_dir)?;
+    fs::copy(
+        process_dir.join(wasm_file),
+        objects_dir.join(wasm_file.file_name().unwrap()),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,303 @@
+use std::path::Path;
+
+use color_eyre::{eyre::WrapErr, Result};
+use fs_err as fs;
+use regex::Regex;
+use serde::Deserialize;
+use tracing::{instrument, warn};
+use walkdir::WalkDir;
+
+use super::{KINODE_PROCESS_LIB_CRATE_NAME, RUST_SRC_PATH};
+
+const DEPRECATIONS: &str = include_str!("deprecations.toml");
+
+#[derive(Debug, Deserialize)]
+struct Deprecations {
+    deprecations: Vec<Deprecation>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Deprecation {
+    name: String,
+    pattern: Option<String>,
+    replacement: String,
+    since: String,
+    note: Option<String>,
+}
+
+impl Deprecation {
+    fn regex(&self) -> Result<Regex> {
+        let pattern = self
+            .pattern
+            .clone()
+            .unwrap_or_else(|| format!(r"\b{}\b", regex::escape(&self.name)));
+        Ok(Regex::new(&pattern)?)
+    }
+
+    /// Matches a `use` of `kinode_process_lib` that brings the first segment
+    /// of `name` into scope, so that a bare `name` refers to the library's
+    /// & not, e.g., a user function of the same name; `None` for a `pattern`
+    fn import_regex(&self) -> Result<Option<Regex>> {
+        if self.pattern.is_some() {
+            return Ok(None);
+        }
+        let first_segment = self.name.split("::").next().unwrap_or(&self.name);
+        Ok(Some(Regex::new(&format!(
+            r"^\s*(?:pub\s+)?use\s+(?:::)?{KINODE_PROCESS_LIB_CRATE_NAME}::.*(?:\b{}\b|\*)",
+            regex::escape(first_segment),
+        ))?))
+    }
+}
+
+struct Matcher {
+    regex: Regex,
+    import_regex: Option<Regex>,
+    deprecation: Deprecation,
+}
+
+fn make_matchers(deprecations: Vec<Deprecation>) -> Result<Vec<Matcher>> {
+    deprecations
+        .into_iter()
+        .map(|deprecation| {
+            Ok(Matcher {
+                regex: deprecation.regex()?,
+                import_regex: deprecation.import_regex()?,
+                deprecation,
+            })
+        })
+        .collect()
+}
+
+/// Lines of `contents` with their index, except that a `use` statement
+/// spanning several lines is joined onto its first
+fn statements(contents: &str) -> Vec<(usize, String)> {
+    let mut statements: Vec<(usize, String)> = vec![];
+    let mut in_use = false;
+    for (line_number, line) in contents.lines().enumerate() {
+        if in_use {
+            let (_, statement) = statements.last_mut().unwrap();
+            statement.push(' ');
+            statement.push_str(line.trim());
+        } else {
+            statements.push((line_number, line.to_string()));
+            let trimmed = line.trim_start();
+            in_use = trimmed.starts_with("use ") || trimmed.starts_with("pub use ");
+        }
+        if in_use && line.trim_end().ends_with(';') {
+            in_use = false;
+        }
+    }
+    statements
+}
+
+/// Line index & deprecation of each use of a deprecated API in `contents`.
+/// Only files using `kinode_process_lib` are checked; a bare `name` must be
+/// imported from it, or its line must spell out `kinode_process_lib::`
+fn find_deprecated<'a>(contents: &str, matchers: &'a [Matcher]) -> Vec<(usize, &'a Deprecation)> {
+    let statements = statements(contents);
+    let lib_path = format!("{KINODE_PROCESS_LIB_CRATE_NAME}::");
+    if !statements.iter().any(|(_, s)| s.contains(&lib_path)) {
+        return vec![];
+    }
+    let imported: Vec<bool> = matchers
+        .iter()
+        .map(|m| match m.import_regex {
+            None => true,
+            Some(ref import_regex) => statements.iter().any(|(_, s)| import_regex.is_match(s)),
+        })
+        .collect();
+    let mut found = vec![];
+    for (line_number, line) in &statements {
+        if line.trim_start().starts_with("//") {
+            continue;
+        }
+        for (matcher, imported) in matchers.iter().zip(&imported) {
+            if matcher.regex.is_match(line) && (*imported || line.contains(&lib_path)) {
+                found.push((*line_number, &matcher.deprecation));
+            }
+        }
+    }
+    found
+}
+
+/// Warn about uses of deprecated `kinode_process_lib` APIs in the
+/// package's Rust processes; see `deprecations.toml`
+#[instrument(level = "trace", skip_all)]
+pub fn check_deprecations(package_dir: &Path) -> Result<()> {
+    let Deprecations { deprecations } =
+        toml::from_str(DEPRECATIONS).wrap_err("Failed to parse deprecations.toml")?;
+    let matchers = make_matchers(deprecations)?;
+
+    let mut found = 0;
+    for entry in fs::read_dir(package_dir)? {
+        let process_dir = entry?.path();
+        if !process_dir.join(RUST_SRC_PATH).exists() {
+            continue;
+        }
+        for file in WalkDir::new(process_dir.join("src"))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().and_then(|e| e.to_str()) == Some("rs"))
+        {
+            let contents = fs::read_to_string(file.path())?;
+            for (line_number, deprecation) in find_deprecated(&contents, &matchers) {
+                found += 1;
+                warn!(
+                    "{}:{}: `{}` is deprecated since {} {}; use `{}` instead.{}",
+                    file.path().display(),
+                    line_number + 1,
+                    deprecation.name,
+                    KINODE_PROCESS_LIB_CRATE_NAME,
+                    deprecation.since,
+                    deprecation.replacement,
+                    deprecation
+                        .note
+                        .as_ref()
+                        .map(|n| format!(" {n}"))
+                        .unwrap_or_default(),
+                );
+            }
+        }
+    }
+    if found > 0 {
+        warn!(
+            "Found {found} use(s) of deprecated {} APIs: migrate before the next major version.",
+            KINODE_PROCESS_LIB_CRATE_NAME,
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matchers() -> Vec<Matcher> {
+        let Deprecations { deprecations } = toml::from_str(DEPRECATIONS).unwrap();
+        make_matchers(deprecations).unwrap()
+    }
+
+    /// Names of the deprecations found in `contents`, by line
+    fn found(contents: &str) -> Vec<(usize, String)> {
+        find_deprecated(contents, &matchers())
+            .into_iter()
+            .map(|(line_number, d)| (line_number, d.name.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn statements_joins_multi_line_use() {
+        let contents = "use kinode_process_lib::{\n    Address,\n    Payload,\n};\nfn main() {}\n";
+        assert_eq!(
+            statements(contents),
+            [
+                (
+                    0,
+                    "use kinode_process_lib::{ Address, Payload, };".to_string()
+                ),
+                (4, "fn main() {}".to_string()),
+            ],
+        );
+    }
+
+    #[test]
+    fn every_deprecation_is_checked() {
+        let names: Vec<String> = matchers().into_iter().map(|m| m.deprecation.name).collect();
+        assert_eq!(
+            names,
+            [
+                "get_payload",
+                "Payload",
+                "ipc",
+                "http::bind_http_path",
+                "http::bind_ws_path",
+                "http::serve_ui",
+                "http::serve_index_html",
+                "http::send_request_await_response",
+                "eth::get_block_number",
+                "eth::get_logs",
+            ],
+        );
+    }
+
+    #[test]
+    fn get_payload() {
+        let lib = "use kinode_process_lib::{get_payload, Address};\nlet p = get_payload();\n";
+        assert_eq!(
+            found(lib),
+            [(0, "get_payload".into()), (1, "get_payload".into())]
+        );
+        let user =
+            "use kinode_process_lib::Address;\nfn get_payload() {}\nlet p = get_payload();\n";
+        assert!(found(user).is_empty());
+    }
+
+    #[test]
+    fn payload() {
+        let lib = "use kinode_process_lib::{\n    Address,\n    Payload,\n};\n";
+        assert_eq!(found(lib), [(0, "Payload".into())]);
+        let user = "use kinode_process_lib::Address;\nstruct Payload;\nlet p = Payload;\n";
+        assert!(found(user).is_empty());
+    }
+
+    #[test]
+    fn ipc() {
+        let lib = "use kinode_process_lib::Message;\nlet body = message.ipc();\n";
+        assert_eq!(found(lib), [(1, "ipc".into())]);
+        let user = "use my_lib::Message;\nlet body = message.ipc();\n";
+        assert!(found(user).is_empty());
+    }
+
+    #[test]
+    fn http_functions() {
+        for (name, call) in [
+            (
+                "http::bind_http_path",
+                "http::bind_http_path(\"/\", true, false)?;",
+            ),
+            (
+                "http::bind_ws_path",
+                "http::bind_ws_path(\"/\", true, false)?;",
+            ),
+            (
+                "http::serve_ui",
+                "http::serve_ui(&our, \"ui\", true, false, vec![\"/\"])?;",
+            ),
+            (
+                "http::serve_index_html",
+                "http::serve_index_html(&our, \"ui\", true, false, vec![\"/\"])?;",
+            ),
+            (
+                "http::send_request_await_response",
+                "http::send_request_await_response(Method::GET, url, None, 5, vec![])?;",
+            ),
+        ] {
+            let lib = format!("use kinode_process_lib::{{http, Address}};\n{call}\n");
+            assert_eq!(found(&lib), [(1, name.to_string())], "{name}");
+            let qualified =
+                format!("use kinode_process_lib::Address;\nkinode_process_lib::{call}\n");
+            assert_eq!(found(&qualified), [(1, name.to_string())], "{name}");
+            let user = format!("use kinode_process_lib::Address;\nmod http {{}}\n{call}\n");
+            assert!(found(&user).is_empty(), "{name}");
+        }
+    }
+
+    #[test]
+    fn eth_functions() {
+        for (name, call) in [
+            ("eth::get_block_number", "let n = eth::get_block_number()?;"),
+            ("eth::get_logs", "let logs = eth::get_logs(&filter)?;"),
+        ] {
+            let lib = format!("use kinode_process_lib::{{eth, Address}};\n{call}\n");
+            assert_eq!(found(&lib), [(1, name.to_string())], "{name}");
+            let user = format!("use kinode_process_lib::Address;\nmod eth {{}}\n{call}\n");
+            assert!(found(&user).is_empty(), "{name}");
+        }
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let contents = "use kinode_process_lib::get_payload;\n// get_payload() was renamed\n";
+        assert_eq!(found(contents), [(0, "get_payload".into())]);
+    }
+}
@@ -0,0 +1,163 @@
+use std::path::Path;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tracing::{info, instrument};
+
+use kinode_process_lib::kernel_types::Erc721Metadata;
+
+use crate::start_package::{install, new_package};
+
+/// Files carrying this header were written by `kit build --docker`
+/// and may be overwritten by it
+const GENERATED_HEADER: &str = "# generated by `kit build --docker`";
+const DOCKER_DIR: &str = "docker";
+const DEFAULT_KINODE_IMAGE: &str = "nick1udwig/kinode:latest";
+const HTTP_PORT: u16 = 8080;
+
+fn make_dockerfile(pkg_publisher: &str) -> String {
+    format!(
+        r#"{GENERATED_HEADER}
+ARG KINODE_IMAGE={DEFAULT_KINODE_IMAGE}
+FROM ${{KINODE_IMAGE}}
+
+RUN apt-get update \
+    && apt-get install -y --no-install-recommends curl ca-certificates \
+    && rm -rf /var/lib/apt/lists/*
+
+# {pkg_publisher}
+COPY pkg/ /kinode-package/pkg/
+COPY target/{DOCKER_DIR}/ /kinode-package/
+RUN chmod +x /kinode-package/entrypoint.sh
+
+ENV KINODE_BIN=kinode
+ENV KINODE_HOME=/kinode-home
+ENV KINODE_PORT={HTTP_PORT}
+EXPOSE {HTTP_PORT}
+VOLUME /kinode-home
+
+ENTRYPOINT ["/kinode-package/entrypoint.sh"]
+"#
+    )
+}
+
+fn make_docker_compose(package_name: &str) -> String {
+    format!(
+        r#"{GENERATED_HEADER}
+services:
+  {package_name}:
+    build: .
+    ports:
+      - "{HTTP_PORT}:{HTTP_PORT}"
+    volumes:
+      - {package_name}-home:/kinode-home
+    stdin_open: true
+    tty: true
+    restart: unless-stopped
+
+volumes:
+  {package_name}-home:
+"#
+    )
+}
+
+/// Boot the node, then, once it answers local RPC (i.e. after it has been
+/// registered or logged into), install the package unless already installed
+fn make_entrypoint(pkg_publisher: &str, hash_string: &str) -> String {
+    format!(
+        r#"#!/bin/sh
+{GENERATED_HEADER}
+set -u
+
+MARKER="$KINODE_HOME/.kit-installed-{pkg_publisher}-{hash_string}"
+RPC="http://localhost:$KINODE_PORT/rpc:distro:sys/message"
+
+send() {{
+    curl -sf -X POST -H "Content-Type: application/json" --data @"$1" "$RPC" \
+        | grep -q "Success"
+}}
+
+install_package() {{
+    until send /kinode-package/new-package.json; do
+        sleep 5
+    done
+    until send /kinode-package/install.json; do
+        sleep 5
+    done
+    touch "$MARKER"
+    echo "kit: installed {pkg_publisher}"
+}}
+
+if [ ! -f "$MARKER" ]; then
+    install_package &
+fi
+
+exec "$KINODE_BIN" "$KINODE_HOME" --port "$KINODE_PORT" "$@"
+"#
+    )
+}
+
+const DOCKERIGNORE: &str = "*
+!pkg/
+!target/docker/
+";
+
+/// Write `path` unless it exists and was not generated by kit
+fn write_generated(path: &Path, contents: &str) -> Result<()> {
+    if path.exists() && !fs::read_to_string(path)?.contains(GENERATED_HEADER) {
+        return Err(
+            eyre!("Not overwriting {path:?}: it was not generated by kit")
+                .with_suggestion(|| "Move it out of the way and re-run with --docker."),
+        );
+    }
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+/// Write a `Dockerfile` and `docker-compose.yml` that run a Kinode which
+/// installs the built package on startup, along with the requests the
+/// container sends to install it (`target/docker/`)
+#[instrument(level = "trace", skip_all)]
+pub fn write_docker_files(
+    package_dir: &Path,
+    metadata: &Erc721Metadata,
+    pkg_publisher: &str,
+    zip_path: &Path,
+    hash_string: &str,
+) -> Result<()> {
+    let package_name = metadata.properties.package_name.as_str();
+    let publisher = metadata.properties.publisher.as_str();
+    let Some(zip_path) = zip_path.to_str() else {
+        return Err(eyre!("Non-UTF-8 zip path {zip_path:?}"));
+    };
+
+    let docker_dir = package_dir.join("target").join(DOCKER_DIR);
+    fs::create_dir_all(&docker_dir)?;
+    let new_package_request = new_package(None, package_name, publisher, zip_path)?;
+    fs::write(
+        docker_dir.join("new-package.json"),
+        serde_json::to_string(&new_package_request)?,
+    )?;
+    let install_request = install(None, hash_string, metadata)?;
+    fs::write(
+        docker_dir.join("install.json"),
+        serde_json::to_string(&install_request)?,
+    )?;
+    fs::write(
+        docker_dir.join("entrypoint.sh"),
+        make_entrypoint(pkg_publisher, hash_string),
+    )?;
+
+    let dockerignore_path = package_dir.join(".dockerignore");
+    if !dockerignore_path.exists() {
+        fs::write(&dockerignore_path, DOCKERIGNORE)?;
+    }
+    let dockerfile_path = package_dir.join("Dockerfile");
+    let compose_path = package_dir.join("docker-compose.yml");
+    write_generated(&dockerfile_path, &make_dockerfile(pkg_publisher))?;
+    write_generated(&compose_path, &make_docker_compose(package_name))?;
+    info!(
+        "Wrote {dockerfile_path:?} and {compose_path:?}; run with `docker compose up` and register the node at http://localhost:{HTTP_PORT}"
+    );
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use std::path::Path;
+
+use color_eyre::Result;
+use fs_err as fs;
+use regex::Regex;
+use tracing::{info, instrument};
+
+const DOCS_DIR: &str = "docs";
+
+#[derive(Debug)]
+pub struct WitItem {
+    pub kind: &'static str,
+    pub name: String,
+    docs: Vec<String>,
+    pub definition: String,
+}
+
+#[derive(Debug)]
+pub struct WitInterface {
+    pub name: String,
+    docs: Vec<String>,
+    pub items: Vec<WitItem>,
+}
+
+fn net_braces(line: &str) -> i32 {
+    line.matches('{').count() as i32 - line.matches('}').count() as i32
+}
+
+/// Parse the interfaces of a WIT file, along with their functions & types
+/// and the `///` doc comments preceding each
+pub fn parse_interfaces(wit: &str) -> Vec<WitInterface> {
+    let interface_re = Regex::new(r"^interface\s+%?([\w\-]+)").unwrap();
+    let type_re = Regex::new(r"^(record|variant|enum|flags|resource|type)\s+%?([\w\-]+)").unwrap();
+    let func_re = Regex::new(r"^%?([\w\-]+)\s*:\s*func\b").unwrap();
+
+    let mut interfaces: Vec<WitInterface> = vec![];
+    let mut docs: Vec<String> = vec![];
+    let mut depth = 0;
+    let mut lines = wit.lines();
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if let Some(doc) = trimmed.strip_prefix("///") {
+            docs.push(doc.strip_prefix(' ').unwrap_or(doc).to_string());
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with("//") {
+            if trimmed.is_empty() {
+                docs.clear();
+            }
+            continue;
+        }
+
+        if depth == 0 {
+            if let Some(interface) = interface_re.captures(trimmed) {
+                interfaces.push(WitInterface {
+                    name: interface[1].to_string(),
+                    docs: std::mem::take(&mut docs),
+                    items: vec![],
+                });
+            }
+            depth += net_braces(trimmed);
+            docs.clear();
+            continue;
+        }
+
+        let Some(interface) = interfaces.last_mut().filter(|_| depth == 1) else {
+            depth += net_braces(trimmed);
+            docs.clear();
+            continue;
+        };
+        let (kind, name) = if let Some(item) = type_re.captures(trimmed) {
+            let kind = match &item[1] {
+                "record" => "record",
+                "variant" => "variant",
+                "enum" => "enum",
+                "flags" => "flags",
+                "resource" => "resource",
+                _ => "type",
+            };
+            (kind, item[2].to_string())
+        } else if let Some(item) = func_re.captures(trimmed) {
+            ("func", item[1].to_string())
+        } else {
+            // e.g. `use` statements
+            depth += net_braces(trimmed);
+            docs.clear();
+            continue;
+        };
+
+        // collect the whole definition: a `{ ... }` block or up to `;`
+        let mut definition = vec![line.trim_end().to_string()];
+        let mut item_depth = net_braces(trimmed);
+        let mut done = item_depth == 0 && (trimmed.ends_with(';') || trimmed.ends_with('}'));
+        while !done {
+            let Some(line) = lines.next() else {
+                break;
+            };
+            definition.push(line.trim_end().to_string());
+            item_depth += net_braces(line);
+            let trimmed = line.trim();
+            done = item_depth <= 0 && (trimmed.ends_with(';') || trimmed.ends_with('}'));
+        }
+        // re-indent block bodies relative to the item's own indentation
+        let indent = line.len() - line.trim_start().len();
+        let definition = definition
+            .iter()
+            .map(|l| l.get(indent..).unwrap_or(l.trim_start()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        interface.items.push(WitItem {
+            kind,
+            name,
+            docs: std::mem::take(&mut docs),
+            definition,
+        });
+    }
+    interfaces
+}
+
+fn make_interface_markdown(package: Option<&str>, interface: &WitInterface) -> String {
+    let mut markdown = format!("# `{}`\n\n", interface.name);
+    if let Some(package) = package {
+        markdown.push_str(&format!("Package: `{package}`\n\n"));
+    }
+    if !interface.docs.is_empty() {
+        markdown.push_str(&format!("{}\n\n", interface.docs.join("\n")));
+    }
+    for (heading, is_func) in [("Functions", true), ("Types", false)] {
+        let items: Vec<&WitItem> = interface
+            .items
+            .iter()
+            .filter(|i| (i.kind == "func") == is_func)
+            .collect();
+        if items.is_empty() {
+            continue;
+        }
+        markdown.push_str(&format!("## {heading}\n\n"));
+        for item in items {
+            markdown.push_str(&format!("### {} `{}`\n\n", item.kind, item.name));
+            if !item.docs.is_empty() {
+                markdown.push_str(&format!("{}\n\n", item.docs.join("\n")));
+            }
+            markdown.push_str(&format!("```wit\n{}\n```\n\n", item.definition));
+        }
+    }
+    markdown
+}
+
+/// Write Markdown documentation of each WIT interface in `package_dir/api/`
+/// to `package_dir/pkg/docs/`: one file per interface and a `README.md` index
+#[instrument(level = "trace", skip_all)]
+pub fn write_api_docs(package_dir: &Path) -> Result<()> {
+    let api_dir = package_dir.join("api");
+    if !api_dir.exists() {
+        info!("No api/ directory in {package_dir:?}: skipping --emit-docs.");
+        return Ok(());
+    }
+    let package_re = Regex::new(r"(?m)^\s*package\s+([^;\s]+)\s*;").unwrap();
+
+    let mut wit_paths: Vec<_> = fs::read_dir(&api_dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("wit"))
+        .collect();
+    wit_paths.sort();
+
+    let docs_dir = package_dir.join("pkg").join(DOCS_DIR);
+    if docs_dir.exists() {
+        fs::remove_dir_all(&docs_dir)?;
+    }
+    fs::create_dir_all(&docs_dir)?;
+
+    let mut index = "# API\n\n".to_string();
+    let mut num_interfaces = 0;
+    for wit_path in wit_paths {
+        let wit = fs::read_to_string(&wit_path)?;
+        let package = package_re.captures(&wit).map(|p| p[1].to_string());
+        for interface in parse_interfaces(&wit) {
+            let file_name = format!("{}.md", interface.name);
+            fs::write(
+                docs_dir.join(&file_name),
+                make_interface_markdown(package.as_deref(), &interface),
+            )?;
+            let summary = interface
+                .docs
+                .first()
+                .map(|d| format!(": {d}"))
+                .unwrap_or_default();
+            index.push_str(&format!("- [`{}`]({file_name}){summary}\n", interface.name));
+            num_interfaces += 1;
+        }
+    }
+    fs::write(docs_dir.join("README.md"), index)?;
+    info!("Wrote docs for {num_interfaces} WIT interface(s) to {docs_dir:?}");
+    Ok(())
+}
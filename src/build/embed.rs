@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tracing::{info, instrument};
+
+/// Generated into `<package>/target/` so that a process can
+/// `include!("../../target/embedded_files.rs");` from its `src/lib.rs`
+pub const EMBEDDED_FILES_NAME: &str = "embedded_files.rs";
+
+#[derive(Debug)]
+pub struct EmbedFile {
+    pub path: PathBuf,
+    pub name: String,
+}
+
+/// Parse `--embed-file <path>:<name>` values
+pub fn parse_embed_files(embed_files: &[String]) -> Result<Vec<EmbedFile>> {
+    embed_files
+        .iter()
+        .map(|embed_file| {
+            let Some((path, name)) = embed_file.rsplit_once(':') else {
+                return Err(eyre!("Invalid --embed-file {embed_file}")
+                    .with_suggestion(|| "Use the form `<path>:<name>`."));
+            };
+            if name.is_empty() {
+                return Err(eyre!("--embed-file {embed_file} is missing a name"));
+            }
+            let path = fs::canonicalize(path)
+                .map_err(|e| eyre!("Could not find --embed-file {path}: {e}"))?;
+            if !path.is_file() {
+                return Err(eyre!("--embed-file {path:?} is not a file"));
+            }
+            Ok(EmbedFile {
+                path,
+                name: name.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Summarize the embedded files such that any change to them
+/// (including to their contents) marks the package out-of-date
+pub fn describe_embed_files(embed_files: &[EmbedFile]) -> Result<String> {
+    let mut description = Vec::with_capacity(embed_files.len());
+    for EmbedFile { path, name } in embed_files {
+        let metadata = fs::metadata(path)?;
+        description.push(format!(
+            "{name}: {path:?} ({} bytes, modified {:?})",
+            metadata.len(),
+            metadata.modified()?,
+        ));
+    }
+    Ok(description.join(", "))
+}
+
+/// Write `target/embedded_files.rs`, which exposes each embedded file
+/// through `embedded_file(name)` using `include_bytes!`
+#[instrument(level = "trace", skip_all)]
+pub fn write_embedded_files(package_dir: &Path, embed_files: &[EmbedFile]) -> Result<()> {
+    let mut arms = String::new();
+    let mut names = String::new();
+    for EmbedFile { path, name } in embed_files {
+        let path = path
+            .to_str()
+            .ok_or_else(|| eyre!("Non-UTF-8 path {path:?}"))?;
+        arms.push_str(&format!(
+            "        {name:?} => Some(include_bytes!({path:?})),\n"
+        ));
+        names.push_str(&format!("{name:?}, "));
+    }
+    let content = format!(
+        "// Generated by `kit build --embed-file`: do not edit.
+
+/// Names of all files embedded with `kit build --embed-file`.
+#[allow(dead_code)]
+pub const EMBEDDED_FILE_NAMES: &[&str] = &[{names}];
+
+/// Bytes of the file embedded under `name`, if any.
+#[allow(dead_code)]
+pub fn embedded_file(name: &str) -> Option<&'static [u8]> {{
+    match name {{
+{arms}        _ => None,
+    }}
+}}
+"
+    );
+
+    let target_dir = package_dir.join("target");
+    let path = target_dir.join(EMBEDDED_FILES_NAME);
+    if embed_files.is_empty() && !path.exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(&target_dir)?;
+    // don't touch an unchanged file: it would trigger a cargo rebuild
+    if fs::read_to_string(&path).ok().as_deref() != Some(content.as_str()) {
+        fs::write(&path, content)?;
+    }
+    Ok(())
+}
+
+/// Report each embedded file's contribution to the size of the package's processes
+#[instrument(level = "trace", skip_all)]
+pub fn report_embedded_files(package_dir: &Path, embed_files: &[EmbedFile]) -> Result<()> {
+    if embed_files.is_empty() {
+        return Ok(());
+    }
+    let mut total = 0;
+    let mut report = String::from("Embedded files:");
+    for EmbedFile { path, name } in embed_files {
+        let size = fs::metadata(path)?.len();
+        total += size;
+        report.push_str(&format!("\n  {name}: {size} bytes ({path:?})"));
+    }
+    report.push_str(&format!("\n  total: {total} bytes"));
+    for entry in fs::read_dir(package_dir.join("pkg"))? {
+        let path = entry?.path();
+        if Some("wasm") == path.extension().and_then(|e| e.to_str()) {
+            report.push_str(&format!(
+                "\n  {:?}: {} bytes",
+                path.file_name().unwrap_or_default(),
+                fs::metadata(&path)?.len(),
+            ));
+        }
+    }
+    info!("{report}");
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result, Section,
+};
+use fs_err as fs;
+use tracing::{info, instrument};
+
+use kinode_process_lib::kernel_types::PackageManifestEntry;
+
+/// `--forbid-network`: `request_networking` and the runtime modules that
+/// reach outside the node
+pub const NETWORK_CAPABILITIES: &[&str] = &[
+    "networking",
+    "net:distro:sys",
+    "http-client:distro:sys",
+    "eth:distro:sys",
+];
+
+/// Whether a capability issued by `process` is forbidden by `forbidden`,
+/// which is a process ID (e.g. `http-client:distro:sys`) or a process
+/// name (e.g. `http-client` or `http_client`)
+fn is_forbidden(process: &str, forbidden: &str) -> bool {
+    let forbidden = forbidden.replace('_', "-");
+    process == forbidden || process.split(':').next() == Some(forbidden.as_str())
+}
+
+/// Fail if any process in `pkg/manifest.json` requests a forbidden capability;
+/// `networking` (or `messaging`) forbids `request_networking = true`
+#[instrument(level = "trace", skip_all)]
+pub fn check_forbidden_capabilities(package_dir: &Path, forbidden: &[String]) -> Result<()> {
+    if forbidden.is_empty() {
+        return Ok(());
+    }
+    let manifest_path = package_dir.join("pkg").join("manifest.json");
+    if !manifest_path.exists() {
+        return Ok(());
+    }
+    let manifest: Vec<PackageManifestEntry> =
+        serde_json::from_reader(fs::File::open(&manifest_path)?)
+            .wrap_err_with(|| format!("Failed to parse {manifest_path:?}"))?;
+
+    let forbids_networking = forbidden
+        .iter()
+        .any(|f| f == "networking" || f == "messaging");
+    let mut violations = vec![];
+    for entry in manifest {
+        if forbids_networking && entry.request_networking {
+            violations.push(format!("{}: request_networking", entry.process_name));
+        }
+        for capability in &entry.request_capabilities {
+            let process = match capability {
+                serde_json::Value::String(process) => Some(process.as_str()),
+                _ => capability["process"].as_str(),
+            };
+            let Some(process) = process else {
+                continue;
+            };
+            if forbidden.iter().any(|f| is_forbidden(process, f)) {
+                violations.push(format!("{}: {capability}", entry.process_name));
+            }
+        }
+    }
+
+    if !violations.is_empty() {
+        return Err(eyre!(
+            "{manifest_path:?} requests forbidden capabilities:\n{}",
+            violations.join("\n"),
+        )
+        .with_suggestion(|| {
+            "Remove them from the manifest, or drop --forbid-network/--forbid-capabilities."
+        }));
+    }
+    info!("No forbidden capabilities requested in {manifest_path:?}.");
+    Ok(())
+}
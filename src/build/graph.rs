@@ -0,0 +1,199 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::process::Command;
+
+use color_eyre::{eyre::eyre, Result};
+use fs_err as fs;
+use regex::Regex;
+use tracing::{info, instrument, warn};
+use walkdir::WalkDir;
+
+use super::{read_metadata, RUST_SRC_PATH};
+
+const GRAPH_DOT: &str = "graph.dot";
+const GRAPH_PNG: &str = "graph.png";
+
+#[derive(Debug, Default)]
+struct ProcessGraph {
+    /// process name -> WIT world, from `wit_bindgen::generate!`
+    worlds: BTreeMap<String, String>,
+    /// (from process, to process ID)
+    messages: BTreeSet<(String, String)>,
+}
+
+/// Process IDs a Rust source file sends to: `"name:package:publisher"`,
+/// tuples ending `"name", "package", "publisher")`, and
+/// `ProcessId::new(Some("name"), "package", "publisher")`
+fn find_targets(source: &str) -> BTreeSet<String> {
+    let id_re = Regex::new(r#""([\w\-]+):([\w\-]+):([\w\-\.]+)""#).unwrap();
+    let tuple_re = Regex::new(r#""([\w\-]+)",\s*"([\w\-]+)",\s*"([\w\-\.]+)"\s*\)"#).unwrap();
+    let new_re = Regex::new(
+        r#"ProcessId::new\(\s*Some\("([\w\-]+)"\),\s*"([\w\-]+)",\s*"([\w\-\.]+)"\s*\)"#,
+    )
+    .unwrap();
+
+    let mut targets = BTreeSet::new();
+    for line in source.lines() {
+        if line.trim_start().starts_with("//") {
+            continue;
+        }
+        for re in [&id_re, &tuple_re, &new_re] {
+            for target in re.captures_iter(line) {
+                targets.insert(format!("{}:{}:{}", &target[1], &target[2], &target[3]));
+            }
+        }
+    }
+    targets
+}
+
+fn parse_processes(package_dir: &Path) -> Result<ProcessGraph> {
+    let world_re = Regex::new(r#"world:\s*"([\w\-]+)""#).unwrap();
+    let mut graph = ProcessGraph::default();
+    for entry in fs::read_dir(package_dir)? {
+        let process_dir = entry?.path();
+        if !process_dir.join(RUST_SRC_PATH).exists() {
+            continue;
+        }
+        let Some(process) = process_dir.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        for file in WalkDir::new(process_dir.join("src"))
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().and_then(|e| e.to_str()) == Some("rs"))
+        {
+            let source = fs::read_to_string(file.path())?;
+            if let Some(world) = world_re.captures(&source) {
+                graph
+                    .worlds
+                    .insert(process.to_string(), world[1].to_string());
+            }
+            if !source.contains("Request::") {
+                continue;
+            }
+            for target in find_targets(&source) {
+                graph.messages.insert((process.to_string(), target));
+            }
+        }
+        graph.worlds.entry(process.to_string()).or_default();
+    }
+    Ok(graph)
+}
+
+/// world name -> interfaces it imports, from the WIT files in `api/`
+fn parse_world_imports(package_dir: &Path) -> Result<BTreeMap<String, Vec<String>>> {
+    let world_re = Regex::new(r"(?s)\bworld\s+([\w\-]+)\s*\{(.*?)\}").unwrap();
+    let import_re = Regex::new(r"\bimport\s+([\w\-:/@\.]+)\s*;").unwrap();
+    let mut imports = BTreeMap::new();
+    let api_dir = package_dir.join("api");
+    if !api_dir.exists() {
+        return Ok(imports);
+    }
+    for entry in fs::read_dir(&api_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("wit") {
+            continue;
+        }
+        let wit = fs::read_to_string(&path)?;
+        for world in world_re.captures_iter(&wit) {
+            let world_imports = import_re
+                .captures_iter(&world[2])
+                .map(|i| i[1].to_string())
+                .collect();
+            imports.insert(world[1].to_string(), world_imports);
+        }
+    }
+    Ok(imports)
+}
+
+fn make_dot(
+    package_name: &str,
+    publisher: &str,
+    graph: &ProcessGraph,
+    world_imports: &BTreeMap<String, Vec<String>>,
+) -> String {
+    let mut dot = format!("digraph \"{package_name}:{publisher}\" {{\n    rankdir=LR;\n");
+    dot.push_str("    node [shape=box, style=rounded];\n\n");
+    for process in graph.worlds.keys() {
+        dot.push_str(&format!("    \"{process}\";\n"));
+    }
+
+    let mut externals = BTreeSet::new();
+    let mut edges = vec![];
+    for (from, target) in &graph.messages {
+        let mut parts = target.splitn(3, ':');
+        let (name, package) = (parts.next().unwrap(), parts.next().unwrap());
+        let to = if package == package_name && graph.worlds.contains_key(name) {
+            name.to_string()
+        } else {
+            externals.insert(target.clone());
+            target.clone()
+        };
+        edges.push(format!("    \"{from}\" -> \"{to}\";\n"));
+    }
+
+    let mut interfaces = BTreeSet::new();
+    for (process, world) in &graph.worlds {
+        for interface in world_imports.get(world).into_iter().flatten() {
+            interfaces.insert(interface.clone());
+            edges.push(format!(
+                "    \"{process}\" -> \"{interface}\" [style=dashed, label=\"import\"];\n"
+            ));
+        }
+    }
+
+    for external in &externals {
+        dot.push_str(&format!(
+            "    \"{external}\" [shape=ellipse, style=dashed, color=gray40];\n"
+        ));
+    }
+    for interface in &interfaces {
+        dot.push_str(&format!("    \"{interface}\" [shape=note];\n"));
+    }
+    dot.push('\n');
+    for edge in edges {
+        dot.push_str(&edge);
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Write `pkg/graph.dot`: processes as nodes, the processes each sends
+/// `Request`s to as edges, and the WIT interfaces each imports as dashed
+/// edges; also render `pkg/graph.png` if GraphViz `dot` is installed
+#[instrument(level = "trace", skip_all)]
+pub fn write_process_graph(package_dir: &Path) -> Result<()> {
+    let metadata = read_metadata(package_dir)?;
+    let graph = parse_processes(package_dir)?;
+    let world_imports = parse_world_imports(package_dir)?;
+    let dot = make_dot(
+        &metadata.properties.package_name,
+        &metadata.properties.publisher,
+        &graph,
+        &world_imports,
+    );
+
+    let dot_path = package_dir.join("pkg").join(GRAPH_DOT);
+    fs::write(&dot_path, dot)?;
+    info!("Wrote process graph to {dot_path:?}.");
+
+    let png_path = package_dir.join("pkg").join(GRAPH_PNG);
+    match Command::new("dot")
+        .args(["-Tpng", "-o"])
+        .arg(&png_path)
+        .arg(&dot_path)
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            info!("Rendered process graph to {png_path:?}.")
+        }
+        Ok(output) => {
+            return Err(eyre!(
+                "`dot` failed to render {dot_path:?}: {}",
+                String::from_utf8_lossy(&output.stderr),
+            ))
+        }
+        Err(_) => warn!("GraphViz `dot` not found: not rendering {png_path:?}."),
+    }
+    Ok(())
+}
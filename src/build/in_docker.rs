@@ -0,0 +1,75 @@
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::process::Command;
+
+use color_eyre::{eyre::WrapErr, Result, Section};
+use fs_err as fs;
+use tracing::{info, instrument};
+
+use super::run_command;
+
+/// Image with the Rust, wasm-tools & wasm-opt versions this version of kit
+/// is tested with, tagged by kit version
+const BUILDER_IMAGE: &str = "nick1udwig/buildpackage";
+/// Where the package dir is bind-mounted in the builder container
+const CONTAINER_PACKAGE_DIR: &str = "/input";
+
+pub fn get_builder_image() -> String {
+    format!("{BUILDER_IMAGE}:{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// `uid:gid` of the owner of `package_dir`, to give back the files the
+/// container writes as root
+fn get_owner(package_dir: &Path) -> Result<String> {
+    let metadata = fs::metadata(package_dir)?;
+    Ok(format!("{}:{}", metadata.uid(), metadata.gid()))
+}
+
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Run `kit build` with `build_args` inside the builder image pinned to
+/// this version of kit, with `package_dir` bind-mounted so that the build
+/// is written back to `package_dir/pkg/`
+#[instrument(level = "trace", skip_all)]
+pub fn build_in_docker(package_dir: &Path, build_args: &[String], verbose: bool) -> Result<()> {
+    let image = get_builder_image();
+    let source = package_dir.canonicalize()?;
+    let source = source.to_str().unwrap();
+    info!("Building {package_dir:?} in Docker image {image}...");
+
+    run_command(Command::new("docker").args(["pull", &image]), verbose)
+        .wrap_err_with(|| format!("Failed to pull {image}"))
+        .with_suggestion(|| "Is Docker installed and running?")?;
+
+    let kit_build = std::iter::once(format!("kit build {CONTAINER_PACKAGE_DIR}"))
+        .chain(build_args.iter().map(|a| shell_quote(a)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let script = format!(
+        "{kit_build}; status=$?; chown -R {} {CONTAINER_PACKAGE_DIR}; exit $status",
+        get_owner(package_dir)?,
+    );
+    let args = [
+        "run",
+        "--rm",
+        // reach a node on the host to fetch dependencies from
+        "--network",
+        "host",
+        "--mount",
+        &format!("type=bind,source={source},target={CONTAINER_PACKAGE_DIR}"),
+        "--workdir",
+        CONTAINER_PACKAGE_DIR,
+        "--entrypoint",
+        "sh",
+        &image,
+        "-c",
+        &script,
+    ];
+    run_command(Command::new("docker").args(args), true)
+        .wrap_err_with(|| format!("Failed to build {package_dir:?} in {image}"))?;
+
+    info!("Done building {package_dir:?} in Docker image {image}.");
+    Ok(())
+}
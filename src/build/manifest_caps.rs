@@ -0,0 +1,165 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use color_eyre::{eyre::WrapErr, Result};
+use fs_err as fs;
+use regex::Regex;
+use tracing::{instrument, warn};
+
+use kinode_process_lib::kernel_types::PackageManifestEntry;
+
+use super::{read_metadata, RUST_SRC_PATH};
+
+/// Interfaces of `kit run-tests`' harness, which messages test processes
+/// with capabilities of its own
+const HARNESS_INTERFACES: &[&str] = &["tester"];
+
+#[derive(Debug, Default)]
+pub struct WitApis {
+    /// world name -> names of the interfaces it imports
+    pub world_imports: BTreeMap<String, BTreeSet<String>>,
+    /// every interface defined outside `kinode.wit` -> the package
+    /// defining it, from the `<package>:<publisher>-v<n>.wit` file name
+    interfaces: BTreeMap<String, String>,
+}
+
+/// `chat`, `chat:template.os/chat` & `chat:template.os/chat@0.1.0` -> `chat`
+fn get_interface_name(import: &str) -> String {
+    let import = import.split('@').next().unwrap_or_default();
+    import.rsplit('/').next().unwrap_or_default().to_string()
+}
+
+/// `http_client` -> `http-client`: process & package names and WIT
+/// interface names differ only in `_` vs `-`
+fn normalize_name(name: &str) -> String {
+    name.replace('_', "-")
+}
+
+/// `chat:chat:template.os` -> (`chat`, `chat`)
+fn parse_issuer(process_id: &str) -> (String, String) {
+    let mut parts = process_id.split(':');
+    let name = normalize_name(parts.next().unwrap_or_default());
+    let package = normalize_name(parts.next().unwrap_or_default());
+    (name, package)
+}
+
+/// The package's API & those of its dependencies, as placed in a built
+/// process's `target/wit/`
+pub fn parse_wit_apis(wit_dir: &Path) -> Result<WitApis> {
+    let world_re = Regex::new(r"(?s)\bworld\s+%?([\w\-]+)\s*\{(.*?)\}").unwrap();
+    let import_re = Regex::new(r"\bimport\s+%?([\w\-:/@\.]+)\s*;").unwrap();
+    let interface_re = Regex::new(r"(?m)^\s*interface\s+%?([\w\-]+)").unwrap();
+    let mut apis = WitApis::default();
+    for entry in fs::read_dir(wit_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("wit")
+            || path.file_name().and_then(|f| f.to_str()) == Some("kinode.wit")
+        {
+            continue;
+        }
+        let package = path
+            .file_name()
+            .and_then(|f| f.to_str())
+            .and_then(|f| f.split(':').next())
+            .map(normalize_name)
+            .unwrap_or_default();
+        let wit = fs::read_to_string(&path)?;
+        for interface in interface_re.captures_iter(&wit) {
+            apis.interfaces
+                .insert(interface[1].to_string(), package.clone());
+        }
+        for world in world_re.captures_iter(&wit) {
+            let imports = import_re
+                .captures_iter(&world[2])
+                .map(|i| get_interface_name(&i[1]))
+                .collect();
+            apis.world_imports.insert(world[1].to_string(), imports);
+        }
+    }
+    Ok(apis)
+}
+
+/// The world a Rust process is built against, from `wit_bindgen::generate!`
+pub fn find_world(process_dir: &Path) -> Result<Option<String>> {
+    let world_re = Regex::new(r#"world:\s*"([\w\-]+)""#).unwrap();
+    let source = fs::read_to_string(process_dir.join(RUST_SRC_PATH))?;
+    Ok(world_re.captures(&source).map(|w| w[1].to_string()))
+}
+
+/// Warn where `pkg/manifest.json` & WIT disagree: a process that imports
+/// an interface but neither requests nor grants a capability for a process
+/// of the package serving it, or that requests a capability from a process
+/// of a package with an API it does not import; interfaces & processes of
+/// the process's own package are not checked
+#[instrument(level = "trace", skip_all)]
+pub fn check_manifest_capabilities(package_dir: &Path) -> Result<()> {
+    let manifest_path = package_dir.join("pkg").join("manifest.json");
+    if !manifest_path.exists() {
+        return Ok(());
+    }
+    let manifest: Vec<PackageManifestEntry> =
+        serde_json::from_reader(fs::File::open(&manifest_path)?)
+            .wrap_err_with(|| format!("Failed to parse {manifest_path:?}"))?;
+    let own_package = normalize_name(&read_metadata(package_dir)?.properties.package_name);
+
+    for entry in manifest {
+        let process_dir_name = entry
+            .process_wasm_path
+            .trim_start_matches('/')
+            .trim_end_matches(".wasm");
+        let process_dir = package_dir.join(process_dir_name);
+        let wit_dir = process_dir.join("target").join("wit");
+        if !process_dir.join(RUST_SRC_PATH).exists() || !wit_dir.exists() {
+            continue;
+        }
+        let Some(world) = find_world(&process_dir)? else {
+            continue;
+        };
+        let apis = parse_wit_apis(&wit_dir)?;
+        let Some(imports) = apis.world_imports.get(&world) else {
+            continue;
+        };
+
+        let get_issuers = |capabilities: &Vec<serde_json::Value>| -> BTreeSet<(String, String)> {
+            capabilities
+                .iter()
+                .filter_map(|c| c.as_str().or_else(|| c["process"].as_str()))
+                .map(parse_issuer)
+                .collect()
+        };
+        let requested = get_issuers(&entry.request_capabilities);
+        // granting a capability lets the process serving an import message us
+        let granted = get_issuers(&entry.grant_capabilities);
+
+        for import in imports {
+            let import_package = apis.interfaces.get(import).cloned().unwrap_or_default();
+            let is_covered = requested
+                .iter()
+                .chain(granted.iter())
+                .any(|(name, package)| name == import || *package == import_package);
+            if HARNESS_INTERFACES.contains(&import.as_str())
+                || import_package == own_package
+                || is_covered
+            {
+                continue;
+            }
+            warn!(
+                "{}: world {world} imports interface `{import}` but {manifest_path:?} requests no capability from a process of package `{import_package}`: forgotten capability?",
+                entry.process_name,
+            );
+        }
+        for (name, package) in &requested {
+            let has_api = apis.interfaces.values().any(|p| p == package);
+            let is_imported = imports
+                .iter()
+                .any(|i| i == name || apis.interfaces.get(i) == Some(package));
+            if *package != own_package && has_api && !is_imported {
+                warn!(
+                    "{}: {manifest_path:?} requests a capability from `{name}:{package}` but world {world} imports none of its package's interfaces: unused capability or forgotten WIT import?",
+                    entry.process_name,
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tracing::{info, instrument};
+
+use super::run_command;
+
+#[derive(Debug)]
+pub struct InjectMock {
+    pub process_name: String,
+    pub mock_path: PathBuf,
+}
+
+/// Parse `--inject-mock <process-name>=<mock-wasm-path>` values
+pub fn parse_inject_mocks(inject_mocks: &[String]) -> Result<Vec<InjectMock>> {
+    inject_mocks
+        .iter()
+        .map(|inject_mock| {
+            let Some((process_name, mock_path)) = inject_mock.split_once('=') else {
+                return Err(eyre!("Invalid --inject-mock {inject_mock}")
+                    .with_suggestion(|| "Use the form `<process-name>=<mock-wasm-path>`."));
+            };
+            let mock_path = fs::canonicalize(mock_path)
+                .map_err(|e| eyre!("Could not find --inject-mock wasm {mock_path}: {e}"))?;
+            Ok(InjectMock {
+                process_name: process_name.to_string(),
+                mock_path,
+            })
+        })
+        .collect()
+}
+
+/// The WIT world a component targets, as printed by `wasm-tools component wit`
+fn get_component_world(wasm_path: &Path) -> Result<String> {
+    let Some((world, _)) = run_command(
+        Command::new("wasm-tools").args(["component", "wit", wasm_path.to_str().unwrap()]),
+        false,
+    )?
+    else {
+        return Err(eyre!("Failed to read WIT world of {wasm_path:?}"));
+    };
+    Ok(world)
+}
+
+/// Replace built processes in `package_dir/pkg/` with mock implementations,
+/// after checking that each mock targets the same WIT world as the real process
+#[instrument(level = "trace", skip_all)]
+pub fn inject_mocks(package_dir: &Path, inject_mocks: &[InjectMock]) -> Result<()> {
+    for InjectMock {
+        process_name,
+        mock_path,
+    } in inject_mocks
+    {
+        let wasm_path = package_dir
+            .join("pkg")
+            .join(format!("{}.wasm", process_name.replace('_', "-")));
+        if !wasm_path.exists() {
+            return Err(
+                eyre!("Cannot inject mock for {process_name}: no {wasm_path:?} was built")
+                    .with_suggestion(|| {
+                        "--inject-mock takes the name of a process in the package."
+                    }),
+            );
+        }
+        let world = get_component_world(&wasm_path)?;
+        let mock_world = get_component_world(mock_path)?;
+        if world != mock_world {
+            return Err(eyre!(
+                "Mock {mock_path:?} does not match the WIT world of {process_name}:\n{process_name}:\n{world}\nmock:\n{mock_world}"
+            ));
+        }
+        fs::copy(mock_path, &wasm_path)?;
+        info!("Injected mock {mock_path:?} for {process_name}.");
+    }
+    Ok(())
+}
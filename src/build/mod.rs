@@ -1,8 +1,9 @@
 use std::collections::{HashMap, HashSet};
 use std::io::{BufRead, Read, Write};
 use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::time::SystemTime;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 use color_eyre::{
     Section,
@@ -18,17 +19,70 @@ use tracing::{debug, info, instrument, warn};
 use walkdir::WalkDir;
 use zip::write::FileOptions;
 
-use kinode_process_lib::{kernel_types::Erc721Metadata, PackageId};
+use kinode_process_lib::{
+    kernel_types::{Erc721Metadata, PackageManifestEntry},
+    PackageId,
+};
 
+use crate::new::{replace_dots, snake_to_upper_camel_case};
 use crate::publish::make_local_file_link_path;
 use crate::run_tests::types::BroadcastRecvBool;
 use crate::setup::{
-    check_js_deps, check_py_deps, check_rust_deps, get_deps, get_newest_valid_node_version,
-    get_python_version, REQUIRED_PY_PACKAGE,
+    check_cargo_component_deps, check_js_deps, check_py_deps, check_rust_deps, get_deps,
+    get_newest_valid_node_version, get_python_version, REQUIRED_PY_PACKAGE,
 };
 use crate::view_api;
 use crate::KIT_CACHE;
 
+mod breaking;
+mod coverage;
+mod deprecations;
+mod docker;
+mod docs;
+mod embed;
+mod forbid;
+mod graph;
+mod in_docker;
+mod manifest_caps;
+mod mock;
+mod plugins;
+mod process_cache;
+mod reproducible;
+mod sandbox;
+mod sbom;
+mod sign;
+mod stats;
+mod symbols;
+mod unused_wit;
+mod validate_wit;
+mod wit_json;
+mod wit_registry;
+use breaking::{check_breaking_changes, read_api_zip};
+use coverage::{check_coverage_writer, get_coverage_rustflags, save_instrumented_module};
+pub use coverage::{get_coverage_dir, get_coverage_objects_dir};
+use deprecations::check_deprecations;
+use docker::write_docker_files;
+use docs::write_api_docs;
+use embed::{describe_embed_files, parse_embed_files, report_embedded_files, write_embedded_files};
+use forbid::check_forbidden_capabilities;
+pub use forbid::NETWORK_CAPABILITIES;
+use graph::write_process_graph;
+use in_docker::{build_in_docker, get_builder_image};
+use manifest_caps::{check_manifest_capabilities, find_world};
+use mock::{inject_mocks, parse_inject_mocks};
+use plugins::run_post_build_plugins;
+use process_cache::{cache_wasm, evict_stale_wasms, hash_process_inputs, restore_cached_wasm};
+use reproducible::{clean_target_dirs, compare_builds, read_pkg_wasms};
+use sandbox::{find_sandbox, Sandbox};
+use sbom::write_sbom;
+use sign::{sign_pkg, SIGNATURE_FILE_NAME};
+pub use stats::{print_build_stats, reset_build_stats};
+use stats::{record_builds, record_cache_hits, record_process_cache_hits};
+use symbols::write_symbol_maps;
+use unused_wit::report_unused_wit_types;
+use validate_wit::validate_wit_dir;
+use wit_json::write_wit_json;
+use wit_registry::{fetch_wit_dependencies, WitDependency};
 mod rewrite;
 use rewrite::copy_and_rewrite_package;
 
@@ -48,6 +102,133 @@ const WASI_VERSION: &str = "27.0.0"; // TODO: un-hardcode
 const DEFAULT_WORLD_0_7_0: &str = "process";
 const DEFAULT_WORLD_0_8_0: &str = "process-v0";
 const KINODE_PROCESS_LIB_CRATE_NAME: &str = "kinode_process_lib";
+/// Features enabled by default in Rust's `wasm32-wasip1` target
+const WASM_OPT_FEATURES: &[&str] = &[
+    "--enable-bulk-memory",
+    "--enable-multivalue",
+    "--enable-mutable-globals",
+    "--enable-nontrapping-float-to-int",
+    "--enable-reference-types",
+    "--enable-sign-ext",
+];
+const WASM_OPT_PATH_ENV_VAR: &str = "WASM_OPT_PATH";
+/// `wasm-opt -Os`: `kit build` always builds release, so optimize for size
+pub const DEFAULT_WASM_OPT_LEVEL: &str = "s";
+pub(crate) const KIT_TOML_NAME: &str = "kit.toml";
+const PUBLISHER_PREPROCESS_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "jsx", "ts", "tsx", "html", "json", "toml", "wit",
+];
+
+/// How to build a package: one field per `kit build` flag;
+///  `Default` is a plain `kit build`
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    pub no_ui: bool,
+    pub ui_only: bool,
+    pub include: HashSet<PathBuf>,
+    pub exclude: HashSet<PathBuf>,
+    pub skip_deps_check: bool,
+    pub features: String,
+    pub all_features: bool,
+    /// Build with each process's `default` feature, which kit otherwise disables
+    pub default_features: bool,
+    pub url: Option<String>,
+    pub download_from: Option<String>,
+    pub default_world: Option<String>,
+    pub local_dependencies: Vec<PathBuf>,
+    pub add_paths_to_api: Vec<PathBuf>,
+    pub rewrite: bool,
+    pub strip_custom_sections: bool,
+    pub wasm_opt_path: Option<String>,
+    /// `kit.toml`'s level, or `DEFAULT_WASM_OPT_LEVEL`, if `None`
+    pub wasm_opt_level: Option<String>,
+    pub publisher: Option<String>,
+    pub sign: Option<PathBuf>,
+    pub embed_files: Vec<String>,
+    pub inject_mock: Vec<String>,
+    pub emit_docs: bool,
+    pub graph: bool,
+    pub emit_wit_json: bool,
+    pub emit_symbols: bool,
+    pub warn_unused_wit_types: bool,
+    pub check_breaking: bool,
+    pub sbom: bool,
+    pub forbid_capabilities: Vec<String>,
+    pub docker: bool,
+    pub sandbox: bool,
+    pub coverage: bool,
+    pub reproducible: bool,
+    pub in_docker: bool,
+    pub verify_reproducible: bool,
+    pub no_cache: bool,
+    pub jobs: Option<usize>,
+    pub force: bool,
+    pub verbose: bool,
+    /// For internal use; may cause problems when adding recursive deps
+    pub ignore_deps: bool,
+}
+
+impl BuildOptions {
+    /// Options to build a dependency of this package with:
+    ///  its processes only, compiled the same way
+    fn for_dependency(&self, local_dependencies: Vec<PathBuf>, ignore_deps: bool) -> Self {
+        BuildOptions {
+            no_ui: true,
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            skip_deps_check: true,
+            features: self.features.clone(),
+            all_features: self.all_features,
+            default_features: self.default_features,
+            url: self.url.clone(),
+            download_from: self.download_from.clone(),
+            default_world: self.default_world.clone(),
+            local_dependencies,
+            rewrite: self.rewrite,
+            strip_custom_sections: self.strip_custom_sections,
+            wasm_opt_path: self.wasm_opt_path.clone(),
+            wasm_opt_level: self.wasm_opt_level.clone(),
+            sandbox: self.sandbox,
+            no_cache: self.no_cache,
+            jobs: self.jobs,
+            force: self.force,
+            verbose: self.verbose,
+            ignore_deps,
+            ..Default::default()
+        }
+    }
+}
+
+/// Per-package kit settings, read from `kit.toml` in the package dir
+#[derive(Debug, Default, Deserialize)]
+struct KitToml {
+    wasm_opt_path: Option<String>,
+    /// Used unless `--wasm-opt-level` is given
+    wasm_opt_level: Option<String>,
+    /// Used unless `--features` is given
+    #[serde(default)]
+    features: Vec<String>,
+    /// Processes & UIs not to build, unless `--include` or `--exclude` is given
+    #[serde(default)]
+    skip_processes: Vec<String>,
+    /// Appended to the `cargo build` of each Rust process
+    #[serde(default)]
+    extra_cargo_args: Vec<String>,
+    /// Default registry of `[[wit_dependency]]`s
+    wit_registry: Option<String>,
+    #[serde(default)]
+    wit_dependency: Vec<WitDependency>,
+    #[serde(default)]
+    plugins: KitPlugins,
+}
+
+/// `[plugins]` in `kit.toml`
+#[derive(Debug, Default, Deserialize)]
+struct KitPlugins {
+    /// Executables run, in order, on each built process's `pkg/` Wasm
+    #[serde(default)]
+    post_build: Vec<String>,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct CargoFile {
@@ -171,6 +352,21 @@ pub fn remove_missing_features(cargo_toml_path: &Path, features: Vec<&str>) -> R
         .collect())
 }
 
+/// Whether the process at `process_dir` configures the WIT `target` or
+/// `dependencies` of `cargo-component` & so needs it installed to build
+fn requires_cargo_component(process_dir: &Path) -> Result<bool> {
+    let cargo_toml: toml::Value = fs::read_to_string(process_dir.join("Cargo.toml"))?.parse()?;
+    let Some(component) = cargo_toml
+        .get("package")
+        .and_then(|p| p.get("metadata"))
+        .and_then(|m| m.get("component"))
+        .and_then(|c| c.as_table())
+    else {
+        return Ok(false);
+    };
+    Ok(component.contains_key("target") || component.contains_key("dependencies"))
+}
+
 /// Check if the first element is empty and there are no more elements
 #[instrument(level = "trace", skip_all)]
 fn is_only_empty_string(splitted: &Vec<&str>) -> bool {
@@ -228,6 +424,72 @@ pub fn run_command(cmd: &mut Command, verbose: bool) -> Result<Option<(String, S
     }
 }
 
+/// As `run_command`, but with each line of output prefixed by `[<prefix>]`
+/// so that the output of commands run concurrently remains attributable
+#[instrument(level = "trace", skip_all)]
+pub fn run_prefixed_command(
+    cmd: &mut Command,
+    prefix: &str,
+    verbose: bool,
+) -> Result<Option<(String, String)>> {
+    if !verbose {
+        let result = run_command(cmd, false).wrap_err_with(|| format!("[{prefix}] failed"))?;
+        return Ok(result.map(|(stdout, stderr)| {
+            (prefix_lines(&stdout, prefix), prefix_lines(&stderr, prefix))
+        }));
+    }
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let stdout_prefix = format!("[{prefix}]");
+    let stderr_prefix = stdout_prefix.clone();
+    let print_stdout = std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stdout)
+            .lines()
+            .map_while(|l| l.ok())
+        {
+            println!("{stdout_prefix} {line}");
+        }
+    });
+    let print_stderr = std::thread::spawn(move || {
+        for line in std::io::BufReader::new(stderr)
+            .lines()
+            .map_while(|l| l.ok())
+        {
+            eprintln!("{stderr_prefix} {line}");
+        }
+    });
+    let result = child.wait()?;
+    let _ = print_stdout.join();
+    let _ = print_stderr.join();
+    if !result.success() {
+        return Err(eyre!(
+            "[{prefix}] Command `{} {:?}` failed with exit code {:?}",
+            cmd.get_program().to_str().unwrap(),
+            cmd.get_args()
+                .map(|a| a.to_str().unwrap())
+                .collect::<Vec<_>>(),
+            result.code(),
+        ));
+    }
+    Ok(None)
+}
+
+fn prefix_lines(output: &str, prefix: &str) -> String {
+    output
+        .lines()
+        .map(|line| format!("[{prefix}] {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Number of logical CPUs: by default, how many processes compile at once
+fn get_default_jobs() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
 #[instrument(level = "trace", skip_all)]
 pub async fn download_file(url: &str, path: &Path) -> Result<()> {
     fs::create_dir_all(&KIT_CACHE)?;
@@ -388,7 +650,7 @@ fn get_world_or_default(directory: &Path, default_world: &str) -> String {
 }
 
 #[instrument(level = "trace", skip_all)]
-fn copy_dir(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
+pub fn copy_dir(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
     let src = src.as_ref();
     let dst = dst.as_ref();
     if !dst.exists() {
@@ -409,6 +671,205 @@ fn copy_dir(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
     Ok(())
 }
 
+fn read_kit_toml(package_dir: &Path) -> Result<KitToml> {
+    let kit_toml_path = package_dir.join(KIT_TOML_NAME);
+    if !kit_toml_path.exists() {
+        return Ok(KitToml::default());
+    }
+    toml::from_str(&fs::read_to_string(&kit_toml_path)?)
+        .wrap_err_with(|| format!("Failed to parse {kit_toml_path:?}"))
+}
+
+/// Use the given `wasm-opt` path, else `$WASM_OPT_PATH`, else `wasm_opt_path`
+/// from `kit.toml`; `None` means do not optimize
+fn get_wasm_opt_path(package_dir: &Path, wasm_opt_path: Option<&str>) -> Result<Option<String>> {
+    if let Some(wasm_opt_path) = wasm_opt_path {
+        return Ok(Some(wasm_opt_path.to_string()));
+    }
+    if let Ok(wasm_opt_path) = std::env::var(WASM_OPT_PATH_ENV_VAR) {
+        if !wasm_opt_path.is_empty() {
+            return Ok(Some(wasm_opt_path));
+        }
+    }
+    Ok(read_kit_toml(package_dir)?.wasm_opt_path)
+}
+
+/// Regexes rewriting the names derived from `old_publisher` to those derived
+/// from `publisher`: WIT worlds, e.g. `chat-template-dot-os-v0`, and the
+/// Python bindings' `chat_template_dot_os_v0` & `ChatTemplateDotOsV0`
+fn make_world_name_rewrites(
+    old_publisher: &str,
+    publisher: &str,
+) -> Result<Vec<(regex::Regex, String)>> {
+    let (old_snake, old_kebab) = replace_dots(old_publisher);
+    let (snake, kebab) = replace_dots(publisher);
+    let old_camel = snake_to_upper_camel_case(&old_snake);
+    let camel = snake_to_upper_camel_case(&snake);
+    [
+        (
+            format!("-{}-v", regex::escape(&old_kebab)),
+            format!("-{kebab}-v"),
+        ),
+        (
+            format!("_{}_v", regex::escape(&old_snake)),
+            format!("_{snake}_v"),
+        ),
+        (
+            format!("{}V", regex::escape(&old_camel)),
+            format!("{camel}V"),
+        ),
+    ]
+    .into_iter()
+    .map(|(old, new)| {
+        Ok((
+            regex::Regex::new(&format!(r"(?P<name>\w){old}(?P<version>\d+)\b"))?,
+            format!("${{name}}{new}${{version}}"),
+        ))
+    })
+    .collect()
+}
+
+/// Copy `source_dir` to `package_dir/target/publisher/<publisher>/`, setting
+/// the publisher in `metadata.json`, in source address literals (both
+/// `{package_name}:{package_name}:{publisher}` placeholders and literals
+/// using the publisher from `metadata.json`, and `"<package_name>", "<publisher>"`
+/// pairs), in WIT file names & `package` lines, and in the world names
+/// derived from the publisher
+#[instrument(level = "trace", skip_all)]
+fn copy_and_set_publisher(
+    source_dir: &Path,
+    package_dir: &Path,
+    publisher: &str,
+) -> Result<PathBuf> {
+    let metadata = read_metadata(source_dir)?;
+    let package_name = metadata.properties.package_name.as_str();
+    let old_publisher = metadata.properties.publisher.as_str();
+    debug!("Setting publisher {old_publisher} -> {publisher} for {package_name}...");
+
+    let publisher_dir = package_dir.join("target").join("publisher").join(publisher);
+    if publisher_dir.exists() {
+        // keep `target/` for incremental builds
+        for entry in fs::read_dir(&publisher_dir)? {
+            let path = entry?.path();
+            if path.file_name().and_then(|f| f.to_str()) == Some("target") {
+                continue;
+            }
+            if path.is_dir() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+    }
+    fs::create_dir_all(&publisher_dir)?;
+
+    // the package & any nested ones, e.g. under `test/`, with the same publisher
+    let mut package_names = vec![package_name.to_string()];
+    for entry in WalkDir::new(source_dir)
+        .min_depth(2)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "target")
+    {
+        let entry = entry?;
+        if entry.file_name() != "metadata.json" {
+            continue;
+        }
+        if let Ok(nested) = read_metadata(entry.path().parent().unwrap()) {
+            if nested.properties.publisher == old_publisher {
+                package_names.push(nested.properties.package_name);
+            }
+        }
+    }
+    let package_names = package_names
+        .iter()
+        .map(|p| regex::escape(p))
+        .collect::<Vec<_>>()
+        .join("|");
+
+    let address_regex = regex::Regex::new(&format!(
+        r"(?P<process>\{{package_name\}}|[\w\-]+):(?P<package>\{{package_name\}}|{package_names}):(?:\{{publisher\}}|{}\b)",
+        regex::escape(old_publisher),
+    ))?;
+    // `<package_name>:<publisher>`, as in API WIT file names, e.g.
+    // `chat:template.os-v0.wit`, & `metadata.json` dependencies
+    let package_id_regex = regex::Regex::new(&format!(
+        r"\b(?P<package>{package_names}):{}\b",
+        regex::escape(old_publisher),
+    ))?;
+    let package_id_replacement = format!("${{package}}:{publisher}");
+    // `"<package_name>", "<publisher>"`, as in `ProcessId::new()` & address tuples
+    let quoted_package_regex = regex::Regex::new(&format!(
+        r#""(?P<package>{package_names})",(?P<space>\s*)"{}""#,
+        regex::escape(old_publisher),
+    ))?;
+    let quoted_package_replacement = format!(r#""${{package}}",${{space}}"{publisher}""#);
+    let world_name_rewrites = make_world_name_rewrites(old_publisher, publisher)?;
+
+    for entry in WalkDir::new(source_dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "target")
+    {
+        let entry = entry?;
+        let mut destination = publisher_dir.join(entry.path().strip_prefix(source_dir)?);
+        if entry.path().extension().and_then(|e| e.to_str()) == Some("wit") {
+            let file_name = entry.file_name().to_string_lossy();
+            let file_name =
+                package_id_regex.replace_all(&file_name, package_id_replacement.as_str());
+            destination.set_file_name(file_name.as_ref());
+        }
+        if entry.file_type().is_dir() {
+            fs::create_dir_all(&destination)?;
+            continue;
+        }
+        let is_source = entry
+            .path()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| PUBLISHER_PREPROCESS_EXTENSIONS.contains(&e))
+            .unwrap_or(false);
+        let content = if is_source {
+            fs::read_to_string(entry.path()).ok()
+        } else {
+            None
+        };
+        let Some(content) = content else {
+            fs::copy(entry.path(), &destination)?;
+            continue;
+        };
+        let content = address_regex.replace_all(&content, |caps: &regex::Captures| {
+            let process = match &caps["process"] {
+                "{package_name}" => package_name,
+                process => process,
+            };
+            let package = match &caps["package"] {
+                "{package_name}" => package_name,
+                package => package,
+            };
+            format!("{process}:{package}:{publisher}")
+        });
+        let content = package_id_regex.replace_all(&content, package_id_replacement.as_str());
+        let mut content = quoted_package_regex
+            .replace_all(&content, quoted_package_replacement.as_str())
+            .into_owned();
+        for (regex, replacement) in &world_name_rewrites {
+            content = regex
+                .replace_all(&content, replacement.as_str())
+                .into_owned();
+        }
+        if entry.file_name() == "metadata.json" {
+            let mut metadata: serde_json::Value = serde_json::from_str(&content)?;
+            if metadata["properties"]["publisher"] == old_publisher {
+                metadata["properties"]["publisher"] =
+                    serde_json::Value::String(publisher.to_string());
+                content = serde_json::to_string_pretty(&metadata)?;
+            }
+        }
+        fs::write(&destination, content)?;
+    }
+
+    Ok(publisher_dir)
+}
+
 fn file_with_extension_exists(dir: &Path, extension: &str) -> bool {
     if let Ok(entries) = fs::read_dir(dir) {
         for entry in entries.filter_map(Result::ok) {
@@ -512,6 +973,82 @@ fn check_process_lib_version(cargo_toml_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Check that every process listed in `pkg/manifest.json` either ships prebuilt
+/// in `pkg/` or has a process directory containing source to build
+#[instrument(level = "trace", skip_all)]
+fn check_manifest_processes_exist(package_dir: &Path) -> Result<()> {
+    let manifest_path = package_dir.join("pkg").join("manifest.json");
+    if !manifest_path.exists() {
+        // missing manifest is reported by `start-package`
+        return Ok(());
+    }
+    let manifest: Vec<PackageManifestEntry> =
+        serde_json::from_reader(fs::File::open(&manifest_path)?)
+            .wrap_err_with(|| format!("Failed to parse {manifest_path:?}"))?;
+
+    let mut missing = vec![];
+    for entry in manifest {
+        let Some(process_dir_name) = entry
+            .process_wasm_path
+            .strip_prefix('/')
+            .and_then(|s| s.strip_suffix(".wasm"))
+        else {
+            // malformed paths are reported by `start-package`
+            continue;
+        };
+        if package_dir
+            .join("pkg")
+            .join(format!("{process_dir_name}.wasm"))
+            .exists()
+        {
+            // prebuilt process shipped in `pkg/`: nothing to build
+            continue;
+        }
+        // wasm files are kebab-case but process dirs may be snake_case
+        let process_dir = [
+            process_dir_name.to_string(),
+            process_dir_name.replace('-', "_"),
+        ]
+        .iter()
+        .map(|name| package_dir.join(name))
+        .find(|dir| dir.is_dir());
+        let Some(process_dir) = process_dir else {
+            missing.push(format!(
+                "{}: no directory {:?}",
+                entry.process_name,
+                package_dir.join(process_dir_name),
+            ));
+            continue;
+        };
+        if process_dir.join(RUST_SRC_PATH).exists() {
+            if !process_dir.join("Cargo.toml").exists() {
+                missing.push(format!(
+                    "{}: no Cargo.toml in {process_dir:?}",
+                    entry.process_name,
+                ));
+            }
+        } else if !process_dir.join(PYTHON_SRC_PATH).exists()
+            && !process_dir.join(JAVASCRIPT_SRC_PATH).exists()
+        {
+            missing.push(format!(
+                "{}: no {RUST_SRC_PATH}, {PYTHON_SRC_PATH}, or {JAVASCRIPT_SRC_PATH} in {process_dir:?}",
+                entry.process_name,
+            ));
+        }
+    }
+
+    if !missing.is_empty() {
+        return Err(eyre!(
+            "Processes listed in {manifest_path:?} are missing their sources:\n{}",
+            missing.join("\n"),
+        )
+        .with_suggestion(|| {
+            "Check for typos in `process_wasm_path` or remove deleted processes from manifest.json."
+        }));
+    }
+    Ok(())
+}
+
 /// Scans all .rs files in a directory recursively and returns the most recent
 /// modification time of any included file
 pub fn get_latest_include_mod_time<P: AsRef<Path>>(dir: P) -> Result<Option<SystemTime>> {
@@ -837,17 +1374,19 @@ async fn compile_javascript_wasm_process(
         })
         .unwrap_or_else(|| (install, componentize));
 
-    run_command(
+    run_prefixed_command(
         Command::new("bash")
             .args(&["-c", &install])
             .current_dir(process_dir),
+        wasm_file_name,
         verbose,
     )?;
 
-    run_command(
+    run_prefixed_command(
         Command::new("bash")
             .args(&["-c", &componentize])
             .current_dir(process_dir),
+        wasm_file_name,
         verbose,
     )?;
 
@@ -877,16 +1416,18 @@ async fn compile_python_wasm_process(
         world_name, wasm_file_name,
     );
 
-    run_command(
+    run_prefixed_command(
         Command::new(python)
             .args(&["-m", "venv", PY_VENV_NAME])
             .current_dir(process_dir),
+        wasm_file_name,
         verbose,
     )?;
-    run_command(
+    run_prefixed_command(
         Command::new("bash")
             .args(&["-c", &format!("{source} && {install} && {componentize}")])
             .current_dir(process_dir.join("src")),
+        wasm_file_name,
         verbose,
     )?;
 
@@ -897,12 +1438,31 @@ async fn compile_python_wasm_process(
 #[instrument(level = "trace", skip_all)]
 async fn compile_rust_wasm_process(
     process_dir: &Path,
-    features: &str,
-    verbose: bool,
+    options: &BuildOptions,
+    extra_cargo_args: &[String],
+    sandbox: Option<&Sandbox>,
 ) -> Result<()> {
+    let BuildOptions {
+        ref features,
+        all_features,
+        default_features,
+        ref wasm_opt_path,
+        emit_symbols,
+        coverage,
+        verbose,
+        ..
+    } = *options;
+    let wasm_opt_level = options
+        .wasm_opt_level
+        .as_deref()
+        .unwrap_or(DEFAULT_WASM_OPT_LEVEL);
     info!("Compiling Rust Kinode process in {:?}...", process_dir);
+    if coverage {
+        check_coverage_writer(process_dir)?;
+    }
 
     // Paths
+    let process_name = process_dir.file_name().and_then(|s| s.to_str()).unwrap();
     let wit_dir = process_dir.join("target").join("wit");
     let bindings_dir = process_dir
         .join("target")
@@ -935,7 +1495,6 @@ async fn compile_rust_wasm_process(
         "+nightly",
         "build",
         "--release",
-        "--no-default-features",
         "--target",
         "wasm32-wasip1",
         "--target-dir",
@@ -961,10 +1520,37 @@ async fn compile_rust_wasm_process(
         args.push("--features");
         args.push(&features);
     }
-    let result = run_command(
-        Command::new("cargo").args(&args).current_dir(process_dir),
-        verbose,
-    )?;
+    if all_features {
+        args.push("--all-features");
+    }
+    if !default_features {
+        args.push("--no-default-features");
+    }
+    args.extend(extra_cargo_args.iter().map(|a| a.as_str()));
+    let mut command = match sandbox {
+        None => {
+            let mut command = Command::new("cargo");
+            command.args(&args).current_dir(process_dir);
+            command
+        }
+        Some(sandbox) => {
+            // download outside the sandbox, where there is network;
+            //  fetching does not run any build scripts
+            run_prefixed_command(
+                Command::new("cargo")
+                    .args(["+nightly", "fetch", "--target", "wasm32-wasip1"])
+                    .current_dir(process_dir),
+                process_name,
+                verbose,
+            )?;
+            args.push("--offline");
+            sandbox.command("cargo", &args, process_dir)?
+        }
+    };
+    if coverage {
+        command.env("RUSTFLAGS", get_coverage_rustflags());
+    }
+    let result = run_prefixed_command(&mut command, process_name, verbose)?;
 
     if let Some((stdout, stderr)) = result {
         if stdout.contains("warning") {
@@ -997,7 +1583,31 @@ async fn compile_rust_wasm_process(
 
     let wasi_snapshot_file = Path::new("target/wasi_snapshot_preview1.wasm");
 
-    run_command(
+    if coverage {
+        save_instrumented_module(process_dir, &wasm_file_cab)?;
+    }
+
+    if let Some(wasm_opt_path) = wasm_opt_path.as_deref().filter(|_| !coverage) {
+        // optimize the core module: wasm-opt does not accept components
+        let wasm_file_cab = wasm_file_cab.to_str().unwrap();
+        let level = format!("-O{wasm_opt_level}");
+        let mut args = vec![wasm_file_cab, "-o", wasm_file_cab, &level];
+        args.extend_from_slice(WASM_OPT_FEATURES);
+        if emit_symbols {
+            // keep the name section, which wasm-opt otherwise drops
+            args.push("-g");
+        }
+        run_prefixed_command(
+            Command::new(wasm_opt_path)
+                .args(&args)
+                .current_dir(process_dir),
+            process_name,
+            verbose,
+        )
+        .wrap_err_with(|| format!("Failed to optimize {wasm_file_cab} with {wasm_opt_path}"))?;
+    }
+
+    run_prefixed_command(
         Command::new("wasm-tools")
             .args(&[
                 "component",
@@ -1009,6 +1619,7 @@ async fn compile_rust_wasm_process(
                 wasi_snapshot_file.to_str().unwrap(),
             ])
             .current_dir(process_dir),
+        process_name,
         verbose,
     )?;
 
@@ -1090,22 +1701,78 @@ async fn build_wit_dir(
 #[instrument(level = "trace", skip_all)]
 async fn compile_package_item(
     path: PathBuf,
-    features: String,
+    options: BuildOptions,
+    extra_cargo_args: Vec<String>,
+    sandbox: Option<Sandbox>,
     apis: HashMap<String, Vec<u8>>,
     world: String,
     wit_version: Option<u32>,
-    verbose: bool,
-) -> Result<()> {
+) -> Result<Option<(PathBuf, Option<Duration>)>> {
+    let BuildOptions {
+        ref features,
+        all_features,
+        default_features,
+        ref wasm_opt_path,
+        emit_symbols,
+        coverage,
+        no_cache,
+        verbose,
+        ..
+    } = options;
+    let wasm_opt_level = options
+        .wasm_opt_level
+        .as_deref()
+        .unwrap_or(DEFAULT_WASM_OPT_LEVEL);
     if path.is_dir() {
         let is_rust_process = path.join(RUST_SRC_PATH).exists();
         let is_py_process = path.join(PYTHON_SRC_PATH).exists();
         let is_js_process = path.join(JAVASCRIPT_SRC_PATH).exists();
-        if is_rust_process || is_py_process || is_js_process {
-            build_wit_dir(&path, &apis, wit_version).await?;
+        if !(is_rust_process || is_py_process || is_js_process) {
+            return Ok(None);
         }
+        let start = Instant::now();
+        build_wit_dir(&path, &apis, wit_version).await?;
+        let process_world = if is_rust_process {
+            find_world(&path)?
+        } else {
+            None
+        };
+        validate_wit_dir(
+            path.parent().unwrap(),
+            &path.join("target").join("wit"),
+            process_world.as_deref(),
+        )?;
 
         if is_rust_process {
-            compile_rust_wasm_process(&path, &features, verbose).await?;
+            let wasm_file_name = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap()
+                .replace("_", "-");
+            let wasm_path = path
+                .parent()
+                .unwrap()
+                .join("pkg")
+                .join(format!("{wasm_file_name}.wasm"));
+            // coverage builds also write the instrumented module to `target/`
+            let cache_key = if no_cache || coverage {
+                None
+            } else {
+                let settings = format!(
+                    "features: {features} all={all_features} default={default_features}\ncargo_args: {extra_cargo_args:?}\nwasm_opt: {wasm_opt_path:?} -O{wasm_opt_level} symbols={emit_symbols}\nworld: {world}\nwit_version: {wit_version:?}",
+                );
+                Some(hash_process_inputs(&path, &settings)?)
+            };
+            if let Some(ref cache_key) = cache_key {
+                if restore_cached_wasm(cache_key, &wasm_path)? {
+                    info!("{path:?} unchanged: using cached {wasm_path:?}.");
+                    return Ok(Some((path, None)));
+                }
+            }
+            compile_rust_wasm_process(&path, &options, &extra_cargo_args, sandbox.as_ref()).await?;
+            if let Some(ref cache_key) = cache_key {
+                cache_wasm(cache_key, &wasm_path)?;
+            }
         } else if is_py_process {
             let python = get_python_version(None, None)?
                 .ok_or_else(|| eyre!("kit requires Python 3.10 or newer"))?;
@@ -1114,8 +1781,9 @@ async fn compile_package_item(
             let valid_node = get_newest_valid_node_version(None, None)?;
             compile_javascript_wasm_process(&path, valid_node, &world, verbose).await?;
         }
+        return Ok(Some((path, Some(start.elapsed()))));
     }
-    Ok(())
+    Ok(None)
 }
 
 #[instrument(level = "trace", skip_all)]
@@ -1154,38 +1822,18 @@ async fn fetch_dependencies(
     dependencies: &Vec<String>,
     apis: &mut HashMap<String, Vec<u8>>,
     wasm_paths: &mut HashSet<PathBuf>,
-    url: Option<String>,
-    download_from: Option<&str>,
-    mut local_dependencies: Vec<PathBuf>,
-    features: &str,
-    default_world: Option<&str>,
-    include: &HashSet<PathBuf>,
-    exclude: &HashSet<PathBuf>,
-    rewrite: bool,
-    force: bool,
-    verbose: bool,
+    options: &BuildOptions,
 ) -> Result<()> {
-    if let Err(e) = Box::pin(execute(
-        package_dir,
-        true,
-        false,
-        include,
-        exclude,
-        true,
-        features,
-        url.clone(),
-        download_from,
-        default_world,
-        vec![], // TODO: what about deps-of-deps?
-        vec![],
-        rewrite,
-        false,
-        force,
-        verbose,
-        true,
-    ))
-    .await
-    {
+    let BuildOptions {
+        ref url,
+        ref download_from,
+        ref local_dependencies,
+        ..
+    } = *options;
+    let mut local_dependencies = local_dependencies.clone();
+    // TODO: what about deps-of-deps?
+    let self_options = options.for_dependency(vec![], true);
+    if let Err(e) = Box::pin(execute(package_dir, &self_options)).await {
         debug!("Failed to build self as dependency: {e:?}");
     } else if let Err(e) = fetch_local_built_dependency(apis, wasm_paths, package_dir) {
         debug!("Failed to fetch self as dependency: {e:?}");
@@ -1202,26 +1850,8 @@ async fn fetch_dependencies(
             .into_iter()
             .filter(|d| *d != canon_package_dir)
             .collect();
-        Box::pin(execute(
-            local_dependency,
-            true,
-            false,
-            include,
-            exclude,
-            true,
-            features,
-            url.clone(),
-            download_from,
-            default_world,
-            local_dep_deps,
-            vec![],
-            rewrite,
-            false,
-            force,
-            verbose,
-            false,
-        ))
-        .await?;
+        let dep_options = options.for_dependency(local_dep_deps, false);
+        Box::pin(execute(local_dependency, &dep_options)).await?;
         fetch_local_built_dependency(apis, wasm_paths, &local_dependency)?;
     }
     let Some(ref url) = url else {
@@ -1243,7 +1873,7 @@ async fn fetch_dependencies(
             continue;
         }
         let Some(zip_dir) =
-            view_api::execute(None, Some(dependency), url, download_from, false).await?
+            view_api::execute(None, Some(dependency), url, download_from.as_deref(), false).await?
         else {
             return Err(eyre!(
                 "Got unexpected result from fetching API for {dependency}"
@@ -1408,6 +2038,7 @@ async fn check_and_populate_dependencies(
     verbose: bool,
 ) -> Result<(HashMap<String, Vec<u8>>, HashSet<String>)> {
     let mut checked_rust = false;
+    let mut checked_cargo_component = false;
     let mut checked_py = false;
     let mut checked_js = false;
     let mut apis = HashMap::new();
@@ -1420,10 +2051,18 @@ async fn check_and_populate_dependencies(
         let entry = entry?;
         let path = entry.path();
         if path.is_dir() {
-            if path.join(RUST_SRC_PATH).exists() && !checked_rust && !skip_deps_check {
-                let deps = check_rust_deps()?;
-                get_deps(deps, &mut recv_kill, verbose).await?;
-                checked_rust = true;
+            if path.join(RUST_SRC_PATH).exists() {
+                if !checked_rust && !skip_deps_check {
+                    let deps = check_rust_deps()?;
+                    get_deps(deps, &mut recv_kill, verbose).await?;
+                    checked_rust = true;
+                }
+                // kit cannot fetch cargo-component, so it is checked for even
+                //  under `--skip-deps-check`, rather than fail mid-build
+                if !checked_cargo_component && requires_cargo_component(&path)? {
+                    check_cargo_component_deps()?;
+                    checked_cargo_component = true;
+                }
             } else if path.join(PYTHON_SRC_PATH).exists() && !checked_py {
                 check_py_deps()?;
                 checked_py = true;
@@ -1457,6 +2096,32 @@ async fn check_and_populate_dependencies(
     Ok((apis, dependencies))
 }
 
+#[instrument(level = "trace", skip_all)]
+fn strip_wasm_custom_sections(package_dir: &Path, verbose: bool) -> Result<()> {
+    for entry in fs::read_dir(package_dir.join("pkg"))? {
+        let entry = entry?;
+        let path = entry.path();
+        if !(path.is_file() && Some("wasm") == path.extension().and_then(|e| e.to_str())) {
+            continue;
+        }
+        let path_str = path.to_str().unwrap();
+        let size_before = fs::metadata(&path)?.len();
+        run_command(
+            Command::new("wasm-tools").args(["strip", "--all", path_str, "-o", path_str]),
+            verbose,
+        )?;
+        let size_after = fs::metadata(&path)?.len();
+        info!(
+            "Stripped custom sections from {:?}: {} -> {} bytes ({:.1}% smaller)",
+            path.file_name().unwrap_or_default(),
+            size_before,
+            size_after,
+            100.0 * size_before.saturating_sub(size_after) as f64 / size_before.max(1) as f64,
+        );
+    }
+    Ok(())
+}
+
 #[instrument(level = "trace", skip_all)]
 fn zip_api(
     package_dir: &Path,
@@ -1522,20 +2187,23 @@ fn is_cluded(path: &Path, include: &HashSet<PathBuf>, exclude: &HashSet<PathBuf>
 #[instrument(level = "trace", skip_all)]
 async fn compile_package(
     package_dir: &Path,
-    skip_deps_check: bool,
-    features: &str,
-    url: Option<String>,
-    default_world: Option<&str>,
-    download_from: Option<&str>,
-    local_dependencies: Vec<PathBuf>,
-    add_paths_to_api: &Vec<PathBuf>,
-    include: &HashSet<PathBuf>,
-    exclude: &HashSet<PathBuf>,
-    rewrite: bool,
-    force: bool,
-    verbose: bool,
-    ignore_deps: bool, // for internal use; may cause problems when adding recursive deps
+    options: &BuildOptions,
+    sandbox: Option<&Sandbox>,
 ) -> Result<()> {
+    let BuildOptions {
+        skip_deps_check,
+        ref default_world,
+        ref add_paths_to_api,
+        ref include,
+        ref exclude,
+        strip_custom_sections,
+        emit_symbols,
+        jobs,
+        verbose,
+        no_cache,
+        ignore_deps,
+        ..
+    } = *options;
     let metadata = read_and_update_metadata(package_dir)?;
     let mut wasm_paths = HashSet::new();
     let (mut apis, dependencies) =
@@ -1547,29 +2215,33 @@ async fn compile_package(
             &dependencies.iter().map(|s| s.to_string()).collect(),
             &mut apis,
             &mut wasm_paths,
-            url.clone(),
-            download_from,
-            local_dependencies.clone(),
-            features,
-            default_world,
-            include,
-            exclude,
-            rewrite,
-            force,
-            verbose,
+            options,
         )
         .await?;
     }
 
+    let kit_toml = read_kit_toml(package_dir)?;
+    fetch_wit_dependencies(
+        kit_toml.wit_registry.as_deref(),
+        &kit_toml.wit_dependency,
+        &mut apis,
+    )
+    .await?;
+
     let wit_world = default_world
+        .as_deref()
         .unwrap_or_else(|| match metadata.properties.wit_version {
             None => DEFAULT_WORLD_0_7_0,
             Some(0) | _ => DEFAULT_WORLD_0_8_0,
         })
         .to_string();
 
+    if !no_cache {
+        evict_stale_wasms()?;
+    }
+    let jobs = jobs.unwrap_or_else(get_default_jobs);
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(jobs));
     let mut tasks = tokio::task::JoinSet::new();
-    let features = features.to_string();
     for entry in fs::read_dir(package_dir)? {
         let Ok(entry) = entry else {
             continue;
@@ -1578,18 +2250,33 @@ async fn compile_package(
         if !is_cluded(&path, include, exclude) {
             continue;
         }
-        tasks.spawn(compile_package_item(
+        let semaphore = Arc::clone(&semaphore);
+        let item = compile_package_item(
             path,
-            features.clone(),
+            options.clone(),
+            kit_toml.extra_cargo_args.clone(),
+            sandbox.cloned(),
             apis.clone(),
             wit_world.clone(),
             metadata.properties.wit_version,
-            verbose.clone(),
-        ));
+        );
+        tasks.spawn(async move {
+            // at most `jobs` processes compile at once
+            let _permit = semaphore.acquire_owned().await?;
+            item.await
+        });
     }
+    let mut builds = vec![];
+    let mut cached = vec![];
     while let Some(res) = tasks.join_next().await {
-        res??;
+        match res?? {
+            Some((path, Some(duration))) => builds.push((path, duration)),
+            Some((path, None)) => cached.push(path),
+            None => {}
+        }
     }
+    record_builds(&builds);
+    record_process_cache_hits(&cached);
 
     // create a target/api/ dir: this will be zipped & published in pkg/
     //  In addition, exporters, below, will be placed here to complete the API
@@ -1640,6 +2327,25 @@ async fn compile_package(
         }
     }
 
+    if emit_symbols {
+        // before stripping, which drops the name section symbols come from
+        write_symbol_maps(package_dir)?;
+    }
+    if strip_custom_sections {
+        strip_wasm_custom_sections(package_dir, verbose)?;
+    }
+
+    let process_dirs: Vec<PathBuf> = builds
+        .iter()
+        .map(|(path, _)| path.clone())
+        .chain(cached)
+        .collect();
+    run_post_build_plugins(
+        package_dir,
+        &read_kit_toml(package_dir)?.plugins.post_build,
+        &process_dirs,
+    )?;
+
     if target_api_dir.exists() {
         // zip & place API inside of pkg/ to publish API
         zip_api(package_dir, &target_api_dir, add_paths_to_api, &metadata)?;
@@ -1649,44 +2355,44 @@ async fn compile_package(
 }
 
 #[instrument(level = "trace", skip_all)]
-pub async fn execute(
-    package_dir: &Path,
-    no_ui: bool,
-    ui_only: bool,
-    include: &HashSet<PathBuf>,
-    exclude: &HashSet<PathBuf>,
-    skip_deps_check: bool,
-    features: &str,
-    url: Option<String>,
-    download_from: Option<&str>,
-    default_world: Option<&str>,
-    local_dependencies: Vec<PathBuf>,
-    add_paths_to_api: Vec<PathBuf>,
-    rewrite: bool,
-    reproducible: bool,
-    force: bool,
-    verbose: bool,
-    ignore_deps: bool, // for internal use; may cause problems when adding recursive deps
-) -> Result<()> {
-    debug!(
-        "execute:
-    package_dir={package_dir:?},
-    no_ui={no_ui},
-    ui_only={ui_only},
-    include={include:?},
-    exclude={exclude:?},
-    skip_deps_check={skip_deps_check},
-    features={features},
-    url={url:?},
-    download_from={download_from:?},
-    default_world={default_world:?},
-    local_dependencies={local_dependencies:?},
-    add_paths_to_api={add_paths_to_api:?},
-    reproducible={reproducible},
-    force={force},
-    verbose={verbose},
-    ignore_deps={ignore_deps},"
-    );
+pub async fn execute(package_dir: &Path, options: &BuildOptions) -> Result<()> {
+    debug!("execute: package_dir={package_dir:?}, options={options:#?}");
+    let BuildOptions {
+        no_ui,
+        ui_only,
+        ref include,
+        skip_deps_check,
+        all_features,
+        default_features,
+        ref url,
+        ref download_from,
+        ref default_world,
+        rewrite,
+        strip_custom_sections,
+        ref publisher,
+        ref sign,
+        ref embed_files,
+        ref inject_mock,
+        emit_docs,
+        graph,
+        emit_wit_json,
+        emit_symbols,
+        warn_unused_wit_types,
+        check_breaking,
+        sbom,
+        ref forbid_capabilities,
+        docker,
+        sandbox,
+        coverage,
+        reproducible,
+        in_docker,
+        verify_reproducible,
+        no_cache,
+        jobs,
+        force,
+        verbose,
+        ..
+    } = *options;
     if no_ui && ui_only {
         return Err(eyre!(
             "Cannot set both `no_ui` and `ui_only` to true at the same time"
@@ -1703,37 +2409,149 @@ pub async fn execute(
         )
         .with_suggestion(|| "Please re-run targeting a package."));
     }
+    if verify_reproducible {
+        // build twice from scratch & compare the outputs
+        let mut builds = vec![];
+        for build in ["first", "second"] {
+            info!("Verifying reproducibility: {build} build...");
+            clean_target_dirs(package_dir)?;
+            Box::pin(execute(
+                package_dir,
+                &BuildOptions {
+                    verify_reproducible: false,
+                    no_cache: true,
+                    force: true,
+                    ..options.clone()
+                },
+            ))
+            .await?;
+            builds.push(read_pkg_wasms(package_dir)?);
+        }
+        return compare_builds(package_dir, &builds[0], &builds[1]);
+    }
+    if in_docker {
+        // the build in the container checks whether it is up-to-date itself
+        let mut build_args = vec![];
+        let flags = [
+            (no_ui, "--no-ui"),
+            (ui_only, "--ui-only"),
+            (skip_deps_check, "--skip-deps-check"),
+            (all_features, "--all-features"),
+            (default_features, "--default-features"),
+            (rewrite, "--rewrite"),
+            (strip_custom_sections, "--strip-custom-sections"),
+            (emit_docs, "--emit-docs"),
+            (graph, "--graph"),
+            (emit_wit_json, "--emit-wit-json"),
+            (emit_symbols, "--emit-symbols"),
+            (warn_unused_wit_types, "--warn-unused-wit-types"),
+            (check_breaking, "--check-breaking"),
+            (sbom, "--sbom"),
+            (docker, "--docker"),
+            (no_cache, "--no-cache"),
+            (force, "--force"),
+            (verbose, "--verbose"),
+        ];
+        for (is_set, flag) in flags {
+            if is_set {
+                build_args.push(flag.to_string());
+            }
+        }
+        let options = [
+            (
+                "--features",
+                Some(options.features.clone()).filter(|f| !f.is_empty()),
+            ),
+            (
+                "--port",
+                url.as_ref()
+                    .and_then(|u| u.rsplit(':').next())
+                    .map(|p| p.to_string()),
+            ),
+            ("--download-from", download_from.clone()),
+            ("--world", default_world.clone()),
+            ("--jobs", jobs.map(|j| j.to_string())),
+            ("--wasm-opt-level", options.wasm_opt_level.clone()),
+        ];
+        for (option, value) in options {
+            if let Some(value) = value {
+                build_args.extend([option.to_string(), value]);
+            }
+        }
+        for forbidden in forbid_capabilities {
+            build_args.extend(["--forbid-capabilities".to_string(), forbidden.clone()]);
+        }
+        return build_in_docker(package_dir, &build_args, verbose);
+    }
+
+    // `kit.toml` settings apply where no flag overrides them
+    let kit_toml = read_kit_toml(package_dir)?;
+    let wasm_opt_level = options
+        .wasm_opt_level
+        .clone()
+        .or(kit_toml.wasm_opt_level.clone())
+        .unwrap_or_else(|| DEFAULT_WASM_OPT_LEVEL.to_string());
+    let options = &BuildOptions {
+        features: if options.features.is_empty() {
+            kit_toml.features.join(",")
+        } else {
+            options.features.clone()
+        },
+        exclude: if include.is_empty() && options.exclude.is_empty() {
+            kit_toml
+                .skip_processes
+                .iter()
+                .map(|p| package_dir.join(p))
+                .collect()
+        } else {
+            options.exclude.clone()
+        },
+        wasm_opt_path: get_wasm_opt_path(package_dir, options.wasm_opt_path.as_deref())?
+            .filter(|_| wasm_opt_level != "none"),
+        wasm_opt_level: Some(wasm_opt_level),
+        ..options.clone()
+    };
+    let features = &options.features;
+    let exclude = &options.exclude;
     let build_with_features_path = package_dir.join("target").join("build_with_features.txt");
+    let features_record = format!("{features}\nall: {all_features}\ndefault: {default_features}");
     let build_with_cludes_path = package_dir.join("target").join("build_with_cludes.txt");
-    let cludes = format!("include: {include:?}\nexclude: {exclude:?}");
+    let embed_files = parse_embed_files(embed_files)?;
+    let inject_mock = parse_inject_mocks(inject_mock)?;
+    check_forbidden_capabilities(package_dir, forbid_capabilities)?;
+    let cludes = format!(
+        "include: {include:?}\nexclude: {exclude:?}\nembed: {}\nmock: {inject_mock:?}\ndocs: {emit_docs}\ngraph: {graph}\nwit_json: {emit_wit_json}\ndocker: {docker}\ncoverage: {coverage}",
+        describe_embed_files(&embed_files)?,
+    );
+    // `--publisher` builds happen in a copy, so `package_dir/pkg/` says nothing about them
     if !force
+        && publisher.is_none()
+        && sign.is_none()
         && is_up_to_date(
             &build_with_features_path,
             &build_with_cludes_path,
-            features,
+            &features_record,
             &cludes,
             package_dir,
         )?
     {
+        record_cache_hits(package_dir);
         return Ok(());
     }
 
     if reproducible {
-        let version = env!("CARGO_PKG_VERSION");
+        let image = get_builder_image();
         let source = package_dir.canonicalize().unwrap();
         let source = source.to_str().unwrap();
         // get latest version of image
-        run_command(
-            Command::new("docker").args(&["pull", &format!("nick1udwig/buildpackage:{version}")]),
-            true,
-        )?;
+        run_command(Command::new("docker").args(["pull", &image]), true)?;
         run_command(
             Command::new("docker").args(&[
                 "run",
                 "--rm",
                 "--mount",
                 &format!("type=bind,source={source},target=/input"),
-                &format!("nick1udwig/buildpackage:{version}"),
+                &image,
             ]),
             true,
         )?;
@@ -1741,7 +2559,7 @@ pub async fn execute(
     }
 
     fs::create_dir_all(package_dir.join("target"))?;
-    fs::write(&build_with_features_path, features)?;
+    fs::write(&build_with_features_path, &features_record)?;
     fs::write(&build_with_cludes_path, &cludes)?;
 
     check_process_lib_version(&package_dir.join("Cargo.toml"))?;
@@ -1754,6 +2572,15 @@ pub async fn execute(
     } else {
         copy_and_rewrite_package(package_dir)?
     };
+    let live_dir = match publisher {
+        None => live_dir,
+        Some(publisher) => copy_and_set_publisher(&live_dir, package_dir, publisher)?,
+    };
+
+    if !ui_only {
+        check_manifest_processes_exist(&live_dir)?;
+        write_embedded_files(&live_dir, &embed_files)?;
+    }
 
     let ui_dirs = get_ui_dirs(&live_dir, &include, &exclude)?;
     if !no_ui && !ui_dirs.is_empty() {
@@ -1769,36 +2596,139 @@ pub async fn execute(
     }
 
     if !ui_only {
-        compile_package(
-            &live_dir,
-            skip_deps_check,
-            features,
-            url,
-            default_world.clone(),
-            download_from,
-            local_dependencies,
-            &add_paths_to_api,
-            &include,
-            &exclude,
-            rewrite,
-            force,
-            verbose,
-            ignore_deps,
-        )
-        .await?;
+        let previous_api = if check_breaking {
+            read_api_zip(&live_dir)?
+        } else {
+            None
+        };
+        let sandbox = if sandbox { find_sandbox() } else { None };
+        compile_package(&live_dir, options, sandbox.as_ref()).await?;
+
+        check_deprecations(package_dir)?;
+        check_manifest_capabilities(&live_dir)?;
+        report_embedded_files(&live_dir, &embed_files)?;
+        inject_mocks(&live_dir, &inject_mock)?;
+        if emit_docs {
+            write_api_docs(&live_dir)?;
+        }
+        if graph {
+            write_process_graph(&live_dir)?;
+        }
+        if emit_wit_json {
+            write_wit_json(&live_dir)?;
+        }
+        if warn_unused_wit_types {
+            report_unused_wit_types(&live_dir)?;
+        }
+        if check_breaking {
+            check_breaking_changes(&live_dir, previous_api)?;
+        }
+        if sbom {
+            write_sbom(&live_dir)?;
+        }
     }
 
-    if rewrite {
+    if rewrite && publisher.is_none() {
         if package_dir.join("pkg").exists() {
             fs::remove_dir_all(package_dir.join("pkg"))?;
         }
         copy_dir(live_dir.join("pkg"), package_dir.join("pkg"))?;
     }
 
-    let metadata = read_metadata(package_dir)?;
+    let pkg_root = if publisher.is_none() {
+        package_dir
+    } else {
+        live_dir.as_path()
+    };
+    match sign {
+        Some(key_path) => sign_pkg(pkg_root, key_path)?,
+        None => {
+            // a signature over the previous build's `pkg/` no longer holds
+            let signature_path = pkg_root.join("pkg").join(SIGNATURE_FILE_NAME);
+            if signature_path.exists() {
+                info!("Removing stale {signature_path:?}");
+                fs::remove_file(&signature_path)?;
+            }
+        }
+    }
+
+    let metadata = read_metadata(&live_dir)?;
     let pkg_publisher = make_pkg_publisher(&metadata);
-    let (_zip_filename, hash_string) = zip_pkg(package_dir, &pkg_publisher)?;
+    let (zip_filename, hash_string) = if publisher.is_none() {
+        zip_pkg(package_dir, &pkg_publisher)?
+    } else {
+        // zip the preprocessed `pkg/` so that the committed `pkg/` is untouched
+        let zip_filename = make_zip_filename(package_dir, &pkg_publisher);
+        zip_directory(&live_dir.join("pkg"), zip_filename.to_str().unwrap())?;
+        let hash = hash_zip_pkg(&zip_filename)?;
+        (zip_filename, hash)
+    };
     info!("package zip hash: {hash_string}");
 
+    if docker {
+        write_docker_files(
+            package_dir,
+            &metadata,
+            &pkg_publisher,
+            &zip_filename,
+            &hash_string,
+        )?;
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process_with_cargo_toml(cargo_toml: &str) -> tempfile::TempDir {
+        let process_dir = tempfile::tempdir().unwrap();
+        fs::write(process_dir.path().join("Cargo.toml"), cargo_toml).unwrap();
+        process_dir
+    }
+
+    #[test]
+    fn requires_cargo_component_when_component_target_is_set() {
+        let process_dir = process_with_cargo_toml(
+            r#"
+[package]
+name = "foo"
+
+[package.metadata.component.target]
+path = "wit"
+"#,
+        );
+        assert!(requires_cargo_component(process_dir.path()).unwrap());
+    }
+
+    #[test]
+    fn requires_cargo_component_when_component_dependencies_are_set() {
+        let process_dir = process_with_cargo_toml(
+            r#"
+[package]
+name = "foo"
+
+[package.metadata.component.dependencies]
+"kinode:process" = { path = "wit" }
+"#,
+        );
+        assert!(requires_cargo_component(process_dir.path()).unwrap());
+    }
+
+    #[test]
+    fn does_not_require_cargo_component_otherwise() {
+        let process_dir = process_with_cargo_toml(
+            r#"
+[package]
+name = "foo"
+
+[package.metadata.component]
+package = "kinode:foo"
+"#,
+        );
+        assert!(!requires_cargo_component(process_dir.path()).unwrap());
+        let process_dir = process_with_cargo_toml("[package]\nname = \"foo\"\n");
+        assert!(!requires_cargo_component(process_dir.path()).unwrap());
+    }
+}
@@ -0,0 +1,60 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use tracing::{info, instrument};
+
+/// `pkg/` Wasm of a process built from `process_dir`; Rust processes
+/// are written with `_`s in the dir name rewritten to `-`s
+fn process_wasm_path(package_dir: &Path, process_dir: &Path) -> Option<PathBuf> {
+    let name = process_dir.file_name()?.to_str()?;
+    [name.replace('_', "-"), name.to_string()]
+        .into_iter()
+        .map(|name| package_dir.join("pkg").join(format!("{name}.wasm")))
+        .find(|path| path.exists())
+}
+
+/// Run each `[plugins] post_build` executable from `kit.toml`, in order,
+/// on the `pkg/` Wasm of each process built; plugins may transform the
+/// Wasm in place
+#[instrument(level = "trace", skip_all)]
+pub fn run_post_build_plugins(
+    package_dir: &Path,
+    plugins: &[String],
+    process_dirs: &[PathBuf],
+) -> Result<()> {
+    if plugins.is_empty() {
+        return Ok(());
+    }
+    for process_dir in process_dirs {
+        let Some(wasm_path) = process_wasm_path(package_dir, process_dir) else {
+            continue;
+        };
+        for plugin in plugins {
+            // relative to the package dir if it exists there, else on `$PATH`
+            let plugin_path = package_dir.join(plugin);
+            let plugin_path = if plugin_path.exists() {
+                plugin_path
+            } else {
+                PathBuf::from(plugin)
+            };
+            let output = Command::new(&plugin_path)
+                .arg(&wasm_path)
+                .current_dir(package_dir)
+                .output()
+                .wrap_err_with(|| format!("Failed to run plugin {plugin:?}"))?;
+            if !output.status.success() {
+                return Err(eyre!(
+                    "Plugin {plugin:?} failed on {wasm_path:?} with {}\nstderr: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr),
+                ));
+            }
+            info!("Ran plugin {plugin:?} on {wasm_path:?}.");
+        }
+    }
+    Ok(())
+}
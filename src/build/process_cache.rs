@@ -0,0 +1,300 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use fs_err as fs;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use tracing::{debug, instrument};
+use walkdir::WalkDir;
+
+use super::embed::EMBEDDED_FILES_NAME;
+use crate::KIT_CACHE;
+
+const PROCESS_CACHE_DIR: &str = "process-wasm";
+/// Cached Wasm not built or restored for this long is evicted
+const PROCESS_CACHE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+/// Not inputs: build outputs & installed JS packages
+const SKIP_DIRS: &[&str] = &["target", "node_modules"];
+
+fn get_process_cache_dir() -> PathBuf {
+    PathBuf::from(KIT_CACHE).join(PROCESS_CACHE_DIR)
+}
+
+fn get_cached_wasm_path(key: &str) -> PathBuf {
+    get_process_cache_dir().join(format!("{key}.wasm"))
+}
+
+/// `rustc -vV` of the toolchain processes are built with, so that a
+/// `rustup update` changes every key
+fn get_toolchain_version(process_dir: &Path) -> Result<Vec<u8>> {
+    let output = Command::new("rustc")
+        .args(["+nightly", "-vV"])
+        .current_dir(process_dir)
+        .output()
+        .wrap_err("Failed to run `rustc +nightly -vV`")?;
+    if !output.status.success() {
+        return Err(eyre!(
+            "`rustc +nightly -vV` failed: {}",
+            String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// The `Cargo.lock` cargo resolves the process's dependencies with: that of
+/// the nearest dir above it holding one, i.e. its workspace root
+fn find_lockfile(process_dir: &Path) -> Option<PathBuf> {
+    process_dir
+        .ancestors()
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lockfile| lockfile.is_file())
+}
+
+/// Dirs of `path = "..."` dependencies in the process's `Cargo.toml`,
+/// e.g. crates shared between the processes of a package
+fn find_path_dependencies(process_dir: &Path) -> Result<Vec<PathBuf>> {
+    let cargo_toml = fs::read_to_string(process_dir.join("Cargo.toml"))?;
+    let path_re = Regex::new(r#"\bpath\s*=\s*"([^"]+)""#).unwrap();
+    Ok(path_re
+        .captures_iter(&cargo_toml)
+        .map(|p| process_dir.join(&p[1]))
+        .filter(|p| p.is_dir())
+        .collect())
+}
+
+fn hash_dir(hasher: &mut Sha256, dir: &Path, skip_dirs: &[&str]) -> Result<()> {
+    let mut files = BTreeSet::new();
+    for entry in WalkDir::new(dir).into_iter().filter_entry(|e| {
+        !(e.file_type().is_dir()
+            && e.file_name()
+                .to_str()
+                .map(|n| skip_dirs.contains(&n))
+                .unwrap_or(false))
+    }) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            files.insert(entry.into_path());
+        }
+    }
+    for file in files {
+        hash_file(hasher, dir, &file)?;
+    }
+    Ok(())
+}
+
+fn hash_file(hasher: &mut Sha256, root: &Path, file: &Path) -> Result<()> {
+    let relative = file.strip_prefix(root).unwrap_or(file);
+    hasher.update(relative.to_string_lossy().as_bytes());
+    hasher.update([0]);
+    hasher.update(fs::read(file)?);
+    hasher.update([0]);
+    Ok(())
+}
+
+/// Hash the package's generated `target/embedded_files.rs`, if any, and the
+/// contents of each file it `include_bytes!`s: `--embed-file`s live outside
+/// the process dir & `target/` is otherwise skipped
+fn hash_embedded_files(hasher: &mut Sha256, package_dir: &Path) -> Result<()> {
+    let embedded_files_path = package_dir.join("target").join(EMBEDDED_FILES_NAME);
+    if !embedded_files_path.exists() {
+        return Ok(());
+    }
+    hash_file(hasher, package_dir, &embedded_files_path)?;
+    let embedded_files = fs::read_to_string(&embedded_files_path)?;
+    let include_re = Regex::new(r#"include_bytes!\(("(?:[^"\\]|\\.)*")\)"#).unwrap();
+    for include in include_re.captures_iter(&embedded_files) {
+        let path: String = serde_json::from_str(&include[1])?;
+        hash_file(hasher, package_dir, Path::new(&path))?;
+    }
+    Ok(())
+}
+
+/// Content address of everything a Rust process's Wasm is built from:
+/// the toolchain, its source, its path dependencies, the package's
+/// `Cargo.toml`, the workspace `Cargo.lock`, its `--embed-file`s, and its
+/// `target/wit/` -- which holds the package's & dependencies' APIs, so a
+/// change to a shared WIT file changes the key of every process; `settings`
+/// covers how it is built, e.g. features
+#[instrument(level = "trace", skip_all)]
+pub fn hash_process_inputs(process_dir: &Path, settings: &str) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+    hasher.update([0]);
+    hasher.update(get_toolchain_version(process_dir)?);
+    hasher.update([0]);
+    hasher.update(settings.as_bytes());
+    hasher.update([0]);
+
+    hash_dir(&mut hasher, process_dir, SKIP_DIRS)?;
+    for path_dependency in find_path_dependencies(process_dir)? {
+        hash_dir(&mut hasher, &path_dependency, SKIP_DIRS)?;
+    }
+    if let Some(package_dir) = process_dir.parent() {
+        let cargo_toml_path = package_dir.join("Cargo.toml");
+        if cargo_toml_path.exists() {
+            hash_file(&mut hasher, package_dir, &cargo_toml_path)?;
+        }
+        hash_embedded_files(&mut hasher, package_dir)?;
+    }
+    if let Some(lockfile) = find_lockfile(process_dir) {
+        hash_file(&mut hasher, lockfile.parent().unwrap(), &lockfile)?;
+    }
+    hash_dir(&mut hasher, &process_dir.join("target").join("wit"), &[])?;
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Copy the Wasm cached under `key`, if any, to `wasm_path`;
+/// returns whether there was one
+pub fn restore_cached_wasm(key: &str, wasm_path: &Path) -> Result<bool> {
+    let cached_wasm_path = get_cached_wasm_path(key);
+    if !cached_wasm_path.exists() {
+        return Ok(false);
+    }
+    fs::copy(&cached_wasm_path, wasm_path)?;
+    // still in use: keep it from being evicted
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&cached_wasm_path)?
+        .file()
+        .set_modified(SystemTime::now())?;
+    debug!("Restored {wasm_path:?} from {cached_wasm_path:?}");
+    Ok(true)
+}
+
+pub fn cache_wasm(key: &str, wasm_path: &Path) -> Result<()> {
+    let cached_wasm_path = get_cached_wasm_path(key);
+    fs::create_dir_all(cached_wasm_path.parent().unwrap())?;
+    fs::copy(wasm_path, &cached_wasm_path)?;
+    Ok(())
+}
+
+/// Remove Wasm in `cache_dir` neither built nor restored within `max_age`
+fn evict_stale_wasms_in(cache_dir: &Path, max_age: Duration) -> Result<()> {
+    if !cache_dir.exists() {
+        return Ok(());
+    }
+    let now = SystemTime::now();
+    for entry in fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("wasm") {
+            continue;
+        }
+        let age = now
+            .duration_since(fs::metadata(&path)?.modified()?)
+            .unwrap_or_default();
+        if age > max_age {
+            debug!("Evicting {path:?}, unused for {}s", age.as_secs());
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Keep the process cache from growing without bound
+pub fn evict_stale_wasms() -> Result<()> {
+    evict_stale_wasms_in(&get_process_cache_dir(), PROCESS_CACHE_MAX_AGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `<workspace>/package/process/`, with the lockfile at the workspace root
+    fn make_workspace() -> (tempfile::TempDir, PathBuf) {
+        let workspace = tempfile::tempdir().unwrap();
+        let process_dir = workspace.path().join("package").join("process");
+        fs::create_dir_all(process_dir.join("src")).unwrap();
+        fs::write(
+            process_dir.join("Cargo.toml"),
+            "[package]\nname = \"process\"\n",
+        )
+        .unwrap();
+        fs::write(process_dir.join("src").join("lib.rs"), "fn main() {}\n").unwrap();
+        // written by `build_wit_dir()` before the key is computed
+        fs::create_dir_all(process_dir.join("target").join("wit")).unwrap();
+        fs::write(workspace.path().join("Cargo.lock"), "version = 3\n").unwrap();
+        (workspace, process_dir)
+    }
+
+    #[test]
+    fn key_is_stable_for_unchanged_inputs() {
+        let (_workspace, process_dir) = make_workspace();
+        assert_eq!(
+            hash_process_inputs(&process_dir, "").unwrap(),
+            hash_process_inputs(&process_dir, "").unwrap(),
+        );
+    }
+
+    #[test]
+    fn key_changes_with_source_and_settings() {
+        let (_workspace, process_dir) = make_workspace();
+        let key = hash_process_inputs(&process_dir, "").unwrap();
+        assert_ne!(
+            key,
+            hash_process_inputs(&process_dir, "features: x").unwrap()
+        );
+        fs::write(process_dir.join("src").join("lib.rs"), "fn main() { }\n").unwrap();
+        assert_ne!(key, hash_process_inputs(&process_dir, "").unwrap());
+    }
+
+    #[test]
+    fn key_changes_with_workspace_lockfile() {
+        let (workspace, process_dir) = make_workspace();
+        let key = hash_process_inputs(&process_dir, "").unwrap();
+        fs::write(workspace.path().join("Cargo.lock"), "version = 4\n").unwrap();
+        assert_ne!(key, hash_process_inputs(&process_dir, "").unwrap());
+    }
+
+    #[test]
+    fn key_ignores_target_dir() {
+        let (_workspace, process_dir) = make_workspace();
+        let key = hash_process_inputs(&process_dir, "").unwrap();
+        fs::create_dir_all(process_dir.join("target")).unwrap();
+        fs::write(process_dir.join("target").join("out.wasm"), "wasm").unwrap();
+        assert_eq!(key, hash_process_inputs(&process_dir, "").unwrap());
+    }
+
+    #[test]
+    fn find_lockfile_finds_nearest_ancestor() {
+        let (workspace, process_dir) = make_workspace();
+        assert_eq!(
+            find_lockfile(&process_dir),
+            Some(workspace.path().join("Cargo.lock")),
+        );
+        let package_lockfile = process_dir.parent().unwrap().join("Cargo.lock");
+        fs::write(&package_lockfile, "version = 3\n").unwrap();
+        assert_eq!(find_lockfile(&process_dir), Some(package_lockfile));
+    }
+
+    #[test]
+    fn evicts_only_stale_wasms() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let stale = cache_dir.path().join("stale.wasm");
+        let fresh = cache_dir.path().join("fresh.wasm");
+        let other = cache_dir.path().join("other.txt");
+        for path in [&stale, &fresh, &other] {
+            fs::write(path, "").unwrap();
+        }
+        let long_ago = SystemTime::now() - Duration::from_secs(60 * 60);
+        for path in [&stale, &other] {
+            fs::OpenOptions::new()
+                .append(true)
+                .open(path)
+                .unwrap()
+                .file()
+                .set_modified(long_ago)
+                .unwrap();
+        }
+        evict_stale_wasms_in(cache_dir.path(), Duration::from_secs(60)).unwrap();
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        assert!(other.exists());
+    }
+}
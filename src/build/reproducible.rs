@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use sha2::{Digest, Sha256};
+use tracing::{info, instrument, warn};
+
+const REPRODUCIBLE_DIR: &str = "reproducible";
+
+/// Remove the `target/` of the package & of each of its processes so
+/// that nothing from a previous build is reused
+#[instrument(level = "trace", skip_all)]
+pub fn clean_target_dirs(package_dir: &Path) -> Result<()> {
+    let mut target_dirs = vec![package_dir.join("target")];
+    for entry in fs::read_dir(package_dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            target_dirs.push(path.join("target"));
+        }
+    }
+    for target_dir in target_dirs {
+        if target_dir.exists() {
+            fs::remove_dir_all(&target_dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// `pkg/<name>.wasm` file name -> contents
+pub fn read_pkg_wasms(package_dir: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut wasms = BTreeMap::new();
+    for entry in fs::read_dir(package_dir.join("pkg"))? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("wasm") {
+            continue;
+        }
+        let file_name = path.file_name().unwrap().to_string_lossy().to_string();
+        wasms.insert(file_name, fs::read(&path)?);
+    }
+    Ok(wasms)
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    !needle.is_empty() && haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+/// Likely sources of non-determinism in a Wasm that differs between builds
+fn diagnose(package_dir: &Path, first: &[u8], second: &[u8]) -> Vec<String> {
+    let mut causes = vec![];
+    if first.len() != second.len() {
+        causes.push(format!(
+            "sizes differ: {} vs {} bytes",
+            first.len(),
+            second.len()
+        ));
+    }
+    if let Some(offset) = first.iter().zip(second).position(|(a, b)| a != b) {
+        causes.push(format!("first difference at byte {offset}"));
+    }
+    if contains(first, b".debug_") {
+        causes.push(
+            "contains DWARF debug info (.debug_* sections), which may embed timestamps & paths: build with --strip-custom-sections".to_string(),
+        );
+    }
+    let package_dir = package_dir
+        .canonicalize()
+        .unwrap_or_else(|_| package_dir.to_path_buf());
+    if contains(first, package_dir.to_string_lossy().as_bytes()) {
+        causes.push(format!(
+            "embeds the absolute build path {package_dir:?}: builds elsewhere will differ; consider `--remap-path-prefix` in RUSTFLAGS"
+        ));
+    }
+    causes
+}
+
+/// Compare two builds' `pkg/` Wasms, writing any that differ to
+/// `target/reproducible/{first,second}/` for inspection
+#[instrument(level = "trace", skip_all)]
+pub fn compare_builds(
+    package_dir: &Path,
+    first: &BTreeMap<String, Vec<u8>>,
+    second: &BTreeMap<String, Vec<u8>>,
+) -> Result<()> {
+    let file_names: BTreeSet<&String> = first.keys().chain(second.keys()).collect();
+    let mut differing = vec![];
+    for file_name in file_names {
+        match (first.get(file_name), second.get(file_name)) {
+            (Some(a), Some(b)) if sha256(a) == sha256(b) => {
+                info!("{file_name}: reproducible ({})", sha256(a));
+            }
+            (Some(a), Some(b)) => {
+                warn!("{file_name}: {} != {}", sha256(a), sha256(b));
+                for cause in diagnose(package_dir, a, b) {
+                    warn!("  {cause}");
+                }
+                let reproducible_dir = package_dir.join("target").join(REPRODUCIBLE_DIR);
+                for (build, contents) in [("first", a), ("second", b)] {
+                    let dir = reproducible_dir.join(build);
+                    fs::create_dir_all(&dir)?;
+                    fs::write(dir.join(file_name), contents)?;
+                }
+                differing.push(file_name.clone());
+            }
+            _ => {
+                warn!("{file_name}: built only once");
+                differing.push(file_name.clone());
+            }
+        }
+    }
+    if !differing.is_empty() {
+        return Err(
+            eyre!("Build is not reproducible: {differing:?} differ between builds")
+                .with_suggestion(|| {
+                    format!(
+                        "Compare the builds in {:?}, e.g. with `wasm-tools print`.",
+                        package_dir.join("target").join(REPRODUCIBLE_DIR),
+                    )
+                }),
+        );
+    }
+    info!("Build is reproducible: {} Wasm files match.", first.len());
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use color_eyre::{eyre::eyre, Result};
+use tracing::{instrument, warn};
+
+/// A tool that can run a process build without network access and with
+/// writes confined to the process's `target/`
+#[derive(Debug, Clone)]
+pub enum Sandbox {
+    /// Linux
+    Nsjail(PathBuf),
+    /// macOS
+    SandboxExec(PathBuf),
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    })
+}
+
+/// Cargo writes its download locks & caches here even when `--offline`
+fn get_cargo_home() -> Option<PathBuf> {
+    std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")))
+        .and_then(|cargo_home| cargo_home.canonicalize().ok())
+}
+
+/// The sandbox available on this platform, warning if there is none
+#[instrument(level = "trace", skip_all)]
+pub fn find_sandbox() -> Option<Sandbox> {
+    let sandbox = if cfg!(target_os = "linux") {
+        find_in_path("nsjail").map(Sandbox::Nsjail)
+    } else if cfg!(target_os = "macos") {
+        find_in_path("sandbox-exec").map(Sandbox::SandboxExec)
+    } else {
+        None
+    };
+    if sandbox.is_none() {
+        warn!(
+            "--sandbox requires `nsjail` (Linux) or `sandbox-exec` (macOS), neither of which was found: building unsandboxed."
+        );
+    }
+    sandbox
+}
+
+fn make_sandbox_exec_profile(writable: &[&Path]) -> String {
+    let writable: String = writable
+        .iter()
+        .map(|path| format!(" (subpath \"{}\")", path.display()))
+        .collect();
+    format!(
+        r#"(version 1)
+(allow default)
+(deny network*)
+(deny file-write*)
+(allow file-write*{writable} (subpath "/private/tmp") (subpath "/private/var/folders") (literal "/dev/null"))
+"#
+    )
+}
+
+impl Sandbox {
+    /// A `Command` running `program` with `args` in `process_dir`, with no
+    /// network and the filesystem read-only except for `process_dir/target/`,
+    /// the Cargo home, and `/tmp`
+    pub fn command(&self, program: &str, args: &[&str], process_dir: &Path) -> Result<Command> {
+        let Some(program) = find_in_path(program) else {
+            return Err(eyre!("{program} not found in $PATH"));
+        };
+        let process_dir = process_dir.canonicalize()?;
+        let target_dir = process_dir.join("target");
+        let mut writable = vec![target_dir.as_path()];
+        let cargo_home = get_cargo_home();
+        if let Some(ref cargo_home) = cargo_home {
+            writable.push(cargo_home);
+        }
+
+        let command = match self {
+            Sandbox::Nsjail(nsjail) => {
+                let mut command = Command::new(nsjail);
+                command.args([
+                    "--mode",
+                    "o",
+                    "--quiet",
+                    "--keep_env",
+                    "--time_limit",
+                    "0",
+                    "--rlimit_as",
+                    "max",
+                    "--rlimit_fsize",
+                    "max",
+                    "--rlimit_nofile",
+                    "max",
+                    "--rlimit_nproc",
+                    "max",
+                    "-R",
+                    "/",
+                    "-T",
+                    "/tmp",
+                ]);
+                for path in writable {
+                    command.arg("-B").arg(path);
+                }
+                command
+                    .arg("--cwd")
+                    .arg(&process_dir)
+                    .arg("--")
+                    .arg(program)
+                    .args(args);
+                command
+            }
+            Sandbox::SandboxExec(sandbox_exec) => {
+                let mut command = Command::new(sandbox_exec);
+                command
+                    .arg("-p")
+                    .arg(make_sandbox_exec_profile(&writable))
+                    .arg(program)
+                    .args(args)
+                    .current_dir(&process_dir);
+                command
+            }
+        };
+        Ok(command)
+    }
+}
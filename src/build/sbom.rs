@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use color_eyre::{eyre::WrapErr, Result};
+use fs_err as fs;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tracing::{info, instrument, warn};
+
+const SBOM_FILE_NAME: &str = "sbom.spdx.json";
+const CRATES_IO_SOURCE: &str = "registry+https://github.com/rust-lang/crates.io-index";
+const NOASSERTION: &str = "NOASSERTION";
+
+/// `name version` -> sha256 of the `.crate`, for each registry package in `Cargo.lock`
+fn read_lock_checksums(cargo_lock: &str) -> Result<HashMap<String, String>> {
+    let lock: toml::Value = toml::from_str(cargo_lock)?;
+    let mut checksums = HashMap::new();
+    let packages = lock.get("package").and_then(|p| p.as_array());
+    for package in packages.into_iter().flatten() {
+        let (Some(name), Some(version), Some(checksum)) = (
+            package.get("name").and_then(|n| n.as_str()),
+            package.get("version").and_then(|v| v.as_str()),
+            package.get("checksum").and_then(|c| c.as_str()),
+        ) else {
+            continue;
+        };
+        checksums.insert(format!("{name} {version}"), checksum.to_string());
+    }
+    Ok(checksums)
+}
+
+/// Write `pkg/sbom.spdx.json`: an SPDX 2.3 document listing every crate the
+/// package's Rust processes depend on, transitively, with its version,
+/// declared license & `Cargo.lock` checksum
+#[instrument(level = "trace", skip_all)]
+pub fn write_sbom(package_dir: &Path) -> Result<()> {
+    let cargo_toml_path = package_dir.join("Cargo.toml");
+    let cargo_lock_path = package_dir.join("Cargo.lock");
+    if !cargo_toml_path.exists() || !cargo_lock_path.exists() {
+        warn!("No Cargo.toml & Cargo.lock in {package_dir:?}: not writing {SBOM_FILE_NAME}.");
+        return Ok(());
+    }
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(&cargo_toml_path)
+        .exec()
+        .wrap_err_with(|| format!("Failed to read cargo metadata of {cargo_toml_path:?}"))?;
+    let cargo_lock = fs::read_to_string(&cargo_lock_path)?;
+    let checksums = read_lock_checksums(&cargo_lock)?;
+
+    let mut packages: Vec<&cargo_metadata::Package> = metadata.packages.iter().collect();
+    packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    let spdx_ids: HashMap<&cargo_metadata::PackageId, String> = packages
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (&p.id, format!("SPDXRef-Package-{i}")))
+        .collect();
+
+    let spdx_packages: Vec<serde_json::Value> = packages
+        .iter()
+        .map(|package| {
+            let is_crates_io = package
+                .source
+                .as_ref()
+                .is_some_and(|s| s.repr == CRATES_IO_SOURCE);
+            let download_location = if is_crates_io {
+                format!(
+                    "https://crates.io/api/v1/crates/{}/{}/download",
+                    package.name, package.version,
+                )
+            } else {
+                NOASSERTION.to_string()
+            };
+            let mut spdx_package = json!({
+                "SPDXID": spdx_ids[&package.id],
+                "name": package.name,
+                "versionInfo": package.version.to_string(),
+                "downloadLocation": download_location,
+                "filesAnalyzed": false,
+                "licenseConcluded": NOASSERTION,
+                "licenseDeclared": package.license.as_deref().unwrap_or(NOASSERTION),
+                "copyrightText": NOASSERTION,
+                "externalRefs": [{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": format!("pkg:cargo/{}@{}", package.name, package.version),
+                }],
+            });
+            if let Some(checksum) = checksums.get(&format!("{} {}", package.name, package.version))
+            {
+                spdx_package["checksums"] = json!([{
+                    "algorithm": "SHA256",
+                    "checksumValue": checksum,
+                }]);
+            }
+            spdx_package
+        })
+        .collect();
+
+    let mut relationships: Vec<serde_json::Value> = metadata
+        .workspace_members
+        .iter()
+        .filter_map(|id| spdx_ids.get(id))
+        .map(|spdx_id| {
+            json!({
+                "spdxElementId": "SPDXRef-DOCUMENT",
+                "relationshipType": "DESCRIBES",
+                "relatedSpdxElement": spdx_id,
+            })
+        })
+        .collect();
+    for node in metadata.resolve.iter().flat_map(|r| r.nodes.iter()) {
+        for dep in &node.deps {
+            relationships.push(json!({
+                "spdxElementId": spdx_ids[&node.id],
+                "relationshipType": "DEPENDS_ON",
+                "relatedSpdxElement": spdx_ids[&dep.pkg],
+            }));
+        }
+    }
+
+    // the same Cargo.lock gives the same namespace, so that the SBOM of a
+    //  reproducible build is reproducible but for its creation time
+    let name = package_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("package");
+    let crate_count = spdx_packages.len();
+    let lock_hash = hex::encode(Sha256::digest(cargo_lock.as_bytes()));
+    let sbom = json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": name,
+        "documentNamespace": format!("https://kinode.org/spdxdocs/{name}-{lock_hash}"),
+        "creationInfo": {
+            "created": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            "creators": [format!("Tool: kit-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": spdx_packages,
+        "relationships": relationships,
+    });
+
+    let sbom_path = package_dir.join("pkg").join(SBOM_FILE_NAME);
+    fs::write(&sbom_path, serde_json::to_string_pretty(&sbom)?)?;
+    info!("Wrote SBOM of {crate_count} crates to {sbom_path:?}.");
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use std::path::Path;
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result, Section,
+};
+use fs_err as fs;
+use ring::signature::{Ed25519KeyPair, KeyPair};
+use sha2::{Digest, Sha256};
+use tracing::{info, instrument};
+use walkdir::WalkDir;
+
+pub const SIGNATURE_FILE_NAME: &str = "signature.sig";
+
+/// Read an ed25519 private key: either a PKCS#8 document (as used for
+/// Kinode networking keys) or a 32-byte seed, raw or hex-encoded
+fn read_key_pair(key_path: &Path) -> Result<Ed25519KeyPair> {
+    let bytes = fs::read(key_path)?;
+    let bytes = match std::str::from_utf8(&bytes)
+        .ok()
+        .map(|s| s.trim().trim_start_matches("0x"))
+        .and_then(|s| hex::decode(s).ok())
+    {
+        Some(decoded) => decoded,
+        None => bytes,
+    };
+    let key_pair = if bytes.len() == 32 {
+        Ed25519KeyPair::from_seed_unchecked(&bytes).ok()
+    } else {
+        Ed25519KeyPair::from_pkcs8_maybe_unchecked(&bytes).ok()
+    };
+    key_pair.ok_or_else(|| {
+        eyre!("Could not read ed25519 private key from {key_path:?}").with_suggestion(|| {
+            "Key must be a PKCS#8 document or a 32-byte seed, either raw or hex-encoded."
+        })
+    })
+}
+
+/// Hash the content of `pkg_dir`, excluding any existing signature:
+/// sha256 over each file's relative path and content, in path order
+#[instrument(level = "trace", skip_all)]
+pub fn hash_pkg_content(pkg_dir: &Path) -> Result<[u8; 32]> {
+    let mut entries = WalkDir::new(pkg_dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.path().to_owned());
+
+    let mut hasher = Sha256::new();
+    for entry in entries {
+        let name = entry.path().strip_prefix(pkg_dir)?;
+        if name == Path::new(SIGNATURE_FILE_NAME) {
+            continue;
+        }
+        let name = name.to_string_lossy();
+        let content = fs::read(entry.path())?;
+        hasher.update((name.len() as u64).to_be_bytes());
+        hasher.update(name.as_bytes());
+        hasher.update((content.len() as u64).to_be_bytes());
+        hasher.update(&content);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// Sign the content hash of `package_dir/pkg/` with the ed25519 key at
+/// `key_path`, writing the signature to `pkg/signature.sig` and the public
+/// key to `metadata.json` `properties.public_key`
+#[instrument(level = "trace", skip_all)]
+pub fn sign_pkg(package_dir: &Path, key_path: &Path) -> Result<()> {
+    let key_pair = read_key_pair(key_path)?;
+    let public_key = format!("0x{}", hex::encode(key_pair.public_key().as_ref()));
+
+    let pkg_dir = package_dir.join("pkg");
+    let hash = hash_pkg_content(&pkg_dir)?;
+    let signature = key_pair.sign(&hash);
+    fs::write(pkg_dir.join(SIGNATURE_FILE_NAME), signature.as_ref())?;
+
+    let metadata_path = package_dir.join("metadata.json");
+    let mut metadata: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&metadata_path)?)
+            .wrap_err_with(|| format!("Failed to parse {metadata_path:?}"))?;
+    if metadata["properties"]["public_key"].as_str() != Some(&public_key) {
+        metadata["properties"]["public_key"] = serde_json::Value::String(public_key.clone());
+        fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?)?;
+    }
+
+    info!(
+        "Signed pkg content hash 0x{} with public key {public_key}",
+        hex::encode(hash),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{UnparsedPublicKey, ED25519};
+
+    const SEED: [u8; 32] = [7; 32];
+
+    fn make_package() -> tempfile::TempDir {
+        let package_dir = tempfile::tempdir().unwrap();
+        let pkg_dir = package_dir.path().join("pkg");
+        fs::create_dir_all(pkg_dir.join("ui")).unwrap();
+        fs::write(pkg_dir.join("manifest.json"), "[]").unwrap();
+        fs::write(pkg_dir.join("ui").join("index.html"), "<html></html>").unwrap();
+        fs::write(
+            package_dir.path().join("metadata.json"),
+            r#"{"properties":{}}"#,
+        )
+        .unwrap();
+        package_dir
+    }
+
+    #[test]
+    fn read_key_pair_accepts_raw_and_hex_seeds() {
+        let dir = tempfile::tempdir().unwrap();
+        let raw = dir.path().join("raw");
+        fs::write(&raw, SEED).unwrap();
+        let hexed = dir.path().join("hex");
+        fs::write(&hexed, format!("0x{}\n", hex::encode(SEED))).unwrap();
+        assert_eq!(
+            read_key_pair(&raw).unwrap().public_key().as_ref(),
+            read_key_pair(&hexed).unwrap().public_key().as_ref(),
+        );
+        let invalid = dir.path().join("invalid");
+        fs::write(&invalid, "not a key").unwrap();
+        assert!(read_key_pair(&invalid).is_err());
+    }
+
+    #[test]
+    fn hash_pkg_content_ignores_signature_but_not_content() {
+        let package_dir = make_package();
+        let pkg_dir = package_dir.path().join("pkg");
+        let hash = hash_pkg_content(&pkg_dir).unwrap();
+        fs::write(pkg_dir.join(SIGNATURE_FILE_NAME), "signature").unwrap();
+        assert_eq!(hash_pkg_content(&pkg_dir).unwrap(), hash);
+        fs::write(pkg_dir.join("manifest.json"), "[{}]").unwrap();
+        assert_ne!(hash_pkg_content(&pkg_dir).unwrap(), hash);
+    }
+
+    #[test]
+    fn sign_pkg_writes_verifiable_signature_and_public_key() {
+        let package_dir = make_package();
+        let key_path = package_dir.path().join("key");
+        fs::write(&key_path, SEED).unwrap();
+        sign_pkg(package_dir.path(), &key_path).unwrap();
+
+        let metadata: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(package_dir.path().join("metadata.json")).unwrap(),
+        )
+        .unwrap();
+        let public_key = metadata["properties"]["public_key"].as_str().unwrap();
+        let public_key = hex::decode(public_key.trim_start_matches("0x")).unwrap();
+
+        let pkg_dir = package_dir.path().join("pkg");
+        let signature = fs::read(pkg_dir.join(SIGNATURE_FILE_NAME)).unwrap();
+        let hash = hash_pkg_content(&pkg_dir).unwrap();
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&hash, &signature)
+            .unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use color_eyre::{eyre::WrapErr, Result};
+use fs_err as fs;
+use serde::{Deserialize, Serialize};
+use tracing::{info, instrument, warn};
+
+use crate::KIT_CACHE;
+
+use super::{JAVASCRIPT_SRC_PATH, PYTHON_SRC_PATH, RUST_SRC_PATH};
+
+const BUILD_STATS_NAME: &str = "build-stats.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BuildStats {
+    /// keyed by process dir path
+    processes: BTreeMap<String, ProcessStats>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ProcessStats {
+    builds: u64,
+    cache_hits: u64,
+    total_build_ms: u64,
+    last_build_ms: Option<u64>,
+    last_built: Option<String>,
+}
+
+fn get_build_stats_path() -> PathBuf {
+    PathBuf::from(KIT_CACHE).join(BUILD_STATS_NAME)
+}
+
+fn read_build_stats() -> Result<BuildStats> {
+    let path = get_build_stats_path();
+    if !path.exists() {
+        return Ok(BuildStats::default());
+    }
+    serde_json::from_str(&fs::read_to_string(&path)?)
+        .wrap_err_with(|| format!("Failed to parse {path:?}; try `kit build --reset-stats`"))
+}
+
+fn write_build_stats(stats: &BuildStats) -> Result<()> {
+    fs::create_dir_all(KIT_CACHE)?;
+    fs::write(get_build_stats_path(), serde_json::to_string_pretty(stats)?)?;
+    Ok(())
+}
+
+fn process_key(process_dir: &Path) -> String {
+    process_dir
+        .canonicalize()
+        .unwrap_or_else(|_| process_dir.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Record how long each process took to compile
+#[instrument(level = "trace", skip_all)]
+pub fn record_builds(builds: &[(PathBuf, Duration)]) {
+    let result = read_build_stats().and_then(|mut stats| {
+        let now = chrono::Utc::now().to_rfc3339();
+        for (process_dir, duration) in builds {
+            let duration_ms = duration.as_millis() as u64;
+            let process = stats.processes.entry(process_key(process_dir)).or_default();
+            process.builds += 1;
+            process.total_build_ms += duration_ms;
+            process.last_build_ms = Some(duration_ms);
+            process.last_built = Some(now.clone());
+        }
+        write_build_stats(&stats)
+    });
+    if let Err(e) = result {
+        warn!("Failed to record build stats: {e:?}");
+    }
+}
+
+/// Record a cache hit for each process of a package that was already up-to-date
+#[instrument(level = "trace", skip_all)]
+pub fn record_cache_hits(package_dir: &Path) {
+    let result = fs::read_dir(package_dir)
+        .map_err(Into::into)
+        .and_then(|entries| {
+            let mut stats = read_build_stats()?;
+            for entry in entries {
+                let process_dir = entry?.path();
+                let is_process = [RUST_SRC_PATH, PYTHON_SRC_PATH, JAVASCRIPT_SRC_PATH]
+                    .iter()
+                    .any(|src| process_dir.join(src).exists());
+                if is_process {
+                    stats
+                        .processes
+                        .entry(process_key(&process_dir))
+                        .or_default()
+                        .cache_hits += 1;
+                }
+            }
+            write_build_stats(&stats)
+        });
+    if let Err(e) = result {
+        warn!("Failed to record build stats: {e:?}");
+    }
+}
+
+/// Record a cache hit for each process whose Wasm was restored from the per-process cache
+#[instrument(level = "trace", skip_all)]
+pub fn record_process_cache_hits(process_dirs: &[PathBuf]) {
+    if process_dirs.is_empty() {
+        return;
+    }
+    let result = read_build_stats().and_then(|mut stats| {
+        for process_dir in process_dirs {
+            stats
+                .processes
+                .entry(process_key(process_dir))
+                .or_default()
+                .cache_hits += 1;
+        }
+        write_build_stats(&stats)
+    });
+    if let Err(e) = result {
+        warn!("Failed to record build stats: {e:?}");
+    }
+}
+
+/// Print the accumulated build stats as a table
+#[instrument(level = "trace", skip_all)]
+pub fn print_build_stats() -> Result<()> {
+    let stats = read_build_stats()?;
+    if stats.processes.is_empty() {
+        info!(
+            "No build stats recorded yet in {:?}.",
+            get_build_stats_path()
+        );
+        return Ok(());
+    }
+    let mut table = format!(
+        "{:>7} {:>10} {:>10} {:>11} {:<25} process",
+        "builds", "cache hits", "avg (ms)", "last (ms)", "last built",
+    );
+    for (process, s) in &stats.processes {
+        let average_ms = s
+            .total_build_ms
+            .checked_div(s.builds)
+            .map(|ms| ms.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        table.push_str(&format!(
+            "\n{:>7} {:>10} {:>10} {:>11} {:<25} {process}",
+            s.builds,
+            s.cache_hits,
+            average_ms,
+            s.last_build_ms
+                .map(|ms| ms.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            s.last_built
+                .as_deref()
+                .map(|t| t.split('.').next().unwrap_or(t))
+                .unwrap_or("-"),
+        ));
+    }
+    info!("Build stats from {:?}:\n{table}", get_build_stats_path());
+    Ok(())
+}
+
+#[instrument(level = "trace", skip_all)]
+pub fn reset_build_stats() -> Result<()> {
+    let path = get_build_stats_path();
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+    info!("Cleared build stats in {path:?}.");
+    Ok(())
+}
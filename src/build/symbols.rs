@@ -0,0 +1,81 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use color_eyre::{eyre::eyre, Result};
+use fs_err as fs;
+use tracing::{info, instrument, warn};
+use wasmparser::{Encoding, KnownCustom, Name, Parser, Payload};
+
+/// Function index -> name, from the `name` section of each core module in `wasm`.
+/// A component embeds the process's module alongside adapter & shim modules: the
+/// process's is taken to be that naming the most functions
+fn read_function_names(wasm: &[u8]) -> Result<Option<BTreeMap<u32, String>>> {
+    let mut modules: Vec<BTreeMap<u32, String>> = vec![];
+    // the encodings of the modules & components being parsed, innermost last
+    let mut encodings = vec![];
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::Version { encoding, .. } => {
+                if encoding == Encoding::Module {
+                    modules.push(BTreeMap::new());
+                }
+                encodings.push(encoding);
+            }
+            Payload::End(_) => {
+                encodings.pop();
+            }
+            Payload::CustomSection(reader) if encodings.last() == Some(&Encoding::Module) => {
+                let KnownCustom::Name(names) = reader.as_known() else {
+                    continue;
+                };
+                let Some(functions) = modules.last_mut() else {
+                    continue;
+                };
+                for name in names {
+                    let Name::Function(map) = name? else {
+                        continue;
+                    };
+                    for naming in map {
+                        let naming = naming?;
+                        functions.insert(
+                            naming.index,
+                            format!("{:#}", rustc_demangle::demangle(naming.name)),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(modules
+        .into_iter()
+        .filter(|m| !m.is_empty())
+        .max_by_key(|m| m.len()))
+}
+
+/// Write `pkg/{process}.symbols.json` for each `pkg/{process}.wasm`: a map of the
+/// process's function indices, as in `wasm-tools print`, to demangled Rust names
+#[instrument(level = "trace", skip_all)]
+pub fn write_symbol_maps(package_dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(package_dir.join("pkg"))? {
+        let path = entry?.path();
+        if !(path.is_file() && Some("wasm") == path.extension().and_then(|e| e.to_str())) {
+            continue;
+        }
+        let wasm = fs::read(&path)?;
+        let functions = read_function_names(&wasm)
+            .map_err(|e| eyre!("Failed to read function names of {path:?}: {e}"))?;
+        let Some(functions) = functions else {
+            warn!("No function names in {path:?} (built without debug info?): not writing its symbols.");
+            continue;
+        };
+        let symbols_path = path.with_extension("symbols.json");
+        fs::write(&symbols_path, serde_json::to_string_pretty(&functions)?)?;
+        info!(
+            "Wrote {} function names to {:?}.",
+            functions.len(),
+            symbols_path.file_name().unwrap_or_default(),
+        );
+    }
+    Ok(())
+}
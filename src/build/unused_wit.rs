@@ -0,0 +1,118 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use color_eyre::Result;
+use fs_err as fs;
+use regex::Regex;
+use tracing::{info, instrument, warn};
+use walkdir::WalkDir;
+
+use super::docs::parse_interfaces;
+use super::manifest_caps::{find_world, parse_wit_apis};
+use super::RUST_SRC_PATH;
+
+/// `kv-error` -> `KvError`, as `wit_bindgen::generate!` names it
+fn to_rust_type_name(wit_name: &str) -> String {
+    wit_name
+        .split('-')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Interface name -> the types it defines, for every WIT file of a built
+/// process's `target/wit/` but `kinode.wit`
+fn parse_interface_types(wit_dir: &Path) -> Result<BTreeMap<String, Vec<String>>> {
+    let mut types = BTreeMap::new();
+    for entry in fs::read_dir(wit_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("wit")
+            || path.file_name().and_then(|f| f.to_str()) == Some("kinode.wit")
+        {
+            continue;
+        }
+        for interface in parse_interfaces(&fs::read_to_string(&path)?) {
+            let interface_types = interface
+                .items
+                .into_iter()
+                .filter(|item| item.kind != "func")
+                .map(|item| item.name)
+                .collect();
+            types.insert(interface.name, interface_types);
+        }
+    }
+    Ok(types)
+}
+
+/// Every identifier in the Rust source of `process_dir`, comments aside
+fn find_identifiers(process_dir: &Path) -> Result<BTreeSet<String>> {
+    let ident_re = Regex::new(r"\b[A-Za-z_][A-Za-z0-9_]*\b").unwrap();
+    let mut identifiers = BTreeSet::new();
+    for file in WalkDir::new(process_dir.join("src"))
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().and_then(|e| e.to_str()) == Some("rs"))
+    {
+        for line in fs::read_to_string(file.path())?.lines() {
+            let code = line.split("//").next().unwrap_or_default();
+            identifiers.extend(ident_re.find_iter(code).map(|i| i.as_str().to_string()));
+        }
+    }
+    Ok(identifiers)
+}
+
+/// Warn about types of the interfaces a Rust process's world imports that
+/// its source never names: candidates to remove from the WIT, since
+/// `wit_bindgen::generate!` emits (& derives traits for) each regardless
+#[instrument(level = "trace", skip_all)]
+pub fn report_unused_wit_types(package_dir: &Path) -> Result<()> {
+    let mut entries = fs::read_dir(package_dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+    for process_dir in entries {
+        let wit_dir = process_dir.join("target").join("wit");
+        if !process_dir.join(RUST_SRC_PATH).exists() || !wit_dir.exists() {
+            continue;
+        }
+        let Some(process) = process_dir.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some(world) = find_world(&process_dir)? else {
+            continue;
+        };
+        let apis = parse_wit_apis(&wit_dir)?;
+        let Some(imports) = apis.world_imports.get(&world) else {
+            continue;
+        };
+        let interface_types = parse_interface_types(&wit_dir)?;
+        let identifiers = find_identifiers(&process_dir)?;
+
+        let unused: Vec<String> = imports
+            .iter()
+            .filter_map(|import| interface_types.get(import).map(|t| (import, t)))
+            .flat_map(|(import, types)| {
+                types
+                    .iter()
+                    .filter(|t| !identifiers.contains(&to_rust_type_name(t)))
+                    .map(move |t| format!("{import}/{t}"))
+            })
+            .collect();
+        if unused.is_empty() {
+            info!("{process}: every WIT type world {world} imports is used.");
+        } else {
+            warn!(
+                "{process}: {} WIT type(s) imported by world {world} are never used in {:?}: {}",
+                unused.len(),
+                process_dir.join("src"),
+                unused.join(", "),
+            );
+        }
+    }
+    Ok(())
+}
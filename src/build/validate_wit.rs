@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use regex::Regex;
+use tracing::instrument;
+
+/// Classify a `wit-parser` error message: `WIT001` an unknown type, `WIT002`
+/// a missing world, `WIT003` a duplicate name, `WIT000` anything else
+fn classify(message: &str) -> (&'static str, String) {
+    let name_re = Regex::new(r"`([^`]+)`").unwrap();
+    let name = name_re
+        .captures(message)
+        .map(|n| n[1].to_string())
+        .unwrap_or_default();
+    if message.starts_with("type ") && message.contains("does not exist")
+        || message.starts_with("name ") && message.contains("is not defined")
+    {
+        ("WIT001", format!("unknown type '{name}'"))
+    } else if message.starts_with("no world named") {
+        ("WIT002", format!("missing world '{name}'"))
+    } else if message.contains("defined more than once") {
+        ("WIT003", format!("duplicate name '{name}'"))
+    } else {
+        ("WIT000", message.to_string())
+    }
+}
+
+/// `error[WITnnn]: <message> at <file>:<line>`, with the file given relative to
+/// the package's `api/` if it came from there
+fn format_error(package_dir: &Path, wit_dir: &Path, chain: &[String]) -> String {
+    let location_re = Regex::new(r"-->\s*(\S+?):(\d+):\d+").unwrap();
+    let full = chain.join("\n");
+    // the innermost error is the most specific: `failed to parse ...` wraps it
+    let message = chain
+        .last()
+        .and_then(|m| m.lines().next())
+        .unwrap_or_default()
+        .trim();
+    let (code, message) = classify(message);
+    let Some(location) = location_re.captures(&full) else {
+        return format!("error[{code}]: {message} in {}", wit_dir.display());
+    };
+    let path = Path::new(&location[1]);
+    let file_name = path.file_name().unwrap_or_default();
+    let path = if package_dir.join("api").join(file_name).exists() {
+        Path::new("api").join(file_name)
+    } else {
+        path.to_path_buf()
+    };
+    format!(
+        "error[{code}]: {message} at {}:{}",
+        path.display(),
+        &location[2]
+    )
+}
+
+/// Parse & resolve the WIT a process is built against, checking it defines
+/// `world`, so that malformed WIT fails fast rather than deep in `cargo build`
+#[instrument(level = "trace", skip_all)]
+pub fn validate_wit_dir(package_dir: &Path, wit_dir: &Path, world: Option<&str>) -> Result<()> {
+    let mut resolve = wit_parser::Resolve::default();
+    if let Err(error) = resolve.push_path(wit_dir) {
+        let chain: Vec<String> = error.chain().map(|e| e.to_string()).collect();
+        return Err(eyre!("{}", format_error(package_dir, wit_dir, &chain))
+            .with_note(|| format!("{error:?}")));
+    }
+    // the world may be in any of the packages in the dir, not only the one
+    //  `wit-parser` deems the main package
+    let Some(world) = world else {
+        return Ok(());
+    };
+    if resolve.worlds.iter().any(|(_, w)| w.name == world) {
+        return Ok(());
+    }
+    let chain = vec![format!("no world named `{world}` in any package")];
+    Err(eyre!("{}", format_error(package_dir, wit_dir, &chain))
+        .with_suggestion(|| "Check the `world` given to `wit_bindgen::generate!`."))
+}
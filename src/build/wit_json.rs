@@ -0,0 +1,51 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use color_eyre::{eyre::eyre, Result};
+use fs_err as fs;
+use tracing::{info, instrument, warn};
+
+use super::run_command;
+
+const WIT_JSON: &str = "wit.json";
+
+/// The `target/wit/` of a built process: the package's API along with
+/// `kinode.wit` & the APIs of its dependencies, which it may refer to
+fn find_built_wit_dir(package_dir: &Path) -> Result<Option<PathBuf>> {
+    let mut wit_dirs = vec![];
+    for entry in fs::read_dir(package_dir)? {
+        let wit_dir = entry?.path().join("target").join("wit");
+        if wit_dir.join("kinode.wit").exists() {
+            wit_dirs.push(wit_dir);
+        }
+    }
+    wit_dirs.sort();
+    Ok(wit_dirs.into_iter().next())
+}
+
+/// Write `pkg/wit.json`: the package's WIT as resolved by
+/// `wasm-tools component wit --json`, for generating bindings in other languages
+#[instrument(level = "trace", skip_all)]
+pub fn write_wit_json(package_dir: &Path) -> Result<()> {
+    if !package_dir.join("api").exists() {
+        warn!("No api/ in {package_dir:?}: not writing {WIT_JSON}.");
+        return Ok(());
+    }
+    let Some(wit_dir) = find_built_wit_dir(package_dir)? else {
+        return Err(eyre!(
+            "No built process in {package_dir:?} to resolve the WIT of"
+        ));
+    };
+    let Some((wit_json, _)) = run_command(
+        Command::new("wasm-tools").args(["component", "wit", "--json", wit_dir.to_str().unwrap()]),
+        false,
+    )?
+    else {
+        return Err(eyre!("`wasm-tools component wit --json` wrote nothing"));
+    };
+
+    let wit_json_path = package_dir.join("pkg").join(WIT_JSON);
+    fs::write(&wit_json_path, wit_json)?;
+    info!("Wrote WIT as JSON to {wit_json_path:?}.");
+    Ok(())
+}
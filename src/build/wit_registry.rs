@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use serde::Deserialize;
+use tracing::{debug, info, instrument};
+
+use crate::KIT_CACHE;
+
+/// `[[wit_dependency]]` in `kit.toml`: a versioned WIT file, fetched from
+/// `<registry>/<name>/<version>.wit`
+#[derive(Debug, Deserialize)]
+pub struct WitDependency {
+    pub name: String,
+    pub version: String,
+    /// Registry to fetch from (default: `wit_registry` in `kit.toml`)
+    pub registry: Option<String>,
+}
+
+impl WitDependency {
+    /// `name` & `version` become path components, both of the URL & the cache
+    fn check(&self) -> Result<()> {
+        for part in [&self.name, &self.version] {
+            if part.is_empty() || part.contains('/') || part.contains("..") {
+                return Err(eyre!(
+                    "Invalid wit_dependency {}@{}: name & version must be non-empty and contain no `/` or `..`",
+                    self.name,
+                    self.version,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn cache_path(&self) -> PathBuf {
+        PathBuf::from(KIT_CACHE)
+            .join("wit")
+            .join(&self.name)
+            .join(format!("{}.wit", self.version))
+    }
+
+    /// File name in each process's `target/wit/`
+    fn file_name(&self) -> String {
+        format!("{}-{}.wit", self.name, self.version)
+    }
+}
+
+/// Fetch each of `wit_dependencies`, unless cached under `KIT_CACHE/wit/`,
+/// into `apis`, so that it is written alongside the package's own API
+/// into every process's WIT dir before bindings are generated
+#[instrument(level = "trace", skip_all)]
+pub async fn fetch_wit_dependencies(
+    wit_registry: Option<&str>,
+    wit_dependencies: &[WitDependency],
+    apis: &mut HashMap<String, Vec<u8>>,
+) -> Result<()> {
+    for dependency in wit_dependencies {
+        dependency.check()?;
+        let cache_path = dependency.cache_path();
+        let contents = if cache_path.exists() {
+            debug!("Using cached {cache_path:?}.");
+            fs::read(&cache_path)?
+        } else {
+            let Some(registry) = dependency.registry.as_deref().or(wit_registry) else {
+                return Err(eyre!(
+                    "No registry to fetch wit_dependency {}@{} from",
+                    dependency.name,
+                    dependency.version,
+                )
+                .with_suggestion(|| {
+                    "Set `wit_registry` in kit.toml or `registry` in the [[wit_dependency]]."
+                }));
+            };
+            let url = format!(
+                "{}/{}/{}.wit",
+                registry.trim_end_matches('/'),
+                dependency.name,
+                dependency.version,
+            );
+            info!("Fetching {url}...");
+            let response = reqwest::get(&url).await?;
+            if response.status() != reqwest::StatusCode::OK {
+                return Err(eyre!(
+                    "Failed to fetch wit_dependency from {url}: HTTP Status {}",
+                    response.status(),
+                ));
+            }
+            let contents = response.bytes().await?.to_vec();
+            fs::create_dir_all(cache_path.parent().unwrap())?;
+            fs::write(&cache_path, &contents)?;
+            contents
+        };
+        apis.insert(dependency.file_name(), contents);
+    }
+    Ok(())
+}
@@ -0,0 +1,282 @@
+use std::path::Path;
+
+use alloy::json_abi::JsonAbi;
+use alloy_sol_macro::sol;
+use color_eyre::{eyre::eyre, Result};
+use fs_err as fs;
+use reqwest::Client;
+use tracing::{info, instrument};
+
+use super::{call_anvil, get_kinostate};
+
+/// ERC-1967 implementation slot: `keccak256("eip1967.proxy.implementation") - 1`
+const ERC1967_IMPLEMENTATION_SLOT: &str =
+    "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc";
+
+sol! {
+    #[sol(abi)]
+    interface Kimap {
+        event Mint(bytes32 indexed parenthash, bytes32 indexed childhash, bytes indexed labelhash, bytes label);
+        event Fact(bytes32 indexed parenthash, bytes32 indexed facthash, bytes indexed labelhash, bytes label, bytes data);
+        event Note(bytes32 indexed parenthash, bytes32 indexed notehash, bytes indexed labelhash, bytes label, bytes data);
+        event Gene(bytes32 indexed entry, address indexed gene);
+        event Zero(address indexed zeroTba);
+        event Transfer(address indexed from, address indexed to, uint256 indexed id);
+        event Approval(address indexed owner, address indexed spender, uint256 indexed id);
+        event ApprovalForAll(address indexed owner, address indexed operator, bool approved);
+
+        function get(bytes32 namehash) external view returns (address tba, address owner, bytes memory data);
+        function mint(address who, bytes calldata label, bytes calldata initialization, bytes calldata erc721Data, address implementation) external returns (address tba);
+        function gene(address _gene) external;
+        function fact(bytes calldata fact, bytes calldata data) external returns (bytes32 facthash);
+        function note(bytes calldata note, bytes calldata data) external returns (bytes32 notehash);
+        function balanceOf(address owner) external view returns (uint256);
+        function getApproved(uint256 entry) external view returns (address);
+        function isApprovedForAll(address owner, address operator) external view returns (bool);
+        function ownerOf(uint256 entry) external view returns (address);
+        function setApprovalForAll(address operator, bool approved) external;
+        function approve(address spender, uint256 entry) external;
+        function safeTransferFrom(address from, address to, uint256 id) external;
+        function safeTransferFrom(address from, address to, uint256 id, bytes calldata data) external;
+        function transferFrom(address from, address to, uint256 id) external;
+        function supportsInterface(bytes4 interfaceId) external view returns (bool);
+    }
+
+    #[sol(abi)]
+    interface ERC6551Registry {
+        event ERC6551AccountCreated(address account, address indexed implementation, bytes32 salt, uint256 chainId, address indexed tokenContract, uint256 indexed tokenId);
+        error AccountCreationFailed();
+
+        function createAccount(address implementation, bytes32 salt, uint256 chainId, address tokenContract, uint256 tokenId) external returns (address account);
+        function account(address implementation, bytes32 salt, uint256 chainId, address tokenContract, uint256 tokenId) external view returns (address account);
+    }
+
+    #[sol(abi)]
+    interface ERC6551Account {
+        function token() external view returns (uint256 chainId, address tokenContract, uint256 tokenId);
+        function state() external view returns (uint256);
+        function isValidSigner(address signer, bytes calldata context) external view returns (bytes4 magicValue);
+        function execute(address to, uint256 value, bytes calldata data, uint8 operation) external payable returns (bytes memory result);
+    }
+
+    #[sol(abi)]
+    interface Multicall3 {
+        struct Call {
+            address target;
+            bytes callData;
+        }
+        struct Call3 {
+            address target;
+            bool allowFailure;
+            bytes callData;
+        }
+        struct Call3Value {
+            address target;
+            bool allowFailure;
+            uint256 value;
+            bytes callData;
+        }
+        struct Result {
+            bool success;
+            bytes returnData;
+        }
+
+        function aggregate(Call[] calldata calls) external payable returns (uint256 blockNumber, bytes[] memory returnData);
+        function aggregate3(Call3[] calldata calls) external payable returns (Result[] memory returnData);
+        function aggregate3Value(Call3Value[] calldata calls) external payable returns (Result[] memory returnData);
+        function blockAndAggregate(Call[] calldata calls) external payable returns (uint256 blockNumber, bytes32 blockHash, Result[] memory returnData);
+        function tryAggregate(bool requireSuccess, Call[] calldata calls) external payable returns (Result[] memory returnData);
+        function tryBlockAndAggregate(bool requireSuccess, Call[] calldata calls) external payable returns (uint256 blockNumber, bytes32 blockHash, Result[] memory returnData);
+        function getBasefee() external view returns (uint256 basefee);
+        function getBlockHash(uint256 blockNumber) external view returns (bytes32 blockHash);
+        function getBlockNumber() external view returns (uint256 blockNumber);
+        function getChainId() external view returns (uint256 chainid);
+        function getCurrentBlockCoinbase() external view returns (address coinbase);
+        function getCurrentBlockDifficulty() external view returns (uint256 difficulty);
+        function getCurrentBlockGasLimit() external view returns (uint256 gaslimit);
+        function getCurrentBlockTimestamp() external view returns (uint256 timestamp);
+        function getEthBalance(address addr) external view returns (uint256 balance);
+        function getLastBlockHash() external view returns (bytes32 blockHash);
+    }
+}
+
+fn get_embedded_abis() -> Vec<(&'static str, JsonAbi)> {
+    vec![
+        ("Kimap", Kimap::abi::contract()),
+        ("ERC6551Registry", ERC6551Registry::abi::contract()),
+        ("ERC6551Account", ERC6551Account::abi::contract()),
+        ("Multicall3", Multicall3::abi::contract()),
+    ]
+}
+
+/// The embedded ABI all of whose function selectors are dispatched on in
+/// `code`, i.e. appear as `PUSH4 <selector>`
+fn match_abi<'a>(
+    code: &str,
+    abis: &'a [(&'static str, JsonAbi)],
+) -> Option<&'a (&'static str, JsonAbi)> {
+    let code = code.to_lowercase();
+    abis.iter().find(|(_, abi)| {
+        abi.functions()
+            .all(|f| code.contains(&format!("63{}", hex::encode(f.selector()))))
+    })
+}
+
+/// A contract of the initial Kinode state, identified by its code
+struct Contract {
+    address: String,
+    /// The name & ABI of the embedded ABI its code matches, if any
+    matched: Option<(&'static str, JsonAbi)>,
+    /// The implementation, if the contract is an ERC-1967 proxy
+    implementation: Option<String>,
+}
+
+/// Identify each contract in the initial Kinode state by matching its code
+/// (or, for ERC-1967 proxies, their implementation's code) against the
+/// embedded ABIs
+async fn identify_contracts(
+    port: u16,
+    fakenode_version: Option<&semver::Version>,
+) -> Result<Vec<Contract>> {
+    let kinostate: serde_json::Value = serde_json::from_str(get_kinostate(fakenode_version)?)?;
+    let Some(accounts) = kinostate["accounts"].as_object() else {
+        return Err(eyre!("kinostate has no accounts to identify contracts of"));
+    };
+    let abis = get_embedded_abis();
+
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    let mut contracts = vec![];
+    for (address, account) in accounts {
+        if account["code"].as_str().unwrap_or("0x") == "0x" {
+            continue;
+        }
+        let code = call_anvil(
+            &client,
+            &url,
+            "eth_getCode",
+            serde_json::json!([address, "latest"]),
+        )
+        .await?;
+        let code = code.as_str().unwrap_or("0x");
+
+        let mut implementation = None;
+        let mut matched = match_abi(code, &abis);
+        if matched.is_none() {
+            let slot = call_anvil(
+                &client,
+                &url,
+                "eth_getStorageAt",
+                serde_json::json!([address, ERC1967_IMPLEMENTATION_SLOT, "latest"]),
+            )
+            .await?;
+            let slot = slot.as_str().unwrap_or("0x");
+            if slot.trim_start_matches("0x").chars().any(|c| c != '0') {
+                let implementation_address = format!("0x{}", &slot[slot.len() - 40..]);
+                let implementation_code = call_anvil(
+                    &client,
+                    &url,
+                    "eth_getCode",
+                    serde_json::json!([implementation_address, "latest"]),
+                )
+                .await?;
+                matched = match_abi(implementation_code.as_str().unwrap_or("0x"), &abis);
+                implementation = Some(implementation_address);
+            }
+        }
+        contracts.push(Contract {
+            address: address.clone(),
+            matched: matched.cloned(),
+            implementation,
+        });
+    }
+    Ok(contracts)
+}
+
+/// Write the JSON ABI of each contract in the initial Kinode state to
+/// `abi_dir/<address>.json`, along with an `index.json` of address to
+/// contract name
+#[instrument(level = "trace", skip_all)]
+pub async fn export_abis(
+    port: u16,
+    fakenode_version: Option<&semver::Version>,
+    abi_dir: &Path,
+) -> Result<()> {
+    let contracts = identify_contracts(port, fakenode_version).await?;
+    fs::create_dir_all(abi_dir)?;
+    let mut index = serde_json::Map::new();
+    for Contract {
+        address,
+        matched,
+        implementation,
+    } in contracts
+    {
+        let contents = match matched {
+            Some((name, abi)) => {
+                info!("{address}: {name}");
+                index.insert(address.clone(), serde_json::json!(name));
+                serde_json::json!({
+                    "address": address,
+                    "name": name,
+                    "implementation": implementation,
+                    "abi": abi,
+                })
+            }
+            None => {
+                info!("{address}: unknown contract; writing placeholder");
+                index.insert(address.clone(), serde_json::Value::Null);
+                serde_json::json!({
+                    "address": address,
+                    "name": null,
+                    "implementation": implementation,
+                    "comment": "no embedded ABI matches the code at this address",
+                    "abi": [],
+                })
+            }
+        };
+        fs::write(
+            abi_dir.join(format!("{address}.json")),
+            serde_json::to_string_pretty(&contents)?,
+        )?;
+    }
+    fs::write(
+        abi_dir.join("index.json"),
+        serde_json::to_string_pretty(&index)?,
+    )?;
+    info!("Exported ABIs of {} contracts to {abi_dir:?}", index.len());
+    Ok(())
+}
+
+/// Write a JSON object of contract name to address for each identified
+/// contract in the initial Kinode state to `path`; a name that several
+/// contracts match is suffixed `-2`, `-3`, ... after its first
+#[instrument(level = "trace", skip_all)]
+pub async fn export_addresses(
+    port: u16,
+    fakenode_version: Option<&semver::Version>,
+    path: &Path,
+) -> Result<()> {
+    let contracts = identify_contracts(port, fakenode_version).await?;
+    let mut addresses = serde_json::Map::new();
+    for contract in contracts {
+        let Some((name, _)) = contract.matched else {
+            continue;
+        };
+        let mut key = name.to_string();
+        let mut count = 1;
+        while addresses.contains_key(&key) {
+            count += 1;
+            key = format!("{name}-{count}");
+        }
+        addresses.insert(key, serde_json::json!(contract.address));
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&addresses)?)?;
+    info!(
+        "Exported addresses of {} contracts to {path:?}",
+        addresses.len()
+    );
+    Ok(())
+}
@@ -0,0 +1,85 @@
+use color_eyre::Result;
+use reqwest::Client;
+use tokio::time::{sleep, Duration};
+use tracing::{info, warn};
+
+use super::call_anvil;
+
+const VERIFY_CODE_RETRIES: u32 = 5;
+const VERIFY_CODE_RETRY_DELAY_MS: u64 = 100;
+
+pub async fn get_code(client: &Client, url: &str, address: &str) -> Result<String> {
+    let code = call_anvil(
+        client,
+        url,
+        "eth_getCode",
+        serde_json::json!([address, "latest"]),
+    )
+    .await?;
+    Ok(code.as_str().unwrap_or("0x").to_string())
+}
+
+/// Whether `address` has code, retrying while `eth_getCode` returns `0x`:
+/// a loaded machine may not yet serve code just set
+async fn verify_code_set(client: &Client, url: &str, address: &str) -> Result<bool> {
+    for attempt in 0..=VERIFY_CODE_RETRIES {
+        if attempt > 0 {
+            sleep(Duration::from_millis(VERIFY_CODE_RETRY_DELAY_MS)).await;
+        }
+        if get_code(client, url, address).await? != "0x" {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Addresses of the accounts with code in an anvil state, e.g. kinostate
+pub fn accounts_with_code(state: &serde_json::Value) -> Vec<String> {
+    state["accounts"]
+        .as_object()
+        .map(|accounts| {
+            accounts
+                .iter()
+                .filter(|(_, account)| account["code"].as_str().unwrap_or("0x") != "0x")
+                .map(|(address, _)| address.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Report whether the code of each of `addresses`, just loaded into the
+/// chain at `url`, is set & verified or set but with verification pending
+pub async fn report_code_set(client: &Client, url: &str, addresses: Vec<String>) -> Result<()> {
+    let mut pending = vec![];
+    for address in addresses {
+        if !verify_code_set(client, url, &address).await? {
+            pending.push(address);
+        }
+    }
+    if pending.is_empty() {
+        info!("Code set and verified for each Kinode contract.");
+    } else {
+        warn!(
+            "Code set but verification pending: eth_getCode still returns 0x for {pending:?} after {VERIFY_CODE_RETRIES} retries."
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accounts_with_code_skips_eoas() {
+        let state = serde_json::json!({
+            "accounts": {
+                "0x01": { "code": "0x6080", "balance": "0x0" },
+                "0x02": { "code": "0x", "balance": "0x1" },
+                "0x03": { "balance": "0x1" },
+            }
+        });
+        assert_eq!(accounts_with_code(&state), ["0x01"]);
+        assert!(accounts_with_code(&serde_json::json!({})).is_empty());
+    }
+}
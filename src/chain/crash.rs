@@ -0,0 +1,43 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+
+use nix::sys::signal::Signal;
+
+/// `exit code 1`, or `killed by SIGKILL`
+pub fn describe_exit(status: &ExitStatus) -> String {
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exit code {code}"),
+        (None, Some(signal)) => match Signal::try_from(signal) {
+            Ok(signal) => format!("killed by {signal}"),
+            Err(_) => format!("killed by signal {signal}"),
+        },
+        (None, None) => format!("{status}"),
+    }
+}
+
+/// The kernel's OOM killer sends SIGKILL; a failed allocation in anvil aborts
+pub fn is_likely_oom(status: &ExitStatus) -> bool {
+    matches!(
+        status.signal().and_then(|s| Signal::try_from(s).ok()),
+        Some(Signal::SIGKILL) | Some(Signal::SIGABRT)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describe_exit_names_codes_and_signals() {
+        assert_eq!(describe_exit(&ExitStatus::from_raw(1 << 8)), "exit code 1");
+        assert_eq!(describe_exit(&ExitStatus::from_raw(9)), "killed by SIGKILL");
+    }
+
+    #[test]
+    fn is_likely_oom_on_kill_or_abort() {
+        assert!(is_likely_oom(&ExitStatus::from_raw(9)));
+        assert!(is_likely_oom(&ExitStatus::from_raw(6)));
+        assert!(!is_likely_oom(&ExitStatus::from_raw(15)));
+        assert!(!is_likely_oom(&ExitStatus::from_raw(1 << 8)));
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result, Section,
+};
+use fs_err as fs;
+use reqwest::Client;
+use tracing::{info, instrument, warn};
+
+use super::call_anvil;
+
+/// Balance given to impersonated senders that have none, to pay for gas: 1000 ETH
+const IMPERSONATED_BALANCE: &str = "0x3635c9adc5dea00000";
+
+/// Read the `transactions` of a `forge script --broadcast` output,
+/// e.g. `broadcast/Deploy.s.sol/31337/run-latest.json`
+fn read_broadcast_transactions(script_path: &Path) -> Result<Vec<serde_json::Value>> {
+    let broadcast: serde_json::Value = serde_json::from_str(&fs::read_to_string(script_path)?)
+        .wrap_err_with(|| format!("Failed to parse {script_path:?}"))?;
+    let Some(transactions) = broadcast["transactions"].as_array() else {
+        return Err(eyre!("{script_path:?} has no `transactions`")
+            .with_suggestion(|| "Pass the JSON output of `forge script --broadcast`."));
+    };
+    Ok(transactions.clone())
+}
+
+/// Accounts anvil holds keys for, lowercase
+pub(super) async fn get_unlocked_accounts(client: &Client, url: &str) -> Result<HashSet<String>> {
+    let accounts = call_anvil(client, url, "eth_accounts", serde_json::json!([])).await?;
+    Ok(accounts
+        .as_array()
+        .map(|a| {
+            a.iter()
+                .filter_map(|a| a.as_str().map(|a| a.to_lowercase()))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Let `eth_sendTransaction` send from `from`, funding it for gas if it has
+/// no balance; a no-op for accounts already in `unlocked`
+pub(super) async fn impersonate(
+    client: &Client,
+    url: &str,
+    from: &str,
+    unlocked: &mut HashSet<String>,
+) -> Result<()> {
+    if !unlocked.insert(from.to_lowercase()) {
+        return Ok(());
+    }
+    call_anvil(
+        client,
+        url,
+        "anvil_impersonateAccount",
+        serde_json::json!([from]),
+    )
+    .await?;
+    let balance = call_anvil(
+        client,
+        url,
+        "eth_getBalance",
+        serde_json::json!([from, "latest"]),
+    )
+    .await?;
+    if balance.as_str() == Some("0x0") {
+        call_anvil(
+            client,
+            url,
+            "anvil_setBalance",
+            serde_json::json!([from, IMPERSONATED_BALANCE]),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Replay the transactions of a Forge broadcast file, in order, via
+/// `eth_sendTransaction`, impersonating any sender anvil does not hold a key for
+#[instrument(level = "trace", skip_all)]
+pub async fn replay_deployment_script(port: u16, script_path: &Path) -> Result<()> {
+    let transactions = read_broadcast_transactions(script_path)?;
+
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    let mut unlocked = get_unlocked_accounts(&client, &url).await?;
+
+    let num_transactions = transactions.len();
+    for (i, broadcast_transaction) in transactions.iter().enumerate() {
+        let transaction = &broadcast_transaction["transaction"];
+        let Some(from) = transaction["from"].as_str() else {
+            return Err(eyre!("transaction {i} of {script_path:?} has no `from`"));
+        };
+        impersonate(&client, &url, from, &mut unlocked).await?;
+
+        let mut params = serde_json::json!({ "from": from });
+        for field in ["to", "gas", "value"] {
+            if !transaction[field].is_null() {
+                params[field] = transaction[field].clone();
+            }
+        }
+        // newer Forge versions write `input`, older ones `data`
+        let input = match transaction["input"] {
+            serde_json::Value::Null => &transaction["data"],
+            ref input => input,
+        };
+        if !input.is_null() {
+            params["input"] = input.clone();
+        }
+
+        let description = format!(
+            "{} {}{}",
+            broadcast_transaction["transactionType"]
+                .as_str()
+                .unwrap_or("CALL"),
+            broadcast_transaction["contractName"]
+                .as_str()
+                .unwrap_or("<unknown>"),
+            broadcast_transaction["function"]
+                .as_str()
+                .map(|f| format!(".{f}"))
+                .unwrap_or_default(),
+        );
+        let hash = call_anvil(
+            &client,
+            &url,
+            "eth_sendTransaction",
+            serde_json::json!([params]),
+        )
+        .await
+        .wrap_err_with(|| format!("Failed to replay transaction {i} ({description})"))?;
+        let hash = hash.as_str().unwrap_or_default().to_string();
+        let receipt = call_anvil(
+            &client,
+            &url,
+            "eth_getTransactionReceipt",
+            serde_json::json!([hash]),
+        )
+        .await?;
+        if receipt["status"].as_str() != Some("0x1") {
+            return Err(eyre!(
+                "transaction {i} ({description}) of {script_path:?} reverted: {hash}"
+            ));
+        }
+
+        let expected_address = broadcast_transaction["contractAddress"].as_str();
+        let address = receipt["contractAddress"].as_str();
+        match (expected_address, address) {
+            (Some(expected), Some(address)) if !expected.eq_ignore_ascii_case(address) => {
+                warn!("[{}/{num_transactions}] {description}: deployed at {address}, not {expected} as in the broadcast", i + 1)
+            }
+            (_, Some(address)) => {
+                info!(
+                    "[{}/{num_transactions}] {description}: deployed at {address}",
+                    i + 1
+                )
+            }
+            (_, None) => info!("[{}/{num_transactions}] {description}: {hash}", i + 1),
+        }
+    }
+    info!("Replayed {num_transactions} transactions from {script_path:?}");
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use color_eyre::{eyre::eyre, Result};
+use reqwest::Client;
+use tracing::{info, instrument, warn};
+
+use super::call_anvil;
+use super::code::{accounts_with_code, get_code, report_code_set};
+
+/// Kimap proxies live on public chains, as (chain ID, chain, address).
+/// Forking one of these chains gives the real Kimap at its real address;
+/// the Kinode contracts of the fake chain, including its Kimap at
+/// `0xEce71a05B36CA55B895427cD9a440eEF7Cf3669D`, are added alongside
+/// wherever the forked chain has no code
+pub const LIVE_KIMAP_PROXIES: &[(u64, &str, &str)] =
+    &[(10, "Optimism", "0xcA92476B2483aBD5D82AEBF0b56701Bb2e9be658")];
+
+/// Report which Kimap, if any, is live on the forked chain
+async fn report_live_kimap(client: &Client, url: &str) -> Result<()> {
+    let chain_id = call_anvil(client, url, "eth_chainId", serde_json::json!([])).await?;
+    let Some(chain_id) = chain_id
+        .as_str()
+        .and_then(|c| u64::from_str_radix(c.trim_start_matches("0x"), 16).ok())
+    else {
+        return Err(eyre!("Got invalid chain ID from forked chain: {chain_id}"));
+    };
+    match LIVE_KIMAP_PROXIES.iter().find(|(id, _, _)| *id == chain_id) {
+        Some((_, chain, address)) if get_code(client, url, address).await? != "0x" => {
+            info!("Forked {chain} (chain ID {chain_id}): Kimap is live at {address}.");
+        }
+        Some((_, chain, address)) => {
+            warn!(
+                "Forked {chain} (chain ID {chain_id}) has no Kimap at {address} at the fork block."
+            );
+        }
+        None => {
+            warn!("No Kimap is live on forked chain ID {chain_id}; only the fake chain's Kimap will be available.");
+        }
+    }
+    Ok(())
+}
+
+/// Load the Kinode contracts & accounts of `kinostate_content` into a
+/// chain forked from another network, skipping any address that already
+/// has code at the forked state
+#[instrument(level = "trace", skip_all)]
+pub async fn load_kinostate_over_fork(port: u16, kinostate_content: &str) -> Result<()> {
+    let kinostate: serde_json::Value = serde_json::from_str(kinostate_content)?;
+    let Some(accounts) = kinostate["accounts"].as_object() else {
+        return Err(eyre!("kinostate has no `accounts`"));
+    };
+
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    report_live_kimap(&client, &url).await?;
+
+    let mut skipped = vec![];
+    let mut injected = serde_json::Map::new();
+    for (address, account) in accounts {
+        if get_code(&client, &url, address).await? != "0x" {
+            skipped.push(address.to_string());
+        } else {
+            injected.insert(address.clone(), account.clone());
+        }
+    }
+    let num_accounts = injected.len();
+    let state = serde_json::json!({ "accounts": injected });
+    call_anvil(
+        &client,
+        &url,
+        "anvil_loadState",
+        serde_json::json!([format!("0x{}", hex::encode(state.to_string()))]),
+    )
+    .await?;
+    if !skipped.is_empty() {
+        info!("Code already present: kept forked contracts over Kinode state for {skipped:?}.");
+    }
+    report_code_set(&client, &url, accounts_with_code(&state)).await?;
+    info!("Loaded {num_accounts} Kinode state accounts over forked chain.");
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result, Section,
+};
+use fs_err as fs;
+use reqwest::Client;
+use tracing::{info, instrument};
+
+use super::call_anvil;
+use super::code::{accounts_with_code, report_code_set};
+
+/// `0xabc...`, lowercase, whether or not the genesis prefixes with `0x`
+fn normalize_address(address: &str) -> String {
+    let address = address.to_lowercase();
+    match address.strip_prefix("0x") {
+        Some(_) => address,
+        None => format!("0x{address}"),
+    }
+}
+
+/// Addresses given an `alloc` entry in a standard Ethereum genesis JSON
+fn read_genesis_alloc(genesis_path: &Path) -> Result<HashSet<String>> {
+    let genesis: serde_json::Value = serde_json::from_str(&fs::read_to_string(genesis_path)?)
+        .wrap_err_with(|| format!("Failed to parse {genesis_path:?}"))?;
+    let Some(alloc) = genesis["alloc"].as_object() else {
+        return Err(eyre!("{genesis_path:?} has no `alloc`")
+            .with_suggestion(|| "Pass a standard Ethereum genesis JSON file."));
+    };
+    Ok(alloc.keys().map(|a| normalize_address(a)).collect())
+}
+
+/// Load the Kinode contracts & accounts of `kinostate_content` into a
+/// chain started from `genesis_path`, skipping any the genesis allocates
+#[instrument(level = "trace", skip_all)]
+pub async fn load_kinostate_over_genesis(
+    port: u16,
+    kinostate_content: &str,
+    genesis_path: &Path,
+) -> Result<()> {
+    let genesis_alloc = read_genesis_alloc(genesis_path)?;
+    let kinostate: serde_json::Value = serde_json::from_str(kinostate_content)?;
+    let Some(accounts) = kinostate["accounts"].as_object() else {
+        return Err(eyre!("kinostate has no `accounts`"));
+    };
+
+    let mut skipped = vec![];
+    let accounts: serde_json::Map<String, serde_json::Value> = accounts
+        .iter()
+        .filter(|(address, _)| {
+            let in_genesis = genesis_alloc.contains(&normalize_address(address));
+            if in_genesis {
+                skipped.push(address.to_string());
+            }
+            !in_genesis
+        })
+        .map(|(address, account)| (address.clone(), account.clone()))
+        .collect();
+    let num_accounts = accounts.len();
+    let state = serde_json::json!({ "accounts": accounts });
+
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    call_anvil(
+        &client,
+        &url,
+        "anvil_loadState",
+        serde_json::json!([format!("0x{}", hex::encode(state.to_string()))]),
+    )
+    .await?;
+    if !skipped.is_empty() {
+        info!("Kept genesis accounts over Kinode state for {skipped:?}.");
+    }
+    report_code_set(&client, &url, accounts_with_code(&state)).await?;
+    info!("Loaded {num_accounts} Kinode state accounts over genesis {genesis_path:?}.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_address_lowercases_and_prefixes() {
+        assert_eq!(normalize_address("0xAbC"), "0xabc");
+        assert_eq!(normalize_address("AbC"), "0xabc");
+    }
+
+    #[test]
+    fn read_genesis_alloc_normalizes_addresses() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis_path = dir.path().join("genesis.json");
+        fs::write(
+            &genesis_path,
+            r#"{"alloc":{"AbC":{"balance":"0x1"},"0xDEF":{"balance":"0x1"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            read_genesis_alloc(&genesis_path).unwrap(),
+            HashSet::from(["0xabc".to_string(), "0xdef".to_string()]),
+        );
+        fs::write(&genesis_path, r#"{"config":{}}"#).unwrap();
+        assert!(read_genesis_alloc(&genesis_path).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use tracing::warn;
+
+/// The hardfork of Optimism, where the Kinode contracts live, since Ecotone
+pub const DEFAULT_HARDFORK: &str = "cancun";
+
+/// Anvil's hardforks, oldest first
+pub const HARDFORKS: &[&str] = &[
+    "frontier",
+    "homestead",
+    "dao",
+    "tangerine",
+    "spuriousdragon",
+    "byzantium",
+    "constantinople",
+    "petersburg",
+    "istanbul",
+    "muirglacier",
+    "berlin",
+    "london",
+    "arrowglacier",
+    "grayglacier",
+    "paris",
+    "shanghai",
+    "cancun",
+    "prague",
+    "latest",
+];
+
+fn predates(hardfork: &str, other: &str) -> bool {
+    let index = |h: &str| HARDFORKS.iter().position(|f| *f == h);
+    matches!((index(hardfork), index(other)), (Some(h), Some(o)) if h < o)
+}
+
+/// Warn of the ways in which `hardfork` breaks the Kinode contracts
+pub fn warn_if_incompatible(hardfork: &str) {
+    if predates(hardfork, "london") {
+        warn!(
+            "Hardfork {hardfork} predates London (EIP-1559): nodes send type-2 transactions, \
+            so registering names & writing notes/facts to Kimap will fail."
+        );
+    }
+    if predates(hardfork, "shanghai") {
+        warn!(
+            "Hardfork {hardfork} predates Shanghai (PUSH0): calls to the preloaded Kimap, \
+            KinoAccount & Multicall contracts, compiled for Shanghai or later, may fail."
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn predates_follows_hardfork_order() {
+        assert!(predates("berlin", "london"));
+        assert!(!predates("london", "london"));
+        assert!(!predates("cancun", "shanghai"));
+        assert!(!predates("unknown", "london"));
+    }
+
+    #[test]
+    fn default_hardfork_is_known() {
+        assert!(HARDFORKS.contains(&DEFAULT_HARDFORK));
+    }
+}
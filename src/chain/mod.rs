@@ -1,6 +1,9 @@
 use std::collections::HashMap;
+use std::os::unix::process::CommandExt;
 use std::path::PathBuf;
 use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Arc;
 
 use color_eyre::{
     eyre::{eyre, Result},
@@ -9,13 +12,30 @@ use color_eyre::{
 use fs_err as fs;
 use reqwest::Client;
 use tokio::time::{sleep, Duration};
-use tracing::{info, instrument};
+use tracing::{error, info, instrument, warn};
 
 use crate::run_tests::cleanup::{clean_process_by_pid, cleanup_on_signal};
 use crate::run_tests::types::BroadcastRecvBool;
 use crate::setup::{check_foundry_deps, get_deps};
 use crate::KIT_CACHE;
 
+mod abis;
+mod code;
+mod crash;
+mod deployment_script;
+mod fork;
+mod genesis;
+mod hardfork;
+mod replay;
+mod rpc_proxy;
+mod snapshot;
+mod time;
+pub use hardfork::{DEFAULT_HARDFORK, HARDFORKS};
+pub use replay::replay_trace;
+pub use snapshot::{restore, snapshot};
+pub use time::{advance_time, set_time, time_travel};
+mod watch_storage;
+
 include!("../../target/chain_includes.rs");
 
 const DEFAULT_MAX_ATTEMPTS: u16 = 16;
@@ -27,13 +47,52 @@ pub const FOUNDRY_COMMIT_TO_DATE: &[(&str, &str)] = &[
 ];
 pub const FOUNDRY_NEWEST_COMMIT: &str = "c3069a5";
 
+/// How to run anvil: one field per `kit chain` flag;
+///  `Default` is a plain anvil loaded with Kinode state
+#[derive(Debug, Clone, Default)]
+pub struct ChainOptions {
+    pub rpc_proxy_port: Option<u16>,
+    pub rpc_log: Option<PathBuf>,
+    pub rpc_log_max_mb: Option<u64>,
+    pub reset: bool,
+    pub watch_storage: Vec<String>,
+    pub watch_interval_ms: u64,
+    pub impersonate: Option<String>,
+    pub verify: bool,
+    pub export_abis: Option<PathBuf>,
+    pub export_addresses: Option<PathBuf>,
+    pub deployment_script: Option<PathBuf>,
+    pub timestamp: Option<u64>,
+    pub block_time: Option<f64>,
+    pub hardfork: Option<String>,
+    pub genesis_file: Option<PathBuf>,
+    pub fork_url: Option<String>,
+    pub fork_block_number: Option<u64>,
+    pub snapshot: Option<PathBuf>,
+    /// Anvil then runs in its own process group, so that it outlives Ctrl-C until dumped
+    pub dump_state: Option<PathBuf>,
+    pub verbose: bool,
+}
+
 #[instrument(level = "trace", skip_all)]
 pub async fn start_chain(
     port: u16,
     mut recv_kill: BroadcastRecvBool,
     fakenode_version: Option<semver::Version>,
-    verbose: bool,
+    options: &ChainOptions,
 ) -> Result<Option<Child>> {
+    let ChainOptions {
+        timestamp,
+        block_time,
+        fork_block_number,
+        verbose,
+        ..
+    } = *options;
+    let hardfork = options.hardfork.as_deref();
+    let genesis_file = options.genesis_file.as_deref();
+    let fork_url = options.fork_url.as_deref();
+    let snapshot = options.snapshot.as_deref();
+    let own_process_group = options.dump_state.is_some();
     let fakenode_to_foundry: HashMap<semver::VersionReq, String> = FAKENODE_TO_FOUNDRY
         .iter()
         .map(|ss| (ss.0.parse().unwrap(), ss.1.to_string()))
@@ -80,11 +139,37 @@ pub async fn start_chain(
         return Ok(None);
     }
 
-    let mut child = Command::new("anvil")
-        .arg("--port")
-        .arg(port.to_string())
-        .arg("--load-state")
-        .arg(&kinostate_path)
+    let mut command = Command::new("anvil");
+    command.arg("--port").arg(port.to_string());
+    // Kinode state is loaded once anvil is up, so as to not clobber the genesis
+    // or the forked state; a snapshot replaces it
+    match (genesis_file, fork_url) {
+        (Some(genesis_file), _) => command.arg("--init").arg(genesis_file.canonicalize()?),
+        (None, Some(fork_url)) => command.arg("--fork-url").arg(fork_url),
+        (None, None) if snapshot.is_some() => &mut command,
+        (None, None) => command.arg("--load-state").arg(&kinostate_path),
+    };
+    if let Some(fork_block_number) = fork_block_number {
+        command
+            .arg("--fork-block-number")
+            .arg(fork_block_number.to_string());
+    }
+    if let Some(timestamp) = timestamp {
+        command.arg("--timestamp").arg(timestamp.to_string());
+    }
+    // `0`, like no block time, mines a block as each transaction arrives
+    if let Some(block_time) = block_time.filter(|b| *b > 0.0) {
+        command.arg("--block-time").arg(block_time.to_string());
+    }
+    if let Some(hardfork) = hardfork {
+        hardfork::warn_if_incompatible(hardfork);
+        command.arg("--hardfork").arg(hardfork);
+    }
+    if own_process_group {
+        // keep a terminal's Ctrl-C from reaching anvil: kit stops it in cleanup
+        command.process_group(0);
+    }
+    let mut child = command
         .current_dir(KIT_CACHE)
         .stdout(if verbose {
             Stdio::inherit()
@@ -98,6 +183,30 @@ pub async fn start_chain(
         let _ = child.kill();
         return Err(e);
     }
+    if let Some(genesis_file) = genesis_file {
+        if let Err(e) =
+            genesis::load_kinostate_over_genesis(port, kinostate_content, genesis_file).await
+        {
+            let _ = child.kill();
+            return Err(e);
+        }
+    } else if fork_url.is_some() {
+        if let Err(e) = fork::load_kinostate_over_fork(port, kinostate_content).await {
+            let _ = child.kill();
+            return Err(e);
+        }
+    } else if let Some(snapshot_path) = snapshot {
+        if let Err(e) = snapshot::load_snapshot(port, snapshot_path).await {
+            let _ = child.kill();
+            return Err(e);
+        }
+        info!("Loaded snapshot {snapshot_path:?}.");
+    } else {
+        // anvil loaded kinostate with `--load-state`
+        let kinostate: serde_json::Value = serde_json::from_str(kinostate_content)?;
+        let url = format!("http://localhost:{}", port);
+        code::report_code_set(&Client::new(), &url, code::accounts_with_code(&kinostate)).await?;
+    }
 
     Ok(Some(child))
 }
@@ -154,9 +263,189 @@ async fn wait_for_anvil(
     .with_suggestion(|| "Is port already occupied?"))
 }
 
+#[instrument(level = "trace", skip_all)]
+async fn call_anvil(
+    client: &Client,
+    url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value> {
+    let request_body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+        "id": 1
+    });
+    let response: serde_json::Value = client
+        .post(url)
+        .json(&request_body)
+        .send()
+        .await?
+        .json()
+        .await?;
+    if let Some(error) = response.get("error") {
+        return Err(eyre!("{method} failed: {error}"));
+    }
+    Ok(response["result"].clone())
+}
+
+fn check_address(address: &str, flag: &str) -> Result<()> {
+    let is_address = address.len() == 42
+        && address.starts_with("0x")
+        && address[2..].chars().all(|c| c.is_ascii_hexdigit());
+    if !is_address {
+        return Err(eyre!("Invalid {flag} address {address}")
+            .with_suggestion(|| "Address must be `0x` followed by 40 hex digits."));
+    }
+    Ok(())
+}
+
+/// Impersonate `address` for the rest of the session, with a zero base fee so
+/// that it can send transactions without being funded or stopping impersonation
+#[instrument(level = "trace", skip_all)]
+async fn impersonate(port: u16, address: &str) -> Result<()> {
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    call_anvil(
+        &client,
+        &url,
+        "anvil_setNextBlockBaseFeePerGas",
+        serde_json::json!(["0x0"]),
+    )
+    .await?;
+    call_anvil(
+        &client,
+        &url,
+        "anvil_impersonateAccount",
+        serde_json::json!([address]),
+    )
+    .await?;
+    info!(
+        "Impersonating {} for all transactions on port {}.",
+        address, port
+    );
+    Ok(())
+}
+
+/// The initial Kinode chain state (an anvil state dump) for the given Kinode version
+fn get_kinostate(fakenode_version: Option<&semver::Version>) -> Result<&'static str> {
+    let commit = match fakenode_version {
+        None => FOUNDRY_NEWEST_COMMIT,
+        Some(v) => FAKENODE_TO_FOUNDRY
+            .iter()
+            .find(|(vr, _)| vr.parse::<semver::VersionReq>().unwrap().matches(v))
+            .map(|(_, commit)| *commit)
+            .ok_or_else(|| eyre!("No foundry version known for Kinode version {v}"))?,
+    };
+    FOUNDRY_COMMIT_TO_CONTENT
+        .iter()
+        .find(|(c, _)| *c == commit)
+        .map(|(_, kinostate_content)| *kinostate_content)
+        .ok_or_else(|| eyre!("couldn't find kinostate content for foundry commit {commit}"))
+}
+
+/// Compare the code of each contract in the initial Kinode state against
+/// `eth_getCode` on the chain on `port`, printing OK or MISMATCH for each
+#[instrument(level = "trace", skip_all)]
+async fn verify_chain(port: u16, fakenode_version: Option<&semver::Version>) -> Result<()> {
+    let kinostate: serde_json::Value = serde_json::from_str(get_kinostate(fakenode_version)?)?;
+    let Some(accounts) = kinostate["accounts"].as_object() else {
+        return Err(eyre!("kinostate has no accounts to verify"));
+    };
+
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    let mut mismatches = 0;
+    let mut contracts = 0;
+    for (address, account) in accounts {
+        let expected = account["code"].as_str().unwrap_or("0x").to_lowercase();
+        if expected == "0x" {
+            continue;
+        }
+        contracts += 1;
+        let code = call_anvil(
+            &client,
+            &url,
+            "eth_getCode",
+            serde_json::json!([address, "latest"]),
+        )
+        .await?;
+        if code.as_str().map(|c| c.to_lowercase()) == Some(expected) {
+            info!("{address}: OK");
+        } else {
+            mismatches += 1;
+            warn!("{address}: MISMATCH");
+        }
+    }
+
+    if mismatches > 0 {
+        return Err(eyre!(
+            "{mismatches} of {contracts} contracts on port {port} do not match the expected bytecode."
+        )
+        .with_suggestion(|| "Restore the initial state with `kit chain --reset`."));
+    }
+    info!("All {contracts} contracts on port {port} match the expected bytecode.");
+    Ok(())
+}
+
+/// Reset the chain running on `port` to the initial Kinode state
+/// (`anvil_reset` then `anvil_loadState`) without restarting anvil
+#[instrument(level = "trace", skip_all)]
+async fn reset_chain(port: u16, fakenode_version: Option<semver::Version>) -> Result<()> {
+    let kinostate_content = get_kinostate(fakenode_version.as_ref())?;
+
+    if wait_for_anvil(port, 1, None).await.is_err() {
+        return Err(eyre!("No chain running on port {port} to reset.")
+            .with_suggestion(|| "Start one with `kit chain`."));
+    }
+
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    call_anvil(&client, &url, "anvil_reset", serde_json::json!([])).await?;
+    call_anvil(
+        &client,
+        &url,
+        "anvil_loadState",
+        serde_json::json!([format!("0x{}", hex::encode(kinostate_content))]),
+    )
+    .await?;
+    let kinostate: serde_json::Value = serde_json::from_str(kinostate_content)?;
+    code::report_code_set(&client, &url, code::accounts_with_code(&kinostate)).await?;
+
+    info!("Reset chain on port {} to initial state.", port);
+    Ok(())
+}
+
 /// kit chain, alias to anvil
 #[instrument(level = "trace", skip_all)]
-pub async fn execute(port: u16, version: &str, verbose: bool) -> Result<()> {
+pub async fn execute(port: u16, version: &str, options: &ChainOptions) -> Result<()> {
+    let ChainOptions {
+        rpc_proxy_port,
+        rpc_log_max_mb,
+        reset,
+        ref watch_storage,
+        watch_interval_ms,
+        verify,
+        ..
+    } = *options;
+    let rpc_log = options.rpc_log.as_deref();
+    let impersonate_address = options.impersonate.as_deref();
+    let export_abis = options.export_abis.as_deref();
+    let export_addresses = options.export_addresses.as_deref();
+    let deployment_script = options.deployment_script.as_deref();
+    let version: Option<semver::Version> = if version == "latest" {
+        None
+    } else {
+        Some(version.parse()?)
+    };
+    if reset {
+        return reset_chain(port, version).await;
+    }
+    let storage_watches = watch_storage::parse_storage_watches(watch_storage)?;
+    if let Some(address) = impersonate_address {
+        check_address(address, "--impersonate")?;
+    }
+
     let (send_to_cleanup, mut recv_in_cleanup) = tokio::sync::mpsc::unbounded_channel();
     let (send_to_kill, _recv_kill) = tokio::sync::broadcast::channel(1);
     let recv_kill_in_cos = send_to_kill.subscribe();
@@ -164,12 +453,28 @@ pub async fn execute(port: u16, version: &str, verbose: bool) -> Result<()> {
     let handle_signals = tokio::spawn(cleanup_on_signal(send_to_cleanup.clone(), recv_kill_in_cos));
 
     let recv_kill_in_start_chain = send_to_kill.subscribe();
-    let version = if version == "latest" {
-        None
-    } else {
-        Some(version.parse()?)
-    };
-    let child = start_chain(port, recv_kill_in_start_chain, version, verbose).await?;
+    let child = start_chain(port, recv_kill_in_start_chain, version.clone(), options).await?;
+    if child.is_none()
+        && (verify
+            || export_abis.is_some()
+            || export_addresses.is_some()
+            || deployment_script.is_some())
+    {
+        // deploy to, verify, and/or export from the chain that was already running
+        if let Some(script_path) = deployment_script {
+            deployment_script::replay_deployment_script(port, script_path).await?;
+        }
+        if verify {
+            verify_chain(port, version.as_ref()).await?;
+        }
+        if let Some(abi_dir) = export_abis {
+            abis::export_abis(port, version.as_ref(), abi_dir).await?;
+        }
+        if let Some(addresses_path) = export_addresses {
+            abis::export_addresses(port, version.as_ref(), addresses_path).await?;
+        }
+        return Ok(());
+    }
     let Some(mut child) = child else {
         return Err(eyre!(
             "Port {} is already in use by another anvil process",
@@ -177,18 +482,192 @@ pub async fn execute(port: u16, version: &str, verbose: bool) -> Result<()> {
         ));
     };
     let child_id = child.id() as i32;
+    let shared_child_id = Arc::new(AtomicI32::new(child_id));
 
-    let cleanup_anvil = tokio::spawn(async move {
-        recv_in_cleanup.recv().await;
-        clean_process_by_pid(child_id);
-    });
+    if let Some(script_path) = deployment_script {
+        if let Err(e) = deployment_script::replay_deployment_script(port, script_path).await {
+            clean_process_by_pid(child_id);
+            return Err(e);
+        }
+    }
+
+    if verify {
+        if let Err(e) = verify_chain(port, version.as_ref()).await {
+            clean_process_by_pid(child_id);
+            return Err(e);
+        }
+    }
+
+    if let Some(abi_dir) = export_abis {
+        if let Err(e) = abis::export_abis(port, version.as_ref(), abi_dir).await {
+            clean_process_by_pid(child_id);
+            return Err(e);
+        }
+    }
+
+    if let Some(addresses_path) = export_addresses {
+        if let Err(e) = abis::export_addresses(port, version.as_ref(), addresses_path).await {
+            clean_process_by_pid(child_id);
+            return Err(e);
+        }
+    }
+
+    if let Some(address) = impersonate_address {
+        if let Err(e) = impersonate(port, address).await {
+            clean_process_by_pid(child_id);
+            return Err(e);
+        }
+    }
+
+    if let Some(rpc_proxy_port) = rpc_proxy_port {
+        if let Err(e) = rpc_proxy::start_rpc_proxy(
+            rpc_proxy_port,
+            port,
+            rpc_log,
+            rpc_log_max_mb,
+            send_to_kill.subscribe(),
+        )
+        .await
+        {
+            clean_process_by_pid(child_id);
+            return Err(e);
+        }
+    }
+
+    if !storage_watches.is_empty() {
+        if let Err(e) = watch_storage::start_watch_storage(
+            port,
+            storage_watches,
+            watch_interval_ms,
+            send_to_kill.subscribe(),
+        )
+        .await
+        {
+            clean_process_by_pid(child_id);
+            return Err(e);
+        }
+    }
+
+    // set once kit stops anvil, so that its exiting is not taken for a crash
+    let stopping = Arc::new(AtomicBool::new(false));
+    let dump_state = options.dump_state.clone();
+    let cleanup_anvil = {
+        let stopping = Arc::clone(&stopping);
+        let shared_child_id = Arc::clone(&shared_child_id);
+        tokio::spawn(async move {
+            recv_in_cleanup.recv().await;
+            if let Some(dump_state) = dump_state {
+                match snapshot::dump_state(port, &dump_state).await {
+                    Ok(()) => info!("Dumped chain state to {dump_state:?}."),
+                    Err(e) => error!("Failed to dump chain state to {dump_state:?}: {e:?}"),
+                }
+            }
+            stopping.store(true, Ordering::SeqCst);
+            clean_process_by_pid(shared_child_id.load(Ordering::SeqCst));
+        })
+    };
 
-    let _ = child.wait();
+    let mut restarted = false;
+    let result = loop {
+        let status = match child.wait() {
+            Ok(status) => status,
+            Err(e) => break Err(e.into()),
+        };
+        if status.success() || stopping.load(Ordering::SeqCst) {
+            break Ok(());
+        }
+        let exit = crash::describe_exit(&status);
+        let cause = if crash::is_likely_oom(&status) {
+            "likely out of memory"
+        } else {
+            "possibly out of memory"
+        };
+        if restarted {
+            break Err(eyre!(
+                "Anvil on port {port} crashed again after restarting ({exit}), {cause}"
+            )
+            .with_suggestion(|| {
+                "Give anvil more memory, or restart `kit chain` to begin again from the initial Kinode state."
+            }));
+        }
+        restarted = true;
+        error!(
+            "!!! Anvil on port {port} crashed ({exit}), {cause}: restarting it once; chain state since it started is lost !!!"
+        );
 
+        let restarted_child = match start_chain(
+            port,
+            send_to_kill.subscribe(),
+            version.clone(),
+            options,
+        )
+        .await
+        {
+            Ok(Some(restarted_child)) => restarted_child,
+            Ok(None) => {
+                break Err(eyre!(
+                    "Failed to restart anvil after it crashed ({exit}), {cause}: port {port} was taken by another anvil process"
+                ))
+            }
+            Err(e) => {
+                break Err(e.wrap_err(format!(
+                    "Failed to restart anvil after it crashed ({exit}), {cause}"
+                )))
+            }
+        };
+        shared_child_id.store(restarted_child.id() as i32, Ordering::SeqCst);
+        child = restarted_child;
+
+        if let Some(script_path) = deployment_script {
+            if let Err(e) = deployment_script::replay_deployment_script(port, script_path).await {
+                clean_process_by_pid(child.id() as i32);
+                break Err(e.wrap_err("Failed to redeploy to restarted anvil"));
+            }
+        }
+        if let Some(address) = impersonate_address {
+            if let Err(e) = impersonate(port, address).await {
+                clean_process_by_pid(child.id() as i32);
+                break Err(e);
+            }
+        }
+        warn!("Restarted anvil on port {port}.");
+    };
+
+    let _ = send_to_kill.send(true);
     let _ = handle_signals.await;
     let _ = cleanup_anvil.await;
 
-    let _ = send_to_kill.send(true);
+    result
+}
 
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_address_requires_0x_and_40_hex_digits() {
+        assert!(check_address(
+            "0xEce71a05B36CA55B895427cD9a440eEF7Cf3669D",
+            "--impersonate"
+        )
+        .is_ok());
+        assert!(check_address(
+            "Ece71a05B36CA55B895427cD9a440eEF7Cf3669D00",
+            "--impersonate"
+        )
+        .is_err());
+        assert!(check_address("0xEce71a05", "--impersonate").is_err());
+        assert!(check_address(
+            "0xZce71a05B36CA55B895427cD9a440eEF7Cf3669D",
+            "--impersonate"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn get_kinostate_defaults_to_newest() {
+        let kinostate: serde_json::Value =
+            serde_json::from_str(get_kinostate(None).unwrap()).unwrap();
+        assert!(!code::accounts_with_code(&kinostate).is_empty());
+    }
 }
@@ -0,0 +1,147 @@
+use std::path::Path;
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result, Section,
+};
+use fs_err as fs;
+use reqwest::Client;
+use tracing::{info, instrument, warn};
+
+use super::call_anvil;
+use super::deployment_script::{get_unlocked_accounts, impersonate};
+
+/// Flatten a trace into its transactions, in order. Accepts any nesting of
+/// JSON-RPC responses (`{"result": ...}`), blocks with full transactions
+/// (`eth_getBlockByNumber(_, true)`), lists of either, and transactions
+/// (`eth_getTransactionByHash`)
+fn collect_transactions(value: &serde_json::Value, transactions: &mut Vec<serde_json::Value>) {
+    match value {
+        serde_json::Value::Array(values) => {
+            for value in values {
+                collect_transactions(value, transactions);
+            }
+        }
+        serde_json::Value::Object(object) => {
+            if let Some(result) = object.get("result") {
+                collect_transactions(result, transactions);
+            } else if let Some(block_transactions) = object.get("transactions") {
+                collect_transactions(block_transactions, transactions);
+            } else if object.contains_key("from") {
+                transactions.push(value.clone());
+            }
+        }
+        _ => {}
+    }
+}
+
+fn read_trace_transactions(trace_path: &Path) -> Result<Vec<serde_json::Value>> {
+    let trace: serde_json::Value = serde_json::from_str(&fs::read_to_string(trace_path)?)
+        .wrap_err_with(|| format!("Failed to parse {trace_path:?}"))?;
+    let mut transactions = vec![];
+    collect_transactions(&trace, &mut transactions);
+    if transactions.is_empty() {
+        return Err(eyre!("{trace_path:?} has no transactions").with_suggestion(|| {
+            "Pass transactions as returned by `eth_getTransactionByHash` or blocks as returned by `eth_getBlockByNumber` with full transactions."
+        }));
+    }
+    Ok(transactions)
+}
+
+/// kit chain replay: send the transactions of a trace, in order, to the
+/// running chain with their original gas limits, values & inputs, from
+/// `sender` if given, else from their original senders (impersonated)
+#[instrument(level = "trace", skip_all)]
+pub async fn replay_trace(port: u16, trace_path: &Path, sender: Option<&str>) -> Result<()> {
+    let transactions = read_trace_transactions(trace_path)?;
+
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    let mut unlocked = get_unlocked_accounts(&client, &url).await?;
+
+    let num_transactions = transactions.len();
+    let mut num_reverted = 0;
+    for (i, transaction) in transactions.iter().enumerate() {
+        let original_hash = transaction["hash"].as_str().unwrap_or("<no hash>");
+        let Some(from) = sender.or_else(|| transaction["from"].as_str()) else {
+            return Err(eyre!("transaction {i} of {trace_path:?} has no `from`"));
+        };
+        impersonate(&client, &url, from, &mut unlocked).await?;
+
+        let mut params = serde_json::json!({ "from": from });
+        for field in ["to", "gas", "value"] {
+            if !transaction[field].is_null() {
+                params[field] = transaction[field].clone();
+            }
+        }
+        // archive nodes write `input`; some exporters `data`
+        let input = match transaction["input"] {
+            serde_json::Value::Null => &transaction["data"],
+            ref input => input,
+        };
+        if !input.is_null() {
+            params["input"] = input.clone();
+        }
+
+        let hash = call_anvil(
+            &client,
+            &url,
+            "eth_sendTransaction",
+            serde_json::json!([params]),
+        )
+        .await
+        .wrap_err_with(|| format!("Failed to replay transaction {i} ({original_hash})"))?;
+        let hash = hash.as_str().unwrap_or_default().to_string();
+        let receipt = call_anvil(
+            &client,
+            &url,
+            "eth_getTransactionReceipt",
+            serde_json::json!([hash]),
+        )
+        .await?;
+        if receipt["status"].as_str() == Some("0x1") {
+            info!(
+                "[{}/{num_transactions}] {original_hash}: replayed as {hash}",
+                i + 1
+            );
+        } else {
+            num_reverted += 1;
+            warn!(
+                "[{}/{num_transactions}] {original_hash}: reverted when replayed as {hash}",
+                i + 1
+            );
+        }
+    }
+    info!("Replayed {num_transactions} transactions from {trace_path:?} ({num_reverted} reverted)");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collect_transactions_flattens_responses_and_blocks() {
+        let trace = serde_json::json!([
+            { "result": { "from": "0x1", "nonce": "0x0" } },
+            { "result": { "transactions": [{ "from": "0x2" }, { "from": "0x3" }] } },
+            { "from": "0x4" },
+            { "result": null },
+        ]);
+        let mut transactions = vec![];
+        collect_transactions(&trace, &mut transactions);
+        let senders: Vec<&str> = transactions
+            .iter()
+            .map(|t| t["from"].as_str().unwrap())
+            .collect();
+        assert_eq!(senders, ["0x1", "0x2", "0x3", "0x4"]);
+    }
+
+    #[test]
+    fn read_trace_transactions_requires_transactions() {
+        let dir = tempfile::tempdir().unwrap();
+        let trace_path = dir.path().join("trace.json");
+        fs::write(&trace_path, r#"{"result": {"transactions": []}}"#).unwrap();
+        assert!(read_trace_transactions(&trace_path).is_err());
+    }
+}
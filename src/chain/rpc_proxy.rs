@@ -0,0 +1,544 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use color_eyre::{eyre::eyre, Result};
+use fs_err as fs;
+use serde_json::{json, Value};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tracing::{debug, info, instrument, warn};
+
+use crate::run_tests::types::BroadcastRecvBool;
+
+const HEADER_END: &[u8] = b"\r\n\r\n";
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+const READ_BUFFER_BYTES: usize = 16 * 1024;
+
+type PendingRequests = Arc<Mutex<HashMap<String, (String, Value, Instant)>>>;
+
+/// Rotated-to logs are `<log>.1` (newest) through `<log>.<ROTATED_LOGS>`
+const ROTATED_LOGS: u32 = 5;
+
+struct LogFile {
+    path: PathBuf,
+    file: fs::File,
+    len: u64,
+    max_bytes: Option<u64>,
+}
+
+impl LogFile {
+    fn open(path: &Path, max_bytes: Option<u64>) -> Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        let len = file.metadata()?.len();
+        Ok(LogFile {
+            path: path.to_path_buf(),
+            file,
+            len,
+            max_bytes,
+        })
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+
+    /// Shift `<log>.n` to `<log>.n+1`, dropping the oldest, and start a new `<log>`
+    fn rotate(&mut self) -> Result<()> {
+        for index in (1..ROTATED_LOGS).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+        *self = LogFile::open(&self.path, self.max_bytes)?;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<()> {
+        let line_len = line.len() as u64 + 1;
+        if let Some(max_bytes) = self.max_bytes {
+            if self.len > 0 && self.len + line_len > max_bytes {
+                self.rotate()?;
+            }
+        }
+        writeln!(self.file, "{line}")?;
+        self.len += line_len;
+        Ok(())
+    }
+}
+
+/// Writes one entry per JSON-RPC call: to the given file as JSON lines, else to stdout
+#[derive(Clone)]
+struct RpcLogger {
+    file: Option<Arc<Mutex<LogFile>>>,
+}
+
+impl RpcLogger {
+    fn new(log_path: Option<&Path>, log_max_mb: Option<u64>) -> Result<Self> {
+        let file = match log_path {
+            None => None,
+            Some(log_path) => {
+                if let Some(parent) = log_path.parent() {
+                    if !parent.as_os_str().is_empty() {
+                        fs::create_dir_all(parent)?;
+                    }
+                }
+                let max_bytes = log_max_mb.map(|mb| mb * 1024 * 1024);
+                Some(Arc::new(Mutex::new(LogFile::open(log_path, max_bytes)?)))
+            }
+        };
+        Ok(RpcLogger { file })
+    }
+
+    async fn log(&self, method: &str, params: &Value, result: &Value, duration: Duration) {
+        let duration_ms = duration.as_secs_f64() * 1000.0;
+        match self.file {
+            None => info!("{method} ({duration_ms:.1}ms)\n  params: {params}\n  result: {result}"),
+            Some(ref file) => {
+                let entry = json!({
+                    "timestamp": chrono::Utc::now().to_rfc3339(),
+                    "method": method,
+                    "params": params,
+                    "result": result,
+                    "duration_ms": duration_ms,
+                });
+                let mut file = file.lock().await;
+                if let Err(e) = file.write_line(&entry.to_string()) {
+                    warn!("Failed to write RPC log entry: {e}");
+                }
+            }
+        }
+    }
+
+    /// Log a request/response pair; handles both single and batch calls
+    async fn log_exchange(&self, request: &[u8], response: &[u8], duration: Duration) {
+        let Ok(request) = serde_json::from_slice::<Value>(request) else {
+            debug!("rpc_proxy: non-JSON request");
+            return;
+        };
+        let response = serde_json::from_slice::<Value>(response).unwrap_or(Value::Null);
+        let responses_by_id: HashMap<String, &Value> = match response {
+            Value::Array(ref responses) => {
+                responses.iter().map(|r| (r["id"].to_string(), r)).collect()
+            }
+            ref r => HashMap::from([(r["id"].to_string(), r)]),
+        };
+        let requests = match request {
+            Value::Array(requests) => requests,
+            r => vec![r],
+        };
+        for request in requests {
+            let method = request["method"].as_str().unwrap_or_default();
+            let result = responses_by_id
+                .get(&request["id"].to_string())
+                .map(|r| get_result_or_error(r))
+                .unwrap_or(&Value::Null);
+            self.log(method, &request["params"], result, duration).await;
+        }
+    }
+}
+
+fn get_result_or_error(response: &Value) -> &Value {
+    response
+        .get("result")
+        .or_else(|| response.get("error"))
+        .unwrap_or(&Value::Null)
+}
+
+/// Incrementally decodes WebSocket frames, yielding complete message payloads
+#[derive(Default)]
+struct FrameParser {
+    buffer: Vec<u8>,
+    fragments: Vec<u8>,
+}
+
+impl FrameParser {
+    fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        self.buffer.extend_from_slice(bytes);
+        let mut messages = vec![];
+        while self.buffer.len() >= 2 {
+            let fin = self.buffer[0] & 0x80 != 0;
+            let opcode = self.buffer[0] & 0x0f;
+            let is_masked = self.buffer[1] & 0x80 != 0;
+            let (length, mut offset) = match self.buffer[1] & 0x7f {
+                126 => {
+                    if self.buffer.len() < 4 {
+                        break;
+                    }
+                    (
+                        u16::from_be_bytes([self.buffer[2], self.buffer[3]]) as usize,
+                        4,
+                    )
+                }
+                127 => {
+                    if self.buffer.len() < 10 {
+                        break;
+                    }
+                    let mut length = [0; 8];
+                    length.copy_from_slice(&self.buffer[2..10]);
+                    (u64::from_be_bytes(length) as usize, 10)
+                }
+                length => (length as usize, 2),
+            };
+            let mask = if is_masked {
+                if self.buffer.len() < offset + 4 {
+                    break;
+                }
+                let mut mask = [0; 4];
+                mask.copy_from_slice(&self.buffer[offset..offset + 4]);
+                offset += 4;
+                Some(mask)
+            } else {
+                None
+            };
+            if self.buffer.len() < offset + length {
+                break;
+            }
+            let mut payload = self.buffer[offset..offset + length].to_vec();
+            self.buffer.drain(..offset + length);
+            if let Some(mask) = mask {
+                for (i, byte) in payload.iter_mut().enumerate() {
+                    *byte ^= mask[i % 4];
+                }
+            }
+            // 0x0: continuation, 0x1: text, 0x2: binary; others are control frames
+            if opcode <= 0x2 {
+                self.fragments.extend_from_slice(&payload);
+                if fin {
+                    messages.push(std::mem::take(&mut self.fragments));
+                }
+            }
+        }
+        messages
+    }
+}
+
+fn as_messages(message: &[u8]) -> Vec<Value> {
+    match serde_json::from_slice::<Value>(message) {
+        Ok(Value::Array(messages)) => messages,
+        Ok(message) => vec![message],
+        Err(_) => vec![],
+    }
+}
+
+fn find_header_end(buffer: &[u8]) -> Option<usize> {
+    buffer
+        .windows(HEADER_END.len())
+        .position(|w| w == HEADER_END)
+}
+
+/// Read from `stream` into `buffer` until it holds a full HTTP header;
+/// returns `None` if the stream closes first
+async fn read_header(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Result<Option<usize>> {
+    let mut chunk = [0; READ_BUFFER_BYTES];
+    loop {
+        if let Some(header_end) = find_header_end(buffer) {
+            return Ok(Some(header_end));
+        }
+        if buffer.len() > MAX_HEADER_BYTES {
+            return Err(eyre!("HTTP header exceeds {MAX_HEADER_BYTES} bytes"));
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..n]);
+    }
+}
+
+fn get_header<'a>(head: &'a str, name: &str) -> Option<&'a str> {
+    head.split("\r\n").skip(1).find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        if key.trim().eq_ignore_ascii_case(name) {
+            Some(value.trim())
+        } else {
+            None
+        }
+    })
+}
+
+#[instrument(level = "trace", skip_all)]
+async fn handle_connection(
+    mut client: TcpStream,
+    anvil_port: u16,
+    logger: RpcLogger,
+) -> Result<()> {
+    let http = reqwest::Client::new();
+    let anvil_url = format!("http://localhost:{anvil_port}");
+    let mut buffer = Vec::new();
+    loop {
+        let Some(header_end) = read_header(&mut client, &mut buffer).await? else {
+            return Ok(());
+        };
+        let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+        let body_start = header_end + HEADER_END.len();
+
+        let is_websocket = get_header(&head, "upgrade")
+            .map(|u| u.eq_ignore_ascii_case("websocket"))
+            .unwrap_or(false);
+        if is_websocket {
+            let leftover = buffer.split_off(body_start);
+            return tunnel_websocket(client, &head, leftover, anvil_port, logger).await;
+        }
+
+        let content_length: usize = get_header(&head, "content-length")
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(0);
+        let mut body = buffer.split_off(body_start);
+        let mut chunk = [0; READ_BUFFER_BYTES];
+        while body.len() < content_length {
+            let n = client.read(&mut chunk).await?;
+            if n == 0 {
+                return Ok(());
+            }
+            body.extend_from_slice(&chunk[..n]);
+        }
+        buffer = body.split_off(content_length);
+
+        let start = Instant::now();
+        let response = http
+            .post(&anvil_url)
+            .header("Content-Type", "application/json")
+            .body(body.clone())
+            .send()
+            .await?;
+        let status = response.status();
+        let response_body = response.bytes().await?;
+        logger
+            .log_exchange(&body, &response_body, start.elapsed())
+            .await;
+
+        let response_head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+            status.as_u16(),
+            status.canonical_reason().unwrap_or_default(),
+            response_body.len(),
+        );
+        client.write_all(response_head.as_bytes()).await?;
+        client.write_all(&response_body).await?;
+
+        let is_close = get_header(&head, "connection")
+            .map(|c| c.eq_ignore_ascii_case("close"))
+            .unwrap_or(false);
+        if is_close {
+            return Ok(());
+        }
+    }
+}
+
+/// Forward a WebSocket connection to anvil byte-for-byte,
+/// decoding frames on the side to log JSON-RPC calls
+#[instrument(level = "trace", skip_all)]
+async fn tunnel_websocket(
+    client: TcpStream,
+    head: &str,
+    leftover: Vec<u8>,
+    anvil_port: u16,
+    logger: RpcLogger,
+) -> Result<()> {
+    let mut anvil = TcpStream::connect(("localhost", anvil_port)).await?;
+
+    // drop extension negotiation (e.g. permessage-deflate) so frames can be read
+    let head = head
+        .split("\r\n")
+        .filter(|line| {
+            !line
+                .to_ascii_lowercase()
+                .starts_with("sec-websocket-extensions:")
+        })
+        .collect::<Vec<_>>()
+        .join("\r\n");
+    anvil.write_all(head.as_bytes()).await?;
+    anvil.write_all(HEADER_END).await?;
+    anvil.write_all(&leftover).await?;
+
+    let pending: PendingRequests = Arc::new(Mutex::new(HashMap::new()));
+    let (mut client_read, mut client_write) = client.into_split();
+    let (mut anvil_read, mut anvil_write) = anvil.into_split();
+
+    let pending_upstream = Arc::clone(&pending);
+    let upstream = async move {
+        let mut parser = FrameParser::default();
+        let mut to_record = parser.push(&leftover);
+        let mut chunk = [0; READ_BUFFER_BYTES];
+        loop {
+            for message in to_record.drain(..) {
+                let mut pending = pending_upstream.lock().await;
+                for request in as_messages(&message) {
+                    let Some(method) = request["method"].as_str() else {
+                        continue;
+                    };
+                    pending.insert(
+                        request["id"].to_string(),
+                        (
+                            method.to_string(),
+                            request["params"].clone(),
+                            Instant::now(),
+                        ),
+                    );
+                }
+            }
+            let n = client_read.read(&mut chunk).await?;
+            if n == 0 {
+                anvil_write.shutdown().await?;
+                return Ok::<(), color_eyre::eyre::Error>(());
+            }
+            anvil_write.write_all(&chunk[..n]).await?;
+            to_record = parser.push(&chunk[..n]);
+        }
+    };
+
+    let downstream = async move {
+        let mut parser = FrameParser::default();
+        let mut head = Vec::new();
+        let mut is_head_done = false;
+        let mut chunk = [0; READ_BUFFER_BYTES];
+        loop {
+            let n = anvil_read.read(&mut chunk).await?;
+            if n == 0 {
+                client_write.shutdown().await?;
+                return Ok::<(), color_eyre::eyre::Error>(());
+            }
+            client_write.write_all(&chunk[..n]).await?;
+
+            let frames = if is_head_done {
+                &chunk[..n]
+            } else {
+                head.extend_from_slice(&chunk[..n]);
+                let Some(header_end) = find_header_end(&head) else {
+                    continue;
+                };
+                is_head_done = true;
+                let head_len = head.len();
+                &chunk[n - (head_len - header_end - HEADER_END.len())..n]
+            };
+            for message in parser.push(frames) {
+                for response in as_messages(&message) {
+                    if response["method"].as_str() == Some("eth_subscription") {
+                        logger
+                            .log(
+                                "eth_subscription",
+                                &response["params"],
+                                &Value::Null,
+                                Duration::ZERO,
+                            )
+                            .await;
+                        continue;
+                    }
+                    let request = pending.lock().await.remove(&response["id"].to_string());
+                    if let Some((method, params, start)) = request {
+                        logger
+                            .log(
+                                &method,
+                                &params,
+                                get_result_or_error(&response),
+                                start.elapsed(),
+                            )
+                            .await;
+                    }
+                }
+            }
+        }
+    };
+
+    let (upstream, downstream) = tokio::join!(upstream, downstream);
+    upstream?;
+    downstream?;
+    Ok(())
+}
+
+/// Bind a proxy on `proxy_port` that forwards JSON-RPC (HTTP & WebSocket)
+/// to anvil on `anvil_port`, logging each call
+#[instrument(level = "trace", skip_all)]
+pub async fn start_rpc_proxy(
+    proxy_port: u16,
+    anvil_port: u16,
+    log_path: Option<&Path>,
+    log_max_mb: Option<u64>,
+    mut recv_kill: BroadcastRecvBool,
+) -> Result<tokio::task::JoinHandle<()>> {
+    let logger = RpcLogger::new(log_path, log_max_mb)?;
+    let listener = TcpListener::bind(("127.0.0.1", proxy_port))
+        .await
+        .map_err(|e| eyre!("Failed to bind RPC proxy to port {proxy_port}: {e}"))?;
+    info!(
+        "Logging JSON-RPC proxy listening on port {} -> anvil on port {}{}",
+        proxy_port,
+        anvil_port,
+        log_path
+            .map(|p| match log_max_mb {
+                None => format!("; logging to {p:?}"),
+                Some(mb) => format!("; logging to {p:?}, rotating at {mb}MB"),
+            })
+            .unwrap_or_default(),
+    );
+
+    Ok(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, _) = match accepted {
+                        Ok(a) => a,
+                        Err(e) => {
+                            warn!("RPC proxy failed to accept connection: {e}");
+                            continue;
+                        }
+                    };
+                    let logger = logger.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_connection(stream, anvil_port, logger).await {
+                            debug!("RPC proxy connection closed with error: {e:?}");
+                        }
+                    });
+                }
+                _ = recv_kill.recv() => return,
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_header_is_case_insensitive_and_skips_request_line() {
+        let head = "GET / HTTP/1.1\r\nHost: localhost\r\nupgrade: websocket";
+        assert_eq!(get_header(head, "Upgrade"), Some("websocket"));
+        assert_eq!(get_header(head, "host"), Some("localhost"));
+        assert_eq!(get_header(head, "GET / HTTP/1.1"), None);
+        assert_eq!(get_header(head, "Content-Length"), None);
+    }
+
+    #[test]
+    fn find_header_end_finds_blank_line() {
+        assert_eq!(find_header_end(b"GET / HTTP/1.1\r\n\r\nbody"), Some(14));
+        assert_eq!(find_header_end(b"GET / HTTP/1.1\r\n"), None);
+    }
+
+    #[test]
+    fn as_messages_splits_batches() {
+        assert_eq!(as_messages(br#"[{"id":1},{"id":2}]"#).len(), 2);
+        assert_eq!(as_messages(br#"{"id":1}"#).len(), 1);
+        assert!(as_messages(b"not json").is_empty());
+    }
+
+    #[test]
+    fn get_result_or_error_prefers_result() {
+        let result = serde_json::json!({ "result": "0x1" });
+        assert_eq!(get_result_or_error(&result), "0x1");
+        let error = serde_json::json!({ "error": { "code": -32000 } });
+        assert_eq!(get_result_or_error(&error)["code"], -32000);
+        assert!(get_result_or_error(&serde_json::json!({})).is_null());
+    }
+}
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result, Section,
+};
+use fs_err as fs;
+use reqwest::Client;
+use tracing::{info, instrument};
+
+use super::code::{accounts_with_code, report_code_set};
+use super::{call_anvil, get_kinostate, wait_for_anvil};
+
+async fn check_chain_running(port: u16) -> Result<()> {
+    if wait_for_anvil(port, 1, None).await.is_err() {
+        return Err(eyre!("No chain running on port {port}.")
+            .with_suggestion(|| "Start one with `kit chain`."));
+    }
+    Ok(())
+}
+
+/// Load the `anvil_dumpState` response saved at `snapshot_path` into the
+/// chain on `port`; a snapshot of a kit chain holds the Kinode contracts,
+/// so their code is verified
+pub async fn load_snapshot(port: u16, snapshot_path: &Path) -> Result<()> {
+    let snapshot: serde_json::Value = serde_json::from_str(&fs::read_to_string(snapshot_path)?)
+        .wrap_err_with(|| format!("Failed to parse snapshot {snapshot_path:?}"))
+        .with_suggestion(|| "Create a snapshot with `kit chain snapshot`.")?;
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    call_anvil(
+        &client,
+        &url,
+        "anvil_loadState",
+        serde_json::json!([snapshot]),
+    )
+    .await?;
+    let kinostate: serde_json::Value = serde_json::from_str(get_kinostate(None)?)?;
+    report_code_set(&client, &url, accounts_with_code(&kinostate)).await?;
+    Ok(())
+}
+
+/// Write the raw `anvil_dumpState` response of the chain on `port`
+/// to `snapshot_path`
+pub async fn dump_state(port: u16, snapshot_path: &Path) -> Result<()> {
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    let state = call_anvil(&client, &url, "anvil_dumpState", serde_json::json!([])).await?;
+    if let Some(parent) = snapshot_path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    fs::write(snapshot_path, serde_json::to_string(&state)?)?;
+    Ok(())
+}
+
+/// kit chain snapshot: write the state of the chain on `port`
+/// to `snapshot_path`
+#[instrument(level = "trace", skip_all)]
+pub async fn snapshot(port: u16, snapshot_path: &Path) -> Result<()> {
+    check_chain_running(port).await?;
+    dump_state(port, snapshot_path).await?;
+    info!("Wrote snapshot of chain on port {port} to {snapshot_path:?}.");
+    Ok(())
+}
+
+/// kit chain restore: load a snapshot from `kit chain snapshot` into the
+/// chain on `port`
+#[instrument(level = "trace", skip_all)]
+pub async fn restore(port: u16, snapshot_path: &Path) -> Result<()> {
+    check_chain_running(port).await?;
+    load_snapshot(port, snapshot_path).await?;
+    info!("Restored chain on port {port} from {snapshot_path:?}.");
+    Ok(())
+}
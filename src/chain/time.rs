@@ -0,0 +1,104 @@
+use color_eyre::{eyre::eyre, Result, Section};
+use reqwest::Client;
+use tracing::{info, instrument};
+
+use super::call_anvil;
+
+/// Set the timestamp of the next block & mine it so that the new time
+/// is visible to calls against `latest`
+async fn set_next_block_timestamp(client: &Client, url: &str, timestamp: u64) -> Result<()> {
+    call_anvil(
+        client,
+        url,
+        "evm_setNextBlockTimestamp",
+        serde_json::json!([timestamp]),
+    )
+    .await
+    .with_suggestion(|| "The timestamp must be later than that of the latest block.")?;
+    call_anvil(client, url, "evm_mine", serde_json::json!([])).await?;
+    Ok(())
+}
+
+fn parse_hex_quantity(block: &serde_json::Value, field: &str) -> Result<u64> {
+    let Some(quantity) = block[field].as_str() else {
+        return Err(eyre!("latest block has no {field}: {block}"));
+    };
+    Ok(u64::from_str_radix(quantity.trim_start_matches("0x"), 16)?)
+}
+
+/// (number, timestamp) of the latest block
+async fn get_latest_block(client: &Client, url: &str) -> Result<(u64, u64)> {
+    let block = call_anvil(
+        client,
+        url,
+        "eth_getBlockByNumber",
+        serde_json::json!(["latest", false]),
+    )
+    .await?;
+    Ok((
+        parse_hex_quantity(&block, "number")?,
+        parse_hex_quantity(&block, "timestamp")?,
+    ))
+}
+
+async fn get_latest_block_timestamp(client: &Client, url: &str) -> Result<u64> {
+    Ok(get_latest_block(client, url).await?.1)
+}
+
+/// kit chain set-time: move the running chain to `timestamp` (unix seconds)
+#[instrument(level = "trace", skip_all)]
+pub async fn set_time(port: u16, timestamp: u64) -> Result<()> {
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    set_next_block_timestamp(&client, &url, timestamp).await?;
+    info!("Set chain on port {port} to timestamp {timestamp}.");
+    Ok(())
+}
+
+/// kit chain advance-time: move the running chain `seconds` past its latest block
+#[instrument(level = "trace", skip_all)]
+pub async fn advance_time(port: u16, seconds: u64) -> Result<()> {
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    let timestamp = get_latest_block_timestamp(&client, &url).await? + seconds;
+    set_next_block_timestamp(&client, &url, timestamp).await?;
+    info!("Advanced chain on port {port} by {seconds}s to timestamp {timestamp}.");
+    Ok(())
+}
+
+/// kit chain time-travel: `evm_increaseTime` by `seconds` & mine a block,
+/// then mine `blocks` empty blocks
+#[instrument(level = "trace", skip_all)]
+pub async fn time_travel(port: u16, seconds: Option<u64>, blocks: Option<u64>) -> Result<()> {
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+    if let Some(seconds) = seconds {
+        call_anvil(
+            &client,
+            &url,
+            "evm_increaseTime",
+            serde_json::json!([seconds]),
+        )
+        .await?;
+        call_anvil(&client, &url, "evm_mine", serde_json::json!([])).await?;
+    }
+    for _ in 0..blocks.unwrap_or_default() {
+        call_anvil(&client, &url, "evm_mine", serde_json::json!([])).await?;
+    }
+    let (number, timestamp) = get_latest_block(&client, &url).await?;
+    info!("Chain on port {port} is at block {number}, timestamp {timestamp}.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_quantity_reads_block_fields() {
+        let block = serde_json::json!({ "number": "0x1a", "timestamp": "0x0" });
+        assert_eq!(parse_hex_quantity(&block, "number").unwrap(), 26);
+        assert_eq!(parse_hex_quantity(&block, "timestamp").unwrap(), 0);
+        assert!(parse_hex_quantity(&block, "hash").is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use color_eyre::{eyre::eyre, Result, Section};
+use reqwest::Client;
+use serde_json::json;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, info, instrument, warn};
+
+use crate::run_tests::types::BroadcastRecvBool;
+
+use super::{call_anvil, check_address};
+
+/// A storage slot of a contract to watch for changes
+#[derive(Debug, Clone)]
+pub struct StorageWatch {
+    pub address: String,
+    pub slot: String,
+}
+
+impl StorageWatch {
+    /// Parse an `<address> <slot>` pair; `slot` may be decimal or `0x`-prefixed hex
+    pub fn new(address: &str, slot: &str) -> Result<Self> {
+        check_address(address, "--watch-storage")?;
+        let slot_number = match slot.strip_prefix("0x") {
+            Some(hex_slot) => {
+                let hex_slot = hex_slot.trim_start_matches('0');
+                if hex_slot.len() > 64 || !hex_slot.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(eyre!("Invalid --watch-storage slot {slot}"));
+                }
+                format!("0x{}", if hex_slot.is_empty() { "0" } else { hex_slot })
+            }
+            None => format!(
+                "0x{:x}",
+                slot.parse::<u128>()
+                    .map_err(|_| eyre!("Invalid --watch-storage slot {slot}")
+                        .with_suggestion(|| "Slot must be decimal or `0x`-prefixed hex."))?
+            ),
+        };
+        Ok(StorageWatch {
+            address: address.to_lowercase(),
+            slot: slot_number,
+        })
+    }
+}
+
+async fn get_storage_at(
+    client: &Client,
+    url: &str,
+    watch: &StorageWatch,
+    block: &str,
+) -> Result<String> {
+    let value = call_anvil(
+        client,
+        url,
+        "eth_getStorageAt",
+        json!([watch.address, watch.slot, block]),
+    )
+    .await?;
+    value
+        .as_str()
+        .map(|v| v.to_string())
+        .ok_or_else(|| eyre!("eth_getStorageAt returned non-string {value}"))
+}
+
+async fn get_block_number(client: &Client, url: &str) -> Result<u64> {
+    let value = call_anvil(client, url, "eth_blockNumber", json!([])).await?;
+    let Some(block_number) = value.as_str().and_then(|b| b.strip_prefix("0x")) else {
+        return Err(eyre!("eth_blockNumber returned unexpected {value}"));
+    };
+    Ok(u64::from_str_radix(block_number, 16)?)
+}
+
+/// Find the transaction(s) that changed `watch` from `old_value`
+/// somewhere in blocks `(from_block, to_block]`
+#[instrument(level = "trace", skip_all)]
+async fn find_changing_transactions(
+    client: &Client,
+    url: &str,
+    watch: &StorageWatch,
+    old_value: &str,
+    from_block: u64,
+    to_block: u64,
+) -> Result<(u64, Vec<String>)> {
+    // first block at which the slot no longer holds `old_value`
+    let mut changed_block = to_block;
+    for block in from_block + 1..=to_block {
+        let value = get_storage_at(client, url, watch, &format!("0x{block:x}")).await?;
+        if value != old_value {
+            changed_block = block;
+            break;
+        }
+    }
+
+    let block = call_anvil(
+        client,
+        url,
+        "eth_getBlockByNumber",
+        json!([format!("0x{changed_block:x}"), true]),
+    )
+    .await?;
+    let transactions = block["transactions"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    // prefer transactions sent directly to the contract; else report all in block
+    let to_contract: Vec<String> = transactions
+        .iter()
+        .filter(|tx| tx["to"].as_str().map(|to| to.to_lowercase()) == Some(watch.address.clone()))
+        .filter_map(|tx| tx["hash"].as_str().map(|h| h.to_string()))
+        .collect();
+    let transactions = if to_contract.is_empty() {
+        transactions
+            .iter()
+            .filter_map(|tx| tx["hash"].as_str().map(|h| h.to_string()))
+            .collect()
+    } else {
+        to_contract
+    };
+    Ok((changed_block, transactions))
+}
+
+/// Poll the given storage slots on the chain on `port` every `interval_ms`,
+/// warning whenever a value changes
+#[instrument(level = "trace", skip_all)]
+pub async fn start_watch_storage(
+    port: u16,
+    watches: Vec<StorageWatch>,
+    interval_ms: u64,
+    mut recv_kill: BroadcastRecvBool,
+) -> Result<tokio::task::JoinHandle<()>> {
+    let client = Client::new();
+    let url = format!("http://localhost:{}", port);
+
+    let mut last_block = get_block_number(&client, &url).await?;
+    let mut values = Vec::with_capacity(watches.len());
+    for watch in &watches {
+        let value = get_storage_at(&client, &url, watch, &format!("0x{last_block:x}")).await?;
+        info!(
+            "Watching storage of {} slot {}: {}",
+            watch.address, watch.slot, value
+        );
+        values.push(value);
+    }
+
+    Ok(tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                _ = sleep(Duration::from_millis(interval_ms)) => {}
+                _ = recv_kill.recv() => return,
+            }
+            let block = match get_block_number(&client, &url).await {
+                Ok(b) => b,
+                Err(e) => {
+                    debug!("watch-storage: failed to get block number: {e:?}");
+                    continue;
+                }
+            };
+            if block < last_block {
+                // e.g. the chain was reset
+                last_block = block;
+            }
+            for (watch, old_value) in watches.iter().zip(values.iter_mut()) {
+                let new_value =
+                    match get_storage_at(&client, &url, watch, &format!("0x{block:x}")).await {
+                        Ok(v) => v,
+                        Err(e) => {
+                            debug!("watch-storage: failed to get storage: {e:?}");
+                            continue;
+                        }
+                    };
+                if new_value == *old_value {
+                    continue;
+                }
+                let cause = match find_changing_transactions(
+                    &client, &url, watch, old_value, last_block, block,
+                )
+                .await
+                {
+                    Ok((changed_block, transactions)) if !transactions.is_empty() => format!(
+                        "in block {changed_block} by transaction(s) {}",
+                        transactions.join(", "),
+                    ),
+                    Ok((changed_block, _)) => format!("in block {changed_block}"),
+                    Err(e) => format!("by unknown transaction ({e})"),
+                };
+                warn!(
+                    "Storage of {} slot {} changed {}:\n  old: {}\n  new: {}",
+                    watch.address, watch.slot, cause, old_value, new_value,
+                );
+                *old_value = new_value;
+            }
+            last_block = block;
+        }
+    }))
+}
+
+/// Parse flat `<address> <slot> <address> <slot> ...` flag values
+pub fn parse_storage_watches(values: &[String]) -> Result<Vec<StorageWatch>> {
+    values
+        .chunks(2)
+        .map(|pair| match pair {
+            [address, slot] => StorageWatch::new(address, slot),
+            _ => Err(eyre!("--watch-storage takes an address and a slot")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "0xEce71a05B36CA55B895427cD9a440eEF7Cf3669D";
+
+    #[test]
+    fn storage_watch_normalizes_address_and_slot() {
+        let watch = StorageWatch::new(ADDRESS, "10").unwrap();
+        assert_eq!(watch.address, ADDRESS.to_lowercase());
+        assert_eq!(watch.slot, "0xa");
+        assert_eq!(StorageWatch::new(ADDRESS, "0x000a").unwrap().slot, "0xa");
+        assert_eq!(StorageWatch::new(ADDRESS, "0x0").unwrap().slot, "0x0");
+        assert!(StorageWatch::new(ADDRESS, "0xzz").is_err());
+        assert!(StorageWatch::new(ADDRESS, "ten").is_err());
+        assert!(StorageWatch::new("0x1234", "0").is_err());
+    }
+
+    #[test]
+    fn parse_storage_watches_takes_pairs() {
+        let values = [ADDRESS, "0", ADDRESS, "1"].map(String::from);
+        assert_eq!(parse_storage_watches(&values).unwrap().len(), 2);
+        assert!(parse_storage_watches(&values[..3]).is_err());
+    }
+}
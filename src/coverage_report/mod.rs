@@ -0,0 +1,138 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tracing::{info, instrument, warn};
+use walkdir::WalkDir;
+
+use crate::build::{get_coverage_dir, get_coverage_objects_dir, run_command};
+
+const MERGED_PROFDATA: &str = "merged.profdata";
+/// Report only on the package's own code
+const IGNORE_FILENAME_REGEX: &str = r"(\.cargo/registry|\.cargo/git|/rustc/|/target/)";
+
+/// `llvm-profdata` & `llvm-cov` matching the nightly toolchain are shipped
+/// by its `llvm-tools` component; fall back to whatever is on `PATH`
+fn find_llvm_tool(name: &str) -> PathBuf {
+    let sysroot = run_command(
+        Command::new("rustc").args(["+nightly", "--print", "sysroot"]),
+        false,
+    );
+    if let Ok(Some((sysroot, _))) = sysroot {
+        let rustlib = PathBuf::from(sysroot.trim()).join("lib").join("rustlib");
+        if let Ok(entries) = fs::read_dir(&rustlib) {
+            for entry in entries.filter_map(|e| e.ok()) {
+                let tool = entry.path().join("bin").join(name);
+                if tool.exists() {
+                    return tool;
+                }
+            }
+        }
+    }
+    warn!("{name} not found in nightly toolchain; using {name} from PATH. To install: `rustup +nightly component add llvm-tools`");
+    PathBuf::from(name)
+}
+
+fn find_profraws(profraw_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut profraws: Vec<PathBuf> = profraw_dirs
+        .iter()
+        .flat_map(|dir| WalkDir::new(dir).into_iter().filter_map(|e| e.ok()))
+        .map(|e| e.into_path())
+        .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("profraw"))
+        .collect();
+    profraws.sort();
+    profraws.dedup();
+    profraws
+}
+
+fn find_objects(package_dir: &Path) -> Result<Vec<PathBuf>> {
+    let objects_dir = get_coverage_objects_dir(package_dir);
+    if !objects_dir.exists() {
+        return Ok(vec![]);
+    }
+    let mut objects = vec![];
+    for entry in fs::read_dir(&objects_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) == Some("wasm") {
+            objects.push(path);
+        }
+    }
+    objects.sort();
+    Ok(objects)
+}
+
+#[instrument(level = "trace", skip_all)]
+pub fn execute(
+    package_dir: &Path,
+    profraw_dirs: &[PathBuf],
+    output_dir: Option<&Path>,
+) -> Result<()> {
+    let objects = find_objects(package_dir)?;
+    if objects.is_empty() {
+        return Err(eyre!("No instrumented processes found in {package_dir:?}")
+            .with_suggestion(|| "Build with `kit build --coverage` first."));
+    }
+    let profraw_dirs = if profraw_dirs.is_empty() {
+        vec![package_dir.to_path_buf()]
+    } else {
+        profraw_dirs.to_vec()
+    };
+    let profraws = find_profraws(&profraw_dirs);
+    if profraws.is_empty() {
+        return Err(eyre!("No `.profraw`s found in {profraw_dirs:?}").with_suggestion(|| {
+            "Run the instrumented processes, e.g. with `kit run-tests --persist-state DIR`, then pass `--profraw-dir DIR`."
+        }));
+    }
+    info!(
+        "Merging {} `.profraw`s for {} processes...",
+        profraws.len(),
+        objects.len()
+    );
+
+    let coverage_dir = get_coverage_dir(package_dir);
+    fs::create_dir_all(&coverage_dir)?;
+    let profdata = coverage_dir.join(MERGED_PROFDATA);
+    let mut args = vec!["merge", "-sparse", "-o", profdata.to_str().unwrap()];
+    args.extend(profraws.iter().map(|p| p.to_str().unwrap()));
+    run_command(
+        Command::new(find_llvm_tool("llvm-profdata")).args(&args),
+        false,
+    )?;
+
+    // llvm-cov takes the first object positionally, the rest with `-object`
+    let instr_profile = format!("-instr-profile={}", profdata.to_str().unwrap());
+    let ignore_filename_regex = format!("-ignore-filename-regex={IGNORE_FILENAME_REGEX}");
+    let mut object_args = vec![objects[0].to_str().unwrap()];
+    for object in &objects[1..] {
+        object_args.push("-object");
+        object_args.push(object.to_str().unwrap());
+    }
+    let llvm_cov = find_llvm_tool("llvm-cov");
+
+    let output_dir = output_dir
+        .map(|d| d.to_path_buf())
+        .unwrap_or_else(|| coverage_dir.join("html"));
+    let output_dir_arg = format!("-output-dir={}", output_dir.to_str().unwrap());
+    let mut args = vec![
+        "show",
+        "-format=html",
+        &instr_profile,
+        &ignore_filename_regex,
+        &output_dir_arg,
+    ];
+    args.extend_from_slice(&object_args);
+    run_command(Command::new(&llvm_cov).args(&args), false)?;
+
+    let mut args = vec!["report", &instr_profile, &ignore_filename_regex];
+    args.extend_from_slice(&object_args);
+    if let Some((report, _)) = run_command(Command::new(&llvm_cov).args(&args), false)? {
+        info!("\n{report}");
+    }
+
+    info!(
+        "Wrote coverage report to {:?}.",
+        output_dir.join("index.html")
+    );
+    Ok(())
+}
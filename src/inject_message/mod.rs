@@ -2,7 +2,7 @@ use std::io::Read;
 
 #[allow(deprecated)]
 use base64::{decode, encode};
-use color_eyre::{eyre::eyre, Result};
+use color_eyre::{eyre::eyre, Result, Section};
 use fs_err as fs;
 use serde_json::{json, Value};
 use tracing::{debug, info, instrument};
@@ -17,18 +17,45 @@ const ENDPOINT: &str = "/rpc:distro:sys/message";
 
 impl std::fmt::Display for Response {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let body = pretty_json(&self.body);
         if let Some(Some(ref s)) = self.lazy_load_blob_utf8 {
-            write!(f, "Response:\nbody: {}\nblob: {}", self.body, s,)
+            write!(f, "Response:\nbody: {}\nblob: {}", body, pretty_json(s))
         } else {
             write!(
                 f,
                 "Response:\nbody: {}\nblob: {:?}",
-                self.body, self.lazy_load_blob,
+                body, self.lazy_load_blob
             )
         }
     }
 }
 
+/// `s` pretty-printed if it is JSON, else as-is
+fn pretty_json(s: &str) -> String {
+    serde_json::from_str::<Value>(s)
+        .ok()
+        .and_then(|v| serde_json::to_string_pretty(&v).ok())
+        .unwrap_or_else(|| s.to_string())
+}
+
+/// A `body` given on the command line: `0x`-prefixed hex is decoded,
+/// anything else must be JSON
+pub fn parse_body(body: &str) -> Result<String> {
+    if let Some(hex_body) = body.strip_prefix("0x") {
+        let bytes = hex::decode(hex_body)
+            .map_err(|e| eyre!("Body starts with `0x` but is not hex: {e}"))?;
+        // the node's HTTP API takes the body as a string
+        return String::from_utf8(bytes).map_err(|_| {
+            eyre!("Hex body is not UTF-8, which the node's HTTP API requires of a body.")
+                .with_suggestion(|| "Send binary payloads as a blob with --blob.")
+        });
+    }
+    let json: Value = serde_json::from_str(body)
+        .map_err(|e| eyre!("Body is not JSON: {e}"))
+        .with_suggestion(|| "To send a body that is not JSON, hex-encode it & prefix with `0x`.")?;
+    Ok(json.to_string())
+}
+
 #[instrument(level = "trace", skip_all)]
 pub fn make_message(
     process: &str,
@@ -174,7 +201,8 @@ pub async fn execute(
     node: Option<&str>,
     bytes_path: Option<&str>,
 ) -> Result<()> {
-    let request = make_message(process, expects_response, body, node, None, bytes_path)?;
+    let body = parse_body(body)?;
+    let request = make_message(process, expects_response, &body, node, None, bytes_path)?;
     let response = send_request(url, request).await?;
     if expects_response.is_some() {
         let response = parse_response(response).await?;
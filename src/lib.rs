@@ -4,6 +4,7 @@ pub mod build;
 pub mod build_start_package;
 pub mod chain;
 pub mod connect;
+pub mod coverage_report;
 pub mod dev_ui;
 pub mod inject_message;
 pub mod new;
@@ -15,6 +16,7 @@ pub mod setup;
 pub mod start_package;
 pub mod update;
 pub mod view_api;
+pub mod watch;
 
 pub const KIT_CACHE: &str = "/tmp/kinode-kit-cache";
 pub const KIT_LOG_PATH_DEFAULT: &str = "/tmp/kinode-kit-cache/logs/log.log";
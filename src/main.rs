@@ -1,9 +1,13 @@
-use std::collections::HashSet;
 use std::env;
 use std::path::PathBuf;
 use std::str::FromStr;
 
-use clap::{builder::PossibleValuesParser, command, value_parser, Arg, ArgAction, Command};
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    command,
+    parser::ValueSource,
+    value_parser, Arg, ArgAction, Command,
+};
 use color_eyre::{
     eyre::{eyre, Result},
     Section,
@@ -13,13 +17,16 @@ use serde::Deserialize;
 use tracing::{error, warn, Level};
 use tracing_error::ErrorLayer;
 use tracing_subscriber::{
-    filter, fmt, layer::SubscriberExt, prelude::*, util::SubscriberInitExt, EnvFilter,
+    filter, fmt, fmt::writer::BoxMakeWriter, layer::SubscriberExt, prelude::*,
+    util::SubscriberInitExt, EnvFilter,
 };
 
+use kit::build::BuildOptions;
+use kit::chain::ChainOptions;
 use kit::{
-    boot_fake_node, boot_real_node, build, build_start_package, chain, connect, dev_ui,
-    inject_message, new, publish, remove_package, reset_cache, run_tests, setup, start_package,
-    update, view_api, KIT_LOG_PATH_DEFAULT,
+    boot_fake_node, boot_real_node, build, build_start_package, chain, connect, coverage_report,
+    dev_ui, inject_message, new, publish, remove_package, reset_cache, run_tests, setup,
+    start_package, update, view_api, watch, KIT_LOG_PATH_DEFAULT,
 };
 
 const MAX_REMOTE_VALUES: usize = 3;
@@ -56,7 +63,11 @@ async fn get_latest_commit_sha_from_branch(
     Ok(Some(serde_json::from_slice(&bytes)?))
 }
 
-fn init_tracing(log_path: PathBuf) -> tracing_appender::non_blocking::WorkerGuard {
+/// If `json_output`, keep stdout for the caller's JSON: log to stderr only & without color
+fn init_tracing(
+    log_path: PathBuf,
+    json_output: bool,
+) -> tracing_appender::non_blocking::WorkerGuard {
     // Define a fixed log file name with rolling based on size or execution instance.
     let log_parent_path = log_path.parent().unwrap();
     let log_file_name = log_path.file_name().and_then(|f| f.to_str()).unwrap();
@@ -89,8 +100,12 @@ fn init_tracing(log_path: PathBuf) -> tracing_appender::non_blocking::WorkerGuar
         .with(
             fmt::layer()
                 .without_time()
-                .with_writer(std::io::stdout)
-                .with_ansi(true)
+                .with_writer(if json_output {
+                    BoxMakeWriter::new(std::io::stderr)
+                } else {
+                    BoxMakeWriter::new(std::io::stdout)
+                })
+                .with_ansi(!json_output)
                 .with_level(false)
                 .with_target(false)
                 .fmt_fields(fmt::format::PrettyFields::new())
@@ -102,7 +117,7 @@ fn init_tracing(log_path: PathBuf) -> tracing_appender::non_blocking::WorkerGuar
                 .with_line_number(true)
                 .without_time()
                 .with_writer(std::io::stderr)
-                .with_ansi(true)
+                .with_ansi(!json_output)
                 .with_level(true)
                 .with_target(false)
                 .fmt_fields(fmt::format::PrettyFields::new())
@@ -133,6 +148,7 @@ async fn execute(
             let version = matches.get_one::<String>("VERSION").unwrap();
             let node_home = PathBuf::from(matches.get_one::<String>("HOME").unwrap());
             let node_port = matches.get_one::<u16>("NODE_PORT").unwrap();
+            let num_nodes = matches.get_one::<u16>("NODES").unwrap();
             let fakechain_port = matches.get_one::<u16>("FAKECHAIN_PORT").unwrap();
             let rpc = matches
                 .get_one::<String>("RPC_ENDPOINT")
@@ -142,12 +158,19 @@ async fn execute(
             let is_persist = matches.get_one::<bool>("PERSIST").unwrap();
             let release = matches.get_one::<bool>("RELEASE").unwrap();
             let verbosity = matches.get_one::<u8>("VERBOSITY").unwrap();
+            let install: Vec<PathBuf> = matches
+                .get_many::<String>("INSTALL")
+                .unwrap_or_default()
+                .map(PathBuf::from)
+                .collect();
+            let install_retries = matches.get_one::<u32>("INSTALL_RETRIES").unwrap();
 
             boot_fake_node::execute(
                 runtime_path,
                 version.clone(),
                 node_home,
                 *node_port,
+                *num_nodes,
                 *fakechain_port,
                 rpc,
                 fake_node_name.clone(),
@@ -155,6 +178,8 @@ async fn execute(
                 *is_persist,
                 *release,
                 *verbosity,
+                &install,
+                *install_retries,
                 vec![],
             )
             .await
@@ -187,134 +212,216 @@ async fn execute(
             .await
         }
         Some(("build", matches)) => {
+            if *matches.get_one::<bool>("STATS").unwrap() {
+                return build::print_build_stats();
+            }
+            if *matches.get_one::<bool>("RESET_STATS").unwrap() {
+                return build::reset_build_stats();
+            }
             let package_dir = PathBuf::from(matches.get_one::<String>("DIR").unwrap());
-            let no_ui = matches.get_one::<bool>("NO_UI").unwrap();
-            let ui_only = matches.get_one::<bool>("UI_ONLY").unwrap();
-            let include: HashSet<PathBuf> = matches
-                .get_many::<String>("INCLUDE")
+            let mut forbid_capabilities: Vec<String> = matches
+                .get_many::<String>("FORBID_CAPABILITIES")
                 .unwrap_or_default()
-                .map(|s| package_dir.join(s))
+                .map(|s| s.to_string())
                 .collect();
-            let exclude: HashSet<PathBuf> = matches
-                .get_many::<String>("EXCLUDE")
-                .unwrap_or_default()
-                .map(|s| package_dir.join(s))
-                .collect();
-            let skip_deps_check = matches.get_one::<bool>("SKIP_DEPS_CHECK").unwrap();
-            let features = match matches.get_one::<String>("FEATURES") {
-                Some(f) => f.clone(),
-                None => "".into(),
+            if *matches.get_one::<bool>("FORBID_NETWORK").unwrap() {
+                forbid_capabilities
+                    .extend(build::NETWORK_CAPABILITIES.iter().map(|c| c.to_string()));
+            }
+            let options = BuildOptions {
+                no_ui: *matches.get_one::<bool>("NO_UI").unwrap(),
+                ui_only: *matches.get_one::<bool>("UI_ONLY").unwrap(),
+                include: matches
+                    .get_many::<String>("INCLUDE")
+                    .unwrap_or_default()
+                    .map(|s| package_dir.join(s))
+                    .collect(),
+                exclude: matches
+                    .get_many::<String>("EXCLUDE")
+                    .unwrap_or_default()
+                    .map(|s| package_dir.join(s))
+                    .collect(),
+                skip_deps_check: *matches.get_one::<bool>("SKIP_DEPS_CHECK").unwrap(),
+                features: matches
+                    .get_one::<String>("FEATURES")
+                    .cloned()
+                    .unwrap_or_default(),
+                all_features: *matches.get_one::<bool>("ALL_FEATURES").unwrap(),
+                default_features: *matches.get_one::<bool>("DEFAULT_FEATURES").unwrap(),
+                url: matches
+                    .get_one::<u16>("NODE_PORT")
+                    .map(|p| format!("http://localhost:{p}")),
+                download_from: matches.get_one::<String>("NODE").cloned(),
+                default_world: matches.get_one::<String>("WORLD").cloned(),
+                local_dependencies: matches
+                    .get_many::<String>("DEPENDENCY_PACKAGE_PATH")
+                    .unwrap_or_default()
+                    .map(|s| PathBuf::from(s))
+                    .collect(),
+                add_paths_to_api: matches
+                    .get_many::<String>("PATH")
+                    .unwrap_or_default()
+                    .map(|s| PathBuf::from(s))
+                    .collect(),
+                rewrite: *matches.get_one::<bool>("REWRITE").unwrap(),
+                strip_custom_sections: *matches.get_one::<bool>("STRIP_CUSTOM_SECTIONS").unwrap(),
+                wasm_opt_path: matches.get_one::<String>("WASM_OPT_PATH").cloned(),
+                wasm_opt_level: matches.get_one::<String>("WASM_OPT_LEVEL").cloned(),
+                publisher: matches.get_one::<String>("PUBLISHER").cloned(),
+                sign: matches.get_one::<String>("SIGN").map(PathBuf::from),
+                embed_files: matches
+                    .get_many::<String>("EMBED_FILE")
+                    .unwrap_or_default()
+                    .map(|s| s.to_string())
+                    .collect(),
+                inject_mock: matches
+                    .get_many::<String>("INJECT_MOCK")
+                    .unwrap_or_default()
+                    .map(|s| s.to_string())
+                    .collect(),
+                emit_docs: *matches.get_one::<bool>("EMIT_DOCS").unwrap(),
+                graph: *matches.get_one::<bool>("GRAPH").unwrap(),
+                emit_wit_json: *matches.get_one::<bool>("EMIT_WIT_JSON").unwrap(),
+                emit_symbols: *matches.get_one::<bool>("EMIT_SYMBOLS").unwrap(),
+                warn_unused_wit_types: *matches.get_one::<bool>("WARN_UNUSED_WIT_TYPES").unwrap(),
+                check_breaking: *matches.get_one::<bool>("CHECK_BREAKING").unwrap(),
+                sbom: *matches.get_one::<bool>("SBOM").unwrap(),
+                forbid_capabilities,
+                docker: *matches.get_one::<bool>("DOCKER").unwrap(),
+                sandbox: *matches.get_one::<bool>("SANDBOX").unwrap(),
+                coverage: *matches.get_one::<bool>("COVERAGE").unwrap(),
+                reproducible: *matches.get_one::<bool>("REPRODUCIBLE").unwrap(),
+                in_docker: *matches.get_one::<bool>("IN_DOCKER").unwrap(),
+                verify_reproducible: *matches.get_one::<bool>("VERIFY_REPRODUCIBLE").unwrap(),
+                no_cache: *matches.get_one::<bool>("NO_CACHE").unwrap(),
+                jobs: matches.get_one::<u64>("JOBS").map(|j| *j as usize),
+                force: *matches.get_one::<bool>("FORCE").unwrap(),
+                verbose: *matches.get_one::<bool>("VERBOSE").unwrap(),
+                ignore_deps: false,
             };
-            let url = matches
-                .get_one::<u16>("NODE_PORT")
-                .map(|p| format!("http://localhost:{p}"));
-            let download_from = matches
-                .get_one::<String>("NODE")
-                .and_then(|s: &String| Some(s.as_str()));
-            let default_world = matches.get_one::<String>("WORLD");
-            let local_dependencies: Vec<PathBuf> = matches
-                .get_many::<String>("DEPENDENCY_PACKAGE_PATH")
-                .unwrap_or_default()
-                .map(|s| PathBuf::from(s))
-                .collect();
-            let add_paths_to_api: Vec<PathBuf> = matches
-                .get_many::<String>("PATH")
-                .unwrap_or_default()
-                .map(|s| PathBuf::from(s))
-                .collect();
-            let rewrite = matches.get_one::<bool>("REWRITE").unwrap();
-            let reproducible = matches.get_one::<bool>("REPRODUCIBLE").unwrap();
-            let force = matches.get_one::<bool>("FORCE").unwrap();
-            let verbose = matches.get_one::<bool>("VERBOSE").unwrap();
 
-            build::execute(
-                &package_dir,
-                *no_ui,
-                *ui_only,
-                &include,
-                &exclude,
-                *skip_deps_check,
-                &features,
-                url,
-                download_from,
-                default_world.map(|w| w.as_str()),
-                local_dependencies,
-                add_paths_to_api,
-                *rewrite,
-                *reproducible,
-                *force,
-                *verbose,
-                false,
-            )
-            .await
+            build::execute(&package_dir, &options).await
         }
         Some(("build-start-package", matches)) => {
             let package_dir = PathBuf::from(matches.get_one::<String>("DIR").unwrap());
-            let no_ui = matches.get_one::<bool>("NO_UI").unwrap();
-            let ui_only = matches.get_one::<bool>("UI_ONLY").unwrap_or(&false);
-            let include: HashSet<PathBuf> = matches
-                .get_many::<String>("INCLUDE")
-                .unwrap_or_default()
-                .map(|s| package_dir.join(s))
-                .collect();
-            let exclude: HashSet<PathBuf> = matches
-                .get_many::<String>("EXCLUDE")
-                .unwrap_or_default()
-                .map(|s| package_dir.join(s))
-                .collect();
             let url = format!(
                 "http://localhost:{}",
                 matches.get_one::<u16>("NODE_PORT").unwrap(),
             );
-            let skip_deps_check = matches.get_one::<bool>("SKIP_DEPS_CHECK").unwrap();
-            let features = match matches.get_one::<String>("FEATURES") {
-                Some(f) => f.clone(),
-                None => "".into(),
+            let options = BuildOptions {
+                no_ui: *matches.get_one::<bool>("NO_UI").unwrap(),
+                ui_only: *matches.get_one::<bool>("UI_ONLY").unwrap_or(&false),
+                include: matches
+                    .get_many::<String>("INCLUDE")
+                    .unwrap_or_default()
+                    .map(|s| package_dir.join(s))
+                    .collect(),
+                exclude: matches
+                    .get_many::<String>("EXCLUDE")
+                    .unwrap_or_default()
+                    .map(|s| package_dir.join(s))
+                    .collect(),
+                skip_deps_check: *matches.get_one::<bool>("SKIP_DEPS_CHECK").unwrap(),
+                features: matches
+                    .get_one::<String>("FEATURES")
+                    .cloned()
+                    .unwrap_or_default(),
+                all_features: *matches.get_one::<bool>("ALL_FEATURES").unwrap(),
+                default_features: *matches.get_one::<bool>("DEFAULT_FEATURES").unwrap(),
+                download_from: matches.get_one::<String>("NODE").cloned(),
+                default_world: matches.get_one::<String>("WORLD").cloned(),
+                local_dependencies: matches
+                    .get_many::<String>("DEPENDENCY_PACKAGE_PATH")
+                    .unwrap_or_default()
+                    .map(|s| PathBuf::from(s))
+                    .collect(),
+                add_paths_to_api: matches
+                    .get_many::<String>("PATH")
+                    .unwrap_or_default()
+                    .map(|s| PathBuf::from(s))
+                    .collect(),
+                rewrite: *matches.get_one::<bool>("REWRITE").unwrap(),
+                strip_custom_sections: *matches.get_one::<bool>("STRIP_CUSTOM_SECTIONS").unwrap(),
+                wasm_opt_path: matches.get_one::<String>("WASM_OPT_PATH").cloned(),
+                wasm_opt_level: matches.get_one::<String>("WASM_OPT_LEVEL").cloned(),
+                reproducible: *matches.get_one::<bool>("REPRODUCIBLE").unwrap(),
+                force: *matches.get_one::<bool>("FORCE").unwrap(),
+                verbose: *matches.get_one::<bool>("VERBOSE").unwrap(),
+                ..Default::default()
             };
-            let download_from = matches
-                .get_one::<String>("NODE")
-                .and_then(|s: &String| Some(s.as_str()));
-            let default_world = matches.get_one::<String>("WORLD");
-            let local_dependencies: Vec<PathBuf> = matches
-                .get_many::<String>("DEPENDENCY_PACKAGE_PATH")
-                .unwrap_or_default()
-                .map(|s| PathBuf::from(s))
-                .collect();
-            let add_paths_to_api: Vec<PathBuf> = matches
-                .get_many::<String>("PATH")
-                .unwrap_or_default()
-                .map(|s| PathBuf::from(s))
-                .collect();
-            let rewrite = matches.get_one::<bool>("REWRITE").unwrap();
-            let reproducible = matches.get_one::<bool>("REPRODUCIBLE").unwrap();
-            let force = matches.get_one::<bool>("FORCE").unwrap();
-            let verbose = matches.get_one::<bool>("VERBOSE").unwrap();
 
-            build_start_package::execute(
-                &package_dir,
-                *no_ui,
-                *ui_only,
-                &include,
-                &exclude,
-                &url,
-                *skip_deps_check,
-                &features,
-                download_from,
-                default_world.map(|w| w.as_str()),
-                local_dependencies,
-                add_paths_to_api,
-                *rewrite,
-                *reproducible,
-                *force,
-                *verbose,
-            )
-            .await
+            build_start_package::execute(&package_dir, &url, &options).await
         }
         Some(("chain", matches)) => {
+            match matches.subcommand() {
+                Some(("set-time", matches)) => {
+                    let port = matches.get_one::<u16>("PORT").unwrap();
+                    let timestamp = matches.get_one::<u64>("TIMESTAMP").unwrap();
+                    return chain::set_time(*port, *timestamp).await;
+                }
+                Some(("advance-time", matches)) => {
+                    let port = matches.get_one::<u16>("PORT").unwrap();
+                    let seconds = matches.get_one::<u64>("SECONDS").unwrap();
+                    return chain::advance_time(*port, *seconds).await;
+                }
+                Some(("time-travel", matches)) => {
+                    let port = matches.get_one::<u16>("PORT").unwrap();
+                    let seconds = matches.get_one::<u64>("SECONDS");
+                    let blocks = matches.get_one::<u64>("BLOCKS");
+                    return chain::time_travel(*port, seconds.copied(), blocks.copied()).await;
+                }
+                Some(("replay", matches)) => {
+                    let port = matches.get_one::<u16>("PORT").unwrap();
+                    let trace_file =
+                        PathBuf::from(matches.get_one::<String>("TRACE_FILE").unwrap());
+                    let sender = matches.get_one::<String>("SENDER").map(|s| s.as_str());
+                    return chain::replay_trace(*port, &trace_file, sender).await;
+                }
+                Some(("snapshot", matches)) => {
+                    let port = matches.get_one::<u16>("PORT").unwrap();
+                    let path = PathBuf::from(matches.get_one::<String>("PATH").unwrap());
+                    return chain::snapshot(*port, &path).await;
+                }
+                Some(("restore", matches)) => {
+                    let port = matches.get_one::<u16>("PORT").unwrap();
+                    let path = PathBuf::from(matches.get_one::<String>("PATH").unwrap());
+                    return chain::restore(*port, &path).await;
+                }
+                _ => {}
+            }
             let port = matches.get_one::<u16>("PORT").unwrap();
             let version = matches.get_one::<String>("VERSION").unwrap();
-            let verbose = matches.get_one::<bool>("VERBOSE").unwrap();
-            chain::execute(*port, version, *verbose).await
+            let options = ChainOptions {
+                rpc_proxy_port: matches.get_one::<u16>("RPC_PROXY_PORT").copied(),
+                rpc_log: matches.get_one::<String>("RPC_LOG").map(PathBuf::from),
+                rpc_log_max_mb: matches.get_one::<u64>("RPC_LOG_MAX_MB").copied(),
+                reset: *matches.get_one::<bool>("RESET").unwrap(),
+                watch_storage: matches
+                    .get_many::<String>("WATCH_STORAGE")
+                    .unwrap_or_default()
+                    .map(|s| s.to_string())
+                    .collect(),
+                watch_interval_ms: *matches.get_one::<u64>("WATCH_INTERVAL_MS").unwrap(),
+                impersonate: matches.get_one::<String>("IMPERSONATE").cloned(),
+                verify: *matches.get_one::<bool>("VERIFY").unwrap(),
+                export_abis: matches.get_one::<String>("EXPORT_ABIS").map(PathBuf::from),
+                export_addresses: matches
+                    .get_one::<String>("EXPORT_ADDRESSES")
+                    .map(PathBuf::from),
+                deployment_script: matches
+                    .get_one::<String>("DEPLOYMENT_SCRIPT")
+                    .map(PathBuf::from),
+                timestamp: matches.get_one::<u64>("TIMESTAMP").copied(),
+                block_time: matches.get_one::<f64>("BLOCK_TIME").copied(),
+                hardfork: matches.get_one::<String>("HARDFORK").cloned(),
+                genesis_file: matches.get_one::<String>("GENESIS_FILE").map(PathBuf::from),
+                fork_url: matches.get_one::<String>("FORK_URL").cloned(),
+                fork_block_number: matches.get_one::<u64>("FORK_BLOCK_NUMBER").copied(),
+                snapshot: matches.get_one::<String>("SNAPSHOT").map(PathBuf::from),
+                dump_state: matches.get_one::<String>("DUMP_STATE").map(PathBuf::from),
+                verbose: *matches.get_one::<bool>("VERBOSE").unwrap(),
+            };
+            chain::execute(*port, version, &options).await
         }
         Some(("connect", matches)) => {
             let local_port = matches.get_one::<u16>("LOCAL_PORT").unwrap();
@@ -323,6 +430,17 @@ async fn execute(
             let host_port = matches.get_one::<u16>("HOST_PORT").map(|hp| hp.clone());
             connect::execute(*local_port, *disconnect, host, host_port)
         }
+        Some(("coverage-report", matches)) => {
+            let package_dir = PathBuf::from(matches.get_one::<String>("DIR").unwrap());
+            let profraw_dirs: Vec<PathBuf> = matches
+                .get_many::<String>("PROFRAW_DIR")
+                .unwrap_or_default()
+                .map(PathBuf::from)
+                .collect();
+            let output_dir = matches.get_one::<String>("OUTPUT_DIR").map(PathBuf::from);
+
+            coverage_report::execute(&package_dir, &profraw_dirs, output_dir.as_deref())
+        }
         Some(("dev-ui", matches)) => {
             let package_dir = PathBuf::from(matches.get_one::<String>("DIR").unwrap());
             let url = format!(
@@ -341,7 +459,11 @@ async fn execute(
             );
             let process: &String = matches.get_one("PROCESS").unwrap();
             let non_block: &bool = matches.get_one("NONBLOCK").unwrap();
-            let body: &String = matches.get_one("BODY_JSON").unwrap();
+            let timeout: &u64 = matches.get_one("TIMEOUT").unwrap();
+            let body: &String = matches
+                .get_one("BODY_JSON")
+                .or_else(|| matches.get_one("BODY"))
+                .unwrap();
             let node: Option<&str> = matches
                 .get_one("NODE_NAME")
                 .and_then(|s: &String| Some(s.as_str()));
@@ -349,7 +471,7 @@ async fn execute(
                 .get_one("PATH")
                 .and_then(|s: &String| Some(s.as_str()));
 
-            let expects_response = if *non_block { None } else { Some(15) };
+            let expects_response = if *non_block { None } else { Some(*timeout) };
             inject_message::execute(&url, process, expects_response, body, node, bytes).await
         }
         Some(("new", matches)) => {
@@ -361,14 +483,60 @@ async fn execute(
             let language: new::Language = matches.get_one::<String>("LANGUAGE").unwrap().into();
             let template: new::Template = matches.get_one::<String>("TEMPLATE").unwrap().into();
             let ui = matches.get_one::<bool>("UI").unwrap_or(&false);
+            let with_readme = matches.get_one::<bool>("WITH_README").unwrap_or(&false);
+            let test_template: Option<new::Template> =
+                matches.get_one::<String>("TEST_TEMPLATE").map(|t| t.into());
+            let template_url = matches.get_one::<String>("TEMPLATE_URL").cloned();
+            let capabilities: Vec<String> = matches
+                .get_many::<String>("CAPABILITY")
+                .unwrap_or_default()
+                .map(|s| s.to_string())
+                .collect();
+            let workspace_root = matches
+                .get_one::<String>("WORKSPACE_ROOT")
+                .map(PathBuf::from);
+            let interactive = matches.get_one::<bool>("INTERACTIVE").unwrap();
+            let channel_type = matches.get_one::<new::ChannelType>("CHANNEL_TYPE").copied();
+
+            let is_template_given = template_url.is_some()
+                || matches.value_source("TEMPLATE") != Some(ValueSource::DefaultValue);
+            let (package_name, publisher, template, ui) = if *interactive && !is_template_given {
+                let mut other_args = vec![];
+                if *with_readme {
+                    other_args.push("--with-readme".to_string());
+                }
+                if let Some(test_template) = matches.get_one::<String>("TEST_TEMPLATE") {
+                    other_args.push(format!("--test-template {test_template}"));
+                }
+                for capability in &capabilities {
+                    other_args.push(format!("--capability {capability}"));
+                }
+                if let Some(ref workspace_root) = workspace_root {
+                    other_args.push(format!("--workspace-root {}", workspace_root.display()));
+                }
+                if let Some(ref channel_type) = channel_type {
+                    other_args.push(format!("--channel-type {}", channel_type.as_str()));
+                }
+                new::prompt_new_params(&new_dir, package_name, publisher, *ui, &other_args)?
+            } else {
+                (package_name, publisher.clone(), template, *ui)
+            };
 
             new::execute(
                 new_dir,
-                package_name,
-                publisher.clone(),
-                language.clone(),
-                template.clone(),
-                *ui,
+                new::NewOptions {
+                    package_name,
+                    publisher,
+                    language,
+                    template,
+                    ui,
+                    with_readme: *with_readme,
+                    test_template,
+                    template_url,
+                    capabilities,
+                    workspace_root,
+                    channel_type,
+                },
             )
         }
         Some(("publish", matches)) => {
@@ -434,7 +602,25 @@ async fn execute(
                 return Err(eyre!(error));
             }
 
-            run_tests::execute(config_path).await
+            let persist_state = matches
+                .get_one::<String>("PERSIST_STATE")
+                .map(PathBuf::from);
+            let reset_state = matches.get_one::<bool>("RESET_STATE").unwrap();
+            let measure_io = matches.get_one::<bool>("MEASURE_IO").unwrap();
+            let gantt_output = matches.get_one::<String>("GANTT_OUTPUT").map(PathBuf::from);
+            let json_output = matches.get_one::<String>("OUTPUT").unwrap() == "json";
+            let filter = matches.get_one::<String>("FILTER").cloned();
+
+            run_tests::execute(
+                config_path,
+                persist_state,
+                *reset_state,
+                *measure_io,
+                gantt_output,
+                json_output,
+                filter,
+            )
+            .await
         }
         Some(("setup", matches)) => {
             let verbose = matches.get_one::<bool>("VERBOSE").unwrap();
@@ -456,9 +642,16 @@ async fn execute(
                 .unwrap_or_default()
                 .map(|v| v.to_string())
                 .collect::<Vec<_>>();
-            let branch = matches.get_one::<String>("BRANCH").unwrap();
+            let git_ref = match (
+                matches.get_one::<String>("TAG"),
+                matches.get_one::<String>("REV"),
+            ) {
+                (Some(tag), _) => update::GitRef::Tag(tag.clone()),
+                (_, Some(rev)) => update::GitRef::Rev(rev.clone()),
+                _ => update::GitRef::Branch(matches.get_one::<String>("BRANCH").unwrap().clone()),
+            };
 
-            update::execute(args, branch)
+            update::execute(args, git_ref)
         }
         Some(("view-api", matches)) => {
             let package_id = matches
@@ -475,6 +668,30 @@ async fn execute(
             view_api::execute(None, package_id, &url, download_from, true).await?;
             Ok(())
         }
+        Some(("watch", matches)) => {
+            let package_dir = PathBuf::from(matches.get_one::<String>("DIR").unwrap());
+            let url = format!(
+                "http://localhost:{}",
+                matches.get_one::<u16>("NODE_PORT").unwrap(),
+            );
+            let debounce_ms = matches.get_one::<u64>("DEBOUNCE_MS").unwrap();
+            let no_ui = matches.get_one::<bool>("NO_UI").unwrap();
+            let features = match matches.get_one::<String>("FEATURES") {
+                Some(f) => f.clone(),
+                None => "".into(),
+            };
+            let verbose = matches.get_one::<bool>("VERBOSE").unwrap();
+
+            watch::execute(
+                &package_dir,
+                &url,
+                *debounce_ms,
+                *no_ui,
+                &features,
+                *verbose,
+            )
+            .await
+        }
         _ => {
             warn!("Invalid subcommand. Usage:\n{}", usage);
             Ok(())
@@ -534,6 +751,14 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .default_value("8080")
                 .value_parser(value_parser!(u16))
             )
+            .arg(Arg::new("NODES")
+                .action(ArgAction::Set)
+                .long("nodes")
+                .value_name("N")
+                .help("Boot a network of N fake nodes, fake0.dev, fake1.dev, ..., on consecutive ports from --port, with homes HOME-0, HOME-1, ...")
+                .default_value("1")
+                .value_parser(value_parser!(u16).range(1..))
+            )
             .arg(Arg::new("HOME")
                 .action(ArgAction::Set)
                 .short('o')
@@ -587,6 +812,20 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .default_value("0")
                 .value_parser(value_parser!(u8))
             )
+            .arg(Arg::new("INSTALL")
+                .action(ArgAction::Append)
+                .long("install")
+                .value_name("PATH_TO_ZIP")
+                .help("Once the node(s) are up, install this package zip, named `<package>:<publisher>.zip` as `kit build` writes it (can specify multiple times; installed in order)")
+            )
+            .arg(Arg::new("INSTALL_RETRIES")
+                .action(ArgAction::Set)
+                .long("install-retries")
+                .value_name("N")
+                .help("Retry each --install up to N times")
+                .default_value("3")
+                .value_parser(value_parser!(u32))
+            )
         )
         .subcommand(Command::new("boot-real-node")
             .about("Boot a real node")
@@ -689,7 +928,7 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .action(ArgAction::Append)
                 .short('e')
                 .long("exclude")
-                .help("Build all but these processes/UIs (can specify multiple times) [default: build all]")
+                .help("Build all but these processes/UIs (can specify multiple times) [default: `skip_processes` in kit.toml, else build all]")
             )
             .arg(Arg::new("SKIP_DEPS_CHECK")
                 .action(ArgAction::SetTrue)
@@ -701,7 +940,19 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
             .arg(Arg::new("FEATURES")
                 .action(ArgAction::Set)
                 .long("features")
-                .help("Pass these comma-delimited feature flags to Rust cargo builds")
+                .help("Pass these comma-delimited feature flags to Rust cargo builds [default: `features` in kit.toml]")
+                .required(false)
+            )
+            .arg(Arg::new("ALL_FEATURES")
+                .action(ArgAction::SetTrue)
+                .long("all-features")
+                .help("Pass --all-features to Rust cargo builds")
+                .required(false)
+            )
+            .arg(Arg::new("DEFAULT_FEATURES")
+                .action(ArgAction::SetTrue)
+                .long("default-features")
+                .help("Build Rust processes with their default features [default: pass --no-default-features to cargo]")
                 .required(false)
             )
             .arg(Arg::new("NODE_PORT")
@@ -743,6 +994,141 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .help("Rewrite the package (disables `Spawn!()`) [default: don't rewrite]")
                 .required(false)
             )
+            .arg(Arg::new("STRIP_CUSTOM_SECTIONS")
+                .action(ArgAction::SetTrue)
+                .long("strip-custom-sections")
+                .help("If set, strip custom sections (e.g. `name`, `producers`, DWARF) from built Wasm")
+                .required(false)
+            )
+            .arg(Arg::new("WASM_OPT_PATH")
+                .action(ArgAction::Set)
+                .long("wasm-opt-path")
+                .help("If set, optimize Rust process Wasm with this wasm-opt binary [default: $WASM_OPT_PATH, else `wasm_opt_path` in kit.toml]")
+                .required(false)
+            )
+            .arg(Arg::new("WASM_OPT_LEVEL")
+                .action(ArgAction::Set)
+                .long("wasm-opt-level")
+                .value_name("LEVEL")
+                .value_parser(["0", "1", "2", "3", "4", "s", "z", "none"])
+                .help("Optimization level wasm-opt is run with, if configured, as `-O<LEVEL>`; `none` skips wasm-opt [default: `wasm_opt_level` in kit.toml, else s]")
+                .required(false)
+            )
+            .arg(Arg::new("PUBLISHER")
+                .action(ArgAction::Set)
+                .long("publisher")
+                .help("Build for this publisher node: sets metadata.json publisher & `pkg:publisher` address literals in a copy of the source")
+                .required(false)
+            )
+            .arg(Arg::new("SIGN")
+                .action(ArgAction::Set)
+                .long("sign")
+                .value_name("KEY_PATH")
+                .help("Sign the pkg/ content hash with this ed25519 private key: writes pkg/signature.sig & the public key to metadata.json")
+                .required(false)
+            )
+            .arg(Arg::new("EMBED_FILE")
+                .action(ArgAction::Append)
+                .long("embed-file")
+                .value_name("PATH:NAME")
+                .help("Embed file at PATH into Rust processes as NAME: `include!(\"../../target/embedded_files.rs\")` then `embedded_file(NAME)` (can specify multiple times)")
+                .required(false)
+            )
+            .arg(Arg::new("INJECT_MOCK")
+                .action(ArgAction::Append)
+                .long("inject-mock")
+                .value_name("PROCESS=WASM_PATH")
+                .help("Replace built PROCESS in pkg/ with the mock component at WASM_PATH, which must have the same WIT world (can specify multiple times)")
+                .required(false)
+            )
+            .arg(Arg::new("EMIT_DOCS")
+                .action(ArgAction::SetTrue)
+                .long("emit-docs")
+                .help("If set, generate Markdown API docs from the WIT files in api/ into pkg/docs/")
+                .required(false)
+            )
+            .arg(Arg::new("GRAPH")
+                .action(ArgAction::SetTrue)
+                .long("graph")
+                .help("If set, write a GraphViz pkg/graph.dot of the processes, the processes they send Requests to, and the WIT interfaces they import; also render pkg/graph.png if `dot` is installed")
+                .required(false)
+            )
+            .arg(Arg::new("EMIT_WIT_JSON")
+                .action(ArgAction::SetTrue)
+                .long("emit-wit-json")
+                .help("If set, write the package's WIT, as JSON from `wasm-tools component wit --json`, to pkg/wit.json")
+                .required(false)
+            )
+            .arg(Arg::new("EMIT_SYMBOLS")
+                .action(ArgAction::SetTrue)
+                .long("emit-symbols")
+                .help("If set, write a map of each process's WASM function indices to demangled Rust names to pkg/{process}.symbols.json")
+                .required(false)
+            )
+            .arg(Arg::new("WARN_UNUSED_WIT_TYPES")
+                .action(ArgAction::SetTrue)
+                .long("warn-unused-wit-types")
+                .help("If set, warn about types of the WIT interfaces a Rust process's world imports that its src/ never refers to")
+                .required(false)
+            )
+            .arg(Arg::new("CHECK_BREAKING")
+                .action(ArgAction::SetTrue)
+                .long("check-breaking")
+                .help("If set, compare the package's WIT API with that of its previous build (pkg/api.zip), failing on breaking changes & warning of additive ones")
+                .required(false)
+            )
+            .arg(Arg::new("SBOM")
+                .action(ArgAction::SetTrue)
+                .long("sbom")
+                .help("If set, write an SPDX SBOM of the Rust dependencies, with versions, licenses & checksums, to pkg/sbom.spdx.json")
+                .required(false)
+            )
+            .arg(Arg::new("FORBID_NETWORK")
+                .action(ArgAction::SetTrue)
+                .long("forbid-network")
+                .help("If set, fail if any process in pkg/manifest.json requests networking or the net, http-client, or eth capabilities")
+                .required(false)
+            )
+            .arg(Arg::new("FORBID_CAPABILITIES")
+                .action(ArgAction::Append)
+                .long("forbid-capabilities")
+                .value_name("CAPABILITIES")
+                .value_delimiter(',')
+                .help("Fail if any process in pkg/manifest.json requests a capability from one of these comma-separated processes (e.g. `http-client:distro:sys` or `http-client`; `networking` for request_networking)")
+                .required(false)
+            )
+            .arg(Arg::new("DOCKER")
+                .action(ArgAction::SetTrue)
+                .long("docker")
+                .help("If set, also write a Dockerfile & docker-compose.yml that run a Kinode which installs the built package on startup")
+                .required(false)
+            )
+            .arg(Arg::new("SANDBOX")
+                .action(ArgAction::SetTrue)
+                .long("sandbox")
+                .help("If set, compile each Rust process with no network & writes only to its target/, using nsjail (Linux) or sandbox-exec (macOS)")
+                .required(false)
+            )
+            .arg(Arg::new("COVERAGE")
+                .action(ArgAction::SetTrue)
+                .long("coverage")
+                .help("If set, instrument Rust processes for code coverage (see `kit coverage-report`); skips wasm-opt")
+                .conflicts_with("STRIP_CUSTOM_SECTIONS")
+                .required(false)
+            )
+            .arg(Arg::new("STATS")
+                .action(ArgAction::SetTrue)
+                .long("stats")
+                .help("If set, display per-process build statistics accumulated across builds, then exit")
+                .required(false)
+            )
+            .arg(Arg::new("RESET_STATS")
+                .action(ArgAction::SetTrue)
+                .long("reset-stats")
+                .help("If set, clear accumulated build statistics, then exit")
+                .conflicts_with("STATS")
+                .required(false)
+            )
             .arg(Arg::new("REPRODUCIBLE")
                 .action(ArgAction::SetTrue)
                 .short('r')
@@ -750,6 +1136,35 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .help("Make a reproducible build using Docker")
                 .required(false)
             )
+            .arg(Arg::new("IN_DOCKER")
+                .action(ArgAction::SetTrue)
+                .long("in-docker")
+                .help("If set, build inside the Docker image with the toolchain this version of kit is tested with, writing the build to pkg/")
+                .conflicts_with_all(["REPRODUCIBLE", "SANDBOX", "COVERAGE", "DEPENDENCY_PACKAGE_PATH", "PATH", "WASM_OPT_PATH", "PUBLISHER", "SIGN", "EMBED_FILE", "INJECT_MOCK", "INCLUDE", "EXCLUDE"])
+                .required(false)
+            )
+            .arg(Arg::new("VERIFY_REPRODUCIBLE")
+                .action(ArgAction::SetTrue)
+                .long("verify-reproducible")
+                .help("If set, build twice from clean target/ dirs & fail if the pkg/ Wasm files differ")
+                .conflicts_with_all(["REPRODUCIBLE", "IN_DOCKER", "PUBLISHER"])
+                .required(false)
+            )
+            .arg(Arg::new("NO_CACHE")
+                .action(ArgAction::SetTrue)
+                .long("no-cache")
+                .help("If set, do not reuse (or store) Rust process Wasm from the per-process build cache; entries unused for 30 days are evicted, `kit reset-cache` clears it")
+                .required(false)
+            )
+            .arg(Arg::new("JOBS")
+                .action(ArgAction::Set)
+                .short('j')
+                .long("jobs")
+                .value_name("N")
+                .help("Compile at most N processes at once [default: number of logical CPUs]")
+                .value_parser(value_parser!(u64).range(1..))
+                .required(false)
+            )
             .arg(Arg::new("FORCE")
                 .action(ArgAction::SetTrue)
                 .short('f')
@@ -829,7 +1244,7 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .action(ArgAction::Append)
                 .short('e')
                 .long("exclude")
-                .help("Build all but these processes/UIs (can specify multiple times) [default: build all]")
+                .help("Build all but these processes/UIs (can specify multiple times) [default: `skip_processes` in kit.toml, else build all]")
             )
             .arg(Arg::new("SKIP_DEPS_CHECK")
                 .action(ArgAction::SetTrue)
@@ -841,7 +1256,19 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
             .arg(Arg::new("FEATURES")
                 .action(ArgAction::Set)
                 .long("features")
-                .help("Pass these comma-delimited feature flags to Rust cargo builds")
+                .help("Pass these comma-delimited feature flags to Rust cargo builds [default: `features` in kit.toml]")
+                .required(false)
+            )
+            .arg(Arg::new("ALL_FEATURES")
+                .action(ArgAction::SetTrue)
+                .long("all-features")
+                .help("Pass --all-features to Rust cargo builds")
+                .required(false)
+            )
+            .arg(Arg::new("DEFAULT_FEATURES")
+                .action(ArgAction::SetTrue)
+                .long("default-features")
+                .help("Build Rust processes with their default features [default: pass --no-default-features to cargo]")
                 .required(false)
             )
             .arg(Arg::new("REWRITE")
@@ -850,6 +1277,26 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .help("Rewrite the package (disables `Spawn!()`) [default: don't rewrite]")
                 .required(false)
             )
+            .arg(Arg::new("STRIP_CUSTOM_SECTIONS")
+                .action(ArgAction::SetTrue)
+                .long("strip-custom-sections")
+                .help("If set, strip custom sections (e.g. `name`, `producers`, DWARF) from built Wasm")
+                .required(false)
+            )
+            .arg(Arg::new("WASM_OPT_PATH")
+                .action(ArgAction::Set)
+                .long("wasm-opt-path")
+                .help("If set, optimize Rust process Wasm with this wasm-opt binary [default: $WASM_OPT_PATH, else `wasm_opt_path` in kit.toml]")
+                .required(false)
+            )
+            .arg(Arg::new("WASM_OPT_LEVEL")
+                .action(ArgAction::Set)
+                .long("wasm-opt-level")
+                .value_name("LEVEL")
+                .value_parser(["0", "1", "2", "3", "4", "s", "z", "none"])
+                .help("Optimization level wasm-opt is run with, if configured, as `-O<LEVEL>`; `none` skips wasm-opt [default: `wasm_opt_level` in kit.toml, else s]")
+                .required(false)
+            )
             .arg(Arg::new("REPRODUCIBLE")
                 .action(ArgAction::SetTrue)
                 .short('r')
@@ -911,6 +1358,278 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .help("If set, output stdout and stderr")
                 .required(false)
             )
+            .arg(Arg::new("RPC_PROXY_PORT")
+                .action(ArgAction::Set)
+                .long("rpc-proxy-port")
+                .help("If set, start a JSON-RPC proxy in front of the chain on this port that logs every call")
+                .value_parser(value_parser!(u16))
+                .required(false)
+            )
+            .arg(Arg::new("RPC_LOG")
+                .action(ArgAction::Set)
+                .long("rpc-log")
+                .help("Path to write proxied JSON-RPC calls to as JSON lines [default: stdout]")
+                .requires("RPC_PROXY_PORT")
+                .required(false)
+            )
+            .arg(Arg::new("RPC_LOG_MAX_MB")
+                .action(ArgAction::Set)
+                .long("rpc-log-max-mb")
+                .help("If set, rotate --rpc-log to <PATH>.1 (keeping 5) once it would exceed this many MB")
+                .value_parser(value_parser!(u64).range(1..))
+                .requires("RPC_LOG")
+                .required(false)
+            )
+            .arg(Arg::new("RESET")
+                .action(ArgAction::SetTrue)
+                .long("reset")
+                .help("If set, reset the chain already running on --port to its initial state and exit")
+                .conflicts_with("RPC_PROXY_PORT")
+                .required(false)
+            )
+            .arg(Arg::new("WATCH_STORAGE")
+                .action(ArgAction::Append)
+                .long("watch-storage")
+                .num_args(2)
+                .value_names(["ADDRESS", "SLOT"])
+                .help("Contract address & storage slot to watch: alert when its value changes (can specify multiple times)")
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("WATCH_INTERVAL_MS")
+                .action(ArgAction::Set)
+                .long("watch-interval-ms")
+                .help("How often to poll --watch-storage slots, in milliseconds")
+                .default_value("1000")
+                .value_parser(value_parser!(u64))
+                .required(false)
+            )
+            .arg(Arg::new("IMPERSONATE")
+                .action(ArgAction::Set)
+                .long("impersonate")
+                .value_name("ADDRESS")
+                .help("Impersonate ADDRESS for the whole session (with zero base fee) so it can send transactions without a key")
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("VERIFY")
+                .action(ArgAction::SetTrue)
+                .long("verify")
+                .help("If set, check the bytecode of each Kinode contract on the chain against the expected bytecode, exiting with an error on mismatch (verifies and exits if a chain is already running on --port)")
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("EXPORT_ABIS")
+                .action(ArgAction::Set)
+                .long("export-abis")
+                .value_name("DIR")
+                .help("Write the JSON ABI of each Kinode contract on the chain to DIR/<address>.json, with an index.json of their names (exports and exits if a chain is already running on --port)")
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("EXPORT_ADDRESSES")
+                .action(ArgAction::Set)
+                .long("export-addresses")
+                .value_name("PATH")
+                .help("Write a JSON object of the name to address of each Kinode contract on the chain to PATH (exports and exits if a chain is already running on --port)")
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("DEPLOYMENT_SCRIPT")
+                .action(ArgAction::Set)
+                .long("deployment-script")
+                .value_name("PATH")
+                .help("Replay, in order, the transactions of the broadcast JSON at PATH written by `forge script --broadcast` (replays and exits if a chain is already running on --port)")
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("TIMESTAMP")
+                .action(ArgAction::Set)
+                .long("timestamp")
+                .value_name("UNIX_TS")
+                .help("Start the chain with its genesis block at UNIX_TS (seconds)")
+                .value_parser(value_parser!(u64))
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("BLOCK_TIME")
+                .action(ArgAction::Set)
+                .long("block-time")
+                .value_name("SECONDS")
+                .help("Mine a block every SECONDS; 0 mines a block per transaction [default: 0]")
+                .value_parser(value_parser!(f64))
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("HARDFORK")
+                .action(ArgAction::Set)
+                .long("hardfork")
+                .value_name("NAME")
+                .help("Run the chain at EVM hardfork NAME")
+                .default_value(chain::DEFAULT_HARDFORK)
+                .value_parser(PossibleValuesParser::new(chain::HARDFORKS))
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("GENESIS_FILE")
+                .action(ArgAction::Set)
+                .long("genesis-file")
+                .value_name("PATH")
+                .help("Start the chain from the Ethereum genesis JSON at PATH, adding the Kinode contracts & accounts it does not allocate")
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .arg(Arg::new("FORK_URL")
+                .action(ArgAction::Set)
+                .long("fork-url")
+                .value_name("RPC_URL")
+                .help("Fork the chain at RPC_URL, adding the Kinode contracts & accounts where it has no code; Kimap is live on Optimism (chain ID 10) at 0xcA92476B2483aBD5D82AEBF0b56701Bb2e9be658")
+                .conflicts_with_all(["RESET", "GENESIS_FILE"])
+                .required(false)
+            )
+            .arg(Arg::new("FORK_BLOCK_NUMBER")
+                .action(ArgAction::Set)
+                .long("fork-block-number")
+                .value_name("N")
+                .help("Fork --fork-url at block N [default: latest]")
+                .value_parser(value_parser!(u64))
+                .requires("FORK_URL")
+                .required(false)
+            )
+            .arg(Arg::new("SNAPSHOT")
+                .action(ArgAction::Set)
+                .long("snapshot")
+                .visible_alias("restore-state")
+                .value_name("PATH")
+                .help("Start the chain from a snapshot written by `kit chain snapshot` or --dump-state rather than the initial Kinode state")
+                .conflicts_with_all(["RESET", "GENESIS_FILE", "FORK_URL"])
+                .required(false)
+            )
+            .arg(Arg::new("DUMP_STATE")
+                .action(ArgAction::Set)
+                .long("dump-state")
+                .value_name("PATH")
+                .help("On exit, write the chain's state to PATH, as `kit chain snapshot` does; restart from it with --restore-state")
+                .conflicts_with("RESET")
+                .required(false)
+            )
+            .args_conflicts_with_subcommands(true)
+            .subcommand(Command::new("set-time")
+                .about("Set the timestamp of the chain running on --port & mine a block at it")
+                .arg(Arg::new("TIMESTAMP")
+                    .action(ArgAction::Set)
+                    .value_name("UNIX_TS")
+                    .help("Timestamp (seconds) to move the chain to; must be after the latest block")
+                    .value_parser(value_parser!(u64))
+                    .required(true)
+                )
+                .arg(Arg::new("PORT")
+                    .action(ArgAction::Set)
+                    .short('p')
+                    .long("port")
+                    .help("Port the chain is running on")
+                    .default_value("8545")
+                    .value_parser(value_parser!(u16))
+                )
+            )
+            .subcommand(Command::new("advance-time")
+                .about("Advance the timestamp of the chain running on --port & mine a block at it")
+                .arg(Arg::new("SECONDS")
+                    .action(ArgAction::Set)
+                    .help("Seconds to advance the chain by, from its latest block")
+                    .value_parser(value_parser!(u64))
+                    .required(true)
+                )
+                .arg(Arg::new("PORT")
+                    .action(ArgAction::Set)
+                    .short('p')
+                    .long("port")
+                    .help("Port the chain is running on")
+                    .default_value("8545")
+                    .value_parser(value_parser!(u16))
+                )
+            )
+            .subcommand(Command::new("time-travel")
+                .about("Increase the time of the chain running on --port (`evm_increaseTime`) and/or mine empty blocks (`evm_mine`)")
+                .arg(Arg::new("SECONDS")
+                    .action(ArgAction::Set)
+                    .help("Seconds to increase the chain's time by; a block is mined at the new time")
+                    .value_parser(value_parser!(u64))
+                    .required_unless_present("BLOCKS")
+                )
+                .arg(Arg::new("BLOCKS")
+                    .action(ArgAction::Set)
+                    .long("blocks")
+                    .value_name("N")
+                    .help("Mine N empty blocks")
+                    .value_parser(value_parser!(u64))
+                    .required(false)
+                )
+                .arg(Arg::new("PORT")
+                    .action(ArgAction::Set)
+                    .short('p')
+                    .long("port")
+                    .help("Port the chain is running on")
+                    .default_value("8545")
+                    .value_parser(value_parser!(u16))
+                )
+            )
+            .subcommand(Command::new("replay")
+                .about("Replay, in order, the transactions of an archive node trace on the chain running on --port")
+                .arg(Arg::new("TRACE_FILE")
+                    .action(ArgAction::Set)
+                    .value_name("PATH")
+                    .help("JSON of transactions (`eth_getTransactionByHash`) and/or blocks with full transactions (`eth_getBlockByNumber`)")
+                    .required(true)
+                )
+                .arg(Arg::new("SENDER")
+                    .action(ArgAction::Set)
+                    .long("sender")
+                    .value_name("ADDRESS")
+                    .help("Send every transaction from ADDRESS rather than from its original sender")
+                    .required(false)
+                )
+                .arg(Arg::new("PORT")
+                    .action(ArgAction::Set)
+                    .short('p')
+                    .long("port")
+                    .help("Port the chain is running on")
+                    .default_value("8545")
+                    .value_parser(value_parser!(u16))
+                )
+            )
+            .subcommand(Command::new("snapshot")
+                .about("Write the state of the chain running on --port (`anvil_dumpState`) to a file")
+                .arg(Arg::new("PATH")
+                    .action(ArgAction::Set)
+                    .help("Path to write the snapshot to")
+                    .required(true)
+                )
+                .arg(Arg::new("PORT")
+                    .action(ArgAction::Set)
+                    .short('p')
+                    .long("port")
+                    .help("Port the chain is running on")
+                    .default_value("8545")
+                    .value_parser(value_parser!(u16))
+                )
+            )
+            .subcommand(Command::new("restore")
+                .about("Load a snapshot from `kit chain snapshot` into the chain running on --port (`anvil_loadState`)")
+                .arg(Arg::new("PATH")
+                    .action(ArgAction::Set)
+                    .help("Path of the snapshot to load")
+                    .required(true)
+                )
+                .arg(Arg::new("PORT")
+                    .action(ArgAction::Set)
+                    .short('p')
+                    .long("port")
+                    .help("Port the chain is running on")
+                    .default_value("8545")
+                    .value_parser(value_parser!(u16))
+                )
+            )
         )
         .subcommand(Command::new("connect")
             .about("Connect (or disconnect) a ssh tunnel to a remote server")
@@ -943,6 +1662,27 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .required(false)
             )
         )
+        .subcommand(Command::new("coverage-report")
+            .about("Merge the `.profraw`s written by a `kit build --coverage` package into an HTML coverage report")
+            .arg(Arg::new("DIR")
+                .action(ArgAction::Set)
+                .help("The package directory built with `--coverage`")
+                .default_value(current_dir)
+            )
+            .arg(Arg::new("PROFRAW_DIR")
+                .action(ArgAction::Append)
+                .long("profraw-dir")
+                .help("Directory to search for `.profraw`s, e.g. a `kit run-tests --persist-state` DIR (can specify multiple times) [default: DIR]")
+                .required(false)
+            )
+            .arg(Arg::new("OUTPUT_DIR")
+                .action(ArgAction::Set)
+                .short('o')
+                .long("output-dir")
+                .help("Directory to write the HTML report to [default: DIR/target/coverage/html]")
+                .required(false)
+            )
+        )
         .subcommand(Command::new("dev-ui")
             .about("Start the web UI development server with hot reloading (same as `cd ui && npm i && npm run dev`)")
             .visible_alias("d")
@@ -982,8 +1722,16 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
             )
             .arg(Arg::new("BODY_JSON")
                 .action(ArgAction::Set)
-                .help("Body in JSON format")
-                .required(true)
+                .help("Body in JSON format, or `0x`-prefixed hex")
+                .required_unless_present("BODY")
+            )
+            .arg(Arg::new("BODY")
+                .action(ArgAction::Set)
+                .long("body")
+                .value_name("JSON_OR_HEX")
+                .help("Body in JSON format, or `0x`-prefixed hex; alternative to BODY_JSON")
+                .conflicts_with("BODY_JSON")
+                .required(false)
             )
             .arg(Arg::new("NODE_PORT")
                 .action(ArgAction::Set)
@@ -1013,6 +1761,16 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .long("non-block")
                 .help("If set, don't block on the full node response")
             )
+            .arg(Arg::new("TIMEOUT")
+                .action(ArgAction::Set)
+                .short('t')
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("Seconds to wait for the response")
+                .default_value("15")
+                .value_parser(value_parser!(u64))
+                .conflicts_with("NONBLOCK")
+            )
         )
         .subcommand(Command::new("new")
             .about("Create a Kinode template package")
@@ -1049,7 +1807,7 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .short('t')
                 .long("template")
                 .help("Template to create")
-                .value_parser(["blank", "chat", "echo", "fibonacci", "file-transfer"])
+                .value_parser(["blank", "chat", "echo", "fibonacci", "file-transfer", "stream-pipeline", "lamport-clock", "priority-queue", "saga", "consistent-hash", "graceful-shutdown", "bridge", "kv-store", "onchain-vote", "http-api", "rest-gateway", "pubsub"])
                 .default_value("chat")
             )
             .arg(Arg::new("UI")
@@ -1058,6 +1816,59 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .help("If set, use the template with UI")
                 .required(false)
             )
+            .arg(Arg::new("WITH_README")
+                .action(ArgAction::SetTrue)
+                .long("with-readme")
+                .help("If set, generate a README.md for the package")
+                .required(false)
+            )
+            .arg(Arg::new("TEST_TEMPLATE")
+                .action(ArgAction::Set)
+                .long("test-template")
+                .help("Template to generate `test/` from [default: the template's own tests]")
+                .value_parser(["chat", "echo", "fibonacci", "file-transfer", "stream-pipeline", "lamport-clock", "priority-queue", "saga", "consistent-hash", "graceful-shutdown", "bridge", "kv-store", "onchain-vote", "http-api", "rest-gateway", "pubsub"])
+                .required(false)
+            )
+            .arg(Arg::new("TEMPLATE_URL")
+                .action(ArgAction::Set)
+                .long("template-url")
+                .value_name("GIT_URL")
+                .help("Git repository of a template to use instead of a built-in --template; append `#<branch>` to pick a branch (cached in the kit cache for offline use)")
+                .conflicts_with("UI")
+                .required(false)
+            )
+            .arg(Arg::new("CAPABILITY")
+                .action(ArgAction::Append)
+                .long("capability")
+                .value_name("NAME")
+                .help("Request capability NAME in pkg/manifest.json: one of messaging, http_client, http_server, eth_client, vfs, timer, sqlite, or a process ID (can specify multiple times)")
+                .required(false)
+            )
+            .arg(Arg::new("WORKSPACE_ROOT")
+                .action(ArgAction::Set)
+                .long("workspace-root")
+                .value_name("PATH")
+                .help("Add the new package's crates to the members of the Cargo workspace at PATH, creating PATH/Cargo.toml if need be")
+                .required(false)
+            )
+            .arg(Arg::new("CHANNEL_TYPE")
+                .action(ArgAction::Set)
+                .long("channel-type")
+                .value_name("TYPE")
+                .help("How the template's processes send Requests: `sync` awaits each Response, `async` sends without waiting, `mixed` shows both [default: sync]")
+                .value_parser(
+                    PossibleValuesParser::new(["sync", "async", "mixed"])
+                        .try_map(|c| c.parse::<new::ChannelType>()),
+                )
+                .required(false)
+            )
+            .arg(Arg::new("INTERACTIVE")
+                .action(ArgAction::SetTrue)
+                .short('i')
+                .long("interactive")
+                .help("If set & no --template is given, prompt for the template, package name, publisher & UI")
+                .required(false)
+            )
         )
         .subcommand(Command::new("publish")
             .about("Publish or update a package")
@@ -1182,6 +1993,49 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .help("Path to tests configuration file (or test dir)")
                 .default_value(current_dir)
             )
+            .arg(Arg::new("PERSIST_STATE")
+                .action(ArgAction::Set)
+                .long("persist-state")
+                .value_name("DIR")
+                .help("Load node VFS state from DIR at start & save it to DIR after a successful run")
+                .required(false)
+            )
+            .arg(Arg::new("RESET_STATE")
+                .action(ArgAction::SetTrue)
+                .long("reset-state")
+                .help("If set, clear the state persisted in --persist-state DIR before running")
+                .requires("PERSIST_STATE")
+                .required(false)
+            )
+            .arg(Arg::new("MEASURE_IO")
+                .action(ArgAction::SetTrue)
+                .long("measure-io")
+                .help("If set, report the bytes each test node reads from & writes to disk while the tests run (Linux only)")
+                .required(false)
+            )
+            .arg(Arg::new("GANTT_OUTPUT")
+                .action(ArgAction::Set)
+                .long("gantt-output")
+                .value_name("PATH")
+                .help("Write a Mermaid Gantt chart of the build, startup, run & teardown phases of each test to PATH")
+                .required(false)
+            )
+            .arg(Arg::new("OUTPUT")
+                .action(ArgAction::Set)
+                .long("output")
+                .value_name("FORMAT")
+                .value_parser(["human", "json"])
+                .default_value("human")
+                .help("Output format; `json` prints a JSON line per test & a summary line to stdout, logging to stderr without color")
+                .required(false)
+            )
+            .arg(Arg::new("FILTER")
+                .action(ArgAction::Set)
+                .long("filter")
+                .value_name("PATTERN")
+                .help("Run only tests whose name matches PATTERN: a glob if it contains `*` or `?`, else a substring; skip the rest")
+                .required(false)
+            )
         )
         .subcommand(Command::new("setup")
             .about("Fetch & setup kit dependencies")
@@ -1210,6 +2064,49 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .value_parser(value_parser!(u16))
             )
         )
+        .subcommand(Command::new("watch")
+            .about("Rebuild a Kinode package on source or WIT changes and reinstall it on a running node")
+            .visible_alias("w")
+            .arg(Arg::new("DIR")
+                .action(ArgAction::Set)
+                .help("The package directory to watch")
+                .default_value(current_dir)
+            )
+            .arg(Arg::new("NODE_PORT")
+                .action(ArgAction::Set)
+                .short('p')
+                .long("port")
+                .help("localhost node port; for remote see https://book.kinode.org/hosted-nodes.html#using-kit-with-your-hosted-node")
+                .default_value("8080")
+                .value_parser(value_parser!(u16))
+            )
+            .arg(Arg::new("DEBOUNCE_MS")
+                .action(ArgAction::Set)
+                .long("debounce-ms")
+                .help("Wait for this many milliseconds without changes before rebuilding")
+                .default_value("300")
+                .value_parser(value_parser!(u64))
+            )
+            .arg(Arg::new("NO_UI")
+                .action(ArgAction::SetTrue)
+                .long("no-ui")
+                .help("If set, do NOT build the web UI for the process")
+                .required(false)
+            )
+            .arg(Arg::new("FEATURES")
+                .action(ArgAction::Set)
+                .long("features")
+                .help("Pass these comma-delimited feature flags to Rust cargo builds [default: `features` in kit.toml]")
+                .required(false)
+            )
+            .arg(Arg::new("VERBOSE")
+                .action(ArgAction::SetTrue)
+                .short('v')
+                .long("verbose")
+                .help("If set, output stdout and stderr")
+                .required(false)
+            )
+        )
         .subcommand(Command::new("update")
             .about("Fetch the most recent version of kit")
             .arg(Arg::new("ARGUMENTS")
@@ -1223,6 +2120,20 @@ async fn make_app(current_dir: &std::ffi::OsString) -> Result<Command> {
                 .help("Branch name (e.g. `next-release`)")
                 .default_value("master")
             )
+            .arg(Arg::new("TAG")
+                .action(ArgAction::Set)
+                .long("tag")
+                .help("Install this tag instead of a branch (e.g. `v0.7.2`)")
+                .conflicts_with_all(["BRANCH", "REV"])
+                .required(false)
+            )
+            .arg(Arg::new("REV")
+                .action(ArgAction::Set)
+                .long("rev")
+                .help("Install this commit SHA instead of a branch")
+                .conflicts_with("BRANCH")
+                .required(false)
+            )
         )
         .subcommand(Command::new("view-api")
             .about("Fetch the list of APIs or a specific API")
@@ -1256,7 +2167,6 @@ async fn main() -> Result<()> {
     let log_path =
         std::env::var("KIT_LOG_PATH").unwrap_or_else(|_| KIT_LOG_PATH_DEFAULT.to_string());
     let log_path = PathBuf::from(log_path);
-    let _guard = init_tracing(log_path);
     color_eyre::config::HookBuilder::default()
         .display_env_section(false)
         .install()?;
@@ -1269,6 +2179,12 @@ async fn main() -> Result<()> {
     let matches = app.get_matches();
     let matches = matches.subcommand();
 
+    // tracing starts after parsing args, since `kit run-tests --output json` changes it
+    let json_output = matches.is_some_and(|(subcommand, matches)| {
+        subcommand == "run-tests" && matches.get_one::<String>("OUTPUT").unwrap() == "json"
+    });
+    let _guard = init_tracing(log_path, json_output);
+
     let result = match execute(usage, matches).await {
         Ok(()) => Ok(()),
         Err(mut e) => {
@@ -1302,7 +2218,12 @@ async fn main() -> Result<()> {
     }
 
     if let Err(e) = result {
-        error!("{:?}", e);
+        if json_output {
+            // the `Debug` report is colored
+            error!("{:#}", e);
+        } else {
+            error!("{:?}", e);
+        }
         std::process::exit(1);
     };
     Ok(())
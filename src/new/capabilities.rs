@@ -0,0 +1,60 @@
+use color_eyre::Result;
+use tracing::{info, warn};
+
+use kinode_process_lib::kernel_types::PackageManifestEntry;
+
+/// `kit new --capability` names and the capabilities they request;
+/// `messaging` is `request_networking`, i.e. messaging other nodes
+const KNOWN_CAPABILITIES: &[(&str, &str)] = &[
+    ("messaging", "networking"),
+    ("http_client", "http-client:distro:sys"),
+    ("http_server", "http-server:distro:sys"),
+    ("eth_client", "eth:distro:sys"),
+    ("vfs", "vfs:distro:sys"),
+    ("timer", "timer:distro:sys"),
+    ("sqlite", "sqlite:distro:sys"),
+];
+
+/// Map `--capability` names to the process IDs to request, warning on
+/// unknown names; a full process ID (e.g. `kv:distro:sys`) is passed through
+fn resolve_capabilities(capabilities: &[String]) -> Vec<&str> {
+    let mut resolved = vec![];
+    for capability in capabilities {
+        let name = capability.replace('-', "_");
+        match KNOWN_CAPABILITIES.iter().find(|(known, _)| *known == name) {
+            Some((_, process)) => resolved.push(*process),
+            None if capability.split(':').count() == 3 => resolved.push(capability.as_str()),
+            None => warn!(
+                "Unknown capability {capability:?}; skipping. Known capabilities: {}",
+                KNOWN_CAPABILITIES
+                    .iter()
+                    .map(|(known, _)| *known)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        }
+    }
+    resolved
+}
+
+/// Add `capabilities` to the `request_capabilities` of each process
+/// in the given `pkg/manifest.json` contents
+pub fn add_manifest_capabilities(manifest: &str, capabilities: &[String]) -> Result<String> {
+    let resolved = resolve_capabilities(capabilities);
+    let mut manifest: Vec<PackageManifestEntry> = serde_json::from_str(manifest)?;
+    for entry in manifest.iter_mut() {
+        for process in &resolved {
+            if *process == "networking" {
+                entry.request_networking = true;
+            } else if !entry.request_capabilities.iter().any(|c| c == process) {
+                entry
+                    .request_capabilities
+                    .push(serde_json::Value::String(process.to_string()));
+            }
+        }
+    }
+    if !resolved.is_empty() {
+        info!("Requesting capabilities {resolved:?} in pkg/manifest.json.");
+    }
+    Ok(serde_json::to_string_pretty(&manifest)?)
+}
@@ -0,0 +1,108 @@
+use std::str::FromStr;
+
+/// Opens a block of template lines kept only for the given channel types,
+/// e.g. `// kit-channel-type: async mixed`; `// kit-channel-type: end` closes the region
+const MARKER: &str = "// kit-channel-type:";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChannelType {
+    Sync,
+    Async,
+    Mixed,
+}
+
+impl ChannelType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChannelType::Sync => "sync",
+            ChannelType::Async => "async",
+            ChannelType::Mixed => "mixed",
+        }
+    }
+}
+
+impl FromStr for ChannelType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sync" => Ok(ChannelType::Sync),
+            "async" => Ok(ChannelType::Async),
+            "mixed" => Ok(ChannelType::Mixed),
+            _ => Err(format!(
+                "channel type must be 'sync', 'async', or 'mixed'; not '{s}'"
+            )),
+        }
+    }
+}
+
+pub fn has_channel_type_blocks(content: &str) -> bool {
+    content.lines().any(|l| l.trim_start().starts_with(MARKER))
+}
+
+/// Keep, of each region of `// kit-channel-type: <types>` blocks, only the
+/// lines of the blocks listing `channel_type`, dropping the markers
+pub fn select_channel_type(content: &str, channel_type: &ChannelType) -> String {
+    let channel_type = channel_type.as_str();
+    let mut block: Option<&str> = None;
+    let mut selected: Vec<&str> = vec![];
+    for line in content.lines() {
+        if let Some(marker) = line.trim_start().strip_prefix(MARKER) {
+            block = Some(marker.trim()).filter(|b| *b != "end");
+            continue;
+        }
+        if block.is_none_or(|b| b.split_whitespace().any(|t| t == channel_type)) {
+            selected.push(line);
+        }
+    }
+    let mut selected = selected.join("\n");
+    if content.ends_with('\n') {
+        selected.push('\n');
+    }
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "\
+fn send() {
+    // kit-channel-type: sync
+    send(true);
+    // kit-channel-type: async mixed
+    send(false);
+    // kit-channel-type: end
+}
+";
+
+    #[test]
+    fn channel_type_from_str() {
+        for channel_type in [ChannelType::Sync, ChannelType::Async, ChannelType::Mixed] {
+            assert_eq!(channel_type.as_str().parse(), Ok(channel_type));
+        }
+        assert!("blocking".parse::<ChannelType>().is_err());
+    }
+
+    #[test]
+    fn has_channel_type_blocks_finds_markers() {
+        assert!(has_channel_type_blocks(CONTENT));
+        assert!(!has_channel_type_blocks("fn send() {}\n"));
+    }
+
+    #[test]
+    fn select_channel_type_keeps_blocks_listing_type() {
+        assert_eq!(
+            select_channel_type(CONTENT, &ChannelType::Sync),
+            "fn send() {\n    send(true);\n}\n",
+        );
+        assert_eq!(
+            select_channel_type(CONTENT, &ChannelType::Async),
+            "fn send() {\n    send(false);\n}\n",
+        );
+        assert_eq!(
+            select_channel_type(CONTENT, &ChannelType::Mixed),
+            "fn send() {\n    send(false);\n}\n",
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use color_eyre::{eyre::eyre, Result};
+
+use super::{is_kimap_safe, Template, DISALLOWED_PACKAGE_NAMES};
+
+const DEFAULT_TEMPLATE: &str = "chat";
+
+/// Built-in templates & what each demonstrates, in the order offered
+const TEMPLATE_DESCRIPTIONS: &[(&str, &str)] = &[
+    ("blank", "a bare process that prints & handles nothing"),
+    ("chat", "send & receive chat messages between nodes"),
+    ("echo", "respond to each Request with its own body"),
+    ("fibonacci", "compute Fibonacci numbers on request"),
+    ("file-transfer", "send files between nodes in chunks"),
+    (
+        "stream-pipeline",
+        "chain processes that transform a stream of items",
+    ),
+    (
+        "lamport-clock",
+        "order events across nodes with a Lamport clock",
+    ),
+    ("priority-queue", "enqueue & dequeue work by priority"),
+    (
+        "saga",
+        "run a multi-step transaction, compensating on failure",
+    ),
+    (
+        "consistent-hash",
+        "shard keys across nodes with a hash ring",
+    ),
+    ("graceful-shutdown", "drain in-flight work before exiting"),
+    ("bridge", "translate messages between two protocols"),
+    (
+        "kv-store",
+        "get, set, delete & list keys persisted in sqlite",
+    ),
+    ("onchain-vote", "tally votes written to Kimap notes"),
+    ("http-api", "serve a JSON HTTP API"),
+    (
+        "rest-gateway",
+        "map REST routes to process Requests, with an OpenAPI schema",
+    ),
+    ("pubsub", "publish payloads to the subscribers of a topic"),
+];
+
+/// Templates that have a `ui/` variant
+const UI_TEMPLATES: &[&str] = &["chat"];
+
+/// Print `question [default]: ` & read a line; an empty line gives `default`
+fn prompt(question: &str, default: &str) -> Result<String> {
+    print!("{question} [{default}]: ");
+    io::stdout().flush()?;
+    let mut response = String::new();
+    if io::stdin().read_line(&mut response)? == 0 {
+        return Err(eyre!("stdin closed while prompting for {question:?}"));
+    }
+    let response = response.trim();
+    Ok(if response.is_empty() {
+        default.to_string()
+    } else {
+        response.to_string()
+    })
+}
+
+fn prompt_template() -> Result<String> {
+    println!("Templates:");
+    for (i, (name, description)) in TEMPLATE_DESCRIPTIONS.iter().enumerate() {
+        println!("  {:>2}. {name:<18} {description}", i + 1);
+    }
+    loop {
+        let response = prompt("Template (number or name)", DEFAULT_TEMPLATE)?;
+        let template = match response.parse::<usize>() {
+            Ok(i) => TEMPLATE_DESCRIPTIONS.get(i.wrapping_sub(1)).map(|t| t.0),
+            Err(_) => TEMPLATE_DESCRIPTIONS
+                .iter()
+                .find(|t| t.0 == response)
+                .map(|t| t.0),
+        };
+        match template {
+            Some(template) => return Ok(template.to_string()),
+            None => println!("No template {response:?}: pick one of the above."),
+        }
+    }
+}
+
+fn prompt_package_name(default: &str) -> Result<String> {
+    loop {
+        let package_name = prompt("Package name", default)?;
+        if DISALLOWED_PACKAGE_NAMES.contains(&package_name.as_str()) {
+            println!("Package name {package_name} not allowed; cannot be in {DISALLOWED_PACKAGE_NAMES:?}.");
+        } else if !is_kimap_safe(&package_name, false) {
+            println!("Package name must be Kimap safe (a-z, A-Z, 0-9, - allowed).");
+        } else {
+            return Ok(package_name);
+        }
+    }
+}
+
+fn prompt_publisher(default: &str) -> Result<String> {
+    loop {
+        let publisher = prompt("Publisher node name", default)?;
+        if is_kimap_safe(&publisher, true) {
+            return Ok(publisher);
+        }
+        println!("Publisher must be Kimap safe (a-z, A-Z, 0-9, -, . allowed).");
+    }
+}
+
+fn prompt_ui(default: bool) -> Result<bool> {
+    loop {
+        let response = prompt("Include a UI? (y/n)", if default { "y" } else { "n" })?;
+        match response.to_lowercase().as_str() {
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("Answer y or n."),
+        }
+    }
+}
+
+/// Prompt for the template, package name, publisher & UI of `kit new`, defaulting
+/// to the given values, & print the equivalent non-interactive command.
+/// The package name is `None` if it is that derived from `new_dir`, as without `--package`
+pub fn prompt_new_params(
+    new_dir: &Path,
+    package_name: Option<String>,
+    publisher: &str,
+    ui: bool,
+    other_args: &[String],
+) -> Result<(Option<String>, String, Template, bool)> {
+    let dir_name = new_dir
+        .file_name()
+        .and_then(|f| f.to_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let template = prompt_template()?;
+    let package_name = prompt_package_name(package_name.as_deref().unwrap_or(&dir_name))?;
+    let publisher = prompt_publisher(publisher)?;
+    let ui = if UI_TEMPLATES.contains(&template.as_str()) {
+        prompt_ui(ui)?
+    } else {
+        false
+    };
+
+    let package_name = Some(package_name).filter(|p| *p != dir_name);
+    let mut command = vec![
+        "kit".to_string(),
+        "new".to_string(),
+        new_dir.display().to_string(),
+        format!("--template {template}"),
+    ];
+    if let Some(ref package_name) = package_name {
+        command.push(format!("--package {package_name}"));
+    }
+    command.push(format!("--publisher {publisher}"));
+    if ui {
+        command.push("--ui".to_string());
+    }
+    command.extend_from_slice(other_args);
+    println!(
+        "To skip these prompts next time, run:\n  {}",
+        command.join(" ")
+    );
+
+    Ok((package_name, publisher, (&template).into(), ui))
+}
@@ -3,12 +3,31 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use color_eyre::{eyre::eyre, Result};
+use color_eyre::{eyre::eyre, Result, Section};
 use fs_err as fs;
 use tracing::instrument;
 
+use crate::build::KIT_TOML_NAME;
+
 include!("../../target/new_includes.rs");
 
+mod capabilities;
+use capabilities::add_manifest_capabilities;
+mod channel_type;
+pub use channel_type::ChannelType;
+use channel_type::{has_channel_type_blocks, select_channel_type};
+mod interactive;
+pub use interactive::prompt_new_params;
+mod remote;
+use remote::make_remote_template_files;
+mod workspace;
+use workspace::add_to_workspace;
+
+/// Minimum Kinode version supporting the `process-v1` world the templates target
+const README_MIN_KINODE_VERSION: &str = "0.10.0";
+
+const DISALLOWED_PACKAGE_NAMES: &[&str] = &["api", "test"];
+
 #[derive(Clone)]
 pub enum Language {
     Rust,
@@ -23,6 +42,18 @@ pub enum Template {
     Echo,
     Fibonacci,
     FileTransfer,
+    StreamPipeline,
+    LamportClock,
+    PriorityQueue,
+    Saga,
+    ConsistentHash,
+    GracefulShutdown,
+    Bridge,
+    KvStore,
+    OnchainVote,
+    HttpApi,
+    RestGateway,
+    Pubsub,
 }
 
 impl Language {
@@ -44,6 +75,18 @@ impl Template {
             Template::Echo => "echo",
             Template::Fibonacci => "fibonacci",
             Template::FileTransfer => "file-transfer",
+            Template::StreamPipeline => "stream-pipeline",
+            Template::LamportClock => "lamport-clock",
+            Template::PriorityQueue => "priority-queue",
+            Template::Saga => "saga",
+            Template::ConsistentHash => "consistent-hash",
+            Template::GracefulShutdown => "graceful-shutdown",
+            Template::Bridge => "bridge",
+            Template::KvStore => "kv-store",
+            Template::OnchainVote => "onchain-vote",
+            Template::HttpApi => "http-api",
+            Template::RestGateway => "rest-gateway",
+            Template::Pubsub => "pubsub",
         }
         .to_string()
     }
@@ -68,7 +111,19 @@ impl From<&String> for Template {
             "echo" => Template::Echo,
             "fibonacci" => Template::Fibonacci,
             "file-transfer" => Template::FileTransfer,
-            _ => panic!("kit: template must be 'blank', 'chat', 'echo', or 'fibonacci'; not '{s}'"),
+            "stream-pipeline" => Template::StreamPipeline,
+            "lamport-clock" => Template::LamportClock,
+            "priority-queue" => Template::PriorityQueue,
+            "saga" => Template::Saga,
+            "consistent-hash" => Template::ConsistentHash,
+            "graceful-shutdown" => Template::GracefulShutdown,
+            "bridge" => Template::Bridge,
+            "kv-store" => Template::KvStore,
+            "onchain-vote" => Template::OnchainVote,
+            "http-api" => Template::HttpApi,
+            "rest-gateway" => Template::RestGateway,
+            "pubsub" => Template::Pubsub,
+            _ => panic!("kit: template must be 'blank', 'chat', 'echo', 'fibonacci', 'file-transfer', 'stream-pipeline', 'lamport-clock', 'priority-queue', 'saga', 'consistent-hash', 'graceful-shutdown', 'bridge', 'kv-store', 'onchain-vote', 'http-api', 'rest-gateway', or 'pubsub'; not '{s}'"),
         }
     }
 }
@@ -86,7 +141,7 @@ pub fn snake_to_upper_camel_case(input: &str) -> String {
     camel_case
 }
 
-fn replace_dots(input: &str) -> (String, String) {
+pub(crate) fn replace_dots(input: &str) -> (String, String) {
     let dotted = input.split('.');
     if dotted.clone().count() == 1 {
         (input.to_string(), input.to_string())
@@ -235,78 +290,206 @@ fn replace_vars(
         .to_string()
 }
 
-pub fn is_kimap_safe(input: &str, is_publisher: bool) -> bool {
-    let expression = if is_publisher {
-        r"^[a-zA-Z0-9\-.]+$"
+fn make_readme(
+    package_name: &str,
+    publisher: &str,
+    path_to_content: &HashMap<String, String>,
+) -> String {
+    let mut processes: Vec<&str> = path_to_content
+        .keys()
+        .filter(|p| !p.starts_with("test/"))
+        .filter_map(|p| {
+            ["/src/lib.rs", "/src/lib.py", "/src/lib.js"]
+                .iter()
+                .find_map(|suffix| p.strip_suffix(suffix))
+        })
+        .collect();
+    processes.sort();
+    let mut apis: Vec<&str> = path_to_content
+        .keys()
+        .filter(|p| p.starts_with("api/") && p.ends_with(".wit"))
+        .map(|p| p.as_str())
+        .collect();
+    apis.sort();
+    let has_tests = path_to_content.keys().any(|p| p.starts_with("test/"));
+
+    let diagram = processes
+        .iter()
+        .map(|p| format!("[{p}:{package_name}:{publisher}]"))
+        .collect::<Vec<_>>()
+        .join(" <--> ");
+    let api_reference = if apis.is_empty() {
+        "This package does not yet expose a WIT API.\nAdd `.wit` files to `api/` to publish one."
+            .to_string()
     } else {
-        r"^[a-zA-Z0-9\-]+$"
+        apis.iter()
+            .map(|a| format!("- [`{a}`]({a})"))
+            .collect::<Vec<_>>()
+            .join("\n")
     };
-    let re = regex::Regex::new(expression).unwrap();
-    re.is_match(input)
+    let test_instructions = if has_tests {
+        "\n# Run the tests in `test/`\nkit run-tests"
+    } else {
+        ""
+    };
+
+    format!(
+        r#"# {package_name}
+
+![Kinode](https://img.shields.io/badge/Kinode-%3E%3D{README_MIN_KINODE_VERSION}-blue)
+
+Package ID: `{package_name}:{publisher}`
+
+## Overview
+
+TODO: describe what `{package_name}` does and who it is for.
+
+## Architecture
+
+```
+{diagram}
+```
+
+TODO: describe the messages each process sends and receives.
+
+## Installation
+
+With a Kinode running (e.g., `kit boot-fake-node`):
+
+```bash
+kit build-start-package
+```
+
+## Configuration
+
+TODO: document any settings or capabilities users need to grant.
+
+## API Reference
+
+{api_reference}
+
+## Development Setup
+
+Requires [`kit`](https://github.com/kinode-dao/kit).
+
+```bash
+# Start a fake node on port 8080
+kit boot-fake-node
+
+# In another terminal: build and install the package
+kit build-start-package{test_instructions}
+```
+"#
+    )
 }
 
-#[instrument(level = "trace", skip_all)]
-pub fn execute(
-    new_dir: PathBuf,
-    package_name: Option<String>,
-    publisher: String,
-    language: Language,
-    template: Template,
-    ui: bool,
-) -> Result<()> {
-    // Check if the directory already exists
-    if new_dir.exists() {
-        let error = format!(
-            "Directory {:?} already exists. `kit new` creates a new directory to place the template in. Either remove given directory or provide a non-existing directory to create.",
-            new_dir,
+/// Get the `test/` dir of `test_template`, renamed to test `package_name`;
+/// falls back to the no-UI variant when the UI variant has no tests
+fn make_test_files(
+    package_name: &str,
+    publisher: &str,
+    language: &Language,
+    ui_infix: &str,
+    test_template: &Template,
+) -> Result<HashMap<String, String>> {
+    let test_template_name = test_template.to_string();
+    let mut test_path_to_content = HashMap::new();
+    for infix in [ui_infix, "no-ui"] {
+        let test_prefix = format!(
+            "{}/{infix}/{test_template_name}/test/",
+            language.to_string(),
         );
-        return Err(eyre!(error));
+        for (path, content) in PATH_TO_CONTENT.iter() {
+            let Some(stripped) = path.strip_prefix(&test_prefix) else {
+                continue;
+            };
+            let extension = PathBuf::from(path);
+            let extension = extension
+                .extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default();
+            test_path_to_content.insert(
+                replace_vars(
+                    &format!("test/{stripped}"),
+                    &test_template_name,
+                    package_name,
+                    publisher,
+                    extension,
+                ),
+                replace_vars(
+                    content,
+                    &test_template_name,
+                    package_name,
+                    publisher,
+                    extension,
+                ),
+            );
+        }
+        if !test_path_to_content.is_empty() {
+            break;
+        }
     }
 
-    let (package_name, is_from_dir) = match package_name {
-        Some(pn) => (pn, false),
-        None => (
-            new_dir.file_name().unwrap().to_str().unwrap().to_string(),
-            true,
-        ),
-    };
-
-    let disallowed_package_names = HashSet::from(["api", "test"]);
-    if disallowed_package_names.contains(package_name.as_str()) {
+    if test_path_to_content.is_empty() {
         return Err(eyre!(
-            "Package name {} not allowed; cannot be in {:?}.",
-            package_name,
-            disallowed_package_names,
+            "No {} test template found for template {test_template_name}.",
+            language.to_string(),
         ));
     }
 
-    if !is_kimap_safe(&package_name, false) {
-        let error = if !is_from_dir {
-            eyre!(
-                "`package_name` '{}' must be Kimap safe (a-z, A-Z, 0-9, - allowed).",
-                package_name
-            )
-        } else {
-            eyre!(
-                "`package_name` (derived from given directory {:?}) '{}' must be Kimap safe (a-z, A-Z, 0-9, - allowed).",
-                new_dir,
-                package_name,
-            )
-        };
-        return Err(error);
-    }
-    if !is_kimap_safe(&publisher, true) {
-        return Err(eyre!(
-            "`publisher` '{}' must be Kimap safe (a-z, A-Z, 0-9, -, . allowed).",
-            publisher
-        ));
-    }
+    test_path_to_content.insert(
+        "test/README.md".to_string(),
+        make_test_readme(package_name, &test_template_name),
+    );
+    Ok(test_path_to_content)
+}
 
-    let ui_infix = if ui {
-        "ui".to_string()
-    } else {
-        "no-ui".to_string()
-    };
+fn make_test_readme(package_name: &str, test_template_name: &str) -> String {
+    format!(
+        r#"# {package_name} tests
+
+Tests generated from the `{test_template_name}` test template.
+
+## Layout
+
+- `tests.toml`: configures the test run: which Kinode runtime to use, which packages to build and install (`setup_packages`), which test packages to run (`test_package_paths`), and the fake nodes to boot (`[[tests.nodes]]`).
+- `{package_name}-test/`: a test package.
+  Its process receives a `Run` request from the tester, exercises `{package_name}`, and responds with `Pass` or `Fail`.
+
+## Running
+
+From the package root:
+
+```bash
+kit run-tests test/tests.toml
+```
+
+or, from this directory:
+
+```bash
+kit run-tests
+```
+
+`kit run-tests` builds `{package_name}` and the test package, boots the fake nodes and a fakechain, installs the packages, and reports `PASS` or the failing test.
+
+## Adding tests
+
+Add assertions to `{package_name}-test/{package_name}-test/src/lib.rs`.
+Each test that fails should return an error so that `fail!` reports its location.
+"#
+    )
+}
+
+/// Get the files of built-in `template`, renamed to `package_name` and `publisher`
+fn make_template_files(
+    package_name: &str,
+    publisher: &str,
+    language: &Language,
+    template: &Template,
+    ui: bool,
+    ui_infix: &str,
+    drop_tests: bool,
+) -> Result<HashMap<String, String>> {
     let template_prefix = format!(
         "{}/{}/{}/",
         language.to_string(),
@@ -328,6 +511,8 @@ pub fn execute(
                         None
                     }
                 })
+                // `--test-template` replaces the template's own tests
+                .filter(|stripped| !drop_tests || !stripped.starts_with("test/"))
                 .and_then(|stripped| {
                     let extension = PathBuf::from(path);
                     let extension = extension
@@ -337,15 +522,15 @@ pub fn execute(
                     let modified_path = replace_vars(
                         &stripped,
                         &template.to_string(),
-                        &package_name,
-                        &publisher,
+                        package_name,
+                        publisher,
                         extension,
                     );
                     let modified_content = replace_vars(
                         content,
                         &template.to_string(),
-                        &package_name,
-                        &publisher,
+                        package_name,
+                        publisher,
                         extension,
                     );
                     Some((modified_path, modified_content))
@@ -384,8 +569,8 @@ pub fn execute(
                 replace_vars(
                     PATH_TO_CONTENT[0].1,
                     &template.to_string(),
-                    &package_name,
-                    &publisher,
+                    package_name,
+                    publisher,
                     "js",
                 ),
             );
@@ -393,6 +578,169 @@ pub fn execute(
         _ => {}
     }
 
+    Ok(path_to_content)
+}
+
+pub fn is_kimap_safe(input: &str, is_publisher: bool) -> bool {
+    let expression = if is_publisher {
+        r"^[a-zA-Z0-9\-.]+$"
+    } else {
+        r"^[a-zA-Z0-9\-]+$"
+    };
+    let re = regex::Regex::new(expression).unwrap();
+    re.is_match(input)
+}
+
+/// What package to create: one field per `kit new` flag
+#[derive(Clone)]
+pub struct NewOptions {
+    /// Defaults to the name of the new directory
+    pub package_name: Option<String>,
+    pub publisher: String,
+    pub language: Language,
+    pub template: Template,
+    pub ui: bool,
+    pub with_readme: bool,
+    pub test_template: Option<Template>,
+    pub template_url: Option<String>,
+    pub capabilities: Vec<String>,
+    pub workspace_root: Option<PathBuf>,
+    pub channel_type: Option<ChannelType>,
+}
+
+#[instrument(level = "trace", skip_all)]
+pub fn execute(new_dir: PathBuf, options: NewOptions) -> Result<()> {
+    let NewOptions {
+        package_name,
+        publisher,
+        language,
+        template,
+        ui,
+        with_readme,
+        test_template,
+        template_url,
+        capabilities,
+        workspace_root,
+        channel_type,
+    } = options;
+
+    // Check if the directory already exists
+    if new_dir.exists() {
+        let error = format!(
+            "Directory {:?} already exists. `kit new` creates a new directory to place the template in. Either remove given directory or provide a non-existing directory to create.",
+            new_dir,
+        );
+        return Err(eyre!(error));
+    }
+
+    let (package_name, is_from_dir) = match package_name {
+        Some(pn) => (pn, false),
+        None => (
+            new_dir.file_name().unwrap().to_str().unwrap().to_string(),
+            true,
+        ),
+    };
+
+    if DISALLOWED_PACKAGE_NAMES.contains(&package_name.as_str()) {
+        return Err(eyre!(
+            "Package name {} not allowed; cannot be in {:?}.",
+            package_name,
+            DISALLOWED_PACKAGE_NAMES,
+        ));
+    }
+
+    if !is_kimap_safe(&package_name, false) {
+        let error = if !is_from_dir {
+            eyre!(
+                "`package_name` '{}' must be Kimap safe (a-z, A-Z, 0-9, - allowed).",
+                package_name
+            )
+        } else {
+            eyre!(
+                "`package_name` (derived from given directory {:?}) '{}' must be Kimap safe (a-z, A-Z, 0-9, - allowed).",
+                new_dir,
+                package_name,
+            )
+        };
+        return Err(error);
+    }
+    if !is_kimap_safe(&publisher, true) {
+        return Err(eyre!(
+            "`publisher` '{}' must be Kimap safe (a-z, A-Z, 0-9, -, . allowed).",
+            publisher
+        ));
+    }
+
+    let ui_infix = if ui {
+        "ui".to_string()
+    } else {
+        "no-ui".to_string()
+    };
+    let mut path_to_content = match template_url {
+        Some(ref template_url) => make_remote_template_files(
+            template_url,
+            &package_name,
+            &publisher,
+            test_template.is_some(),
+        )?,
+        None => make_template_files(
+            &package_name,
+            &publisher,
+            &language,
+            &template,
+            ui,
+            &ui_infix,
+            test_template.is_some(),
+        )?,
+    };
+
+    if !capabilities.is_empty() {
+        let Some(manifest) = path_to_content.get_mut("pkg/manifest.json") else {
+            return Err(eyre!(
+                "Template has no pkg/manifest.json to add capabilities to."
+            ));
+        };
+        *manifest = add_manifest_capabilities(manifest, &capabilities)?;
+    }
+
+    if let Some(ref test_template) = test_template {
+        let test_path_to_content = make_test_files(
+            &package_name,
+            &publisher,
+            &language,
+            &ui_infix,
+            test_template,
+        )?;
+        path_to_content.extend(test_path_to_content);
+    }
+
+    // templates offer their Request patterns in `// kit-channel-type:` blocks;
+    //  without `--channel-type`, the sync one is kept
+    if channel_type.is_some() && !path_to_content.values().any(|c| has_channel_type_blocks(c)) {
+        return Err(eyre!(
+            "Template {} does not support --channel-type.",
+            template_url.unwrap_or_else(|| template.to_string()),
+        )
+        .with_suggestion(|| "Use --channel-type with --template chat."));
+    }
+    let channel_type = channel_type.unwrap_or(ChannelType::Sync);
+    for content in path_to_content.values_mut() {
+        if has_channel_type_blocks(content) {
+            *content = select_channel_type(content, &channel_type);
+        }
+    }
+
+    if !path_to_content.contains_key(KIT_TOML_NAME) {
+        if let Some((_, kit_toml)) = PATH_TO_CONTENT.iter().find(|(p, _)| *p == KIT_TOML_NAME) {
+            path_to_content.insert(KIT_TOML_NAME.to_string(), kit_toml.to_string());
+        }
+    }
+
+    if with_readme {
+        let readme = make_readme(&package_name, &publisher, &path_to_content);
+        path_to_content.insert("README.md".to_string(), readme);
+    }
+
     // Create the template directory and subdirectories
     path_to_content
         .keys()
@@ -405,5 +753,9 @@ pub fn execute(
     }
 
     tracing::info!("Template directory created successfully at {:?}.", new_dir);
+
+    if let Some(workspace_root) = workspace_root {
+        add_to_workspace(&workspace_root, &new_dir)?;
+    }
     Ok(())
 }
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tracing::{info, instrument, warn};
+use walkdir::WalkDir;
+
+use crate::build::run_command;
+use crate::KIT_CACHE;
+
+use super::replace_vars;
+
+const TEMPLATES_CACHE_DIR: &str = "templates";
+
+/// Split `<git-url>#<branch>` into the URL and optional branch
+fn parse_template_url(template_url: &str) -> (&str, Option<&str>) {
+    match template_url.rsplit_once('#') {
+        Some((url, branch)) if !branch.is_empty() => (url, Some(branch)),
+        Some((url, _)) => (url, None),
+        None => (template_url, None),
+    }
+}
+
+/// Clone `template_url` into `KIT_CACHE/templates/`, or update the cached
+/// clone; if that fails (e.g. offline), fall back to the cached clone
+#[instrument(level = "trace", skip_all)]
+fn fetch_remote_template(template_url: &str) -> Result<PathBuf> {
+    let (url, branch) = parse_template_url(template_url);
+    let cache_name: String = template_url
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let template_dir = PathBuf::from(KIT_CACHE)
+        .join(TEMPLATES_CACHE_DIR)
+        .join(cache_name);
+
+    if template_dir.join(".git").exists() {
+        let dir = template_dir.to_str().unwrap();
+        let branch = branch.unwrap_or("HEAD");
+        let updated = run_command(
+            Command::new("git").args(["-C", dir, "fetch", "--depth", "1", "origin", branch]),
+            false,
+        )
+        .and_then(|_| {
+            run_command(
+                Command::new("git").args(["-C", dir, "reset", "--hard", "FETCH_HEAD"]),
+                false,
+            )
+        });
+        match updated {
+            Ok(_) => info!("Updated cached template {template_url}."),
+            Err(e) => {
+                warn!("Failed to update cached template {template_url}; using cached copy: {e}")
+            }
+        }
+        return Ok(template_dir);
+    }
+
+    fs::create_dir_all(template_dir.parent().unwrap())?;
+    let mut args = vec!["clone", "--depth", "1"];
+    if let Some(branch) = branch {
+        args.extend(["--branch", branch]);
+    }
+    args.extend([url, template_dir.to_str().unwrap()]);
+    info!("Cloning template {template_url}...");
+    run_command(Command::new("git").args(&args), false).map_err(|e| {
+        eyre!("Failed to clone template {template_url}: {e}")
+            .with_suggestion(|| "Is the URL a git repository reachable from here?")
+    })?;
+    Ok(template_dir)
+}
+
+/// The name of the template package, to be replaced by the new package's
+/// name: `metadata.json`'s `package_name`, else the repository name
+fn get_template_package_name(template_dir: &Path, template_url: &str) -> String {
+    let metadata_name = fs::read_to_string(template_dir.join("metadata.json"))
+        .ok()
+        .and_then(|m| serde_json::from_str::<serde_json::Value>(&m).ok())
+        .and_then(|m| {
+            m["properties"]["package_name"]
+                .as_str()
+                .map(|s| s.to_string())
+        });
+    metadata_name.unwrap_or_else(|| {
+        let (url, _) = parse_template_url(template_url);
+        url.trim_end_matches('/')
+            .trim_end_matches(".git")
+            .rsplit(['/', ':'])
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    })
+}
+
+/// Get the files of the template at `template_url`, renamed to `package_name`
+/// and `publisher` in the same way as the built-in templates
+pub fn make_remote_template_files(
+    template_url: &str,
+    package_name: &str,
+    publisher: &str,
+    drop_tests: bool,
+) -> Result<HashMap<String, String>> {
+    let template_dir = fetch_remote_template(template_url)?;
+    let template_package_name = get_template_package_name(&template_dir, template_url);
+    if template_package_name.is_empty() {
+        return Err(eyre!(
+            "Could not determine template package name of {template_url}"
+        ));
+    }
+
+    let mut path_to_content = HashMap::new();
+    for entry in WalkDir::new(&template_dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != ".git" && e.file_name() != "target")
+    {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let path = entry.path().strip_prefix(&template_dir)?;
+        let Ok(content) = fs::read_to_string(entry.path()) else {
+            warn!("Skipping non-UTF-8 template file {path:?}");
+            continue;
+        };
+        let path = path.to_str().unwrap();
+        // `--test-template` replaces the template's own tests
+        if drop_tests && path.starts_with("test/") {
+            continue;
+        }
+        let extension = entry
+            .path()
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default();
+        path_to_content.insert(
+            replace_vars(
+                path,
+                &template_package_name,
+                package_name,
+                publisher,
+                extension,
+            ),
+            replace_vars(
+                &content,
+                &template_package_name,
+                package_name,
+                publisher,
+                extension,
+            ),
+        );
+    }
+    if path_to_content.is_empty() {
+        return Err(eyre!("Template {template_url} contains no files."));
+    }
+    Ok(path_to_content)
+}
@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::kinode::process::bridge::{Request as BridgeRequest, Response as BridgeResponse, Stats};
+use crate::kinode::process::protocol_a::{MessageA, ReplyA, Request as ARequest};
+use crate::kinode::process::protocol_b::{
+    Envelope, Receipt, Request as BRequest, Response as BResponse,
+};
+use kinode_process_lib::logging::{error, info, init_logging, warn, Level};
+use kinode_process_lib::{
+    await_message, call_init, Address, Message, ProcessId, Request, Response, SendError,
+};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "bridge-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// seconds to wait for the service before answering the sender with an error
+const SERVICE_TIMEOUT: u64 = 5;
+
+/// A forwarded message awaiting the service's Response:
+/// who sent it, and the id to reply with
+struct Pending {
+    sender: Address,
+    id: u64,
+}
+
+struct State {
+    service: Address,
+    /// attached to each forwarded Request as its `context`, which
+    /// the kernel hands back with the Response (or `SendError`)
+    next_correlation_id: u64,
+    pending: HashMap<u64, Pending>,
+    forwarded: u64,
+    replied: u64,
+    failed: u64,
+}
+
+impl State {
+    fn new(our: &Address) -> Self {
+        State {
+            service: Address::new(
+                our.node(),
+                ProcessId::new(Some("service"), our.package(), our.publisher()),
+            ),
+            next_correlation_id: 0,
+            pending: HashMap::new(),
+            forwarded: 0,
+            replied: 0,
+            failed: 0,
+        }
+    }
+
+    fn stats(&self) -> Stats {
+        Stats {
+            forwarded: self.forwarded,
+            replied: self.replied,
+            failed: self.failed,
+            pending: self.pending.len() as u64,
+        }
+    }
+
+    /// Take the pending message a service Response (or `SendError`) is for
+    fn take_pending(&mut self, context: Option<&[u8]>) -> anyhow::Result<Pending> {
+        let Some(context) = context else {
+            return Err(anyhow::anyhow!("service Response has no context"));
+        };
+        let correlation_id: u64 = serde_json::from_slice(context)?;
+        self.pending
+            .remove(&correlation_id)
+            .ok_or_else(|| anyhow::anyhow!("no pending message {correlation_id}"))
+    }
+}
+
+/// `protocol-a` -> `protocol-b`
+fn to_envelope(sender: &Address, message: MessageA) -> Envelope {
+    Envelope {
+        origin: sender.to_string(),
+        headers: vec![
+            ("content-type".to_string(), "text/plain".to_string()),
+            ("sender".to_string(), message.sender),
+        ],
+        body: message.text.into_bytes(),
+    }
+}
+
+/// `protocol-b` -> `protocol-a`
+fn to_reply(id: u64, response: BResponse) -> ReplyA {
+    let outcome = match response {
+        BResponse::Delivered(Receipt { body }) => {
+            String::from_utf8(body).map_err(|e| format!("reply is not UTF-8: {e}"))
+        }
+        BResponse::Rejected(reason) => Err(reason),
+    };
+    ReplyA { id, outcome }
+}
+
+fn send_reply(sender: &Address, reply: ReplyA) -> anyhow::Result<()> {
+    Request::to(sender).body(ARequest::Reply(reply)).send()?;
+    Ok(())
+}
+
+/// Forward a `protocol-a` message to the service as `protocol-b`,
+/// remembering who to send the translated reply to
+fn handle_a_request(source: &Address, request: ARequest, state: &mut State) -> anyhow::Result<()> {
+    let ARequest::Send(message) = request else {
+        return Err(anyhow::anyhow!("only expects `send`, got {request:?}"));
+    };
+    let correlation_id = state.next_correlation_id;
+    state.next_correlation_id += 1;
+    let id = message.id;
+    Request::to(&state.service)
+        .body(BRequest::Deliver(to_envelope(source, message)))
+        .context(serde_json::to_vec(&correlation_id)?)
+        .expects_response(SERVICE_TIMEOUT)
+        .send()?;
+    state.pending.insert(
+        correlation_id,
+        Pending {
+            sender: source.clone(),
+            id,
+        },
+    );
+    state.forwarded += 1;
+    Ok(())
+}
+
+/// Translate a service Response back to `protocol-a` & send it
+/// to the sender of the original message
+fn handle_b_response(message: &Message, state: &mut State) -> anyhow::Result<()> {
+    let pending = state.take_pending(message.context())?;
+    let reply = to_reply(pending.id, message.body().try_into()?);
+    if reply.outcome.is_ok() {
+        state.replied += 1;
+    } else {
+        state.failed += 1;
+    }
+    send_reply(&pending.sender, reply)
+}
+
+/// The service is unreachable or timed out: tell the sender
+fn handle_send_error(send_error: &SendError, state: &mut State) -> anyhow::Result<()> {
+    let pending = state.take_pending(send_error.context())?;
+    state.failed += 1;
+    send_reply(
+        &pending.sender,
+        ReplyA {
+            id: pending.id,
+            outcome: Err(format!("service unreachable: {send_error}")),
+        },
+    )
+}
+
+fn handle_bridge_request(
+    our: &Address,
+    source: &Address,
+    request: BridgeRequest,
+    state: &mut State,
+) -> anyhow::Result<()> {
+    let response = match request {
+        BridgeRequest::SetService(service) => {
+            let result = if source.node != our.node {
+                Err("only local processes may set the service".to_string())
+            } else {
+                service
+                    .parse::<Address>()
+                    .map(|service| {
+                        info!("forwarding to {service}");
+                        state.service = service;
+                    })
+                    .map_err(|e| format!("{e:?}"))
+            };
+            BridgeResponse::SetService(result)
+        }
+        BridgeRequest::GetStats => BridgeResponse::GetStats(state.stats()),
+    };
+    Response::new().body(response).send()?;
+    Ok(())
+}
+
+fn handle_message(our: &Address, message: &Message, state: &mut State) -> anyhow::Result<()> {
+    if !message.is_request() {
+        return handle_b_response(message, state);
+    }
+    if let Ok(request) = message.body().try_into() {
+        return handle_bridge_request(our, message.source(), request, state);
+    }
+    handle_a_request(message.source(), message.body().try_into()?, state)
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let mut state = State::new(&our);
+
+    loop {
+        match await_message() {
+            Err(send_error) => {
+                if let Err(e) = handle_send_error(&send_error, &mut state) {
+                    warn!("got SendError {send_error} but failed to handle it: {e:?}");
+                }
+            }
+            Ok(ref message) => match handle_message(&our, message, &mut state) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::kinode::process::protocol_b::{
+    Envelope, Receipt, Request as BRequest, Response as BResponse,
+};
+use kinode_process_lib::logging::{error, info, init_logging, Level};
+use kinode_process_lib::{await_message, call_init, Address, Message, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "service-bridge-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// An example `protocol-b` service: replies with the body uppercased;
+/// rejects empty bodies
+fn deliver(envelope: Envelope) -> BResponse {
+    if envelope.body.is_empty() {
+        return BResponse::Rejected("empty body".to_string());
+    }
+    let sender = envelope
+        .headers
+        .iter()
+        .find(|(key, _)| key == "sender")
+        .map(|(_, value)| value.as_str())
+        .unwrap_or("unknown");
+    info!("delivering from {sender} via {}", envelope.origin);
+    BResponse::Delivered(Receipt {
+        body: envelope.body.to_ascii_uppercase(),
+    })
+}
+
+fn handle_message(message: &Message) -> anyhow::Result<()> {
+    if !message.is_request() {
+        return Err(anyhow::anyhow!("unexpected Response"));
+    }
+    let BRequest::Deliver(envelope) = message.body().try_into()?;
+    Response::new().body(deliver(envelope)).send()?;
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => {
+                if let Err(e) = handle_message(message) {
+                    error!("got error while handling message: {e:?}");
+                }
+            }
+        }
+    }
+}
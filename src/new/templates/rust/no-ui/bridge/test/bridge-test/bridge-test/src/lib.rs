@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use crate::kinode::process::bridge::{Request as BridgeRequest, Response as BridgeResponse, Stats};
+use crate::kinode::process::protocol_a::{MessageA, ReplyA, Request as ARequest};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, println, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "bridge-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+fn call(address: &Address, request: BridgeRequest) -> anyhow::Result<BridgeResponse> {
+    let response = Request::new()
+        .target(address)
+        .body(request)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("bridge_test");
+    };
+    Ok(response.body().try_into()?)
+}
+
+fn expect(
+    address: &Address,
+    request: BridgeRequest,
+    expected: BridgeResponse,
+) -> anyhow::Result<()> {
+    let response = call(address, request)?;
+    if response != expected {
+        println!("{response:?} != {expected:?}");
+        fail!("bridge_test");
+    }
+    Ok(())
+}
+
+fn send(address: &Address, id: u64, text: &str) -> anyhow::Result<()> {
+    Request::new()
+        .target(address)
+        .body(ARequest::Send(MessageA {
+            id,
+            sender: "alice".to_string(),
+            text: text.to_string(),
+        }))
+        .send()?;
+    Ok(())
+}
+
+/// Await `count` replies, in whatever order they arrive, by id
+fn await_replies(
+    address: &Address,
+    count: usize,
+) -> anyhow::Result<BTreeMap<u64, Result<String, String>>> {
+    let mut replies = BTreeMap::new();
+    while replies.len() < count {
+        let message = await_message()?;
+        if message.source() != address {
+            println!("unexpected message from {}", message.source());
+            fail!("bridge_test");
+        }
+        let ARequest::Reply(ReplyA { id, outcome }) = message.body().try_into()? else {
+            fail!("bridge_test");
+        };
+        replies.insert(id, outcome);
+    }
+    Ok(replies)
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "bridge_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_bridge_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("bridge"), "bridge", "template.os"),
+    };
+
+    // several messages in flight at once: each reply is correlated back
+    //  to its message; `reply`s do not expect a Response, so the Run
+    //  Request can still be responded to below
+    send(&our_bridge_address, 1, "hello")?;
+    send(&our_bridge_address, 2, "")?;
+    send(&our_bridge_address, 3, "world")?;
+    let replies = await_replies(&our_bridge_address, 3)?;
+    let expected = BTreeMap::from([
+        (1, Ok("HELLO".to_string())),
+        (2, Err("empty body".to_string())),
+        (3, Ok("WORLD".to_string())),
+    ]);
+    if replies != expected {
+        println!("{replies:?} != {expected:?}");
+        fail!("bridge_test");
+    }
+
+    print_to_terminal(0, "bridge_test: b");
+    expect(
+        &our_bridge_address,
+        BridgeRequest::GetStats,
+        BridgeResponse::GetStats(Stats {
+            forwarded: 3,
+            replied: 2,
+            failed: 1,
+            pending: 0,
+        }),
+    )?;
+
+    // the service must be a valid address
+    print_to_terminal(0, "bridge_test: c");
+    let BridgeResponse::SetService(Err(_)) = call(
+        &our_bridge_address,
+        BridgeRequest::SetService("not an address".to_string()),
+    )?
+    else {
+        fail!("bridge_test");
+    };
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("bridge_test: error: {e:?}").as_str());
+
+                fail!("bridge_test");
+            }
+        };
+    }
+}
@@ -14,15 +14,49 @@ wit_bindgen::generate!({
 });
 
 type MessageArchive = HashMap<String, Vec<ChatMessage>>;
+// kit-channel-type: mixed
+
+/// Await the counterparty's acknowledgement of each message (sync) when the
+/// sender must know it was delivered; send without waiting (async) when not
+/// blocking other Requests matters more than confirmation
+const AWAIT_DELIVERY: bool = true;
+// kit-channel-type: end
+
+/// Forward a chat message to the counterparty's chat process: await its
+/// acknowledgement when the sender must know the message was delivered;
+/// otherwise return at once so other Requests are not held up, the
+/// acknowledgement arriving later as a Response, a failure to deliver as a
+/// SendError
+fn send_to_counterparty(target: &str, body: &[u8], await_delivery: bool) -> anyhow::Result<()> {
+    let request = Request::new()
+        .target((target, "chat", "chat", "template.os"))
+        .body(body);
+    if await_delivery {
+        request.send_and_await_response(5)??;
+    } else {
+        request.expects_response(5).send()?;
+    }
+    Ok(())
+}
 
 fn handle_message(
     our: &Address,
     message: &Message,
     message_archive: &mut MessageArchive,
 ) -> anyhow::Result<()> {
+    // kit-channel-type: sync
     if !message.is_request() {
         return Err(anyhow::anyhow!("unexpected Response: {:?}", message));
     }
+    // kit-channel-type: async mixed
+    if !message.is_request() {
+        // the counterparty acknowledging a message we sent without awaiting it
+        let ChatResponse::Send = message.body().try_into()? else {
+            return Err(anyhow::anyhow!("unexpected Response: {:?}", message));
+        };
+        return Ok(());
+    }
+    // kit-channel-type: end
 
     let body = message.body();
     let source = message.source();
@@ -42,10 +76,13 @@ fn handle_message(
             if target == &our.node {
                 println!("{}: {}", source.node, message);
             } else {
-                Request::new()
-                    .target((target, "chat", "chat", "template.os"))
-                    .body(body)
-                    .send_and_await_response(5)??;
+                // kit-channel-type: sync
+                send_to_counterparty(target, body, true)?;
+                // kit-channel-type: async
+                send_to_counterparty(target, body, false)?;
+                // kit-channel-type: mixed
+                send_to_counterparty(target, body, AWAIT_DELIVERY)?;
+                // kit-channel-type: end
             }
 
             // Insert message into archive, creating one for counterparty if it DNE
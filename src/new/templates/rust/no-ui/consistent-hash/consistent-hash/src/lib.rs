@@ -0,0 +1,164 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::kinode::process::consistent_hash::{
+    MovedKey, Rebalance, Request as RingRequest, Response as RingResponse, Ring as WitRing,
+    VirtualNode,
+};
+use kinode_process_lib::logging::{error, info, init_logging, Level};
+use kinode_process_lib::{await_message, call_init, Address, Message, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "consistent-hash-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// Virtual nodes placed on the ring per node: more spread each node's
+/// share of the keys more evenly, at the cost of a larger ring
+const REPLICAS: u32 = 16;
+
+/// FNV-1a followed by a finalizing mix so that similar inputs (e.g.
+/// `node-a#0` and `node-a#1`) land far apart on the ring. A hash that is
+/// stable across builds & platforms is required, since every router
+/// must agree on where each key lands
+fn hash(input: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in input.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^ (hash >> 33)
+}
+
+#[derive(Default)]
+struct Ring {
+    /// virtual node hash -> (node, replica)
+    virtual_nodes: BTreeMap<u64, (String, u32)>,
+    nodes: BTreeSet<String>,
+    /// keys routed so far, to report which of them move on rebalance
+    routed: BTreeSet<String>,
+}
+
+impl Ring {
+    fn owner(&self, key: &str) -> Option<String> {
+        let key_hash = hash(key);
+        // first virtual node clockwise of the key, wrapping around
+        self.virtual_nodes
+            .range(key_hash..)
+            .next()
+            .or_else(|| self.virtual_nodes.iter().next())
+            .map(|(_, (node, _))| node.clone())
+    }
+
+    /// Apply `change` to the ring, returning the routed keys whose owner changed
+    fn rebalance<F: FnOnce(&mut Self)>(&mut self, change: F) -> Rebalance {
+        let before: Vec<(String, Option<String>)> = self
+            .routed
+            .iter()
+            .map(|key| (key.clone(), self.owner(key)))
+            .collect();
+        change(self);
+        let moved = before
+            .into_iter()
+            .filter_map(|(key, old_owner)| {
+                let new_owner = self.owner(&key);
+                (old_owner != new_owner).then(|| MovedKey {
+                    key,
+                    old_owner,
+                    new_owner,
+                })
+            })
+            .collect();
+        Rebalance { moved }
+    }
+
+    fn add_node(&mut self, node: String) -> anyhow::Result<Rebalance> {
+        if self.nodes.contains(&node) {
+            return Err(anyhow::anyhow!("{node} is already in the ring"));
+        }
+        let rebalance = self.rebalance(|ring| {
+            for replica in 0..REPLICAS {
+                ring.virtual_nodes
+                    .insert(hash(&format!("{node}#{replica}")), (node.clone(), replica));
+            }
+            ring.nodes.insert(node.clone());
+        });
+        info!("added {node}: {} keys moved", rebalance.moved.len());
+        Ok(rebalance)
+    }
+
+    fn remove_node(&mut self, node: String) -> anyhow::Result<Rebalance> {
+        if !self.nodes.contains(&node) {
+            return Err(anyhow::anyhow!("{node} is not in the ring"));
+        }
+        let rebalance = self.rebalance(|ring| {
+            ring.virtual_nodes.retain(|_, (owner, _)| *owner != node);
+            ring.nodes.remove(&node);
+        });
+        info!("removed {node}: {} keys moved", rebalance.moved.len());
+        Ok(rebalance)
+    }
+
+    fn route(&mut self, key: String) -> Option<String> {
+        let owner = self.owner(&key);
+        self.routed.insert(key);
+        owner
+    }
+
+    fn to_wit(&self) -> WitRing {
+        WitRing {
+            replicas: REPLICAS,
+            virtual_nodes: self
+                .virtual_nodes
+                .iter()
+                .map(|(hash, (node, replica))| VirtualNode {
+                    hash: *hash,
+                    node: node.clone(),
+                    replica: *replica,
+                })
+                .collect(),
+        }
+    }
+
+    fn handle_request(&mut self, request: RingRequest) -> RingResponse {
+        let result = match request {
+            RingRequest::AddNode(node) => self.add_node(node).map(RingResponse::AddNode),
+            RingRequest::RemoveNode(node) => self.remove_node(node).map(RingResponse::RemoveNode),
+            RingRequest::Route(key) => Ok(RingResponse::Route(self.route(key))),
+            RingRequest::GetRing => Ok(RingResponse::GetRing(self.to_wit())),
+        };
+        result.unwrap_or_else(|e| RingResponse::Err(e.to_string()))
+    }
+}
+
+fn handle_message(message: &Message, ring: &mut Ring) -> anyhow::Result<()> {
+    if !message.is_request() {
+        return Ok(());
+    }
+    let response = ring.handle_request(message.body().try_into()?);
+    Response::new().body(response).send()?;
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let mut ring = Ring::default();
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(message, &mut ring) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
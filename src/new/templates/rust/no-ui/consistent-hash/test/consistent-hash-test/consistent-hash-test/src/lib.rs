@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+
+use crate::kinode::process::consistent_hash::{
+    Rebalance, Request as RingRequest, Response as RingResponse,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, println, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "consistent-hash-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+const NUM_KEYS: usize = 100;
+
+fn send(address: &Address, request: RingRequest) -> anyhow::Result<RingResponse> {
+    let response = Request::new()
+        .target(address)
+        .body(request)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("consistent_hash_test");
+    };
+    Ok(response.body().try_into()?)
+}
+
+fn add_node(address: &Address, node: &str) -> anyhow::Result<Rebalance> {
+    let RingResponse::AddNode(rebalance) = send(address, RingRequest::AddNode(node.to_string()))?
+    else {
+        fail!("consistent_hash_test");
+    };
+    Ok(rebalance)
+}
+
+fn remove_node(address: &Address, node: &str) -> anyhow::Result<Rebalance> {
+    let RingResponse::RemoveNode(rebalance) =
+        send(address, RingRequest::RemoveNode(node.to_string()))?
+    else {
+        fail!("consistent_hash_test");
+    };
+    Ok(rebalance)
+}
+
+/// key -> owner, for each of the keys
+fn route_all(address: &Address) -> anyhow::Result<HashMap<String, String>> {
+    let mut owners = HashMap::new();
+    for i in 0..NUM_KEYS {
+        let key = format!("key-{i}");
+        let RingResponse::Route(Some(owner)) = send(address, RingRequest::Route(key.clone()))?
+        else {
+            fail!("consistent_hash_test");
+        };
+        owners.insert(key, owner);
+    }
+    Ok(owners)
+}
+
+/// Only the keys reported as moved changed owner, and each moved as reported
+fn check_rebalance(
+    rebalance: &Rebalance,
+    before: &HashMap<String, String>,
+    after: &HashMap<String, String>,
+) -> anyhow::Result<()> {
+    for (key, owner) in after {
+        let moved = rebalance.moved.iter().find(|m| &m.key == key);
+        let ok = match moved {
+            None => before.get(key) == Some(owner),
+            Some(moved) => {
+                moved.old_owner.as_ref() == before.get(key)
+                    && moved.new_owner.as_ref() == Some(owner)
+            }
+        };
+        if !ok {
+            println!(
+                "{key}: {:?} -> {owner}; reported {moved:?}",
+                before.get(key)
+            );
+            fail!("consistent_hash_test");
+        }
+    }
+    Ok(())
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "consistent_hash_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_ring_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("consistent-hash"), "consistent-hash", "template.os"),
+    };
+
+    // an empty ring routes nowhere
+    if send(&our_ring_address, RingRequest::Route("key-0".to_string()))?
+        != RingResponse::Route(None)
+    {
+        fail!("consistent_hash_test");
+    }
+
+    // keys spread across the nodes
+    for node in ["node-a", "node-b", "node-c"] {
+        add_node(&our_ring_address, node)?;
+    }
+    let RingResponse::GetRing(ring) = send(&our_ring_address, RingRequest::GetRing)? else {
+        fail!("consistent_hash_test");
+    };
+    if ring.virtual_nodes.len() != 3 * ring.replicas as usize
+        || !ring.virtual_nodes.windows(2).all(|w| w[0].hash < w[1].hash)
+    {
+        println!("{ring:?}");
+        fail!("consistent_hash_test");
+    }
+    let owners = route_all(&our_ring_address)?;
+    for node in ["node-a", "node-b", "node-c"] {
+        if !owners.values().any(|owner| owner == node) {
+            println!("no keys routed to {node}: {owners:?}");
+            fail!("consistent_hash_test");
+        }
+    }
+
+    // adding a node moves some keys, all of them to the new node
+    print_to_terminal(0, "consistent_hash_test: b");
+    let rebalance = add_node(&our_ring_address, "node-d")?;
+    if rebalance.moved.is_empty()
+        || rebalance
+            .moved
+            .iter()
+            .any(|m| m.new_owner.as_deref() != Some("node-d"))
+    {
+        println!("{rebalance:?}");
+        fail!("consistent_hash_test");
+    }
+    let owners_after_add = route_all(&our_ring_address)?;
+    check_rebalance(&rebalance, &owners, &owners_after_add)?;
+
+    // removing a node moves only its keys
+    print_to_terminal(0, "consistent_hash_test: c");
+    let rebalance = remove_node(&our_ring_address, "node-b")?;
+    if rebalance.moved.is_empty()
+        || rebalance
+            .moved
+            .iter()
+            .any(|m| m.old_owner.as_deref() != Some("node-b"))
+    {
+        println!("{rebalance:?}");
+        fail!("consistent_hash_test");
+    }
+    let owners_after_remove = route_all(&our_ring_address)?;
+    check_rebalance(&rebalance, &owners_after_add, &owners_after_remove)?;
+    if owners_after_remove.values().any(|owner| owner == "node-b") {
+        fail!("consistent_hash_test");
+    }
+
+    // membership errors
+    print_to_terminal(0, "consistent_hash_test: d");
+    for request in [
+        RingRequest::AddNode("node-a".to_string()),
+        RingRequest::RemoveNode("node-b".to_string()),
+    ] {
+        let RingResponse::Err(_) = send(&our_ring_address, request)? else {
+            fail!("consistent_hash_test");
+        };
+    }
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("consistent_hash_test: error: {e:?}").as_str());
+
+                fail!("consistent_hash_test");
+            }
+        };
+    }
+}
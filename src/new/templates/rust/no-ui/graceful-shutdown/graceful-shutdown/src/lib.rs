@@ -0,0 +1,194 @@
+use crate::kinode::process::graceful_shutdown::{
+    Request as ShutdownRequest, Response as ShutdownResponse, StartKind, Status,
+};
+use kinode_process_lib::logging::{error, info, init_logging, warn, Level};
+use kinode_process_lib::vfs::{create_drive, open_file};
+use kinode_process_lib::{await_message, call_init, Address, Message, OnExit, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "graceful-shutdown-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+const STATE_FILE: &str = "state.json";
+/// saved state that fails validation is moved here rather than overwritten
+const CORRUPT_STATE_FILE: &str = "state.json.corrupt";
+/// bump when `State` changes incompatibly: older snapshots then fail
+/// validation rather than being misread
+const STATE_VERSION: u32 = 1;
+/// checkpoint after this many unsaved entries: a crash loses fewer than this
+const CHECKPOINT_INTERVAL: u32 = 3;
+
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+struct State {
+    entries: Vec<String>,
+    starts: u64,
+}
+
+/// What is written to VFS: the state along with what is needed to
+/// validate it on load; `S` is `&State` when saving, `State` when loading
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct Snapshot<S> {
+    version: u32,
+    /// `true` only if written by a clean shutdown; a running process
+    /// leaves `false` on disk, so finding `false` at startup means a crash
+    clean_shutdown: bool,
+    /// of `state`, to catch truncated or tampered-with files
+    checksum: u64,
+    state: S,
+}
+
+/// FNV-1a: stable across builds, unlike `std`'s `DefaultHasher`
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn validate(bytes: &[u8]) -> anyhow::Result<Snapshot<State>> {
+    let snapshot: Snapshot<State> = serde_json::from_slice(bytes)?;
+    if snapshot.version != STATE_VERSION {
+        return Err(anyhow::anyhow!(
+            "version {} != {STATE_VERSION}",
+            snapshot.version
+        ));
+    }
+    let expected = checksum(&serde_json::to_vec(&snapshot.state)?);
+    if snapshot.checksum != expected {
+        return Err(anyhow::anyhow!(
+            "checksum {} != {expected}",
+            snapshot.checksum
+        ));
+    }
+    Ok(snapshot)
+}
+
+struct Process {
+    drive: String,
+    state: State,
+    start_kind: StartKind,
+    unsaved: u32,
+}
+
+impl Process {
+    fn load(drive: String) -> anyhow::Result<Self> {
+        let state_file = open_file(&format!("{drive}/{STATE_FILE}"), true, None)?;
+        let bytes = state_file.read()?;
+        let (state, start_kind) = if bytes.is_empty() {
+            (State::default(), StartKind::Fresh)
+        } else {
+            match validate(&bytes) {
+                Ok(snapshot) if snapshot.clean_shutdown => (snapshot.state, StartKind::Clean),
+                Ok(snapshot) => {
+                    warn!("recovering from a crash: changes since the last checkpoint are lost");
+                    (snapshot.state, StartKind::Recovered)
+                }
+                Err(e) => {
+                    error!("saved state is corrupt ({e}); setting it aside and starting fresh");
+                    open_file(&format!("{drive}/{CORRUPT_STATE_FILE}"), true, None)?
+                        .write(&bytes)?;
+                    (State::default(), StartKind::Corrupt)
+                }
+            }
+        };
+
+        let mut process = Process {
+            drive,
+            state,
+            start_kind,
+            unsaved: 0,
+        };
+        process.state.starts += 1;
+        info!(
+            "start {}: {:?} with {} entries",
+            process.state.starts,
+            process.start_kind,
+            process.state.entries.len(),
+        );
+        // mark the saved state as that of a running process, so that if
+        //  it is next read after a crash, the crash is detected
+        process.save(false)?;
+        Ok(process)
+    }
+
+    fn save(&mut self, clean_shutdown: bool) -> anyhow::Result<()> {
+        let snapshot = Snapshot {
+            version: STATE_VERSION,
+            clean_shutdown,
+            checksum: checksum(&serde_json::to_vec(&self.state)?),
+            state: &self.state,
+        };
+        let state_file = open_file(&format!("{}/{STATE_FILE}", self.drive), true, None)?;
+        state_file.write(&serde_json::to_vec(&snapshot)?)?;
+        self.unsaved = 0;
+        Ok(())
+    }
+
+    fn add(&mut self, entry: String) -> anyhow::Result<u64> {
+        self.state.entries.push(entry);
+        self.unsaved += 1;
+        if self.unsaved >= CHECKPOINT_INTERVAL {
+            self.save(false)?;
+        }
+        Ok(self.state.entries.len() as u64)
+    }
+
+    fn status(&self) -> Status {
+        Status {
+            entries: self.state.entries.clone(),
+            start_kind: self.start_kind.clone(),
+            starts: self.state.starts,
+            unsaved: self.unsaved,
+        }
+    }
+}
+
+/// Returns `true` if the process should exit
+fn handle_message(message: &Message, process: &mut Process) -> anyhow::Result<bool> {
+    if !message.is_request() {
+        return Ok(false);
+    }
+    let (response, exit) = match message.body().try_into()? {
+        ShutdownRequest::Add(entry) => (ShutdownResponse::Add(process.add(entry)?), false),
+        ShutdownRequest::GetStatus => (ShutdownResponse::GetStatus(process.status()), false),
+        ShutdownRequest::Shutdown(restart) => {
+            process.save(true)?;
+            if !restart {
+                // the manifest's `"on_exit": "Restart"` would bring us back
+                OnExit::None.set()?;
+            }
+            info!("shut down cleanly (restart: {restart})");
+            (ShutdownResponse::Shutdown, true)
+        }
+        ShutdownRequest::Crash => panic!("crash requested"),
+    };
+    Response::new().body(response).send()?;
+    Ok(exit)
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    // `Restart` is set by the manifest; set here for clarity. Alternatively,
+    //  `OnExit::Requests` sends the given Requests when the process exits,
+    //  e.g. to tell a supervisor process
+    OnExit::Restart.set().unwrap();
+
+    let drive = create_drive(our.package_id(), "state", None).unwrap();
+    let mut process = Process::load(drive).unwrap();
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(message, &mut process) {
+                Ok(true) => return,
+                Ok(false) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::kinode::process::graceful_shutdown::{
+    Request as ShutdownRequest, Response as ShutdownResponse, StartKind, Status,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, println, timer, Address, ProcessId, Request,
+    Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "graceful-shutdown-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// attempts to reach the process while it restarts
+const MAX_POLLS: u32 = 20;
+const POLL_INTERVAL_MS: u64 = 500;
+
+fn send(address: &Address, request: ShutdownRequest) -> anyhow::Result<ShutdownResponse> {
+    let response = Request::new()
+        .target(address)
+        .body(request)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("graceful_shutdown_test");
+    };
+    Ok(response.body().try_into()?)
+}
+
+fn add(address: &Address, entry: &str) -> anyhow::Result<()> {
+    let ShutdownResponse::Add(_) = send(address, ShutdownRequest::Add(entry.to_string()))? else {
+        fail!("graceful_shutdown_test");
+    };
+    Ok(())
+}
+
+/// The status once the process has started `starts` times, polling
+/// while it restarts
+fn await_status(address: &Address, starts: u64) -> anyhow::Result<Status> {
+    for _ in 0..MAX_POLLS {
+        let response = Request::new()
+            .target(address)
+            .body(ShutdownRequest::GetStatus)
+            .send_and_await_response(1)?;
+        if let Ok(response) = response {
+            if let Ok(ShutdownResponse::GetStatus(status)) = response.body().try_into() {
+                if status.starts >= starts {
+                    return Ok(status);
+                }
+            }
+        }
+        let _ = timer::set_and_await_timer(POLL_INTERVAL_MS);
+    }
+    println!("process did not start {starts} times");
+    fail!("graceful_shutdown_test");
+}
+
+fn expect_status(
+    status: &Status,
+    start_kind: StartKind,
+    starts: u64,
+    entries: &[&str],
+) -> anyhow::Result<()> {
+    if status.start_kind != start_kind || status.starts != starts || status.entries != entries {
+        println!("{status:?}");
+        fail!("graceful_shutdown_test");
+    }
+    Ok(())
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "graceful_shutdown_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_process_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(
+            Some("graceful-shutdown"),
+            "graceful-shutdown",
+            "template.os",
+        ),
+    };
+
+    // first start: nothing saved
+    let status = await_status(&our_process_address, 1)?;
+    expect_status(&status, StartKind::Fresh, 1, &[])?;
+
+    // a crash loses the entries added since the last checkpoint:
+    //  here, one entry past the checkpoint after the third
+    print_to_terminal(0, "graceful_shutdown_test: b");
+    for entry in ["a", "b", "c", "d"] {
+        add(&our_process_address, entry)?;
+    }
+    Request::new()
+        .target(&our_process_address)
+        .body(ShutdownRequest::Crash)
+        .send()?;
+    let status = await_status(&our_process_address, 2)?;
+    expect_status(&status, StartKind::Recovered, 2, &["a", "b", "c"])?;
+
+    // a clean shutdown loses nothing
+    print_to_terminal(0, "graceful_shutdown_test: c");
+    add(&our_process_address, "e")?;
+    if send(&our_process_address, ShutdownRequest::Shutdown(true))? != ShutdownResponse::Shutdown {
+        fail!("graceful_shutdown_test");
+    }
+    let status = await_status(&our_process_address, 3)?;
+    expect_status(&status, StartKind::Clean, 3, &["a", "b", "c", "e"])?;
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("graceful_shutdown_test: error: {e:?}").as_str());
+
+                fail!("graceful_shutdown_test");
+            }
+        };
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use kinode_process_lib::logging::{error, info, init_logging, Level};
+use kinode_process_lib::{
+    await_message, call_init, get_blob,
+    http::server::{
+        send_response, HttpBindingConfig, HttpServer, HttpServerRequest, IncomingHttpRequest,
+        StatusCode,
+    },
+    Address, Message,
+};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "process-v1",
+});
+
+const HELLO_PATH: &str = "/api/hello";
+const ECHO_PATH: &str = "/api/echo";
+
+fn make_http_address(our: &Address) -> Address {
+    Address::from((our.node(), "http-server", "distro", "sys"))
+}
+
+fn send_json_response(status: StatusCode, body: &serde_json::Value) {
+    let headers = HashMap::from([("Content-Type".to_string(), "application/json".to_string())]);
+    send_response(status, Some(headers), serde_json::to_vec(body).unwrap());
+}
+
+fn send_json_error(status: StatusCode, error: &str) {
+    send_json_response(status, &serde_json::json!({ "error": error }));
+}
+
+/// `GET /api/hello`: greet the caller from this node
+fn handle_hello(our: &Address) {
+    send_json_response(
+        StatusCode::OK,
+        &serde_json::json!({ "message": format!("hello from {}", our.node()) }),
+    );
+}
+
+/// `POST /api/echo`: respond with the JSON body of the request
+fn handle_echo() {
+    let Some(blob) = get_blob() else {
+        send_json_error(StatusCode::BAD_REQUEST, "missing body");
+        return;
+    };
+    match serde_json::from_slice::<serde_json::Value>(&blob.bytes) {
+        Ok(body) => send_json_response(StatusCode::OK, &serde_json::json!({ "echo": body })),
+        Err(e) => send_json_error(StatusCode::BAD_REQUEST, &format!("body is not JSON: {e}")),
+    }
+}
+
+fn handle_http_request(our: &Address, request: &IncomingHttpRequest) -> anyhow::Result<()> {
+    let method = request.method()?;
+    let path = request.bound_path(Some(&our.process.to_string()));
+    match (method.as_str(), path) {
+        ("GET", HELLO_PATH) => handle_hello(our),
+        ("POST", ECHO_PATH) => handle_echo(),
+        (_, HELLO_PATH) | (_, ECHO_PATH) => {
+            send_json_error(StatusCode::METHOD_NOT_ALLOWED, "method not allowed")
+        }
+        _ => send_json_error(StatusCode::NOT_FOUND, "not found"),
+    }
+    Ok(())
+}
+
+fn handle_message(our: &Address, message: &Message) -> anyhow::Result<()> {
+    if !message.is_request() {
+        return Err(anyhow::anyhow!("unexpected Response: {:?}", message));
+    }
+    if message.source() != &make_http_address(our) {
+        return Err(anyhow::anyhow!(
+            "rejecting Message not from http-server: {:?}",
+            message.source(),
+        ));
+    }
+
+    match serde_json::from_slice::<HttpServerRequest>(message.body())? {
+        HttpServerRequest::Http(ref request) => handle_http_request(our, request)?,
+        // only HTTP paths are bound: no WebSocket messages to handle
+        request => info!("ignoring {request:?}"),
+    }
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let mut server = HttpServer::new(5);
+
+    // unauthenticated so that the API can be called without logging in,
+    //  e.g. with `curl`, but local only so that it can't be from elsewhere
+    let config = HttpBindingConfig::default()
+        .authenticated(false)
+        .local_only(true);
+    for path in [HELLO_PATH, ECHO_PATH] {
+        server
+            .bind_http_path(path, config.clone())
+            .expect("failed to bind API");
+    }
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(&our, message) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init,
+    http::{client::send_request_await_response, Method, StatusCode},
+    print_to_terminal, println, Address, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "http-api-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// the `port` of the node in `tests.toml`
+const PORT: u16 = 8080;
+const API_URL: &str = "http-api:http-api:template.os/api";
+
+fn expect(
+    method: Method,
+    endpoint: &str,
+    body: Option<serde_json::Value>,
+    expected: serde_json::Value,
+) -> anyhow::Result<()> {
+    let url = url::Url::parse(&format!("http://localhost:{PORT}/{API_URL}/{endpoint}"))?;
+    let body = body
+        .map(|b| serde_json::to_vec(&b).unwrap())
+        .unwrap_or_default();
+    let response = send_request_await_response(method, url, None, 5, body)?;
+    if response.status() != StatusCode::OK {
+        println!("{endpoint}: status {}", response.status());
+        fail!("http_api_test");
+    }
+    let response: serde_json::Value = serde_json::from_slice(response.body())?;
+    if response != expected {
+        println!("{response:?} != {expected:?}");
+        fail!("http_api_test");
+    }
+    Ok(())
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "http_api_test: a");
+    assert!(node_names.len() == 1);
+
+    expect(
+        Method::GET,
+        "hello",
+        None,
+        serde_json::json!({ "message": format!("hello from {}", our.node()) }),
+    )?;
+
+    print_to_terminal(0, "http_api_test: b");
+    let body = serde_json::json!({ "greeting": "hi", "count": 1 });
+    expect(
+        Method::POST,
+        "echo",
+        Some(body.clone()),
+        serde_json::json!({ "echo": body }),
+    )?;
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("http_api_test: error: {e:?}").as_str());
+
+                fail!("http_api_test");
+            }
+        };
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+
+use crate::kinode::process::kv_store::{
+    Entry, KvError, Request as KvRequest, Response as KvResponse,
+};
+use kinode_process_lib::logging::{error, info, init_logging, Level};
+use kinode_process_lib::sqlite::{self, Sqlite};
+use kinode_process_lib::{await_message, call_init, Address, Message, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "kv-store-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// the sqlite database, owned by this package, that entries persist in:
+/// it outlives restarts of the process & of the node
+const DB_NAME: &str = "kv";
+
+const CREATE_TABLE: &str =
+    "CREATE TABLE IF NOT EXISTS kv (key TEXT PRIMARY KEY, value TEXT NOT NULL);";
+
+fn to_kv_error(e: anyhow::Error) -> KvError {
+    KvError::Database(format!("{e:?}"))
+}
+
+fn check_key(key: &str) -> Result<(), KvError> {
+    if key.is_empty() {
+        return Err(KvError::EmptyKey);
+    }
+    Ok(())
+}
+
+fn to_entry(row: &HashMap<String, serde_json::Value>) -> Option<Entry> {
+    Some(Entry {
+        key: row.get("key")?.as_str()?.to_string(),
+        value: row.get("value")?.as_str()?.to_string(),
+    })
+}
+
+fn get(db: &Sqlite, key: String) -> Result<String, KvError> {
+    check_key(&key)?;
+    let rows = db
+        .read(
+            "SELECT key, value FROM kv WHERE key = ?;".to_string(),
+            vec![key.clone().into()],
+        )
+        .map_err(to_kv_error)?;
+    rows.first()
+        .and_then(to_entry)
+        .map(|entry| entry.value)
+        .ok_or(KvError::KeyNotFound(key))
+}
+
+fn set(db: &Sqlite, entry: Entry) -> Result<(), KvError> {
+    check_key(&entry.key)?;
+    db.write(
+        "INSERT INTO kv (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value;"
+            .to_string(),
+        vec![entry.key.into(), entry.value.into()],
+        None,
+    )
+    .map_err(to_kv_error)
+}
+
+fn delete(db: &Sqlite, key: String) -> Result<(), KvError> {
+    // deleting a missing key is an error, as for `get`
+    get(db, key.clone())?;
+    db.write(
+        "DELETE FROM kv WHERE key = ?;".to_string(),
+        vec![key.into()],
+        None,
+    )
+    .map_err(to_kv_error)
+}
+
+fn list(db: &Sqlite, prefix: String) -> Result<Vec<Entry>, KvError> {
+    // compare the prefix directly rather than with `LIKE`,
+    //  in which `%` & `_` in keys would be wildcards
+    let rows = db
+        .read(
+            "SELECT key, value FROM kv WHERE substr(key, 1, length(?1)) = ?1 ORDER BY key;"
+                .to_string(),
+            vec![prefix.into()],
+        )
+        .map_err(to_kv_error)?;
+    Ok(rows.iter().filter_map(to_entry).collect())
+}
+
+fn handle_message(our: &Address, message: &Message, db: &Sqlite) -> anyhow::Result<()> {
+    if !message.is_request() {
+        return Err(anyhow::anyhow!("unexpected Response: {:?}", message));
+    }
+    if message.source().node != our.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            message.source(),
+        ));
+    }
+
+    let response = match message.body().try_into()? {
+        KvRequest::Get(key) => KvResponse::Get(get(db, key)),
+        KvRequest::Set(entry) => KvResponse::Set(set(db, entry)),
+        KvRequest::Delete(key) => KvResponse::Delete(delete(db, key)),
+        KvRequest::List(prefix) => KvResponse::List(list(db, prefix)),
+    };
+    Response::new().body(response).send()?;
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let db = sqlite::open(our.package_id(), DB_NAME, None).unwrap();
+    db.write(CREATE_TABLE.to_string(), vec![], None).unwrap();
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(&our, message, &db) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
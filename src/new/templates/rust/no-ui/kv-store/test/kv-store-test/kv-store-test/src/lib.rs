@@ -0,0 +1,155 @@
+use crate::kinode::process::kv_store::{
+    Entry, KvError, Request as KvRequest, Response as KvResponse,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, println, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "kv-store-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+fn entry(key: &str, value: &str) -> Entry {
+    Entry {
+        key: key.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn expect(address: &Address, request: KvRequest, expected: KvResponse) -> anyhow::Result<()> {
+    let response = Request::new()
+        .target(address)
+        .body(request)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("kv_store_test");
+    };
+    let response: KvResponse = response.body().try_into()?;
+    if response != expected {
+        println!("{response:?} != {expected:?}");
+        fail!("kv_store_test");
+    }
+    Ok(())
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "kv_store_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_kv_store_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("kv-store"), "kv-store", "template.os"),
+    };
+
+    for (key, value) in [
+        ("fruit/apple", "red"),
+        ("fruit/kiwi", "green"),
+        ("veg/leek", "green"),
+    ] {
+        expect(
+            &our_kv_store_address,
+            KvRequest::Set(entry(key, value)),
+            KvResponse::Set(Ok(())),
+        )?;
+    }
+    // overwrite
+    expect(
+        &our_kv_store_address,
+        KvRequest::Set(entry("fruit/apple", "green")),
+        KvResponse::Set(Ok(())),
+    )?;
+    expect(
+        &our_kv_store_address,
+        KvRequest::Get("fruit/apple".to_string()),
+        KvResponse::Get(Ok("green".to_string())),
+    )?;
+
+    print_to_terminal(0, "kv_store_test: b");
+    expect(
+        &our_kv_store_address,
+        KvRequest::List("fruit/".to_string()),
+        KvResponse::List(Ok(vec![
+            entry("fruit/apple", "green"),
+            entry("fruit/kiwi", "green"),
+        ])),
+    )?;
+
+    print_to_terminal(0, "kv_store_test: c");
+    expect(
+        &our_kv_store_address,
+        KvRequest::Delete("fruit/kiwi".to_string()),
+        KvResponse::Delete(Ok(())),
+    )?;
+    expect(
+        &our_kv_store_address,
+        KvRequest::Get("fruit/kiwi".to_string()),
+        KvResponse::Get(Err(KvError::KeyNotFound("fruit/kiwi".to_string()))),
+    )?;
+    expect(
+        &our_kv_store_address,
+        KvRequest::Delete("fruit/kiwi".to_string()),
+        KvResponse::Delete(Err(KvError::KeyNotFound("fruit/kiwi".to_string()))),
+    )?;
+    expect(
+        &our_kv_store_address,
+        KvRequest::Set(entry("", "empty")),
+        KvResponse::Set(Err(KvError::EmptyKey)),
+    )?;
+    expect(
+        &our_kv_store_address,
+        KvRequest::List("".to_string()),
+        KvResponse::List(Ok(vec![
+            entry("fruit/apple", "green"),
+            entry("veg/leek", "green"),
+        ])),
+    )?;
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("kv_store_test: error: {e:?}").as_str());
+
+                fail!("kv_store_test");
+            }
+        };
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::BTreeMap;
+
+use crate::kinode::process::lamport_clock::{
+    Clock, ClockEntry, ClockState, EventRequest, Request as ClockRequest,
+    Response as ClockResponse, TimestampedEvent,
+};
+use kinode_process_lib::logging::{error, info, init_logging, Level};
+use kinode_process_lib::{await_message, call_init, Address, Message, Request, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "lamport-clock-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+const SYNC_TIMEOUT_SECS: u64 = 5;
+
+/// A Lamport clock alongside a vector clock, one counter per node.
+///
+/// Rules:
+/// 1. before each local event (including a send), increment;
+/// 2. on receiving a clock, take the element-wise max with ours, then
+///    increment (receiving is itself an event).
+struct LogicalClock {
+    node: String,
+    lamport: u64,
+    vector: BTreeMap<String, u64>,
+    events: Vec<TimestampedEvent>,
+}
+
+impl LogicalClock {
+    fn new(node: String) -> Self {
+        LogicalClock {
+            node,
+            lamport: 0,
+            vector: BTreeMap::new(),
+            events: Vec::new(),
+        }
+    }
+
+    fn increment(&mut self) {
+        self.lamport += 1;
+        *self.vector.entry(self.node.clone()).or_default() += 1;
+    }
+
+    fn merge(&mut self, other: &Clock) {
+        self.lamport = self.lamport.max(other.lamport);
+        for ClockEntry { node, counter } in &other.vector {
+            let ours = self.vector.entry(node.clone()).or_default();
+            *ours = (*ours).max(*counter);
+        }
+    }
+
+    fn to_clock(&self) -> Clock {
+        Clock {
+            lamport: self.lamport,
+            vector: self
+                .vector
+                .iter()
+                .map(|(node, counter)| ClockEntry {
+                    node: node.clone(),
+                    counter: *counter,
+                })
+                .collect(),
+        }
+    }
+
+    fn record(&mut self, label: String) -> TimestampedEvent {
+        let event = TimestampedEvent {
+            label,
+            node: self.node.clone(),
+            clock: self.to_clock(),
+        };
+        self.events.push(event.clone());
+        event
+    }
+
+    fn handle_event(
+        &mut self,
+        our: &Address,
+        request: EventRequest,
+    ) -> anyhow::Result<TimestampedEvent> {
+        self.increment();
+        let event = self.record(request.label);
+        if let Some(ref node) = request.send_to {
+            // the clock is sent as it was at the send event
+            let target = Address::new(node, our.process.clone());
+            let response = Request::to(target)
+                .body(ClockRequest::Sync(event.clock.clone()))
+                .send_and_await_response(SYNC_TIMEOUT_SECS)??;
+            let ClockResponse::Sync(_) = response.body().try_into()? else {
+                return Err(anyhow::anyhow!("unexpected response to sync from {node}"));
+            };
+        }
+        Ok(event)
+    }
+
+    fn handle_sync(&mut self, source: &Address, clock: &Clock) -> Clock {
+        self.merge(clock);
+        self.increment();
+        self.record(format!("sync from {}", source.node));
+        self.to_clock()
+    }
+}
+
+fn handle_message(
+    our: &Address,
+    message: &Message,
+    clock: &mut LogicalClock,
+) -> anyhow::Result<()> {
+    if !message.is_request() {
+        return Ok(());
+    }
+
+    let response = match message.body().try_into()? {
+        ClockRequest::Event(request) => {
+            // only we may make our process send our clock elsewhere
+            if request.send_to.is_some() && message.source().node != our.node {
+                return Err(anyhow::anyhow!(
+                    "rejecting foreign Event with send-to from {:?}",
+                    message.source(),
+                ));
+            }
+            clock
+                .handle_event(our, request)
+                .map(ClockResponse::Event)
+                .unwrap_or_else(|e| ClockResponse::Err(e.to_string()))
+        }
+        ClockRequest::Sync(ref other) => {
+            ClockResponse::Sync(clock.handle_sync(message.source(), other))
+        }
+        ClockRequest::GetClock => ClockResponse::GetClock(ClockState {
+            clock: clock.to_clock(),
+            events: clock.events.clone(),
+        }),
+    };
+    Response::new().body(response).send()?;
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let mut clock = LogicalClock::new(our.node.clone());
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(&our, message, &mut clock) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
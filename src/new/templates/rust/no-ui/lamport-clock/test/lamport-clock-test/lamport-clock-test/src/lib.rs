@@ -0,0 +1,171 @@
+use crate::kinode::process::lamport_clock::{
+    Clock, ClockState, EventRequest, Request as ClockRequest, Response as ClockResponse,
+    TimestampedEvent,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, println, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "lamport-clock-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+fn counter(clock: &Clock, node: &str) -> u64 {
+    clock
+        .vector
+        .iter()
+        .find(|entry| entry.node == node)
+        .map(|entry| entry.counter)
+        .unwrap_or(0)
+}
+
+/// `a` happened before `b` iff every counter of `a` is <= that of `b`,
+/// and the clocks differ
+fn happened_before(a: &Clock, b: &Clock) -> bool {
+    a.vector
+        .iter()
+        .all(|entry| entry.counter <= counter(b, &entry.node))
+        && a.vector != b.vector
+}
+
+fn is_concurrent(a: &Clock, b: &Clock) -> bool {
+    !happened_before(a, b) && !happened_before(b, a)
+}
+
+fn event(
+    address: &Address,
+    label: &str,
+    send_to: Option<String>,
+) -> anyhow::Result<TimestampedEvent> {
+    let response = Request::new()
+        .target(address)
+        .body(ClockRequest::Event(EventRequest {
+            label: label.to_string(),
+            send_to,
+        }))
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("lamport_clock_test");
+    };
+    let ClockResponse::Event(event) = response.body().try_into()? else {
+        fail!("lamport_clock_test");
+    };
+    Ok(event)
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "lamport_clock_test: a");
+    assert!(node_names.len() >= 2);
+    if our.node != node_names[0] {
+        // we are not master node: return
+        Response::new()
+            .body(TesterResponse::Run(Ok(())))
+            .send()
+            .unwrap();
+        return Ok(());
+    }
+
+    // we are master node
+
+    let our_clock_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("lamport-clock"), "lamport-clock", "template.os"),
+    };
+    let their_clock_address = Address {
+        node: node_names[1].clone(),
+        process: ProcessId::new(Some("lamport-clock"), "lamport-clock", "template.os"),
+    };
+
+    // a: local event on our node; b: local event on their node
+    print_to_terminal(0, "lamport_clock_test: b");
+    let a = event(&our_clock_address, "a", None)?;
+    let b = event(&their_clock_address, "b", None)?;
+    if !is_concurrent(&a.clock, &b.clock) {
+        println!("{a:?} and {b:?} should be concurrent");
+        fail!("lamport_clock_test");
+    }
+
+    // c: send from our node to theirs, which merges our clock on receipt
+    print_to_terminal(0, "lamport_clock_test: c");
+    let c = event(&our_clock_address, "c", Some(node_names[1].clone()))?;
+
+    let response = Request::new()
+        .target(their_clock_address)
+        .body(ClockRequest::GetClock)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("lamport_clock_test");
+    };
+    let ClockResponse::GetClock(ClockState { events, .. }) = response.body().try_into()? else {
+        fail!("lamport_clock_test");
+    };
+    let Some(receive) = events.iter().find(|e| e.label.starts_with("sync from")) else {
+        println!("no sync event in {events:?}");
+        fail!("lamport_clock_test");
+    };
+
+    // causal order: a -> c -> receive, and b -> receive
+    for earlier in [&a, &c, &b] {
+        if !happened_before(&earlier.clock, &receive.clock) {
+            println!("{earlier:?} should have happened before {receive:?}");
+            fail!("lamport_clock_test");
+        }
+        if earlier.clock.lamport >= receive.clock.lamport {
+            println!("Lamport timestamps of {earlier:?} and {receive:?} are out of order");
+            fail!("lamport_clock_test");
+        }
+    }
+    if !happened_before(&a.clock, &c.clock) {
+        fail!("lamport_clock_test");
+    }
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("lamport_clock_test: error: {e:?}").as_str());
+
+                fail!("lamport_clock_test");
+            }
+        };
+    }
+}
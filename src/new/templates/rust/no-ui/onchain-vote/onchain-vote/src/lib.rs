@@ -0,0 +1,450 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::kinode::process::onchain_vote::{
+    Ballot, Proposal, Request as VoteRequest, Response as VoteResponse, Tally, VoteTransaction,
+};
+use alloy_primitives::{Bytes, PrimitiveSignature, B256};
+use alloy_sol_types::{sol, SolCall, SolValue};
+use kinode_process_lib::eth::{
+    Address as EthAddress, EthSubResult, Filter, Log, Provider, SubscriptionResult, TxHash, U256,
+};
+use kinode_process_lib::kimap::{self, contract::noteCall, Kimap};
+use kinode_process_lib::logging::{error, info, init_logging, warn, Level};
+use kinode_process_lib::{await_message, call_init, timer, Address, Message, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "onchain-vote-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+sol! {
+    /// ERC-6551 token-bound account: a name's TBA calls Kimap on its behalf
+    function execute(address to, uint256 value, bytes calldata data, uint8 operation)
+        external payable returns (bytes memory returnData);
+}
+
+/// the fake chain of `kit boot-fake-node` & `kit chain`; for Optimism,
+/// use `kimap::KIMAP_CHAIN_ID` & `kimap::KIMAP_ADDRESS`
+const CHAIN_ID: u64 = 31337;
+const KIMAP_ADDRESS: &str = "0xEce71a05B36CA55B895427cD9a440eEF7Cf3669D";
+/// seconds to wait on the eth provider
+const ETH_TIMEOUT: u64 = 30;
+/// blocks a vote must be buried under before it is counted: a reorg
+/// shallower than this cannot change the tally
+const FINALITY_DEPTH: u64 = 3;
+const FINALITY_INTERVAL_MS: u64 = 2_000;
+const FINALITY_CONTEXT: &[u8] = b"finality";
+
+fn vote_label(proposal: &str) -> String {
+    format!("~vote-{proposal}")
+}
+
+/// What the owner of the voter's name signs: binding the proposal means
+/// a signature cannot be replayed as a vote on another proposal
+fn vote_message(proposal: &str, choice: &str) -> String {
+    format!("kinode vote\nproposal: {proposal}\nchoice: {choice}")
+}
+
+/// A vote seen on-chain but not yet `FINALITY_DEPTH` blocks deep
+struct PendingVote {
+    voter: String,
+    choice: String,
+    block_number: u64,
+    block_hash: Option<B256>,
+    tx_hash: TxHash,
+}
+
+struct ProposalState {
+    proposal: Proposal,
+    /// `(transaction hash, log index)` of every vote handled, so one
+    /// seen by both a backfill & the subscription is handled once
+    seen: HashSet<(TxHash, u64)>,
+    /// by `(transaction hash, log index)`, which a reorg resends
+    /// with `removed` set
+    pending: HashMap<(TxHash, u64), PendingVote>,
+    /// by voter: a later vote replaces an earlier one
+    finalized: HashMap<String, String>,
+    rejected: u64,
+    quorum_reached: bool,
+}
+
+impl ProposalState {
+    fn tally(&self) -> Tally {
+        let counts = self
+            .proposal
+            .choices
+            .iter()
+            .map(|choice| {
+                let count = self.finalized.values().filter(|c| *c == choice).count();
+                (choice.clone(), count as u64)
+            })
+            .collect();
+        Tally {
+            counts,
+            pending: self.pending.len() as u64,
+            rejected: self.rejected,
+            quorum_reached: self.quorum_reached,
+        }
+    }
+}
+
+struct State {
+    kimap: Kimap,
+    /// by subscription id
+    proposals: Vec<ProposalState>,
+}
+
+impl State {
+    fn new() -> Self {
+        State {
+            kimap: Kimap::new(
+                Provider::new(CHAIN_ID, ETH_TIMEOUT),
+                KIMAP_ADDRESS.parse().unwrap(),
+            ),
+            proposals: vec![],
+        }
+    }
+
+    fn votes_filter(&self, proposal: &Proposal) -> Filter {
+        self.kimap
+            .notes_filter(&[&vote_label(&proposal.id)])
+            .from_block(proposal.start_block)
+    }
+
+    /// Subscribe to votes on the proposal, then backfill those already
+    /// on-chain: subscribing first means none falls between the two
+    fn subscribe(&mut self, sub_id: usize) -> anyhow::Result<()> {
+        let filter = self.votes_filter(&self.proposals[sub_id].proposal);
+        self.kimap
+            .provider
+            .subscribe(sub_id as u64, filter.clone())
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        let logs = self
+            .kimap
+            .provider
+            .get_logs(&filter)
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        info!(
+            "tallying {}: {} vote(s) on-chain",
+            self.proposals[sub_id].proposal.id,
+            logs.len(),
+        );
+        for log in logs {
+            self.handle_log(sub_id, &log);
+        }
+        Ok(())
+    }
+
+    fn get_proposal(&self, id: &str) -> anyhow::Result<&ProposalState> {
+        self.proposals
+            .iter()
+            .find(|p| p.proposal.id == id)
+            .ok_or_else(|| anyhow::anyhow!("no such proposal: {id}"))
+    }
+
+    /// Check `signature` was made by the owner of `voter` on-chain:
+    /// without this, any account able to write a note could vote as anyone
+    fn verify_signature(
+        &self,
+        voter: &str,
+        proposal: &str,
+        choice: &str,
+        signature: &[u8],
+    ) -> anyhow::Result<()> {
+        let (_, owner, _) = self
+            .kimap
+            .get(voter)
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        if owner == EthAddress::ZERO {
+            return Err(anyhow::anyhow!("no such name: {voter}"));
+        }
+        let signer = PrimitiveSignature::try_from(signature)?
+            .recover_address_from_msg(vote_message(proposal, choice))?;
+        if signer != owner {
+            return Err(anyhow::anyhow!(
+                "signed by {signer}, not by {voter}'s owner {owner}"
+            ));
+        }
+        Ok(())
+    }
+
+    fn propose(&mut self, proposal: Proposal) -> anyhow::Result<()> {
+        if !kimap::valid_note(&vote_label(&proposal.id)) {
+            return Err(anyhow::anyhow!(
+                "proposal id must be lowercase letters, digits & hyphens"
+            ));
+        }
+        if self.get_proposal(&proposal.id).is_ok() {
+            return Err(anyhow::anyhow!("proposal {} already exists", proposal.id));
+        }
+        if proposal.choices.is_empty() || proposal.quorum == 0 {
+            return Err(anyhow::anyhow!("proposal needs choices & a nonzero quorum"));
+        }
+        self.proposals.push(ProposalState {
+            proposal,
+            seen: HashSet::new(),
+            pending: HashMap::new(),
+            finalized: HashMap::new(),
+            rejected: 0,
+            quorum_reached: false,
+        });
+        let sub_id = self.proposals.len() - 1;
+        if let Err(e) = self.subscribe(sub_id) {
+            let _ = self.kimap.provider.unsubscribe(sub_id as u64);
+            self.proposals.pop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn prepare_vote(&self, ballot: Ballot) -> anyhow::Result<VoteTransaction> {
+        let proposal = &self.get_proposal(&ballot.proposal)?.proposal;
+        if !proposal.choices.contains(&ballot.choice) {
+            return Err(anyhow::anyhow!("no such choice: {}", ballot.choice));
+        }
+        // fail now rather than have the vote rejected once on-chain
+        self.verify_signature(
+            &ballot.voter,
+            &ballot.proposal,
+            &ballot.choice,
+            &ballot.signature,
+        )?;
+        let (tba, _, _) = self
+            .kimap
+            .get(&ballot.voter)
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        let note = noteCall {
+            note: vote_label(&ballot.proposal).into_bytes().into(),
+            data: (ballot.choice, Bytes::from(ballot.signature))
+                .abi_encode_params()
+                .into(),
+        }
+        .abi_encode();
+        let data = executeCall {
+            to: *self.kimap.address(),
+            value: U256::ZERO,
+            data: note.into(),
+            operation: 0,
+        }
+        .abi_encode();
+        Ok(VoteTransaction {
+            to: tba.to_string(),
+            data,
+        })
+    }
+
+    fn submit(&self, transaction: Vec<u8>) -> anyhow::Result<String> {
+        let tx_hash = self
+            .kimap
+            .provider
+            .send_raw_transaction(transaction.into())
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        Ok(tx_hash.to_string())
+    }
+
+    /// Decode & verify a vote, holding it as pending until it is final
+    fn handle_log(&mut self, sub_id: usize, log: &Log) {
+        let Some(state) = self.proposals.get(sub_id) else {
+            warn!("log for unknown subscription {sub_id}");
+            return;
+        };
+        let (Some(tx_hash), Some(log_index), Some(block_number)) =
+            (log.transaction_hash, log.log_index, log.block_number)
+        else {
+            warn!("skipping log still pending inclusion");
+            return;
+        };
+        let key = (tx_hash, log_index);
+        let proposal_id = state.proposal.id.clone();
+
+        if log.removed {
+            // reorged out while pending: it was never counted, so just forget it
+            let state = &mut self.proposals[sub_id];
+            let was_seen = state.seen.remove(&key);
+            if state.pending.remove(&key).is_some() {
+                info!("{proposal_id}: vote in {tx_hash} reorged out");
+            } else if was_seen {
+                warn!("{proposal_id}: vote in {tx_hash} reorged out after it was counted or rejected: increase FINALITY_DEPTH");
+            }
+            return;
+        }
+
+        if state.seen.contains(&key) {
+            return;
+        }
+
+        let vote = kimap::decode_note_log(log)
+            .map_err(|e| anyhow::anyhow!("{e:?}"))
+            .and_then(|note| {
+                let (choice, signature) = <(String, Bytes)>::abi_decode_params(&note.data, true)?;
+                if !state.proposal.choices.contains(&choice) {
+                    return Err(anyhow::anyhow!("no such choice: {choice}"));
+                }
+                self.verify_signature(&note.parent_path, &proposal_id, &choice, &signature)?;
+                Ok((note.parent_path, choice))
+            });
+        let state = &mut self.proposals[sub_id];
+        state.seen.insert(key);
+        match vote {
+            Ok((voter, choice)) => {
+                state.pending.insert(
+                    key,
+                    PendingVote {
+                        voter,
+                        choice,
+                        block_number,
+                        block_hash: log.block_hash,
+                        tx_hash,
+                    },
+                );
+            }
+            Err(e) => {
+                warn!("{proposal_id}: rejected vote in {tx_hash}: {e:?}");
+                state.rejected += 1;
+            }
+        }
+    }
+
+    /// Count the pending votes now `FINALITY_DEPTH` blocks deep, after
+    /// checking each is still in the canonical chain: a reorg may have
+    /// happened while the subscription was down
+    fn finalize(&mut self) -> anyhow::Result<()> {
+        if self.proposals.iter().all(|p| p.pending.is_empty()) {
+            return Ok(());
+        }
+        let provider = &self.kimap.provider;
+        let head = provider
+            .get_block_number()
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        for state in self.proposals.iter_mut() {
+            // in chain order, so a voter's later vote replaces the earlier
+            let mut final_keys: Vec<(u64, (TxHash, u64))> = state
+                .pending
+                .iter()
+                .filter(|(_, vote)| vote.block_number + FINALITY_DEPTH <= head)
+                .map(|(key, vote)| (vote.block_number, *key))
+                .collect();
+            final_keys.sort_by_key(|(block_number, (_, log_index))| (*block_number, *log_index));
+            for (_, key) in final_keys {
+                // on error, leave the vote pending to check next time
+                let receipt = provider
+                    .get_transaction_receipt(state.pending[&key].tx_hash)
+                    .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+                let vote = state.pending.remove(&key).unwrap();
+                if receipt.and_then(|r| r.block_hash) != vote.block_hash {
+                    warn!(
+                        "{}: vote in {} reorged out",
+                        state.proposal.id, vote.tx_hash
+                    );
+                    state.rejected += 1;
+                    continue;
+                }
+                info!(
+                    "{}: {} voted {} (final at block {head})",
+                    state.proposal.id, vote.voter, vote.choice,
+                );
+                state.finalized.insert(vote.voter, vote.choice);
+            }
+            if !state.quorum_reached && state.finalized.len() as u64 >= state.proposal.quorum {
+                state.quorum_reached = true;
+                info!(
+                    "{}: quorum of {} reached: {:?}",
+                    state.proposal.id,
+                    state.proposal.quorum,
+                    state.tally().counts,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: VoteRequest) -> VoteResponse {
+        let to_string = |e: anyhow::Error| e.to_string();
+        match request {
+            VoteRequest::Propose(proposal) => {
+                VoteResponse::Propose(self.propose(proposal).map_err(to_string))
+            }
+            VoteRequest::PrepareVote(ballot) => {
+                VoteResponse::PrepareVote(self.prepare_vote(ballot).map_err(to_string))
+            }
+            VoteRequest::Submit(transaction) => {
+                VoteResponse::Submit(self.submit(transaction).map_err(to_string))
+            }
+            VoteRequest::GetTally(id) => VoteResponse::GetTally(
+                self.get_proposal(&id)
+                    .map(|state| state.tally())
+                    .map_err(to_string),
+            ),
+        }
+    }
+
+    fn handle_eth_message(&mut self, message: &Message) -> anyhow::Result<()> {
+        match serde_json::from_slice::<EthSubResult>(message.body())? {
+            Ok(sub) => {
+                let SubscriptionResult::Log(log) =
+                    serde_json::from_value::<SubscriptionResult>(sub.result)?
+                else {
+                    return Err(anyhow::anyhow!("subscription {} sent a non-log", sub.id));
+                };
+                self.handle_log(sub.id as usize, &log);
+            }
+            Err(sub_error) => {
+                // votes cast while the subscription was down are caught
+                //  by the backfill
+                warn!("subscription {} closed: {}", sub_error.id, sub_error.error);
+                let sub_id = sub_error.id as usize;
+                if sub_id < self.proposals.len() {
+                    self.subscribe(sub_id)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn handle_message(our: &Address, message: &Message, state: &mut State) -> anyhow::Result<()> {
+    if !message.is_request() {
+        if message.source().process == "timer:distro:sys"
+            && message.context() == Some(FINALITY_CONTEXT)
+        {
+            let result = state.finalize();
+            timer::set_timer(FINALITY_INTERVAL_MS, Some(FINALITY_CONTEXT.to_vec()));
+            result?;
+        }
+        return Ok(());
+    }
+    if message.source().process == "eth:distro:sys" {
+        return state.handle_eth_message(message);
+    }
+    if message.source().node != our.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            message.source(),
+        ));
+    }
+
+    let response = state.handle_request(message.body().try_into()?);
+    Response::new().body(response).send()?;
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let mut state = State::new();
+    timer::set_timer(FINALITY_INTERVAL_MS, Some(FINALITY_CONTEXT.to_vec()));
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(&our, message, &mut state) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use crate::kinode::process::onchain_vote::{
+    Ballot, Proposal, Request as VoteRequest, Response as VoteResponse, Tally,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, println, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "onchain-vote-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+fn call(address: &Address, request: VoteRequest) -> anyhow::Result<VoteResponse> {
+    let response = Request::new()
+        .target(address)
+        .body(request)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("onchain_vote_test");
+    };
+    Ok(response.body().try_into()?)
+}
+
+fn expect(address: &Address, request: VoteRequest, expected: VoteResponse) -> anyhow::Result<()> {
+    let response = call(address, request)?;
+    if response != expected {
+        println!("{response:?} != {expected:?}");
+        fail!("onchain_vote_test");
+    }
+    Ok(())
+}
+
+fn proposal(id: &str) -> Proposal {
+    Proposal {
+        id: id.to_string(),
+        choices: vec!["yes".to_string(), "no".to_string()],
+        quorum: 2,
+        start_block: 0,
+    }
+}
+
+fn ballot(voter: &str, choice: &str) -> Ballot {
+    Ballot {
+        voter: voter.to_string(),
+        proposal: "upgrade".to_string(),
+        choice: choice.to_string(),
+        signature: vec![0; 65],
+    }
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "onchain_vote_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_onchain_vote_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("onchain-vote"), "onchain-vote", "template.os"),
+    };
+
+    // proposal ids become Kimap note labels
+    let VoteResponse::Propose(Err(_)) = call(
+        &our_onchain_vote_address,
+        VoteRequest::Propose(proposal("Not A Label")),
+    )?
+    else {
+        fail!("onchain_vote_test");
+    };
+    expect(
+        &our_onchain_vote_address,
+        VoteRequest::Propose(proposal("upgrade")),
+        VoteResponse::Propose(Ok(())),
+    )?;
+    let VoteResponse::Propose(Err(_)) = call(
+        &our_onchain_vote_address,
+        VoteRequest::Propose(proposal("upgrade")),
+    )?
+    else {
+        fail!("onchain_vote_test");
+    };
+
+    print_to_terminal(0, "onchain_vote_test: b");
+    let VoteResponse::PrepareVote(Err(_)) = call(
+        &our_onchain_vote_address,
+        VoteRequest::PrepareVote(ballot(&our.node, "maybe")),
+    )?
+    else {
+        fail!("onchain_vote_test");
+    };
+    // not signed by the owner of our name
+    let VoteResponse::PrepareVote(Err(_)) = call(
+        &our_onchain_vote_address,
+        VoteRequest::PrepareVote(ballot(&our.node, "yes")),
+    )?
+    else {
+        fail!("onchain_vote_test");
+    };
+
+    print_to_terminal(0, "onchain_vote_test: c");
+    expect(
+        &our_onchain_vote_address,
+        VoteRequest::GetTally("upgrade".to_string()),
+        VoteResponse::GetTally(Ok(Tally {
+            counts: vec![("yes".to_string(), 0), ("no".to_string(), 0)],
+            pending: 0,
+            rejected: 0,
+            quorum_reached: false,
+        })),
+    )?;
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("onchain_vote_test: error: {e:?}").as_str());
+
+                fail!("onchain_vote_test");
+            }
+        };
+    }
+}
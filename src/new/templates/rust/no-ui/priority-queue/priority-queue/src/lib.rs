@@ -0,0 +1,193 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::kinode::process::priority_queue::{
+    EnqueueRequest, QueueItem, Request as QueueRequest, Response as QueueResponse,
+};
+use kinode_process_lib::logging::{error, info, init_logging, Level};
+use kinode_process_lib::vfs::{create_drive, open_file};
+use kinode_process_lib::{await_message, call_init, timer, Address, Message, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "priority-queue-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// how often the worker dequeues and processes an item
+const WORKER_INTERVAL_MS: u64 = 1_000;
+const WORKER_CONTEXT: &[u8] = b"worker";
+const STATE_FILE: &str = "queue.json";
+
+/// `BinaryHeap` is a max-heap: order by priority, then by earliest `seq`
+struct HeapItem(QueueItem);
+
+impl Ord for HeapItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .priority
+            .cmp(&other.0.priority)
+            .then_with(|| other.0.seq.cmp(&self.0.seq))
+    }
+}
+
+impl PartialOrd for HeapItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapItem {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapItem {}
+
+/// persisted to VFS after every change so the queue survives a restart
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct State {
+    items: Vec<QueueItem>,
+    next_seq: u64,
+    worker_enabled: bool,
+}
+
+struct Queue {
+    state_path: String,
+    heap: BinaryHeap<HeapItem>,
+    next_seq: u64,
+    worker_enabled: bool,
+}
+
+impl Queue {
+    fn load(drive: &str) -> anyhow::Result<Self> {
+        let state_path = format!("{drive}/{STATE_FILE}");
+        let state_file = open_file(&state_path, true, None)?;
+        let state = match state_file.read()?.as_slice() {
+            [] => State {
+                items: vec![],
+                next_seq: 0,
+                worker_enabled: true,
+            },
+            bytes => serde_json::from_slice(bytes)?,
+        };
+        if !state.items.is_empty() {
+            info!("restored {} queued item(s)", state.items.len());
+        }
+        Ok(Queue {
+            state_path,
+            heap: state.items.into_iter().map(HeapItem).collect(),
+            next_seq: state.next_seq,
+            worker_enabled: state.worker_enabled,
+        })
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let state = State {
+            items: self.heap.iter().map(|i| i.0.clone()).collect(),
+            next_seq: self.next_seq,
+            worker_enabled: self.worker_enabled,
+        };
+        let state_file = open_file(&self.state_path, true, None)?;
+        state_file.write(&serde_json::to_vec(&state)?)?;
+        Ok(())
+    }
+
+    fn enqueue(&mut self, request: EnqueueRequest) -> anyhow::Result<()> {
+        self.heap.push(HeapItem(QueueItem {
+            item: request.item,
+            priority: request.priority,
+            seq: self.next_seq,
+        }));
+        self.next_seq += 1;
+        self.save()
+    }
+
+    fn dequeue(&mut self) -> anyhow::Result<Option<QueueItem>> {
+        let item = self.heap.pop().map(|i| i.0);
+        if item.is_some() {
+            self.save()?;
+        }
+        Ok(item)
+    }
+
+    fn handle_request(&mut self, request: QueueRequest) -> anyhow::Result<QueueResponse> {
+        Ok(match request {
+            QueueRequest::Enqueue(request) => {
+                self.enqueue(request)?;
+                QueueResponse::Enqueue
+            }
+            QueueRequest::Dequeue => QueueResponse::Dequeue(self.dequeue()?),
+            QueueRequest::Peek => QueueResponse::Peek(self.heap.peek().map(|i| i.0.clone())),
+            QueueRequest::Len => QueueResponse::Len(self.heap.len() as u64),
+            QueueRequest::Clear => {
+                self.heap.clear();
+                self.save()?;
+                QueueResponse::Clear
+            }
+            QueueRequest::SetWorker(enabled) => {
+                self.worker_enabled = enabled;
+                self.save()?;
+                QueueResponse::SetWorker
+            }
+        })
+    }
+
+    /// the worker: dequeue the highest-priority item, if any, and process it
+    fn work(&mut self) -> anyhow::Result<()> {
+        if !self.worker_enabled {
+            return Ok(());
+        }
+        if let Some(item) = self.dequeue()? {
+            // replace with real processing of the item
+            info!(
+                "processed {} (priority {}); {} item(s) left",
+                item.item,
+                item.priority,
+                self.heap.len(),
+            );
+        }
+        Ok(())
+    }
+}
+
+fn handle_message(message: &Message, queue: &mut Queue) -> anyhow::Result<()> {
+    if !message.is_request() {
+        if message.source().process == "timer:distro:sys"
+            && message.context() == Some(WORKER_CONTEXT)
+        {
+            let result = queue.work();
+            timer::set_timer(WORKER_INTERVAL_MS, Some(WORKER_CONTEXT.to_vec()));
+            result?;
+        }
+        return Ok(());
+    }
+
+    let response = queue
+        .handle_request(message.body().try_into()?)
+        .unwrap_or_else(|e| QueueResponse::Err(e.to_string()));
+    Response::new().body(response).send()?;
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let drive = create_drive(our.package_id(), "queue", None).unwrap();
+    let mut queue = Queue::load(&drive).unwrap();
+    timer::set_timer(WORKER_INTERVAL_MS, Some(WORKER_CONTEXT.to_vec()));
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(message, &mut queue) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::kinode::process::priority_queue::{
+    EnqueueRequest, QueueItem, Request as QueueRequest, Response as QueueResponse,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, println, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "priority-queue-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+fn send(address: &Address, request: QueueRequest) -> anyhow::Result<QueueResponse> {
+    let response = Request::new()
+        .target(address)
+        .body(request)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("priority_queue_test");
+    };
+    Ok(response.body().try_into()?)
+}
+
+fn expect(
+    address: &Address,
+    request: QueueRequest,
+    expected: QueueResponse,
+) -> anyhow::Result<()> {
+    let response = send(address, request)?;
+    if response != expected {
+        println!("{response:?} != {expected:?}");
+        fail!("priority_queue_test");
+    }
+    Ok(())
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "priority_queue_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_queue_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("priority-queue"), "priority-queue", "template.os"),
+    };
+
+    // pause the worker so that it does not dequeue items under test
+    expect(
+        &our_queue_address,
+        QueueRequest::SetWorker(false),
+        QueueResponse::SetWorker,
+    )?;
+    expect(&our_queue_address, QueueRequest::Clear, QueueResponse::Clear)?;
+
+    print_to_terminal(0, "priority_queue_test: b");
+    for (item, priority) in [("low", 1), ("high", 5), ("mid", 3), ("high-later", 5)] {
+        expect(
+            &our_queue_address,
+            QueueRequest::Enqueue(EnqueueRequest {
+                item: item.to_string(),
+                priority,
+            }),
+            QueueResponse::Enqueue,
+        )?;
+    }
+    expect(&our_queue_address, QueueRequest::Len, QueueResponse::Len(4))?;
+    let QueueResponse::Peek(Some(QueueItem { item, .. })) =
+        send(&our_queue_address, QueueRequest::Peek)?
+    else {
+        fail!("priority_queue_test");
+    };
+    if item != "high" {
+        fail!("priority_queue_test");
+    }
+
+    // highest priority first; equal priorities in order of enqueueing
+    print_to_terminal(0, "priority_queue_test: c");
+    for expected in ["high", "high-later", "mid", "low"] {
+        let QueueResponse::Dequeue(Some(QueueItem { item, .. })) =
+            send(&our_queue_address, QueueRequest::Dequeue)?
+        else {
+            fail!("priority_queue_test");
+        };
+        if item != expected {
+            println!("dequeued {item}; expected {expected}");
+            fail!("priority_queue_test");
+        }
+    }
+    expect(
+        &our_queue_address,
+        QueueRequest::Dequeue,
+        QueueResponse::Dequeue(None),
+    )?;
+
+    expect(
+        &our_queue_address,
+        QueueRequest::SetWorker(true),
+        QueueResponse::SetWorker,
+    )?;
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("priority_queue_test: error: {e:?}").as_str());
+
+                fail!("priority_queue_test");
+            }
+        };
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use crate::kinode::process::pubsub::{
+    PublishRequest, Request as PubsubRequest, Response as PubsubResponse, TopicError, TopicInfo,
+};
+use kinode_process_lib::logging::{error, info, init_logging, warn, Level};
+use kinode_process_lib::{
+    await_message, call_init, get_typed_state, set_state, Address, Message, Request, Response,
+    SendError, SendErrorKind,
+};
+use serde::{Deserialize, Serialize};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "pubsub-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// seconds a subscriber has to acknowledge a published payload: a subscriber
+/// that is gone is reported back as `Offline` & dropped, one that is slow is kept
+const DELIVERY_TIMEOUT: u64 = 5;
+
+/// topic -> the addresses subscribed to it; persisted so that subscriptions
+/// survive restarts of the process
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    topics: HashMap<String, Vec<Address>>,
+}
+
+impl State {
+    fn load() -> Self {
+        get_typed_state(|bytes| serde_json::from_slice(bytes)).unwrap_or_default()
+    }
+
+    fn save(&self) {
+        set_state(&serde_json::to_vec(self).unwrap());
+    }
+
+    fn subscribe(&mut self, topic: String, subscriber: &Address) {
+        let subscribers = self.topics.entry(topic).or_default();
+        if !subscribers.contains(subscriber) {
+            subscribers.push(subscriber.clone());
+        }
+    }
+
+    fn unsubscribe(&mut self, topic: &str, subscriber: &Address) -> Result<(), TopicError> {
+        let Some(subscribers) = self.topics.get_mut(topic) else {
+            return Err(TopicError::NotSubscribed(topic.to_string()));
+        };
+        let Some(index) = subscribers.iter().position(|s| s == subscriber) else {
+            return Err(TopicError::NotSubscribed(topic.to_string()));
+        };
+        subscribers.remove(index);
+        if subscribers.is_empty() {
+            self.topics.remove(topic);
+        }
+        Ok(())
+    }
+
+    fn list_topics(&self) -> Vec<TopicInfo> {
+        let mut topics: Vec<TopicInfo> = self
+            .topics
+            .iter()
+            .map(|(topic, subscribers)| TopicInfo {
+                topic: topic.clone(),
+                subscribers: subscribers.len() as u32,
+            })
+            .collect();
+        topics.sort_by(|a, b| a.topic.cmp(&b.topic));
+        topics
+    }
+}
+
+/// which delivery a SendError is for, so that its subscriber can be dropped
+#[derive(Debug, Serialize, Deserialize)]
+struct DeliveryContext {
+    topic: String,
+    subscriber: Address,
+}
+
+/// fan the payload out to each subscriber of the topic
+fn publish(state: &State, request: PublishRequest) -> Result<u32, TopicError> {
+    if request.topic.is_empty() {
+        return Err(TopicError::EmptyTopic);
+    }
+    let Some(subscribers) = state.topics.get(&request.topic) else {
+        return Ok(0);
+    };
+    for subscriber in subscribers {
+        let context = DeliveryContext {
+            topic: request.topic.clone(),
+            subscriber: subscriber.clone(),
+        };
+        let sent = Request::new()
+            .target(subscriber)
+            .body(request.payload.clone())
+            .metadata(&request.topic)
+            .context(serde_json::to_vec(&context).unwrap())
+            .expects_response(DELIVERY_TIMEOUT)
+            .send();
+        if let Err(e) = sent {
+            warn!("failed to send to {subscriber}: {e:?}");
+        }
+    }
+    Ok(subscribers.len() as u32)
+}
+
+fn handle_send_error(state: &mut State, send_error: SendError) {
+    let Some(context) = send_error
+        .context()
+        .and_then(|c| serde_json::from_slice::<DeliveryContext>(c).ok())
+    else {
+        error!("got SendError: {send_error}");
+        return;
+    };
+    match send_error.kind() {
+        SendErrorKind::Offline => {
+            info!("{} is gone: unsubscribing it", context.subscriber);
+            // it may already have been dropped on an earlier publish
+            let _ = state.unsubscribe(&context.topic, &context.subscriber);
+            state.save();
+        }
+        SendErrorKind::Timeout => warn!(
+            "{} did not acknowledge {}: keeping it subscribed",
+            context.subscriber, context.topic,
+        ),
+    }
+}
+
+fn handle_message(our: &Address, message: &Message, state: &mut State) -> anyhow::Result<()> {
+    if !message.is_request() {
+        // a subscriber acknowledging a published payload
+        return Ok(());
+    }
+    if message.source().node != our.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            message.source(),
+        ));
+    }
+
+    let response = match message.body().try_into()? {
+        PubsubRequest::Subscribe(topic) => {
+            if topic.is_empty() {
+                return Err(anyhow::anyhow!("cannot subscribe to the empty topic"));
+            }
+            state.subscribe(topic, message.source());
+            state.save();
+            PubsubResponse::Subscribe
+        }
+        PubsubRequest::Unsubscribe(topic) => {
+            let result = state.unsubscribe(&topic, message.source());
+            state.save();
+            PubsubResponse::Unsubscribe(result)
+        }
+        PubsubRequest::Publish(request) => PubsubResponse::Publish(publish(state, request)),
+        PubsubRequest::ListTopics => PubsubResponse::ListTopics(state.list_topics()),
+    };
+    Response::new().body(response).send()?;
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let mut state = State::load();
+
+    loop {
+        match await_message() {
+            Err(send_error) => handle_send_error(&mut state, send_error),
+            Ok(ref message) => match handle_message(&our, message, &mut state) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use crate::kinode::process::pubsub::{
+    PublishRequest, Request as PubsubRequest, Response as PubsubResponse, TopicError, TopicInfo,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, println, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "pubsub-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+fn expect(
+    address: &Address,
+    request: PubsubRequest,
+    expected: PubsubResponse,
+) -> anyhow::Result<()> {
+    let response = Request::new()
+        .target(address)
+        .body(request)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("pubsub_test");
+    };
+    let response: PubsubResponse = response.body().try_into()?;
+    if response != expected {
+        println!("{response:?} != {expected:?}");
+        fail!("pubsub_test");
+    }
+    Ok(())
+}
+
+/// receive a payload published to `topic` & acknowledge it
+fn expect_delivery(topic: &str, payload: &[u8]) -> anyhow::Result<()> {
+    let message = await_message()?;
+    if !message.is_request() || message.metadata() != Some(topic) || message.body() != payload {
+        println!("unexpected delivery {message:?}");
+        fail!("pubsub_test");
+    }
+    Response::new().body(vec![]).send()?;
+    Ok(())
+}
+
+fn publish(topic: &str, payload: &[u8]) -> PubsubRequest {
+    PubsubRequest::Publish(PublishRequest {
+        topic: topic.to_string(),
+        payload: payload.to_vec(),
+    })
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "pubsub_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_pubsub_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("pubsub"), "pubsub", "template.os"),
+    };
+
+    // nobody is subscribed yet
+    expect(
+        &our_pubsub_address,
+        publish("news", b"early"),
+        PubsubResponse::Publish(Ok(0)),
+    )?;
+    // subscribing twice is subscribing once
+    for _ in 0..2 {
+        expect(
+            &our_pubsub_address,
+            PubsubRequest::Subscribe("news".to_string()),
+            PubsubResponse::Subscribe,
+        )?;
+    }
+    expect(
+        &our_pubsub_address,
+        PubsubRequest::ListTopics,
+        PubsubResponse::ListTopics(vec![TopicInfo {
+            topic: "news".to_string(),
+            subscribers: 1,
+        }]),
+    )?;
+
+    print_to_terminal(0, "pubsub_test: b");
+    expect(
+        &our_pubsub_address,
+        publish("news", b"hello"),
+        PubsubResponse::Publish(Ok(1)),
+    )?;
+    expect_delivery("news", b"hello")?;
+    expect(
+        &our_pubsub_address,
+        publish("", b"hello"),
+        PubsubResponse::Publish(Err(TopicError::EmptyTopic)),
+    )?;
+
+    print_to_terminal(0, "pubsub_test: c");
+    expect(
+        &our_pubsub_address,
+        PubsubRequest::Unsubscribe("news".to_string()),
+        PubsubResponse::Unsubscribe(Ok(())),
+    )?;
+    expect(
+        &our_pubsub_address,
+        PubsubRequest::Unsubscribe("news".to_string()),
+        PubsubResponse::Unsubscribe(Err(TopicError::NotSubscribed("news".to_string()))),
+    )?;
+    expect(
+        &our_pubsub_address,
+        PubsubRequest::ListTopics,
+        PubsubResponse::ListTopics(vec![]),
+    )?;
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("pubsub_test: error: {e:?}").as_str());
+
+                fail!("pubsub_test");
+            }
+        };
+    }
+}
@@ -0,0 +1,441 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::kinode::process::rest_gateway::{
+    GatewayError, Request as GatewayRequest, Resource, Response as GatewayResponse,
+};
+use kinode_process_lib::http::server::{
+    send_response, HttpBindingConfig, HttpServer, HttpServerRequest, IncomingHttpRequest,
+    StatusCode,
+};
+use kinode_process_lib::logging::{error, info, init_logging, Level};
+use kinode_process_lib::{await_message, call_init, get_blob, Address, Message, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "rest-gateway-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+const RESOURCES_PATH: &str = "/resource";
+const RESOURCE_PATH: &str = "/resource/:id";
+const OPENAPI_PATH: &str = "/openapi.json";
+
+/// What a successful response to a route holds
+enum Returns {
+    Nothing,
+    Resource,
+    Resources,
+}
+
+/// A REST endpoint & the `Request` it maps to: the OpenAPI schema served
+/// at `OPENAPI_PATH` is generated from these
+struct Route {
+    method: &'static str,
+    path: &'static str,
+    summary: &'static str,
+    has_body: bool,
+    returns: Returns,
+    /// the status codes the endpoint responds with & what each means
+    responses: &'static [(u16, &'static str)],
+}
+
+const ROUTES: &[Route] = &[
+    Route {
+        method: "GET",
+        path: RESOURCES_PATH,
+        summary: "List resources (ListResources)",
+        has_body: false,
+        returns: Returns::Resources,
+        responses: &[(200, "The resources, by id")],
+    },
+    Route {
+        method: "POST",
+        path: RESOURCES_PATH,
+        summary: "Create a resource (CreateResource)",
+        has_body: true,
+        returns: Returns::Resource,
+        responses: &[
+            (201, "The created resource"),
+            (400, "The body is not JSON"),
+            (415, "The body is not application/json"),
+        ],
+    },
+    Route {
+        method: "GET",
+        path: RESOURCE_PATH,
+        summary: "Get a resource (GetResource)",
+        has_body: false,
+        returns: Returns::Resource,
+        responses: &[(200, "The resource"), (404, "No resource has the id")],
+    },
+    Route {
+        method: "PUT",
+        path: RESOURCE_PATH,
+        summary: "Replace the body of a resource (UpdateResource)",
+        has_body: true,
+        returns: Returns::Resource,
+        responses: &[
+            (200, "The updated resource"),
+            (400, "The body is not JSON"),
+            (404, "No resource has the id"),
+            (415, "The body is not application/json"),
+        ],
+    },
+    Route {
+        method: "DELETE",
+        path: RESOURCE_PATH,
+        summary: "Delete a resource (DeleteResource)",
+        has_body: false,
+        returns: Returns::Nothing,
+        responses: &[
+            (204, "The resource was deleted"),
+            (404, "No resource has the id"),
+        ],
+    },
+];
+
+#[derive(Default)]
+struct State {
+    resources: BTreeMap<String, Resource>,
+    next_id: u64,
+}
+
+/// The response formats the gateway can negotiate with `Accept`
+enum Format {
+    Json,
+    Text,
+}
+
+fn make_http_address(our: &Address) -> Address {
+    Address::from((our.node(), "http-server", "distro", "sys"))
+}
+
+fn make_openapi(our: &Address) -> serde_json::Value {
+    let resource_schema = serde_json::json!({ "$ref": "#/components/schemas/Resource" });
+    let mut paths = serde_json::Map::new();
+    for route in ROUTES {
+        let mut responses = serde_json::Map::new();
+        for (status, description) in route.responses {
+            let mut response = serde_json::json!({ "description": description });
+            let schema = match route.returns {
+                _ if *status >= 300 => None,
+                Returns::Nothing => None,
+                Returns::Resource => Some(resource_schema.clone()),
+                Returns::Resources => {
+                    Some(serde_json::json!({ "type": "array", "items": resource_schema }))
+                }
+            };
+            if let Some(schema) = schema {
+                response["content"] = serde_json::json!({
+                    "application/json": { "schema": schema },
+                    "text/plain": { "schema": { "type": "string" } },
+                });
+            }
+            responses.insert(status.to_string(), response);
+        }
+        responses.insert(
+            "406".to_string(),
+            serde_json::json!({ "description": "Accept is neither application/json nor text/plain" }),
+        );
+
+        let mut operation = serde_json::json!({
+            "summary": route.summary,
+            "responses": responses,
+        });
+        if route.path == RESOURCE_PATH {
+            operation["parameters"] = serde_json::json!([{
+                "name": "id",
+                "in": "path",
+                "required": true,
+                "schema": { "type": "string" },
+            }]);
+        }
+        if route.has_body {
+            operation["requestBody"] = serde_json::json!({
+                "required": true,
+                "content": { "application/json": { "schema": {} } },
+            });
+        }
+        // OpenAPI writes path parameters `{id}` where Kinode binds `:id`
+        let path = route.path.replace(":id", "{id}");
+        paths.entry(path).or_insert_with(|| serde_json::json!({}))[route.method.to_lowercase()] =
+            operation;
+    }
+
+    serde_json::json!({
+        "openapi": "3.0.3",
+        "info": { "title": our.process.to_string(), "version": "0.1.0" },
+        "servers": [{ "url": format!("/{}", our.process) }],
+        "paths": paths,
+        "components": {
+            "schemas": {
+                "Resource": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "body": {},
+                    },
+                    "required": ["id", "body"],
+                },
+            },
+        },
+    })
+}
+
+fn check_body(body: &str) -> Result<(), GatewayError> {
+    serde_json::from_str::<serde_json::Value>(body)
+        .map(|_| ())
+        .map_err(|e| GatewayError::InvalidBody(e.to_string()))
+}
+
+/// Handle a `Request`, whether from a Kinode process or mapped from HTTP
+fn handle_gateway_request(state: &mut State, request: GatewayRequest) -> GatewayResponse {
+    match request {
+        GatewayRequest::ListResources => {
+            GatewayResponse::ListResources(state.resources.values().cloned().collect())
+        }
+        GatewayRequest::GetResource(id) => GatewayResponse::GetResource(
+            state
+                .resources
+                .get(&id)
+                .cloned()
+                .ok_or(GatewayError::NotFound(id)),
+        ),
+        GatewayRequest::CreateResource(body) => {
+            GatewayResponse::CreateResource(check_body(&body).map(|()| {
+                let id = state.next_id.to_string();
+                state.next_id += 1;
+                let resource = Resource { id, body };
+                state
+                    .resources
+                    .insert(resource.id.clone(), resource.clone());
+                resource
+            }))
+        }
+        GatewayRequest::UpdateResource(resource) => {
+            GatewayResponse::UpdateResource(check_body(&resource.body).and_then(|()| {
+                match state.resources.get_mut(&resource.id) {
+                    None => Err(GatewayError::NotFound(resource.id)),
+                    Some(existing) => {
+                        *existing = resource.clone();
+                        Ok(resource)
+                    }
+                }
+            }))
+        }
+        GatewayRequest::DeleteResource(id) => GatewayResponse::DeleteResource(
+            state
+                .resources
+                .remove(&id)
+                .map(|_| ())
+                .ok_or(GatewayError::NotFound(id)),
+        ),
+    }
+}
+
+/// The format the `Accept` header asks for first, ignoring quality values;
+/// JSON if there is no `Accept`, `None` if no format it asks for is served
+fn negotiate_format(request: &IncomingHttpRequest) -> Option<Format> {
+    let headers = request.headers();
+    let Some(accept) = headers.get("accept").and_then(|a| a.to_str().ok()) else {
+        return Some(Format::Json);
+    };
+    for media_type in accept.split(',') {
+        match media_type.split(';').next().unwrap_or_default().trim() {
+            "application/json" | "application/*" | "*/*" => return Some(Format::Json),
+            "text/plain" | "text/*" => return Some(Format::Text),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn read_json_body(request: &IncomingHttpRequest) -> Result<String, (StatusCode, String)> {
+    let headers = request.headers();
+    let content_type = headers
+        .get("content-type")
+        .and_then(|c| c.to_str().ok())
+        .unwrap_or_default();
+    if !content_type.starts_with("application/json") {
+        return Err((
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Content-Type must be application/json".to_string(),
+        ));
+    }
+    let body = get_blob().map(|blob| blob.bytes).unwrap_or_default();
+    String::from_utf8(body).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))
+}
+
+/// Map an HTTP request to the `Request` of its route
+fn to_gateway_request(
+    request: &IncomingHttpRequest,
+) -> Result<GatewayRequest, (StatusCode, String)> {
+    let method = request
+        .method()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    match (method.as_str(), request.url_params().get("id").cloned()) {
+        ("GET", None) => Ok(GatewayRequest::ListResources),
+        ("POST", None) => Ok(GatewayRequest::CreateResource(read_json_body(request)?)),
+        ("GET", Some(id)) => Ok(GatewayRequest::GetResource(id)),
+        ("PUT", Some(id)) => Ok(GatewayRequest::UpdateResource(Resource {
+            id,
+            body: read_json_body(request)?,
+        })),
+        ("DELETE", Some(id)) => Ok(GatewayRequest::DeleteResource(id)),
+        _ => Err((
+            StatusCode::METHOD_NOT_ALLOWED,
+            format!("{method} is not allowed"),
+        )),
+    }
+}
+
+/// Map a process-level error to its HTTP status
+fn to_status(error: GatewayError) -> (StatusCode, String) {
+    match error {
+        GatewayError::NotFound(id) => (StatusCode::NOT_FOUND, format!("no resource has id {id}")),
+        GatewayError::InvalidBody(e) => (StatusCode::BAD_REQUEST, format!("body is not JSON: {e}")),
+    }
+}
+
+fn send_formatted(status: StatusCode, format: &Format, json: serde_json::Value, text: String) {
+    let (content_type, body) = match format {
+        Format::Json => ("application/json", serde_json::to_vec(&json).unwrap()),
+        Format::Text => ("text/plain", text.into_bytes()),
+    };
+    let headers = HashMap::from([("Content-Type".to_string(), content_type.to_string())]);
+    send_response(status, Some(headers), body);
+}
+
+fn send_error(status: StatusCode, message: String, format: &Format) {
+    send_formatted(
+        status,
+        format,
+        serde_json::json!({ "error": message }),
+        message,
+    );
+}
+
+/// A resource as JSON, its body embedded as JSON rather than as a string
+fn resource_to_json(resource: &Resource) -> serde_json::Value {
+    serde_json::json!({
+        "id": resource.id,
+        "body": serde_json::from_str::<serde_json::Value>(&resource.body).unwrap_or_default(),
+    })
+}
+
+fn resource_to_text(resource: &Resource) -> String {
+    format!("{}: {}\n", resource.id, resource.body)
+}
+
+fn send_resource(status: StatusCode, resource: &Resource, format: &Format) {
+    send_formatted(
+        status,
+        format,
+        resource_to_json(resource),
+        resource_to_text(resource),
+    );
+}
+
+fn send_gateway_response(response: GatewayResponse, format: &Format) {
+    match response {
+        GatewayResponse::ListResources(resources) => send_formatted(
+            StatusCode::OK,
+            format,
+            resources.iter().map(resource_to_json).collect(),
+            resources.iter().map(resource_to_text).collect(),
+        ),
+        GatewayResponse::GetResource(Ok(resource))
+        | GatewayResponse::UpdateResource(Ok(resource)) => {
+            send_resource(StatusCode::OK, &resource, format)
+        }
+        GatewayResponse::CreateResource(Ok(resource)) => {
+            send_resource(StatusCode::CREATED, &resource, format)
+        }
+        GatewayResponse::DeleteResource(Ok(())) => {
+            send_response(StatusCode::NO_CONTENT, None, vec![])
+        }
+        GatewayResponse::GetResource(Err(e))
+        | GatewayResponse::CreateResource(Err(e))
+        | GatewayResponse::UpdateResource(Err(e))
+        | GatewayResponse::DeleteResource(Err(e)) => {
+            let (status, message) = to_status(e);
+            send_error(status, message, format);
+        }
+    }
+}
+
+fn handle_http_request(our: &Address, state: &mut State, request: &IncomingHttpRequest) {
+    if request.bound_path(Some(&our.process.to_string())) == OPENAPI_PATH {
+        send_formatted(
+            StatusCode::OK,
+            &Format::Json,
+            make_openapi(our),
+            String::new(),
+        );
+        return;
+    }
+    let Some(format) = negotiate_format(request) else {
+        send_response(StatusCode::NOT_ACCEPTABLE, None, vec![]);
+        return;
+    };
+    match to_gateway_request(request) {
+        Err((status, message)) => send_error(status, message, &format),
+        Ok(request) => send_gateway_response(handle_gateway_request(state, request), &format),
+    }
+}
+
+fn handle_message(our: &Address, message: &Message, state: &mut State) -> anyhow::Result<()> {
+    if !message.is_request() {
+        return Err(anyhow::anyhow!("unexpected Response: {:?}", message));
+    }
+
+    if message.source() == &make_http_address(our) {
+        match serde_json::from_slice::<HttpServerRequest>(message.body())? {
+            HttpServerRequest::Http(ref request) => handle_http_request(our, state, request),
+            // only HTTP paths are bound: no WebSocket messages to handle
+            request => info!("ignoring {request:?}"),
+        }
+        return Ok(());
+    }
+    if message.source().node != our.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            message.source(),
+        ));
+    }
+
+    let response = handle_gateway_request(state, message.body().try_into()?);
+    Response::new().body(response).send()?;
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let mut state = State::default();
+    let mut server = HttpServer::new(5);
+
+    // unauthenticated so that the API can be called without logging in,
+    //  e.g. with `curl`, but local only so that it can't be from elsewhere
+    let config = HttpBindingConfig::default()
+        .authenticated(false)
+        .local_only(true);
+    for path in [RESOURCES_PATH, RESOURCE_PATH, OPENAPI_PATH] {
+        server
+            .bind_http_path(path, config.clone())
+            .expect("failed to bind API");
+    }
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(&our, message, &mut state) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use crate::kinode::process::rest_gateway::{
+    GatewayError, Request as GatewayRequest, Resource, Response as GatewayResponse,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init,
+    http::{client::send_request_await_response, Method, StatusCode},
+    print_to_terminal, println, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "rest-gateway-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// the `port` of the node in `tests.toml`
+const PORT: u16 = 8080;
+const API_URL: &str = "rest-gateway:rest-gateway:template.os";
+
+fn resource(id: &str, body: &str) -> Resource {
+    Resource {
+        id: id.to_string(),
+        body: body.to_string(),
+    }
+}
+
+fn expect(
+    address: &Address,
+    request: GatewayRequest,
+    expected: GatewayResponse,
+) -> anyhow::Result<()> {
+    let response = Request::new()
+        .target(address)
+        .body(request)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("rest_gateway_test");
+    };
+    let response: GatewayResponse = response.body().try_into()?;
+    if response != expected {
+        println!("{response:?} != {expected:?}");
+        fail!("rest_gateway_test");
+    }
+    Ok(())
+}
+
+fn expect_http(
+    method: Method,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+    expected: StatusCode,
+) -> anyhow::Result<()> {
+    let url = url::Url::parse(&format!("http://localhost:{PORT}/{API_URL}{path}"))?;
+    let headers: HashMap<String, String> = headers
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let response =
+        send_request_await_response(method, url, Some(headers), 5, body.as_bytes().to_vec())?;
+    if response.status() != expected {
+        println!("{path}: status {} != {expected}", response.status());
+        fail!("rest_gateway_test");
+    }
+    Ok(())
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "rest_gateway_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_rest_gateway_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("rest-gateway"), "rest-gateway", "template.os"),
+    };
+
+    // the Kinode API
+    expect(
+        &our_rest_gateway_address,
+        GatewayRequest::CreateResource(r#"{"color": "red"}"#.to_string()),
+        GatewayResponse::CreateResource(Ok(resource("0", r#"{"color": "red"}"#))),
+    )?;
+    expect(
+        &our_rest_gateway_address,
+        GatewayRequest::UpdateResource(resource("0", r#"{"color": "green"}"#)),
+        GatewayResponse::UpdateResource(Ok(resource("0", r#"{"color": "green"}"#))),
+    )?;
+    expect(
+        &our_rest_gateway_address,
+        GatewayRequest::CreateResource("not json".to_string()),
+        GatewayResponse::CreateResource(Err(GatewayError::InvalidBody(
+            "expected ident at line 1 column 2".to_string(),
+        ))),
+    )?;
+    expect(
+        &our_rest_gateway_address,
+        GatewayRequest::ListResources,
+        GatewayResponse::ListResources(vec![resource("0", r#"{"color": "green"}"#)]),
+    )?;
+
+    // the same API over REST
+    print_to_terminal(0, "rest_gateway_test: b");
+    let json = [("Content-Type", "application/json")];
+    expect_http(Method::GET, "/resource/0", &[], "", StatusCode::OK)?;
+    expect_http(Method::GET, "/resource/1", &[], "", StatusCode::NOT_FOUND)?;
+    expect_http(
+        Method::POST,
+        "/resource",
+        &json,
+        "[1, 2]",
+        StatusCode::CREATED,
+    )?;
+    expect_http(
+        Method::POST,
+        "/resource",
+        &json,
+        "[1, 2",
+        StatusCode::BAD_REQUEST,
+    )?;
+    expect_http(
+        Method::POST,
+        "/resource",
+        &[("Content-Type", "text/plain")],
+        "[1, 2]",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+    )?;
+    expect_http(
+        Method::GET,
+        "/resource",
+        &[("Accept", "text/plain")],
+        "",
+        StatusCode::OK,
+    )?;
+    expect_http(
+        Method::GET,
+        "/resource",
+        &[("Accept", "image/png")],
+        "",
+        StatusCode::NOT_ACCEPTABLE,
+    )?;
+    expect_http(
+        Method::DELETE,
+        "/resource/1",
+        &[],
+        "",
+        StatusCode::NO_CONTENT,
+    )?;
+    expect_http(Method::GET, "/openapi.json", &[], "", StatusCode::OK)?;
+
+    expect(
+        &our_rest_gateway_address,
+        GatewayRequest::GetResource("1".to_string()),
+        GatewayResponse::GetResource(Err(GatewayError::NotFound("1".to_string()))),
+    )?;
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("rest_gateway_test: error: {e:?}").as_str());
+
+                fail!("rest_gateway_test");
+            }
+        };
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use crate::kinode::process::saga::{
+    Order, OrderRecord, OrderStatus, Request as SagaRequest, Response as SagaResponse, Step,
+    StepLogEntry, StepStatus,
+};
+use kinode_process_lib::logging::{error, info, init_logging, Level};
+use kinode_process_lib::{await_message, call_init, Address, Message, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "saga-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+const STEPS: [Step; 4] = [
+    Step::ReserveFunds,
+    Step::ChargePayment,
+    Step::UpdateInventory,
+    Step::ConfirmOrder,
+];
+const INITIAL_BALANCE: u64 = 1_000;
+const INITIAL_STOCK: u32 = 10;
+
+/// The services the steps act on. Here they are in-process for simplicity;
+/// in a real app each step would typically be a Request to another process
+/// (or node), and each compensation a Request undoing it
+#[derive(Default)]
+struct Services {
+    balances: HashMap<String, u64>,
+    reserved: HashMap<u64, u64>,
+    charged: HashMap<u64, u64>,
+    stock: HashMap<String, u32>,
+    confirmed: Vec<u64>,
+}
+
+impl Services {
+    fn balance(&mut self, customer: &str) -> &mut u64 {
+        self.balances
+            .entry(customer.to_string())
+            .or_insert(INITIAL_BALANCE)
+    }
+
+    fn stock(&mut self, item: &str) -> &mut u32 {
+        self.stock.entry(item.to_string()).or_insert(INITIAL_STOCK)
+    }
+
+    /// Run `step` for order `id`
+    fn execute(&mut self, step: &Step, id: u64, order: &Order) -> anyhow::Result<String> {
+        match step {
+            Step::ReserveFunds => {
+                let balance = self.balance(&order.customer);
+                if *balance < order.amount {
+                    return Err(anyhow::anyhow!(
+                        "insufficient funds: {} < {}",
+                        balance,
+                        order.amount
+                    ));
+                }
+                *balance -= order.amount;
+                self.reserved.insert(id, order.amount);
+                Ok(format!("reserved {}", order.amount))
+            }
+            Step::ChargePayment => {
+                let Some(amount) = self.reserved.remove(&id) else {
+                    return Err(anyhow::anyhow!("no funds reserved"));
+                };
+                self.charged.insert(id, amount);
+                Ok(format!("charged {amount}"))
+            }
+            Step::UpdateInventory => {
+                let stock = self.stock(&order.item);
+                if *stock < order.quantity {
+                    return Err(anyhow::anyhow!(
+                        "insufficient stock of {}: {} < {}",
+                        order.item,
+                        stock,
+                        order.quantity
+                    ));
+                }
+                *stock -= order.quantity;
+                Ok(format!("took {} {}", order.quantity, order.item))
+            }
+            Step::ConfirmOrder => {
+                self.confirmed.push(id);
+                Ok(format!("confirmed order {id}"))
+            }
+        }
+    }
+
+    /// Undo a completed `step` of order `id`
+    fn compensate(&mut self, step: &Step, id: u64, order: &Order) -> String {
+        match step {
+            Step::ReserveFunds => {
+                // funds still reserved were not charged: release them
+                if let Some(amount) = self.reserved.remove(&id) {
+                    *self.balance(&order.customer) += amount;
+                }
+                "released reserved funds".to_string()
+            }
+            Step::ChargePayment => {
+                // refund the charge to the customer
+                if let Some(amount) = self.charged.remove(&id) {
+                    *self.balance(&order.customer) += amount;
+                    return format!("refunded {amount}");
+                }
+                "nothing to refund".to_string()
+            }
+            Step::UpdateInventory => {
+                *self.stock(&order.item) += order.quantity;
+                format!("restocked {} {}", order.quantity, order.item)
+            }
+            Step::ConfirmOrder => {
+                self.confirmed.retain(|confirmed| *confirmed != id);
+                format!("cancelled order {id}")
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Coordinator {
+    services: Services,
+    orders: HashMap<u64, OrderRecord>,
+    next_id: u64,
+    fail_at: Option<Step>,
+}
+
+impl Coordinator {
+    /// Run each step in turn; on failure, compensate the completed steps
+    /// in reverse order so the services end as they began
+    fn place_order(&mut self, order: Order) -> OrderRecord {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut log = vec![];
+        let mut completed = vec![];
+        let mut failed = false;
+        for step in STEPS {
+            let result = if self.fail_at.as_ref() == Some(&step) {
+                Err(anyhow::anyhow!("injected failure"))
+            } else {
+                self.services.execute(&step, id, &order)
+            };
+            match result {
+                Ok(detail) => {
+                    log.push(StepLogEntry {
+                        step: step.clone(),
+                        status: StepStatus::Completed,
+                        detail,
+                    });
+                    completed.push(step);
+                }
+                Err(e) => {
+                    info!("order {id}: {step:?} failed: {e}; rolling back");
+                    log.push(StepLogEntry {
+                        step,
+                        status: StepStatus::Failed,
+                        detail: e.to_string(),
+                    });
+                    failed = true;
+                    break;
+                }
+            }
+        }
+
+        if failed {
+            for step in completed.into_iter().rev() {
+                let detail = self.services.compensate(&step, id, &order);
+                log.push(StepLogEntry {
+                    step,
+                    status: StepStatus::Compensated,
+                    detail,
+                });
+            }
+        }
+
+        let record = OrderRecord {
+            id,
+            order,
+            status: if failed {
+                OrderStatus::RolledBack
+            } else {
+                OrderStatus::Confirmed
+            },
+            log,
+        };
+        info!("order {id}: {:?}", record.status);
+        self.orders.insert(id, record.clone());
+        record
+    }
+
+    fn handle_request(&mut self, request: SagaRequest) -> SagaResponse {
+        match request {
+            SagaRequest::PlaceOrder(order) => SagaResponse::PlaceOrder(self.place_order(order)),
+            SagaRequest::GetOrder(id) => SagaResponse::GetOrder(self.orders.get(&id).cloned()),
+            SagaRequest::GetBalance(customer) => {
+                SagaResponse::GetBalance(*self.services.balance(&customer))
+            }
+            SagaRequest::GetStock(item) => SagaResponse::GetStock(*self.services.stock(&item)),
+            SagaRequest::InjectFailure(step) => {
+                self.fail_at = step;
+                SagaResponse::InjectFailure
+            }
+        }
+    }
+}
+
+fn handle_message(message: &Message, coordinator: &mut Coordinator) -> anyhow::Result<()> {
+    if !message.is_request() {
+        return Ok(());
+    }
+    let response = coordinator.handle_request(message.body().try_into()?);
+    Response::new().body(response).send()?;
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let mut coordinator = Coordinator::default();
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(message, &mut coordinator) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use crate::kinode::process::saga::{
+    Order, OrderRecord, OrderStatus, Request as SagaRequest, Response as SagaResponse, Step,
+    StepStatus,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, println, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "saga-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+fn send(address: &Address, request: SagaRequest) -> anyhow::Result<SagaResponse> {
+    let response = Request::new()
+        .target(address)
+        .body(request)
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("saga_test");
+    };
+    Ok(response.body().try_into()?)
+}
+
+fn expect(address: &Address, request: SagaRequest, expected: SagaResponse) -> anyhow::Result<()> {
+    let response = send(address, request)?;
+    if response != expected {
+        println!("{response:?} != {expected:?}");
+        fail!("saga_test");
+    }
+    Ok(())
+}
+
+fn place_order(address: &Address, amount: u64) -> anyhow::Result<OrderRecord> {
+    let SagaResponse::PlaceOrder(record) = send(
+        address,
+        SagaRequest::PlaceOrder(Order {
+            customer: "alice".to_string(),
+            item: "widget".to_string(),
+            quantity: 2,
+            amount,
+        }),
+    )?
+    else {
+        fail!("saga_test");
+    };
+    Ok(record)
+}
+
+/// The (step, status) log of an order
+fn log_of(record: &OrderRecord) -> Vec<(Step, StepStatus)> {
+    record
+        .log
+        .iter()
+        .map(|entry| (entry.step, entry.status))
+        .collect()
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "saga_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_saga_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("saga"), "saga", "template.os"),
+    };
+
+    // every step completes
+    let record = place_order(&our_saga_address, 100)?;
+    if record.status != OrderStatus::Confirmed
+        || log_of(&record)
+            != vec![
+                (Step::ReserveFunds, StepStatus::Completed),
+                (Step::ChargePayment, StepStatus::Completed),
+                (Step::UpdateInventory, StepStatus::Completed),
+                (Step::ConfirmOrder, StepStatus::Completed),
+            ]
+    {
+        println!("{record:?}");
+        fail!("saga_test");
+    }
+    expect(
+        &our_saga_address,
+        SagaRequest::GetBalance("alice".to_string()),
+        SagaResponse::GetBalance(900),
+    )?;
+    expect(
+        &our_saga_address,
+        SagaRequest::GetStock("widget".to_string()),
+        SagaResponse::GetStock(8),
+    )?;
+
+    // a late failure compensates the completed steps, most recent first
+    print_to_terminal(0, "saga_test: b");
+    expect(
+        &our_saga_address,
+        SagaRequest::InjectFailure(Some(Step::ConfirmOrder)),
+        SagaResponse::InjectFailure,
+    )?;
+    let record = place_order(&our_saga_address, 100)?;
+    if record.status != OrderStatus::RolledBack
+        || log_of(&record)
+            != vec![
+                (Step::ReserveFunds, StepStatus::Completed),
+                (Step::ChargePayment, StepStatus::Completed),
+                (Step::UpdateInventory, StepStatus::Completed),
+                (Step::ConfirmOrder, StepStatus::Failed),
+                (Step::UpdateInventory, StepStatus::Compensated),
+                (Step::ChargePayment, StepStatus::Compensated),
+                (Step::ReserveFunds, StepStatus::Compensated),
+            ]
+    {
+        println!("{record:?}");
+        fail!("saga_test");
+    }
+    // ... leaving balance & stock as they were
+    expect(
+        &our_saga_address,
+        SagaRequest::GetBalance("alice".to_string()),
+        SagaResponse::GetBalance(900),
+    )?;
+    expect(
+        &our_saga_address,
+        SagaRequest::GetStock("widget".to_string()),
+        SagaResponse::GetStock(8),
+    )?;
+    expect(
+        &our_saga_address,
+        SagaRequest::InjectFailure(None),
+        SagaResponse::InjectFailure,
+    )?;
+
+    // a failure in the first step has nothing to compensate
+    print_to_terminal(0, "saga_test: c");
+    let record = place_order(&our_saga_address, 10_000)?;
+    if record.status != OrderStatus::RolledBack
+        || log_of(&record) != vec![(Step::ReserveFunds, StepStatus::Failed)]
+    {
+        println!("{record:?}");
+        fail!("saga_test");
+    }
+    expect(
+        &our_saga_address,
+        SagaRequest::GetOrder(record.id),
+        SagaResponse::GetOrder(Some(record)),
+    )?;
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("saga_test: error: {e:?}").as_str());
+
+                fail!("saga_test");
+            }
+        };
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use crate::kinode::process::stream_pipeline::{
+    DataChunk, DataComplete, Request as StreamRequest, Response as StreamResponse,
+};
+use kinode_process_lib::logging::{error, info, init_logging, warn, Level};
+use kinode_process_lib::vfs::{create_drive, open_file, remove_file};
+use kinode_process_lib::{await_message, call_init, get_blob, timer, Address, Message, Response};
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "stream-pipeline-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+/// streams accepted at once: new streams beyond this get `Busy`
+const MAX_ACTIVE_STREAMS: usize = 4;
+const BUSY_RETRY_MS: u64 = 500;
+const MAX_CHUNK_BYTES: usize = 1024 * 1024;
+/// buffered bytes of a stream are flushed to VFS & checkpointed past this size
+const FLUSH_BYTES: usize = 64 * 1024;
+/// streams that receive no chunk for this long are dropped
+const STREAM_TIMEOUT_MS: u64 = 30_000;
+const SWEEP_INTERVAL_MS: u64 = 10_000;
+const SWEEP_CONTEXT: &[u8] = b"sweep";
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// progress persisted to VFS so that streams survive a restart:
+/// a restarted process asks senders to `Resume` from `persisted_chunks`
+#[derive(Debug, Default, serde::Deserialize, serde::Serialize)]
+struct Checkpoint {
+    streams: HashMap<String, StreamCheckpoint>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct StreamCheckpoint {
+    total_chunks: u32,
+    persisted_chunks: u32,
+    persisted_bytes: u64,
+}
+
+/// chunks received but not yet flushed to VFS
+#[derive(Debug)]
+struct Buffer {
+    bytes: Vec<u8>,
+    chunks: u32,
+    last_activity_ms: u64,
+}
+
+impl Buffer {
+    fn new() -> Self {
+        Buffer {
+            bytes: Vec::new(),
+            chunks: 0,
+            last_activity_ms: now_ms(),
+        }
+    }
+}
+
+struct Pipeline {
+    drive: String,
+    checkpoint: Checkpoint,
+    buffers: HashMap<String, Buffer>,
+}
+
+fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// FNV-1a: a simple stand-in for real processing of the dataset
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl Pipeline {
+    fn load(drive: String) -> anyhow::Result<Self> {
+        let checkpoint_file = open_file(&format!("{drive}/{CHECKPOINT_FILE}"), true, None)?;
+        let checkpoint: Checkpoint = match checkpoint_file.read()?.as_slice() {
+            [] => Checkpoint::default(),
+            bytes => serde_json::from_slice(bytes)?,
+        };
+        if !checkpoint.streams.is_empty() {
+            info!(
+                "restored {} partial stream(s) from checkpoint",
+                checkpoint.streams.len()
+            );
+        }
+        // restored streams time out like any other if their sender is gone
+        let buffers = checkpoint
+            .streams
+            .keys()
+            .map(|stream_id| (stream_id.clone(), Buffer::new()))
+            .collect();
+        Ok(Pipeline {
+            drive,
+            checkpoint,
+            buffers,
+        })
+    }
+
+    fn save_checkpoint(&self) -> anyhow::Result<()> {
+        let checkpoint_file = open_file(&format!("{}/{CHECKPOINT_FILE}", self.drive), true, None)?;
+        checkpoint_file.write(&serde_json::to_vec(&self.checkpoint)?)?;
+        Ok(())
+    }
+
+    fn part_path(&self, stream_id: &str) -> String {
+        format!("{}/{stream_id}.part", self.drive)
+    }
+
+    fn flush(&mut self, stream_id: &str) -> anyhow::Result<()> {
+        let Some(buffer) = self.buffers.get_mut(stream_id) else {
+            return Ok(());
+        };
+        let Some(stream) = self.checkpoint.streams.get_mut(stream_id) else {
+            return Ok(());
+        };
+        if buffer.chunks == 0 {
+            return Ok(());
+        }
+        let mut part_file = open_file(&format!("{}/{stream_id}.part", self.drive), true, None)?;
+        part_file.append(&buffer.bytes)?;
+        stream.persisted_chunks += buffer.chunks;
+        stream.persisted_bytes += buffer.bytes.len() as u64;
+        buffer.bytes.clear();
+        buffer.chunks = 0;
+        self.save_checkpoint()
+    }
+
+    fn remove_stream(&mut self, stream_id: &str) -> anyhow::Result<()> {
+        self.buffers.remove(stream_id);
+        if self.checkpoint.streams.remove(stream_id).is_some() {
+            let _ = remove_file(&self.part_path(stream_id), None);
+            self.save_checkpoint()?;
+        }
+        Ok(())
+    }
+
+    fn handle_chunk(&mut self, chunk: &DataChunk) -> anyhow::Result<StreamResponse> {
+        let DataChunk {
+            ref stream_id,
+            index,
+            total_chunks,
+        } = chunk;
+        if stream_id.is_empty()
+            || !stream_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow::anyhow!(
+                "stream-id must be non-empty and contain only a-z, A-Z, 0-9, -, _"
+            ));
+        }
+
+        if !self.checkpoint.streams.contains_key(stream_id) {
+            // backpressure: ask the sender to come back later
+            if self.checkpoint.streams.len() >= MAX_ACTIVE_STREAMS {
+                return Ok(StreamResponse::Busy(BUSY_RETRY_MS));
+            }
+            if *index != 0 {
+                // e.g. the stream timed out: start over
+                return Ok(StreamResponse::Resume(0));
+            }
+            open_file(&self.part_path(stream_id), true, None)?.write(&[])?;
+            self.checkpoint.streams.insert(
+                stream_id.clone(),
+                StreamCheckpoint {
+                    total_chunks: *total_chunks,
+                    persisted_chunks: 0,
+                    persisted_bytes: 0,
+                },
+            );
+            self.save_checkpoint()?;
+        }
+
+        let stream = &self.checkpoint.streams[stream_id];
+        let buffer = self
+            .buffers
+            .entry(stream_id.clone())
+            .or_insert_with(Buffer::new);
+        let expected_index = stream.persisted_chunks + buffer.chunks;
+        if *index != expected_index {
+            // e.g. we restarted and lost unflushed chunks
+            return Ok(StreamResponse::Resume(expected_index));
+        }
+
+        let Some(blob) = get_blob() else {
+            return Err(anyhow::anyhow!("data-chunk must carry its bytes in a blob"));
+        };
+        if blob.bytes.len() > MAX_CHUNK_BYTES {
+            return Err(anyhow::anyhow!(
+                "chunk of {} bytes exceeds maximum of {MAX_CHUNK_BYTES}",
+                blob.bytes.len()
+            ));
+        }
+        buffer.bytes.extend_from_slice(&blob.bytes);
+        buffer.chunks += 1;
+        buffer.last_activity_ms = now_ms();
+
+        let is_last_chunk = index + 1 >= stream.total_chunks;
+        let is_buffer_full = buffer.bytes.len() >= FLUSH_BYTES;
+        if is_last_chunk {
+            self.flush(stream_id)?;
+            let data = open_file(&self.part_path(stream_id), false, None)?.read()?;
+            let complete = DataComplete {
+                stream_id: stream_id.clone(),
+                total_bytes: data.len() as u64,
+                line_count: data.iter().filter(|b| **b == b'\n').count() as u64,
+                checksum: fnv1a(&data),
+            };
+            info!(
+                "stream {stream_id} complete: {} bytes",
+                complete.total_bytes
+            );
+            self.remove_stream(stream_id)?;
+            return Ok(StreamResponse::DataComplete(complete));
+        }
+        if is_buffer_full {
+            self.flush(stream_id)?;
+        }
+        Ok(StreamResponse::Ready)
+    }
+
+    fn sweep_timed_out(&mut self) -> anyhow::Result<()> {
+        let now = now_ms();
+        let timed_out: Vec<String> = self
+            .buffers
+            .iter()
+            .filter(|(_, buffer)| now.saturating_sub(buffer.last_activity_ms) > STREAM_TIMEOUT_MS)
+            .map(|(stream_id, _)| stream_id.clone())
+            .collect();
+        for stream_id in timed_out {
+            warn!("stream {stream_id} timed out: dropping partial data");
+            self.remove_stream(&stream_id)?;
+        }
+        Ok(())
+    }
+}
+
+fn handle_message(message: &Message, pipeline: &mut Pipeline) -> anyhow::Result<()> {
+    if !message.is_request() {
+        if message.source().process == "timer:distro:sys"
+            && message.context() == Some(SWEEP_CONTEXT)
+        {
+            pipeline.sweep_timed_out()?;
+            timer::set_timer(SWEEP_INTERVAL_MS, Some(SWEEP_CONTEXT.to_vec()));
+        }
+        return Ok(());
+    }
+
+    match message.body().try_into()? {
+        StreamRequest::DataChunk(ref chunk) => {
+            let response = pipeline
+                .handle_chunk(chunk)
+                .unwrap_or_else(|e| StreamResponse::Err(e.to_string()));
+            Response::new().body(response).send()?;
+        }
+    }
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    init_logging(&our, Level::DEBUG, Level::INFO, None, None).unwrap();
+    info!("begin");
+
+    let drive = create_drive(our.package_id(), "streams", None).unwrap();
+    let mut pipeline = Pipeline::load(drive).unwrap();
+    timer::set_timer(SWEEP_INTERVAL_MS, Some(SWEEP_CONTEXT.to_vec()));
+
+    loop {
+        match await_message() {
+            Err(send_error) => error!("got SendError: {send_error}"),
+            Ok(ref message) => match handle_message(message, &mut pipeline) {
+                Ok(_) => {}
+                Err(e) => error!("got error while handling message: {e:?}"),
+            },
+        }
+    }
+}
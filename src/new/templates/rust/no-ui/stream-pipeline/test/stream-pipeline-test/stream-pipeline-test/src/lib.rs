@@ -0,0 +1,131 @@
+use crate::kinode::process::stream_pipeline::{
+    DataChunk, DataComplete, Request as StreamRequest, Response as StreamResponse,
+};
+use crate::kinode::process::tester::{
+    FailResponse, Request as TesterRequest, Response as TesterResponse, RunRequest,
+};
+
+use kinode_process_lib::{
+    await_message, call_init, print_to_terminal, Address, ProcessId, Request, Response,
+};
+
+mod tester_lib;
+
+wit_bindgen::generate!({
+    path: "target/wit",
+    world: "stream-pipeline-test-template-dot-os-v0",
+    generate_unused_types: true,
+    additional_derives: [PartialEq, serde::Deserialize, serde::Serialize, process_macros::SerdeJsonInto],
+});
+
+fn send_chunk(
+    address: &Address,
+    stream_id: &str,
+    index: u32,
+    total_chunks: u32,
+    bytes: &[u8],
+) -> anyhow::Result<StreamResponse> {
+    let response = Request::new()
+        .target(address)
+        .body(StreamRequest::DataChunk(DataChunk {
+            stream_id: stream_id.to_string(),
+            index,
+            total_chunks,
+        }))
+        .blob_bytes(bytes.to_vec())
+        .send_and_await_response(15)?
+        .unwrap();
+    if response.is_request() {
+        fail!("stream_pipeline_test");
+    };
+    Ok(response.body().try_into()?)
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn handle_message(our: &Address) -> anyhow::Result<()> {
+    let message = await_message().unwrap();
+
+    if !message.is_request() {
+        unimplemented!();
+    }
+    let source = message.source();
+    if our.node != source.node {
+        return Err(anyhow::anyhow!(
+            "rejecting foreign Message from {:?}",
+            source,
+        ));
+    }
+    let TesterRequest::Run(RunRequest {
+        input_node_names: node_names,
+        ..
+    }) = message.body().try_into()?;
+    print_to_terminal(0, "stream_pipeline_test: a");
+    assert!(node_names.len() == 1);
+
+    let our_stream_address = Address {
+        node: our.node.clone(),
+        process: ProcessId::new(Some("stream-pipeline"), "stream-pipeline", "template.os"),
+    };
+
+    // a chunk out of order is answered with the index to resume from
+    let response = send_chunk(&our_stream_address, "out-of-order", 1, 2, b"a\n")?;
+    if response != StreamResponse::Resume(0) {
+        fail!("stream_pipeline_test");
+    }
+
+    // a full stream is acknowledged chunk-by-chunk, then summarized
+    let chunks: Vec<&[u8]> = vec![b"hello\n", b"stream\n", b"pipeline\n"];
+    let total_chunks = chunks.len() as u32;
+    for (index, chunk) in chunks.iter().enumerate() {
+        let response = send_chunk(
+            &our_stream_address,
+            "test",
+            index as u32,
+            total_chunks,
+            chunk,
+        )?;
+        let is_last_chunk = index as u32 + 1 == total_chunks;
+        if is_last_chunk {
+            let data = chunks.concat();
+            let expected = StreamResponse::DataComplete(DataComplete {
+                stream_id: "test".to_string(),
+                total_bytes: data.len() as u64,
+                line_count: 3,
+                checksum: fnv1a(&data),
+            });
+            if response != expected {
+                fail!("stream_pipeline_test");
+            }
+        } else if response != StreamResponse::Ready {
+            fail!("stream_pipeline_test");
+        }
+    }
+
+    Response::new()
+        .body(TesterResponse::Run(Ok(())))
+        .send()
+        .unwrap();
+
+    Ok(())
+}
+
+call_init!(init);
+fn init(our: Address) {
+    print_to_terminal(0, "begin");
+
+    loop {
+        match handle_message(&our) {
+            Ok(()) => {}
+            Err(e) => {
+                print_to_terminal(0, format!("stream_pipeline_test: error: {e:?}").as_str());
+
+                fail!("stream_pipeline_test");
+            }
+        };
+    }
+}
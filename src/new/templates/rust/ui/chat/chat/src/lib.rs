@@ -22,6 +22,13 @@ wit_bindgen::generate!({
 
 const HTTP_API_PATH: &str = "/messages";
 const WS_PATH: &str = "/";
+// kit-channel-type: mixed
+
+/// Await the counterparty's acknowledgement of each message (sync) when the
+/// sender must know it was delivered; send without waiting (async) when not
+/// blocking other Requests matters more than confirmation
+const AWAIT_DELIVERY: bool = true;
+// kit-channel-type: end
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, process_macros::SerdeJsonInto)]
 struct NewMessage {
@@ -114,6 +121,23 @@ fn handle_http_server_request(
     Ok(())
 }
 
+/// Forward a chat message to the counterparty's chat process: await its
+/// acknowledgement when the sender must know the message was delivered;
+/// otherwise return at once so other Requests are not held up, the
+/// acknowledgement arriving later as a Response, a failure to deliver as a
+/// SendError
+fn send_to_counterparty(target: &str, body: &[u8], await_delivery: bool) -> anyhow::Result<()> {
+    let request = Request::new()
+        .target((target, "chat", "chat", "template.os"))
+        .body(body);
+    if await_delivery {
+        request.send_and_await_response(5)??;
+    } else {
+        request.expects_response(5).send()?;
+    }
+    Ok(())
+}
+
 fn handle_chat_request(
     our: &Address,
     source: &Address,
@@ -138,10 +162,13 @@ fn handle_chat_request(
             if target == &our.node {
                 println!("{}: {}", source.node, message);
             } else {
-                Request::new()
-                    .target((target, "chat", "chat", "template.os"))
-                    .body(body)
-                    .send_and_await_response(5)??;
+                // kit-channel-type: sync
+                send_to_counterparty(target, body, true)?;
+                // kit-channel-type: async
+                send_to_counterparty(target, body, false)?;
+                // kit-channel-type: mixed
+                send_to_counterparty(target, body, AWAIT_DELIVERY)?;
+                // kit-channel-type: end
             }
 
             // Insert message into archive, creating one for counterparty if it DNE
@@ -0,0 +1,76 @@
+use std::path::Path;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tracing::{info, instrument, warn};
+
+const NEW_WORKSPACE_CARGO_TOML: &str = "[workspace]\nresolver = \"2\"\nmembers = []\n";
+
+/// The `[workspace] members` of the Cargo.toml at `cargo_toml_path`
+fn read_members(cargo_toml_path: &Path) -> Result<Vec<String>> {
+    let doc = fs::read_to_string(cargo_toml_path)?.parse::<toml_edit::DocumentMut>()?;
+    Ok(doc
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .map(|m| {
+            m.iter()
+                .filter_map(|m| m.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Add the crates of the package at `new_dir` to the `[workspace] members`
+/// of `workspace_root/Cargo.toml`, creating it if need be; the crates,
+/// rather than the package, since its own Cargo.toml is a workspace too
+/// & Cargo rejects a workspace root as a member of another
+#[instrument(level = "trace", skip_all)]
+pub fn add_to_workspace(workspace_root: &Path, new_dir: &Path) -> Result<()> {
+    let package_cargo_toml_path = new_dir.join("Cargo.toml");
+    if !package_cargo_toml_path.exists() {
+        warn!(
+            "{new_dir:?} has no Cargo.toml: not adding it to the workspace at {workspace_root:?}."
+        );
+        return Ok(());
+    }
+    let workspace_root = fs::canonicalize(workspace_root)?;
+    let new_dir = fs::canonicalize(new_dir)?;
+    let Ok(relative_dir) = new_dir.strip_prefix(&workspace_root) else {
+        return Err(
+            eyre!("Package {new_dir:?} is not within workspace root {workspace_root:?}")
+                .with_suggestion(|| "Create the package in a directory within --workspace-root."),
+        );
+    };
+    let relative_dir = relative_dir.to_str().unwrap();
+
+    let workspace_cargo_toml_path = workspace_root.join("Cargo.toml");
+    let workspace_cargo_toml = if workspace_cargo_toml_path.exists() {
+        fs::read_to_string(&workspace_cargo_toml_path)?
+    } else {
+        info!("Creating workspace {workspace_cargo_toml_path:?}.");
+        NEW_WORKSPACE_CARGO_TOML.to_string()
+    };
+    let mut doc = workspace_cargo_toml.parse::<toml_edit::DocumentMut>()?;
+    let members = doc
+        .entry("workspace")
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .ok_or_else(|| eyre!("workspace is not a table"))?
+        .entry("members")
+        .or_insert(toml_edit::array())
+        .as_array_mut()
+        .ok_or_else(|| eyre!("members is not an array"))?;
+
+    for member in read_members(&package_cargo_toml_path)? {
+        let member = format!("{relative_dir}/{member}");
+        if members.iter().any(|m| m.as_str() == Some(&member)) {
+            continue;
+        }
+        members.push(member.as_str());
+        info!("Added {member} to workspace {workspace_cargo_toml_path:?}.");
+    }
+
+    fs::write(&workspace_cargo_toml_path, doc.to_string())?;
+    Ok(())
+}
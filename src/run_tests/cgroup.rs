@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+use std::sync::Once;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tracing::{debug, info, instrument};
+
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+/// Bounds of cgroup v1 `cpu.shares`, which `cpu_shares` is given in
+const MIN_CPU_SHARES: u64 = 2;
+const MAX_CPU_SHARES: u64 = 262_144;
+
+static NOTE_UNSUPPORTED: Once = Once::new();
+
+/// A cgroup (v2) per test node, limiting its CPU share and/or memory;
+/// the cgroups are removed on drop
+pub struct CgroupLimits {
+    /// (node name, cgroup dir)
+    cgroups: Vec<(String, PathBuf)>,
+}
+
+/// Map cgroup v1 `cpu.shares` onto cgroup v2 `cpu.weight`, as runc does
+fn shares_to_weight(cpu_shares: u64) -> u64 {
+    let cpu_shares = cpu_shares.clamp(MIN_CPU_SHARES, MAX_CPU_SHARES);
+    1 + ((cpu_shares - MIN_CPU_SHARES) * 9999) / (MAX_CPU_SHARES - MIN_CPU_SHARES)
+}
+
+fn write_cgroup_file(cgroup: &PathBuf, file: &str, value: &str) -> Result<()> {
+    fs::write(cgroup.join(file), value).map_err(|e| {
+        eyre!("Failed to set {file} of {cgroup:?}: {e}")
+            .with_suggestion(|| "Run kit as root, or in a cgroup delegated to the user.")
+    })
+}
+
+impl CgroupLimits {
+    /// Put each of `nodes`, given as (name, pid), in its own cgroup with the
+    /// given limits; `None` if there are no limits or this is not Linux
+    #[instrument(level = "trace", skip_all)]
+    pub fn apply(
+        nodes: Vec<(String, i32)>,
+        cpu_shares: Option<u64>,
+        memory_limit_mb: Option<u64>,
+    ) -> Result<Option<Self>> {
+        if cpu_shares.is_none() && memory_limit_mb.is_none() {
+            return Ok(None);
+        }
+        if !cfg!(target_os = "linux") {
+            NOTE_UNSUPPORTED.call_once(|| {
+                info!("cpu_shares & memory_limit_mb require Linux cgroups: ignoring them.");
+            });
+            return Ok(None);
+        }
+        let root = PathBuf::from(CGROUP_ROOT);
+        if !root.join("cgroup.controllers").exists() {
+            return Err(eyre!(
+                "cpu_shares & memory_limit_mb require cgroup v2 mounted at {CGROUP_ROOT}"
+            ));
+        }
+        // controllers may already be enabled for children, in which case this
+        //  fails harmlessly if kit may not write here
+        let _ = fs::write(root.join("cgroup.subtree_control"), "+cpu +memory");
+
+        let mut limits = CgroupLimits { cgroups: vec![] };
+        for (name, pid) in nodes {
+            let cgroup = root.join(format!("kit-test-{}-{name}", std::process::id()));
+            fs::create_dir_all(&cgroup).map_err(|e| {
+                eyre!("Failed to create cgroup {cgroup:?}: {e}")
+                    .with_suggestion(|| "Run kit as root, or in a cgroup delegated to the user.")
+            })?;
+            limits.cgroups.push((name.clone(), cgroup.clone()));
+            if let Some(cpu_shares) = cpu_shares {
+                let weight = shares_to_weight(cpu_shares);
+                write_cgroup_file(&cgroup, "cpu.weight", &weight.to_string())?;
+            }
+            if let Some(memory_limit_mb) = memory_limit_mb {
+                let bytes = memory_limit_mb * 1024 * 1024;
+                write_cgroup_file(&cgroup, "memory.max", &bytes.to_string())?;
+                // else the node swaps rather than feeling the limit
+                let _ = fs::write(cgroup.join("memory.swap.max"), "0");
+            }
+            write_cgroup_file(&cgroup, "cgroup.procs", &pid.to_string())?;
+            debug!("{name} (pid {pid}) limited by cgroup {cgroup:?}");
+        }
+        Ok(Some(limits))
+    }
+
+    /// Error if the kernel killed a node for exceeding `memory_limit_mb`
+    pub fn check_oom(&self, memory_limit_mb: Option<u64>) -> Result<()> {
+        for (name, cgroup) in &self.cgroups {
+            let Ok(events) = fs::read_to_string(cgroup.join("memory.events")) else {
+                continue;
+            };
+            let oom_kills: u64 = events
+                .lines()
+                .find_map(|line| line.strip_prefix("oom_kill "))
+                .and_then(|n| n.trim().parse().ok())
+                .unwrap_or_default();
+            if oom_kills > 0 {
+                return Err(eyre!(
+                    "memory limit exceeded: node {name} was OOM-killed (memory_limit_mb = {})",
+                    memory_limit_mb.unwrap_or_default(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for CgroupLimits {
+    fn drop(&mut self) {
+        for (_, cgroup) in &self.cgroups {
+            // fails while the node is still running, leaving an empty cgroup behind
+            if let Err(e) = fs::remove_dir(cgroup) {
+                debug!("Failed to remove cgroup {cgroup:?}: {e}");
+            }
+        }
+    }
+}
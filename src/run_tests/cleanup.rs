@@ -5,6 +5,9 @@ use tokio::io::AsyncBufReadExt;
 use tokio::signal::unix::{signal, SignalKind};
 use tracing::{error, info, instrument};
 
+use crate::run_tests::metrics::{parse_metric_line, MetricValues};
+use crate::run_tests::network_policy::remove_network_policy;
+use crate::run_tests::report::NodeOutput;
 use crate::run_tests::types::{
     BroadcastRecvBool, BroadcastSendBool, NodeCleanupInfo, NodeCleanupInfos, NodeHandles, RecvBool,
     SendBool,
@@ -90,6 +93,9 @@ pub async fn cleanup(
 
     let _ = send_to_kill.send(should_print_std.is_none() || should_print_std.is_some_and(|b| b));
 
+    // the loopback device is shared with the host: restore it first
+    remove_network_policy();
+
     for NodeCleanupInfo {
         master_fd,
         process_id,
@@ -141,6 +147,8 @@ pub async fn cleanup(
 pub async fn drain_print_runtime(
     stdout: tokio::process::ChildStdout,
     stderr: tokio::process::ChildStderr,
+    metrics: Option<MetricValues>,
+    output: Option<NodeOutput>,
     mut recv_kill: BroadcastRecvBool,
 ) {
     let mut stdout_reader = tokio::io::BufReader::new(stdout).lines();
@@ -151,10 +159,25 @@ pub async fn drain_print_runtime(
     loop {
         tokio::select! {
             Ok(Some(line)) = stdout_reader.next_line() => {
+                if let Some(ref metrics) = metrics {
+                    if let Some((name, value)) = parse_metric_line(&line) {
+                        metrics.lock().await.insert(name, value);
+                    }
+                }
+                if let Some(ref output) = output {
+                    let mut output = output.lock().await;
+                    output.stdout.push_str(&line);
+                    output.stdout.push('\n');
+                }
                 stdout_buffer.push_str(&line);
                 stdout_buffer.push('\n');
             }
             Ok(Some(line)) = stderr_reader.next_line() => {
+                if let Some(ref output) = output {
+                    let mut output = output.lock().await;
+                    output.stderr.push_str(&line);
+                    output.stderr.push('\n');
+                }
                 stderr_buffer.push_str(&line);
                 stderr_buffer.push('\n');
             }
@@ -0,0 +1,75 @@
+use color_eyre::{eyre::eyre, Result};
+use fs_err as fs;
+use tracing::{info, instrument, warn};
+
+const BYTES_PER_MB: f64 = (1024 * 1024) as f64;
+
+/// Bytes read from & written to storage by a test node
+#[derive(Debug, Clone, Copy, Default)]
+struct IoCounters {
+    read_bytes: u64,
+    write_bytes: u64,
+}
+
+/// Snapshot of the storage I/O of each test node, given as (name, pid)
+pub struct IoSnapshot {
+    nodes: Vec<(String, i32, IoCounters)>,
+}
+
+/// `read_bytes` & `write_bytes` from `/proc/<pid>/io`: Linux only
+fn get_io_counters(pid: i32) -> Option<IoCounters> {
+    let io = fs::read_to_string(format!("/proc/{pid}/io")).ok()?;
+    let get = |key: &str| {
+        io.lines()
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.trim().parse().ok())
+    };
+    Some(IoCounters {
+        read_bytes: get("read_bytes:")?,
+        write_bytes: get("write_bytes:")?,
+    })
+}
+
+impl IoSnapshot {
+    #[instrument(level = "trace", skip_all)]
+    pub fn take(nodes: Vec<(String, i32)>) -> Self {
+        if !cfg!(target_os = "linux") {
+            warn!("Disk I/O measurement needs /proc/<pid>/io, which is Linux only; reporting zero I/O.");
+        }
+        let nodes = nodes
+            .into_iter()
+            .map(|(name, pid)| {
+                let counters = get_io_counters(pid).unwrap_or_default();
+                (name, pid, counters)
+            })
+            .collect();
+        IoSnapshot { nodes }
+    }
+
+    /// Report the I/O of each node since the snapshot was taken, returning
+    /// an error if any node wrote more than `max_write_mb`
+    #[instrument(level = "trace", skip_all)]
+    pub fn report(&self, max_write_mb: Option<u64>) -> Result<()> {
+        let mut over_limit = vec![];
+        for (name, pid, start) in &self.nodes {
+            let end = get_io_counters(*pid).unwrap_or(*start);
+            let read_mb = end.read_bytes.saturating_sub(start.read_bytes) as f64 / BYTES_PER_MB;
+            let write_mb = end.write_bytes.saturating_sub(start.write_bytes) as f64 / BYTES_PER_MB;
+            info!("disk I/O: {name}: read {read_mb:.2} MB, wrote {write_mb:.2} MB");
+            if let Some(max_write_mb) = max_write_mb {
+                if write_mb > max_write_mb as f64 {
+                    over_limit.push(format!(
+                        "node {name} wrote {write_mb:.2} MB (max_write_mb = {max_write_mb})"
+                    ));
+                }
+            }
+        }
+        if !over_limit.is_empty() {
+            return Err(eyre!(
+                "disk write limit exceeded:\n{}",
+                over_limit.join("\n")
+            ));
+        }
+        Ok(())
+    }
+}
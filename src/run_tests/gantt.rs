@@ -0,0 +1,123 @@
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use color_eyre::Result;
+use fs_err as fs;
+use tracing::info;
+
+use crate::run_tests::report::test_name;
+use crate::run_tests::types::Test;
+
+#[derive(Debug)]
+struct Phase {
+    test_index: usize,
+    name: &'static str,
+    start: Duration,
+    end: Option<Duration>,
+    failed: bool,
+}
+
+/// Start & end of each phase of each test, relative to the start of the run
+#[derive(Debug)]
+pub struct Timeline {
+    start: Instant,
+    /// Section title of each test
+    tests: Vec<String>,
+    phases: Vec<Phase>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Timeline {
+            start: Instant::now(),
+            tests: vec![],
+            phases: vec![],
+        }
+    }
+
+    fn close_phase(&mut self, failed: bool) {
+        let now = self.start.elapsed();
+        if let Some(phase) = self.phases.last_mut() {
+            if phase.end.is_none() {
+                phase.end = Some(now);
+                phase.failed = failed;
+            }
+        }
+    }
+
+    pub fn start_test(&mut self, test_index: usize, test: &Test) {
+        self.tests.push(test_name(test_index, test));
+    }
+
+    /// End the current phase, if any, and start phase `name`
+    pub fn start_phase(&mut self, name: &'static str) {
+        self.close_phase(false);
+        self.phases.push(Phase {
+            test_index: self.tests.len().saturating_sub(1),
+            name,
+            start: self.start.elapsed(),
+            end: None,
+            failed: false,
+        });
+    }
+
+    /// End the current phase; if the test failed, it failed in that phase
+    pub fn end_test(&mut self, passed: bool) {
+        self.close_phase(!passed);
+    }
+
+    /// Tests run one after another, so every test is on the critical path;
+    /// the phases of the slowest test, the bottleneck, are marked `crit`
+    fn slowest_test(&self) -> Option<usize> {
+        (0..self.tests.len()).max_by_key(|test_index| {
+            self.phases
+                .iter()
+                .filter(|p| p.test_index == *test_index)
+                .map(|p| p.end.unwrap_or(p.start) - p.start)
+                .sum::<Duration>()
+        })
+    }
+
+    fn to_mermaid(&self) -> String {
+        let slowest_test = self.slowest_test();
+        let mut chart =
+            "gantt\n    title kit run-tests\n    dateFormat x\n    axisFormat %M:%S\n".to_string();
+        for (test_index, test) in self.tests.iter().enumerate() {
+            // `:` separates a task's name from its times
+            chart.push_str(&format!("\n    section {}\n", test.replace(':', " ")));
+            for (phase_index, phase) in self
+                .phases
+                .iter()
+                .filter(|p| p.test_index == test_index)
+                .enumerate()
+            {
+                let start = phase.start.as_millis();
+                let end = phase.end.unwrap_or(phase.start).as_millis().max(start + 1);
+                let crit = if Some(test_index) == slowest_test {
+                    "crit, "
+                } else {
+                    ""
+                };
+                let failed = if phase.failed { " (failed)" } else { "" };
+                chart.push_str(&format!(
+                    "    {}{failed} :{crit}t{test_index}p{phase_index}, {start}, {end}\n",
+                    phase.name,
+                ));
+            }
+        }
+        chart
+    }
+
+    /// Write the timeline as a Mermaid Gantt chart; fenced as a
+    /// `mermaid` code block if `path` is Markdown
+    pub fn write_mermaid(&self, path: &Path) -> Result<()> {
+        let chart = self.to_mermaid();
+        let chart = match path.extension().and_then(|e| e.to_str()) {
+            Some("md") => format!("```mermaid\n{chart}```\n"),
+            _ => chart,
+        };
+        fs::write(path, chart)?;
+        info!("Wrote Gantt chart of test timeline to {path:?}.");
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use std::process::Command;
+
+use color_eyre::{eyre::eyre, Result};
+use fs_err as fs;
+use tokio::sync::oneshot;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration};
+use tracing::{debug, error, instrument};
+
+const POLL_INTERVAL_MS: u64 = 500;
+
+/// Polls the RSS of each test node, killing any that exceeds `max_memory_mb`
+pub struct MemoryMonitor {
+    handle: JoinHandle<()>,
+    recv_exceeded: oneshot::Receiver<String>,
+}
+
+/// Resident set size of `pid` in kB: `/proc/<pid>/status` on Linux, else `ps`
+fn get_rss_kb(pid: i32) -> Option<u64> {
+    if let Ok(status) = fs::read_to_string(format!("/proc/{pid}/status")) {
+        return status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))
+            .and_then(|rss| rss.trim().trim_end_matches("kB").trim().parse().ok());
+    }
+    let output = Command::new("ps")
+        .args(["-o", "rss=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+impl MemoryMonitor {
+    /// Start monitoring `nodes`, given as (name, pid)
+    #[instrument(level = "trace", skip_all)]
+    pub fn start(nodes: Vec<(String, i32)>, max_memory_mb: u64) -> Self {
+        let (send_exceeded, recv_exceeded) = oneshot::channel();
+        let handle = tokio::spawn(async move {
+            loop {
+                for (name, pid) in &nodes {
+                    let Some(rss_kb) = get_rss_kb(*pid) else {
+                        continue;
+                    };
+                    let rss_mb = rss_kb / 1024;
+                    debug!("{name}: {rss_mb} MB");
+                    if rss_mb > max_memory_mb {
+                        let message = format!(
+                            "memory limit exceeded: node {name} used {rss_mb} MB (max_memory_mb = {max_memory_mb})"
+                        );
+                        error!("{message}; killing node");
+                        let pid = nix::unistd::Pid::from_raw(*pid);
+                        let _ = nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGKILL);
+                        let _ = send_exceeded.send(message);
+                        return;
+                    }
+                }
+                sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+            }
+        });
+        MemoryMonitor {
+            handle,
+            recv_exceeded,
+        }
+    }
+
+    /// Resolve once a node exceeds the limit
+    pub async fn exceeded(&mut self) -> String {
+        match (&mut self.recv_exceeded).await {
+            Ok(message) => message,
+            // monitor stopped without the limit being exceeded
+            Err(_) => std::future::pending().await,
+        }
+    }
+
+    /// Stop monitoring, returning an error if a node exceeded the limit
+    pub fn stop(mut self) -> Result<()> {
+        self.handle.abort();
+        match self.recv_exceeded.try_recv() {
+            Ok(message) => Err(eyre!(message)),
+            Err(_) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use color_eyre::{eyre::eyre, Result};
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::run_tests::types::MetricAssertion;
+
+/// Latest value of each metric emitted by the test nodes' processes
+pub type MetricValues = Arc<Mutex<HashMap<String, f64>>>;
+
+/// `METRIC: name=requests_processed value=42` -> (`requests_processed`, `42`),
+/// wherever in a line of node output it appears
+pub fn parse_metric_line(line: &str) -> Option<(String, f64)> {
+    if !line.contains("METRIC: ") {
+        return None;
+    }
+    let metric_re =
+        regex::Regex::new(r"METRIC: name=([\w\-\.:]+) value=(-?[0-9][0-9\.eE\+\-]*)").unwrap();
+    let captures = metric_re.captures(line)?;
+    let value = captures[2].parse().ok()?;
+    Some((captures[1].to_string(), value))
+}
+
+/// Check each of `assertions` against the last value emitted for its metric
+pub async fn check_metrics(assertions: &[MetricAssertion], values: &MetricValues) -> Result<()> {
+    let values = values.lock().await;
+    let mut failures = vec![];
+    for assertion in assertions {
+        let name = &assertion.name;
+        let Some(value) = values.get(name) else {
+            failures.push(format!("{name}: never emitted"));
+            continue;
+        };
+        if assertion.min.map(|min| *value < min).unwrap_or(false)
+            || assertion.max.map(|max| *value > max).unwrap_or(false)
+        {
+            failures.push(format!(
+                "{name} = {value}: expected {}..={}",
+                assertion.min.map(|m| m.to_string()).unwrap_or_default(),
+                assertion.max.map(|m| m.to_string()).unwrap_or_default(),
+            ));
+        } else {
+            info!("{name} = {value}: OK");
+        }
+    }
+    if !failures.is_empty() {
+        return Err(eyre!(
+            "Failed {} of {} metrics_assertions:\n{}",
+            failures.len(),
+            assertions.len(),
+            failures.join("\n"),
+        ));
+    }
+    Ok(())
+}
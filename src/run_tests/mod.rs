@@ -3,39 +3,65 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
 
-use color_eyre::{eyre::eyre, Result, Section};
+use color_eyre::{
+    eyre::{eyre, Report, WrapErr},
+    Result, Section,
+};
 use dirs::home_dir;
 use fs_err as fs;
 use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration};
-use tracing::{debug, info, instrument};
+use tracing::{debug, info, instrument, warn};
 
 use kinode_process_lib::kernel_types::PackageManifestEntry;
 
 use crate::boot_fake_node;
-use crate::build;
-use crate::chain;
+use crate::build::{self, BuildOptions};
+use crate::chain::{self, ChainOptions};
 use crate::inject_message;
 use crate::start_package;
 
 use crate::kinode::process::tester::{FailResponse, Response as TesterResponse};
 
+mod cgroup;
+use cgroup::CgroupLimits;
 pub mod cleanup;
 use cleanup::{cleanup, cleanup_on_signal, drain_print_runtime};
 pub mod types;
 use types::*;
+mod disk_io;
+use disk_io::IoSnapshot;
+mod gantt;
+use gantt::Timeline;
+mod memory_limit;
+use memory_limit::MemoryMonitor;
+mod metrics;
+use metrics::{check_metrics, MetricValues};
+mod network_policy;
+use network_policy::{apply_network_policy, assign_ws_ports, check_network_policy_allowed};
+mod storage_latency;
+use storage_latency::{get_traced_pid, make_storage_latency_wrapper};
+mod persist_state;
+use persist_state::{reset_state, restore_node_state, save_state};
+mod report;
+use report::{
+    make_record, make_skipped_record, matches_filter, print_json, NodeOutput, Summary,
+    TestSetupFailed, TestStatus, TestTimeout,
+};
+mod wit_coverage;
+use wit_coverage::{write_wit_coverage_report, MESSAGE_LOG_VERBOSITY};
+mod ws_assert;
+use ws_assert::run_websocket_steps;
 
 impl Config {
     fn expand_home_paths(mut self: Config, config_path: &Path) -> Config {
         let config_path = config_path.parent().unwrap();
         self.runtime = match self.runtime {
             Runtime::FetchVersion(version) => Runtime::FetchVersion(version),
-            Runtime::RepoPath(runtime_path) => {
-                Runtime::RepoPath(expand_home_path(&runtime_path).unwrap_or_else(|| {
-                    fs::canonicalize(config_path.join(&runtime_path))
-                        .unwrap_or_else(|_| runtime_path)
-                }))
-            }
+            Runtime::RepoPath(runtime_path) => Runtime::RepoPath(
+                expand_home_path(&runtime_path)
+                    .unwrap_or_else(|| resolve_config_relative_path(config_path, &runtime_path)),
+            ),
         };
         for test in self.tests.iter_mut() {
             test.test_package_paths = test
@@ -44,11 +70,17 @@ impl Config {
                 .map(|p| expand_home_path(&p).unwrap_or_else(|| p.clone()))
                 .collect();
             for node in test.nodes.iter_mut() {
-                node.home = expand_home_path(&node.home).unwrap_or_else(|| {
-                    fs::canonicalize(config_path.join(&node.home))
-                        .unwrap_or_else(|_| node.home.clone())
-                });
+                node.home = expand_home_path(&node.home)
+                    .unwrap_or_else(|| resolve_config_relative_path(config_path, &node.home));
             }
+            test.pre_install = test
+                .pre_install
+                .iter()
+                .map(|p| {
+                    expand_home_path(p)
+                        .unwrap_or_else(|| resolve_config_relative_path(config_path, p))
+                })
+                .collect();
         }
         self
     }
@@ -88,6 +120,9 @@ fn load_config(config_path: &Path) -> Result<(PathBuf, Config)> {
         }
     };
 
+    // canonical, so that paths in it are resolved relative to its dir
+    //  rather than to the working directory
+    let config_path = fs::canonicalize(&config_path)?;
     let content = fs::read_to_string(&config_path)?;
     Ok((
         config_path.clone(),
@@ -95,6 +130,28 @@ fn load_config(config_path: &Path) -> Result<(PathBuf, Config)> {
     ))
 }
 
+/// Error if the runtime `version` is older than the `min_runtime_version` of `tests.toml`
+fn check_min_runtime_version(min_runtime_version: Option<&str>, version: &str) -> Result<()> {
+    let Some(min_runtime_version) = min_runtime_version else {
+        return Ok(());
+    };
+    let min_version: semver::Version = min_runtime_version
+        .strip_prefix('v')
+        .unwrap_or(min_runtime_version)
+        .parse()
+        .wrap_err_with(|| format!("Invalid min_runtime_version {min_runtime_version:?}"))?;
+    let version: semver::Version = version
+        .parse()
+        .wrap_err_with(|| format!("Could not parse Kinode version {version:?}"))?;
+    if version < min_version {
+        return Err(
+            eyre!("Skipped: requires Kinode >= v{min_version}, got v{version}")
+                .with_suggestion(|| "Set `runtime` in tests.toml to a newer Kinode."),
+        );
+    }
+    Ok(())
+}
+
 fn get_basename(file_path: &Path) -> Option<&str> {
     file_path
         .file_name()
@@ -110,6 +167,13 @@ fn expand_home_path_string(path: &str) -> Option<String> {
     None
 }
 
+/// `path` relative to `config_dir`, the dir of `tests.toml`, if it is relative;
+/// canonicalized if it exists yet, e.g. a node home may not
+fn resolve_config_relative_path(config_dir: &Path, path: &Path) -> PathBuf {
+    let path = config_dir.join(path);
+    fs::canonicalize(&path).unwrap_or(path)
+}
+
 fn expand_home_path(path: &PathBuf) -> Option<PathBuf> {
     path.as_os_str()
         .to_str()
@@ -182,6 +246,17 @@ async fn setup_cleanup(detached: &bool, persist_home: &bool) -> Result<SetupClea
     })
 }
 
+/// How to boot test nodes beyond the defaults a dependency-hosting node
+/// gets: `Default` boots plain nodes
+#[derive(Clone, Copy, Default)]
+struct BootOptions<'a> {
+    /// Restore each node's saved state from `(state_dir, test_index)`
+    persist_state: Option<(&'a Path, usize)>,
+    metrics: Option<&'a MetricValues>,
+    output: Option<&'a NodeOutput>,
+    storage_latency_ms: Option<u64>,
+}
+
 #[instrument(level = "trace", skip_all)]
 async fn boot_nodes(
     nodes: &Vec<Node>,
@@ -194,7 +269,18 @@ async fn boot_nodes(
     node_cleanup_infos: NodeCleanupInfos,
     send_to_kill: &BroadcastSendBool,
     node_handles: NodeHandles,
+    options: BootOptions<'_>,
 ) -> Result<()> {
+    let BootOptions {
+        persist_state,
+        metrics,
+        output,
+        storage_latency_ms,
+    } = options;
+    let wrapper = match storage_latency_ms {
+        None | Some(0) => None,
+        Some(storage_latency_ms) => Some(make_storage_latency_wrapper(storage_latency_ms)?),
+    };
     for node in nodes {
         fs::create_dir_all(&node.home)?;
         let node_home = fs::canonicalize(&node.home)?;
@@ -204,6 +290,9 @@ async fn boot_nodes(
                 fs::remove_dir_all(&node_home.join(dir)).unwrap();
             }
         }
+        if let Some((state_dir, test_index)) = persist_state {
+            restore_node_state(state_dir, test_index, node, &node_home)?;
+        }
 
         let mut args = vec![];
         if let Some(ref rpc) = node.rpc {
@@ -212,6 +301,9 @@ async fn boot_nodes(
         if let Some(ref password) = node.password {
             args.extend_from_slice(&["--password".into(), password.clone()]);
         };
+        if let Some(ws_port) = node.ws_port {
+            args.extend_from_slice(&["--ws-port".into(), ws_port.to_string()]);
+        };
 
         let mut name = node.fake_node_name.clone();
         if !name.contains(".") {
@@ -233,7 +325,12 @@ async fn boot_nodes(
             false,
             detached.clone(),
             node.runtime_verbosity.unwrap_or_else(|| 0u8),
+            wrapper.clone(),
         )?;
+        let process_id = match wrapper {
+            None => runtime_process.id().unwrap() as i32,
+            Some(_) => get_traced_pid(runtime_process.id().unwrap()).await?,
+        };
 
         let mut anvil_cleanup: Option<i32> = None;
         let mut other_processes = vec![];
@@ -248,7 +345,7 @@ async fn boot_nodes(
             let mut node_cleanup_infos = node_cleanup_infos.lock().await;
             node_cleanup_infos.push(NodeCleanupInfo {
                 master_fd,
-                process_id: runtime_process.id().unwrap() as i32,
+                process_id,
                 home: node_home.clone(),
                 anvil_process: anvil_cleanup,
                 other_processes,
@@ -259,6 +356,8 @@ async fn boot_nodes(
         tokio::spawn(drain_print_runtime(
             runtime_process.stdout.take().unwrap(),
             runtime_process.stderr.take().unwrap(),
+            metrics.cloned(),
+            output.cloned(),
             recv_kill_in_dpr,
         ));
 
@@ -294,12 +393,16 @@ async fn build_packages(
     let setup_packages: Vec<SetupPackage> = test
         .setup_packages
         .iter()
-        .cloned()
-        .map(|s| SetupPackage {
-            path: test_dir_path.join(s.path).canonicalize().unwrap(),
-            run: s.run,
+        .map(|s| {
+            Ok(SetupPackage {
+                path: test_dir_path
+                    .join(&s.path)
+                    .canonicalize()
+                    .wrap_err_with(|| format!("Setup package {:?} not found", s.path))?,
+                run: s.run,
+            })
         })
-        .collect();
+        .collect::<Result<Vec<SetupPackage>>>()?;
     let test_package_paths: Vec<PathBuf> = test
         .test_package_paths
         .iter()
@@ -314,6 +417,7 @@ async fn build_packages(
         port: port.clone(),
         home,
         fake_node_name: "fake.dev".into(),
+        ws_port: None,
         password: None,
         rpc: None,
         runtime_verbosity: Some(2),
@@ -336,7 +440,10 @@ async fn build_packages(
         test.fakechain_router,
         recv_kill_in_start_chain,
         version,
-        false,
+        &ChainOptions {
+            block_time: test.block_time,
+            ..Default::default()
+        },
     )
     .await?;
 
@@ -351,11 +458,19 @@ async fn build_packages(
         Arc::clone(&node_cleanup_infos),
         &send_to_kill,
         Arc::clone(&node_handles),
+        BootOptions::default(),
     )
     .await?;
     info!("Done starting node to host dependencies.");
 
     let url = format!("http://localhost:{port}");
+    let build_options = BuildOptions {
+        features: "test".into(),
+        url: Some(url.clone()),
+        local_dependencies: dependency_package_paths.clone(),
+        // TODO: add_paths_to_api
+        ..Default::default()
+    };
 
     for dependency_package_path in &test.dependency_package_paths {
         let path = match expand_home_path(&dependency_package_path) {
@@ -365,73 +480,24 @@ async fn build_packages(
                 .canonicalize()?,
         };
         debug!("Build {path:?}");
-        build::execute(
-            &path,
-            false,
-            false,
-            &HashSet::new(),
-            &HashSet::new(),
-            false,
-            "test",
-            Some(url.clone()),
-            None,
-            None,
-            dependency_package_paths.clone(),
-            vec![], // TODO
-            false,
-            false,
-            false,
-            false,
-            false,
-        )
-        .await?;
+        build::execute(&path, &build_options).await?;
         debug!("Start {path:?}");
         start_package::execute(&path, &url).await?;
     }
 
     for setup_package in &setup_packages {
-        build::execute(
-            &setup_package.path,
-            false,
-            false,
-            &HashSet::new(),
-            &HashSet::new(),
-            false,
-            "test",
-            Some(url.clone()),
-            None,
-            None,
-            dependency_package_paths.clone(),
-            vec![], // TODO
-            false,
-            false,
-            false,
-            false,
-            false,
-        )
-        .await?;
+        debug!("Build setup package {:?}", setup_package.path);
+        build::execute(&setup_package.path, &build_options)
+            .await
+            .wrap_err_with(|| {
+                format!(
+                    "Failed to build setup package {:?}; aborting tests",
+                    setup_package.path
+                )
+            })?;
     }
     for test_package_path in &test_package_paths {
-        build::execute(
-            &test_package_path,
-            false,
-            false,
-            &HashSet::new(),
-            &HashSet::new(),
-            false,
-            "test",
-            Some(url.clone()),
-            None,
-            None,
-            dependency_package_paths.clone(),
-            vec![], // TODO
-            false,
-            false,
-            false,
-            false,
-            false,
-        )
-        .await?;
+        build::execute(&test_package_path, &build_options).await?;
     }
 
     info!("Cleaning up node to host dependencies.");
@@ -497,7 +563,14 @@ async fn load_setups(setup_paths: &Vec<SetupPackage>, port: u16) -> Result<()> {
 
     for setup_path in setup_paths {
         if setup_path.run {
-            start_package::execute(&setup_path.path, &format!("http://localhost:{}", port)).await?;
+            start_package::execute(&setup_path.path, &format!("http://localhost:{}", port))
+                .await
+                .wrap_err_with(|| {
+                    format!(
+                        "Failed to install setup package {:?} on node at port {}; aborting tests",
+                        setup_path.path, port,
+                    )
+                })?;
         }
         load_process(&setup_path.path, "setup", &port).await?;
     }
@@ -543,7 +616,58 @@ async fn load_process(path: &Path, drive: &str, port: &u16) -> Result<()> {
 }
 
 #[instrument(level = "trace", skip_all)]
-async fn load_caps(test_package_paths: &Vec<PathBuf>, port: u16) -> Result<()> {
+fn make_extra_caps(capabilities: &[TestCapability]) -> Result<Vec<serde_json::Value>> {
+    capabilities
+        .iter()
+        .map(|cap| match (cap.kind.as_str(), &cap.params) {
+            ("messaging", None) => Ok(serde_json::json!(cap.target)),
+            (_, Some(params)) => {
+                serde_json::from_str::<serde_json::Value>(params).wrap_err_with(|| {
+                    format!(
+                        "capabilities params {params:?} for {} must be JSON",
+                        cap.target
+                    )
+                })?;
+                Ok(serde_json::json!({
+                    "process": cap.target,
+                    "params": params,
+                }))
+            }
+            (kind, None) => Err(eyre!(
+                "capability of kind {kind:?} for {} requires params",
+                cap.target,
+            )),
+        })
+        .collect()
+}
+
+/// The process a capability is issued by: capabilities are either
+/// a process ID (messaging) or `{"process": ..., "params": ...}`
+fn get_cap_issuer(cap: &serde_json::Value) -> Option<&str> {
+    cap.as_str().or_else(|| cap["process"].as_str())
+}
+
+/// `deny` matches a full process ID, e.g. `"eth:distro:sys"`,
+/// or just its process name, e.g. `"eth"`
+fn is_denied(cap: &serde_json::Value, deny_capabilities: &[String]) -> bool {
+    let Some(issuer) = get_cap_issuer(cap) else {
+        return false;
+    };
+    let process_name = issuer.split(':').next().unwrap_or_default();
+    deny_capabilities
+        .iter()
+        .any(|deny| deny == issuer || deny == process_name)
+}
+
+#[instrument(level = "trace", skip_all)]
+async fn load_caps(
+    test_package_paths: &Vec<PathBuf>,
+    capabilities: &[TestCapability],
+    deny_capabilities: &[String],
+    port: u16,
+) -> Result<()> {
+    let extra_caps = make_extra_caps(capabilities)?;
+    let mut denied = HashSet::new();
     let mut caps = std::collections::HashMap::new();
     for test_package_path in test_package_paths {
         let manifest_path = test_package_path.join("pkg").join("manifest.json");
@@ -556,15 +680,35 @@ async fn load_caps(test_package_paths: &Vec<PathBuf>, port: u16) -> Result<()> {
             return Err(eyre!(""));
         }
         let manifest = manifest.iter().next().unwrap();
+        let mut request_capabilities = manifest.request_capabilities.clone();
+        request_capabilities.extend(extra_caps.iter().cloned());
+        request_capabilities.retain(|cap| {
+            if !is_denied(cap, deny_capabilities) {
+                return true;
+            }
+            denied.insert(get_cap_issuer(cap).unwrap().to_string());
+            false
+        });
         caps.insert(
             test_package_path.file_name().map(|f| f.to_str()).unwrap(),
             serde_json::json!({
-                "request_capabilities": manifest.request_capabilities,
+                "request_capabilities": request_capabilities,
                 "grant_capabilities": manifest.grant_capabilities,
             }),
         );
     }
     let caps = serde_json::to_vec(&caps)?;
+    if !denied.is_empty() {
+        info!("Denying test processes capabilities from {denied:?}.");
+    }
+    for deny in deny_capabilities {
+        let is_used = denied
+            .iter()
+            .any(|d| d == deny || d.split(':').next() == Some(deny.as_str()));
+        if !is_used {
+            warn!("deny_capabilities {deny:?} matches no capability of the test processes");
+        }
+    }
 
     let request = inject_message::make_message(
         "vfs:distro:sys",
@@ -590,14 +734,19 @@ async fn load_caps(test_package_paths: &Vec<PathBuf>, port: u16) -> Result<()> {
 }
 
 #[instrument(level = "trace", skip_all)]
-async fn load_tests(test_package_paths: &Vec<PathBuf>, port: u16) -> Result<()> {
+async fn load_tests(
+    test_package_paths: &Vec<PathBuf>,
+    capabilities: &[TestCapability],
+    deny_capabilities: &[String],
+    port: u16,
+) -> Result<()> {
     info!("Loading tests...");
 
     for test_package_path in test_package_paths {
         load_process(&test_package_path, "tests", &port).await?;
     }
 
-    load_caps(test_package_paths, port).await?;
+    load_caps(test_package_paths, capabilities, deny_capabilities, port).await?;
 
     info!("Done loading tests.");
     Ok(())
@@ -644,7 +793,8 @@ async fn run_tests(
     info!("Running tests...");
     let request = inject_message::make_message(
         "tester:tester:sys",
-        Some(15),
+        // outlast the per-test timeout in handle_test, which reports TIMEOUT
+        Some(test_timeout + 1),
         &serde_json::to_string(&serde_json::json!({
             "Run": {
                 "input_node_names": node_names,
@@ -686,16 +836,201 @@ async fn run_tests(
     Ok(())
 }
 
+/// Replace script arguments that name files in the test dir with their absolute paths
+fn expand_script_paths(script: &str, test_dir_path: &Path) -> String {
+    script
+        .split_whitespace()
+        .map(|item| {
+            test_dir_path
+                .join(item)
+                .canonicalize()
+                .ok()
+                .and_then(|p| p.to_str().map(|s| s.to_string()))
+                .unwrap_or_else(|| item.to_string())
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
 #[instrument(level = "trace", skip_all)]
-async fn handle_test(
-    detached: bool,
-    runtime_path: &Path,
-    version: &str,
-    test: Test,
+fn run_test_script(command: &str, expected_exit_code: i32) -> Result<()> {
+    let output = Command::new("bash").args(["-c", command]).output()?;
+    let exit_code = output.status.code();
+    if exit_code != Some(expected_exit_code) {
+        return Err(eyre!(
+            "Test script `{command}` exited with code {exit_code:?}; expected {expected_exit_code}\nstdout: {}\nstderr: {}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr),
+        ));
+    }
+    Ok(())
+}
+
+/// What `setup_scripts` & `teardown_scripts` are told about the test they
+/// run for
+struct ScriptEnv<'a> {
+    test_name: &'a str,
+    kinode_port: Option<u16>,
+    anvil_port: u16,
+}
+
+impl ScriptEnv<'_> {
+    fn vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = vec![
+            ("TEST_NAME", self.test_name.to_string()),
+            ("ANVIL_PORT", self.anvil_port.to_string()),
+        ];
+        if let Some(kinode_port) = self.kinode_port {
+            vars.push(("KINODE_PORT", kinode_port.to_string()));
+        }
+        vars
+    }
+}
+
+/// The top-level `setup_scripts` spawn before the test's own
+fn setup_scripts_in_order<'a>(
+    config_setup_scripts: &'a [String],
+    test_setup_scripts: &'a [String],
+) -> Vec<&'a String> {
+    config_setup_scripts
+        .iter()
+        .chain(test_setup_scripts.iter())
+        .collect()
+}
+
+/// The test's own `teardown_scripts` run before the top-level ones, undoing
+/// setup innermost first
+fn teardown_scripts_in_order<'a>(
+    test_teardown_scripts: Option<&'a [String]>,
+    config_teardown_scripts: &'a [String],
+) -> Vec<&'a String> {
+    test_teardown_scripts
+        .unwrap_or_default()
+        .iter()
+        .chain(config_teardown_scripts.iter())
+        .collect()
+}
+
+/// Spawn `setup_scripts` in order, without waiting for them
+fn spawn_setup_scripts(
+    setup_scripts: &[&String],
     test_dir_path: &Path,
-    persist_home: bool,
-    always_print_node_output: bool,
+    script_env: &ScriptEnv,
+) -> Result<Vec<(String, std::process::Child)>> {
+    setup_scripts
+        .iter()
+        .map(|script| {
+            let command = expand_script_paths(script, test_dir_path);
+            info!("Spawning setup script `{command}`...");
+            let child = Command::new("bash")
+                .args(["-c", &command])
+                .envs(script_env.vars())
+                .spawn()?;
+            Ok((command, child))
+        })
+        .collect()
+}
+
+/// Fail as `SETUP_FAILED` if any setup script has already exited non-zero
+fn check_setup_scripts(setup_scripts: &mut [(String, std::process::Child)]) -> Result<()> {
+    for (command, child) in setup_scripts.iter_mut() {
+        if let Some(status) = child.try_wait()? {
+            if !status.success() {
+                return Err(Report::new(TestSetupFailed {
+                    reason: format!("Setup script `{command}` failed: {status}"),
+                })
+                .with_suggestion(|| "Check the setup script runs on its own"));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[instrument(level = "trace", skip_all)]
+async fn run_teardown_scripts(
+    teardown_scripts: &[&String],
+    test_dir_path: &Path,
+    teardown_timeout_seconds: Option<u64>,
+    script_env: &ScriptEnv<'_>,
+) -> Result<()> {
+    for script in teardown_scripts {
+        let command = expand_script_paths(script, test_dir_path);
+        info!("Running teardown script `{command}`...");
+        let mut child = tokio::process::Command::new("bash")
+            .args(["-c", &command])
+            .envs(script_env.vars())
+            .kill_on_drop(true)
+            .spawn()?;
+        let status = match teardown_timeout_seconds {
+            None => child.wait().await?,
+            Some(timeout) => {
+                match tokio::time::timeout(Duration::from_secs(timeout), child.wait()).await {
+                    Ok(status) => status?,
+                    Err(_) => {
+                        let _ = child.kill().await;
+                        return Err(eyre!(
+                            "Teardown script `{command}` timed out after {timeout}s"
+                        )
+                        .with_suggestion(|| "Increase `teardown_timeout_seconds` in tests.toml"));
+                    }
+                }
+            }
+        };
+        if !status.success() {
+            return Err(eyre!("Teardown script `{command}` failed: {status}"));
+        }
+    }
+    Ok(())
+}
+
+/// What every test of a `kit run-tests` shares: the runtime, the loaded
+/// tests.toml & the command's flags
+#[derive(Clone, Copy)]
+struct TestEnvironment<'a> {
+    detached: bool,
+    runtime_path: &'a Path,
+    version: &'a str,
+    test_dir_path: &'a Path,
+    config: &'a Config,
+    persist_state: Option<&'a Path>,
+    measure_io: bool,
+}
+
+#[instrument(level = "trace", skip_all)]
+async fn handle_test(
+    env: TestEnvironment<'_>,
+    mut test: Test,
+    test_index: usize,
+    timeline: &mut Timeline,
+    output: &NodeOutput,
 ) -> Result<()> {
+    let TestEnvironment {
+        detached,
+        runtime_path,
+        version,
+        test_dir_path,
+        config,
+        persist_state,
+        measure_io,
+    } = env;
+    let persist_home = config.persist_home;
+    let always_print_node_output = config.always_print_node_output;
+    let teardown_on_failure = config.teardown_on_failure.unwrap_or(true);
+    let teardown_timeout_seconds = config.teardown_timeout_seconds;
+    let wit_coverage = config.wit_coverage.unwrap_or(false);
+    let max_memory_mb = config.max_memory_mb;
+    let timeout_secs = config.timeout_secs;
+    let storage_latency_ms = config.storage_latency_ms;
+
+    let Some(timeout_secs) = test.timeout_secs.or(timeout_secs) else {
+        return Err(
+            eyre!("Test {test_index} has no `timeout_secs`").with_suggestion(|| {
+                "Set `timeout_secs` for the test or at the top level of tests.toml"
+            }),
+        );
+    };
+
+    timeline.start_phase("build");
     let (setup_packages, test_package_paths) = build_packages(
         &test,
         test_dir_path,
@@ -706,6 +1041,18 @@ async fn handle_test(
     )
     .await?;
 
+    timeline.start_phase("startup");
+    let test_name = report::test_name(test_index, &test);
+    let script_env = ScriptEnv {
+        test_name: &test_name,
+        kinode_port: test.nodes.first().map(|n| n.port),
+        anvil_port: test.fakechain_router,
+    };
+    let teardown_scripts =
+        teardown_scripts_in_order(test.teardown_scripts.as_deref(), &config.teardown_scripts);
+    let teardown_on_failure = test.teardown_on_failure.unwrap_or(teardown_on_failure);
+    let teardown_timeout_seconds = test.teardown_timeout_seconds.or(teardown_timeout_seconds);
+
     let SetupCleanupReturn {
         send_to_cleanup,
         send_to_kill,
@@ -716,30 +1063,21 @@ async fn handle_test(
         node_handles,
     } = setup_cleanup(&detached, &persist_home).await?;
 
-    let setup_scripts: Vec<i32> = test
-        .setup_scripts
+    let mut setup_scripts = spawn_setup_scripts(
+        &setup_scripts_in_order(&config.setup_scripts, &test.setup_scripts),
+        test_dir_path,
+        &script_env,
+    )?;
+    let setup_script_pids: Vec<i32> = setup_scripts
         .iter()
-        .map(|script| {
-            let command = script
-                .split_whitespace()
-                .map(|item| {
-                    test_dir_path
-                        .join(&item)
-                        .canonicalize()
-                        .ok()
-                        .and_then(|p| p.to_str().map(|s| s.to_string()))
-                        .unwrap_or_else(|| item.to_string())
-                })
-                .collect::<Vec<String>>()
-                .join(" ");
-            Command::new("bash")
-                .args(["-c", &command])
-                .spawn()
-                .expect("")
-                .id() as i32
-        })
+        .map(|(_, child)| child.id() as i32)
         .collect();
 
+    if test.network_policy.is_some() {
+        check_network_policy_allowed()?;
+        assign_ws_ports(&mut test.nodes)?;
+    }
+
     // boot fakechain
     let recv_kill_in_start_chain = send_to_kill.subscribe();
     let version = Some(version.parse()?);
@@ -747,10 +1085,26 @@ async fn handle_test(
         test.fakechain_router,
         recv_kill_in_start_chain,
         version,
-        false,
+        &ChainOptions {
+            block_time: test.block_time,
+            ..Default::default()
+        },
     )
     .await?;
 
+    let metrics = MetricValues::default();
+
+    if wit_coverage {
+        // the coverage report reads the messages the nodes log
+        for node in test.nodes.iter_mut() {
+            node.runtime_verbosity = Some(
+                node.runtime_verbosity
+                    .unwrap_or_default()
+                    .max(MESSAGE_LOG_VERBOSITY),
+            );
+        }
+    }
+
     // Process each node
     boot_nodes(
         &test.nodes,
@@ -759,63 +1113,246 @@ async fn handle_test(
         &detached,
         &mut master_node_port,
         &anvil_process.as_ref().map(|ap| ap.id() as i32),
-        &setup_scripts,
+        &setup_script_pids,
         Arc::clone(&node_cleanup_infos),
         &send_to_kill,
         Arc::clone(&node_handles),
+        BootOptions {
+            persist_state: persist_state.map(|state_dir| (state_dir, test_index)),
+            metrics: Some(&metrics),
+            output: Some(output),
+            storage_latency_ms: test.storage_latency_ms.or(storage_latency_ms),
+        },
     )
     .await?;
 
+    if let Err(e) = check_setup_scripts(&mut setup_scripts) {
+        // clean up whatever the setup scripts did before one failed
+        if let Err(teardown_error) = run_teardown_scripts(
+            &teardown_scripts,
+            test_dir_path,
+            teardown_timeout_seconds,
+            &script_env,
+        )
+        .await
+        {
+            warn!("{teardown_error:?}");
+        }
+        let _ = send_to_cleanup.send(true);
+        for handle in task_handles {
+            handle.await.unwrap();
+        }
+        return Err(e);
+    }
+
+    let mut memory_monitor = match test.max_memory_mb.or(max_memory_mb) {
+        None => None,
+        Some(max_memory_mb) => {
+            let pids = node_cleanup_infos
+                .lock()
+                .await
+                .iter()
+                .map(|n| n.process_id)
+                .collect::<Vec<_>>();
+            let nodes = test
+                .nodes
+                .iter()
+                .map(|n| n.fake_node_name.clone())
+                .zip(pids)
+                .collect();
+            Some(MemoryMonitor::start(nodes, max_memory_mb))
+        }
+    };
+
+    let cgroup_limits = if test.cpu_shares.is_some() || test.memory_limit_mb.is_some() {
+        let pids = node_cleanup_infos
+            .lock()
+            .await
+            .iter()
+            .map(|n| n.process_id)
+            .collect::<Vec<_>>();
+        let nodes = test
+            .nodes
+            .iter()
+            .map(|n| n.fake_node_name.clone())
+            .zip(pids)
+            .collect();
+        CgroupLimits::apply(nodes, test.cpu_shares, test.memory_limit_mb)?
+    } else {
+        None
+    };
+
     for node in &test.nodes {
+        let url = format!("http://localhost:{}", node.port);
+        for zip_path in &test.pre_install {
+            start_package::install_zip(zip_path, &url, start_package::DEFAULT_INSTALL_RETRIES)
+                .await?;
+        }
         load_setups(&setup_packages, node.port.clone()).await?;
     }
 
-    load_tests(&test_package_paths, master_node_port.unwrap().clone()).await?;
+    load_tests(
+        &test_package_paths,
+        test.capabilities.as_deref().unwrap_or_default(),
+        test.deny_capabilities.as_deref().unwrap_or_default(),
+        master_node_port.unwrap().clone(),
+    )
+    .await?;
+
+    if let Some(warm_up_seconds) = test.warm_up_seconds.filter(|s| *s > 0) {
+        info!("Warming up for {warm_up_seconds}s...");
+        sleep(Duration::from_secs(warm_up_seconds)).await;
+    }
+
+    let network_policy_guard = match test.network_policy {
+        Some(ref network_policy) => Some(apply_network_policy(network_policy, &test.nodes)?),
+        None => None,
+    };
+
+    let io_snapshot = if measure_io || test.max_write_mb.is_some() {
+        let pids = node_cleanup_infos
+            .lock()
+            .await
+            .iter()
+            .map(|n| n.process_id)
+            .collect::<Vec<_>>();
+        let nodes = test
+            .nodes
+            .iter()
+            .map(|n| n.fake_node_name.clone())
+            .zip(pids)
+            .collect();
+        Some(IoSnapshot::take(nodes))
+    } else {
+        None
+    };
 
     let ports = test.nodes.iter().map(|n| n.port).collect();
 
-    let tests_result = run_tests(
-        &test.test_package_paths,
-        ports,
-        make_node_names(test.nodes)?,
-        test.timeout_secs,
-    )
-    .await;
-
-    for script in test.test_scripts {
-        let command = script
-            .split_whitespace()
-            .map(|item| {
-                test_dir_path
-                    .join(&item)
-                    .canonicalize()
-                    .ok()
-                    .and_then(|p| p.to_str().map(|s| s.to_string()))
-                    .unwrap_or_else(|| item.to_string())
-            })
-            .collect::<Vec<String>>()
-            .join(" ");
-        build::run_command(Command::new("bash").args(["-c", &command]), false)?;
+    timeline.start_phase("run");
+    let test_nodes = test.nodes.clone();
+    let node_names = make_node_names(test.nodes)?;
+    let tests = async {
+        let tests = run_tests(&test.test_package_paths, ports, node_names, timeout_secs);
+        match tokio::time::timeout(Duration::from_secs(timeout_secs), tests).await {
+            Ok(tests_result) => tests_result,
+            Err(_) => Err(Report::new(TestTimeout { timeout_secs })
+                .with_suggestion(|| "Increase `timeout_secs` for the test in tests.toml")),
+        }
+    };
+    let tests_result = match memory_monitor {
+        None => tests.await,
+        Some(ref mut memory_monitor) => tokio::select! {
+            tests_result = tests => tests_result,
+            message = memory_monitor.exceeded() => Err(eyre!(message)),
+        },
+    };
+    let tests_result = match (tests_result, &test.websocket_connect) {
+        (Ok(()), Some(websocket_connect)) => run_websocket_steps(websocket_connect).await,
+        (tests_result, _) => tests_result,
+    };
+
+    if wit_coverage {
+        let package_paths: Vec<PathBuf> = setup_packages.iter().map(|s| s.path.clone()).collect();
+        let node_log = output.lock().await.stdout.clone();
+        if let Err(e) = write_wit_coverage_report(
+            test_dir_path,
+            &package_paths,
+            &test_package_paths,
+            &node_log,
+        ) {
+            warn!("Failed to write WIT coverage report: {e:?}");
+        }
+    }
+
+    let expected_exit_code = test.expected_exit_code.unwrap_or(0);
+    let test_scripts_result = test.test_scripts.iter().try_for_each(|script| {
+        let command = expand_script_paths(script, test_dir_path);
+        run_test_script(&command, expected_exit_code)
+    });
+    drop(network_policy_guard);
+    let mut tests_result = tests_result.and(test_scripts_result);
+    if let Some(ref metrics_assertions) = test.metrics_assertions {
+        let metrics_result = check_metrics(metrics_assertions, &metrics).await;
+        if tests_result.is_ok() {
+            tests_result = metrics_result;
+        }
+    }
+    if let Some(io_snapshot) = io_snapshot {
+        let io_result = io_snapshot.report(test.max_write_mb);
+        if tests_result.is_ok() {
+            tests_result = io_result;
+        }
+    }
+    if let Some(memory_monitor) = memory_monitor {
+        let memory_result = memory_monitor.stop();
+        if tests_result.is_ok() {
+            tests_result = memory_result;
+        }
+    }
+    if let Some(ref cgroup_limits) = cgroup_limits {
+        // a node killed for memory is the cause of whatever else failed
+        if let Err(e) = cgroup_limits.check_oom(test.memory_limit_mb) {
+            if let Err(ref test_error) = tests_result {
+                warn!("{test_error:?}");
+            }
+            tests_result = Err(e);
+        }
+    }
+
+    timeline.start_phase("teardown");
+    if tests_result.is_ok() || teardown_on_failure {
+        let teardown_result = run_teardown_scripts(
+            &teardown_scripts,
+            test_dir_path,
+            teardown_timeout_seconds,
+            &script_env,
+        )
+        .await;
+        if let Err(e) = teardown_result {
+            if tests_result.is_ok() {
+                tests_result = Err(e);
+            } else {
+                warn!("{e:?}");
+            }
+        }
+    } else if !teardown_scripts.is_empty() {
+        info!("Test failed and teardown_on_failure = false: skipping teardown_scripts.");
     }
 
     if tests_result.is_ok() {
         info!("PASS");
+        if let Some(state_dir) = persist_state {
+            if let Err(e) = save_state(state_dir, test_index, &test_nodes) {
+                tests_result = Err(e);
+            }
+        }
     }
 
     let _ = send_to_cleanup.send(always_print_node_output || tests_result.is_err());
     for handle in task_handles {
         handle.await.unwrap();
     }
+    // now that the nodes have exited, their cgroups can be removed
+    drop(cgroup_limits);
 
     tests_result?;
     Ok(())
 }
 
 #[instrument(level = "trace", skip_all)]
-pub async fn execute(config_path: PathBuf) -> Result<()> {
+pub async fn execute(
+    config_path: PathBuf,
+    persist_state: Option<PathBuf>,
+    reset: bool,
+    measure_io: bool,
+    gantt_output: Option<PathBuf>,
+    json_output: bool,
+    filter: Option<String>,
+) -> Result<()> {
     let detached = true; // TODO: to arg?
 
-    let (config_path, config) = load_config(&config_path)?;
+    let (config_path, mut config) = load_config(&config_path)?;
 
     debug!("{:?}", std::env::current_dir());
     debug!("{:?}", config);
@@ -823,15 +1360,20 @@ pub async fn execute(config_path: PathBuf) -> Result<()> {
     // TODO: factor out with boot_fake_node?
     let (runtime_path, version) = match config.runtime {
         Runtime::FetchVersion(ref version) => {
+            if version != "latest" {
+                // don't download a runtime too old to run the tests
+                let version = version.strip_prefix('v').unwrap_or(version);
+                check_min_runtime_version(config.min_runtime_version.as_deref(), version)?;
+            }
             boot_fake_node::get_runtime_binary(version, true).await?
         }
-        Runtime::RepoPath(runtime_path) => {
+        Runtime::RepoPath(ref runtime_path) => {
             if !runtime_path.exists() {
                 return Err(eyre!("RepoPath {:?} does not exist.", runtime_path));
             }
             let runtime_path = if runtime_path.is_dir() {
                 // Compile the runtime binary
-                boot_fake_node::compile_runtime(&runtime_path, config.runtime_build_release, true)?;
+                boot_fake_node::compile_runtime(runtime_path, config.runtime_build_release, true)?;
                 runtime_path
                     .join("target")
                     .join(if config.runtime_build_release {
@@ -841,7 +1383,7 @@ pub async fn execute(config_path: PathBuf) -> Result<()> {
                     })
                     .join("kinode")
             } else {
-                runtime_path
+                runtime_path.clone()
             };
             let Some((output, _)) = build::run_command(
                 Command::new("bash").args(["-c", &format!("{} --version", runtime_path.display())]),
@@ -861,21 +1403,227 @@ pub async fn execute(config_path: PathBuf) -> Result<()> {
         }
     };
     let version = version.strip_prefix("v").unwrap_or_else(|| &version);
+    check_min_runtime_version(config.min_runtime_version.as_deref(), version)?;
 
     let test_dir_path = PathBuf::from(config_path).canonicalize()?;
     let test_dir_path = test_dir_path.parent().unwrap();
-    for test in config.tests {
-        handle_test(
-            detached,
-            &runtime_path,
-            &version,
-            test,
-            &test_dir_path,
-            config.persist_home,
-            config.always_print_node_output,
-        )
-        .await?;
+    if let Some(ref state_dir) = persist_state {
+        if reset {
+            reset_state(state_dir)?;
+        }
+    }
+    let mut timeline = Timeline::new();
+    let mut summary = Summary::default();
+    let tests = std::mem::take(&mut config.tests);
+    let env = TestEnvironment {
+        detached,
+        runtime_path: &runtime_path,
+        version,
+        test_dir_path,
+        config: &config,
+        persist_state: persist_state.as_deref(),
+        measure_io,
+    };
+    for (test_index, test) in tests.into_iter().enumerate() {
+        let test_name = report::test_name(test_index, &test);
+        if let Some(ref filter) = filter {
+            if !matches_filter(&test_name, filter)? {
+                info!("SKIPPED {test_name}");
+                if json_output {
+                    let record = make_skipped_record(test_name);
+                    summary.add(record.status);
+                    print_json(&record);
+                }
+                continue;
+            }
+        }
+        timeline.start_test(test_index, &test);
+        let output = NodeOutput::default();
+        let start = std::time::Instant::now();
+        let test_result = handle_test(env, test, test_index, &mut timeline, &output).await;
+        timeline.end_test(test_result.is_ok());
+        if let Some(ref gantt_output) = gantt_output {
+            timeline.write_mermaid(gantt_output)?;
+        }
+        if json_output {
+            let record = make_record(test_name, &test_result, start.elapsed(), &output).await;
+            summary.add(record.status);
+            print_json(&record);
+            if record.status != TestStatus::Pass {
+                print_json(&summary);
+            }
+        }
+        test_result?;
+    }
+    if json_output {
+        print_json(&summary);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `load_config()` tests change the process-wide cwd
+    static CWD_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    const TESTS_TOML: &str = r#"
+runtime = { RepoPath = "../kinode" }
+persist_home = false
+runtime_build_release = false
+always_print_node_output = false
+
+[[tests]]
+dependency_package_paths = []
+setup_packages = []
+setup_scripts = []
+test_package_paths = []
+test_scripts = []
+fakechain_router = 8545
+
+[[tests.nodes]]
+port = 8080
+home = "home/first"
+fake_node_name = "first.dev"
+"#;
+
+    #[test]
+    fn resolve_config_relative_path_keeps_absolute_paths() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let absolute = tempfile::tempdir().unwrap();
+        let absolute = absolute.path().canonicalize().unwrap();
+        assert_eq!(
+            resolve_config_relative_path(config_dir.path(), &absolute),
+            absolute,
+        );
+    }
+
+    #[test]
+    fn resolve_config_relative_path_joins_relative_paths_to_config_dir() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let config_dir = config_dir.path().canonicalize().unwrap();
+        fs::create_dir(config_dir.join("kinode")).unwrap();
+        assert_eq!(
+            resolve_config_relative_path(&config_dir, Path::new("kinode")),
+            config_dir.join("kinode"),
+        );
+        // a node home need not exist yet
+        assert_eq!(
+            resolve_config_relative_path(&config_dir, Path::new("home/first")),
+            config_dir.join("home/first"),
+        );
+    }
+
+    #[test]
+    fn resolve_config_relative_path_resolves_parent_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path().canonicalize().unwrap();
+        fs::create_dir_all(root.join("kinode")).unwrap();
+        fs::create_dir_all(root.join("package").join("test")).unwrap();
+        assert_eq!(
+            resolve_config_relative_path(
+                &root.join("package").join("test"),
+                Path::new("../../kinode"),
+            ),
+            root.join("kinode"),
+        );
+    }
+
+    #[test]
+    fn load_config_resolves_against_tests_toml_dir_not_cwd() {
+        let _lock = CWD_LOCK.lock().unwrap();
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path().canonicalize().unwrap();
+        let test_dir = root.join("package").join("test");
+        fs::create_dir_all(&test_dir).unwrap();
+        fs::create_dir_all(root.join("package").join("kinode")).unwrap();
+        fs::write(test_dir.join("tests.toml"), TESTS_TOML).unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+
+        let original_cwd = std::env::current_dir().unwrap();
+        std::env::set_current_dir(elsewhere.path()).unwrap();
+        let loaded = load_config(&test_dir);
+        std::env::set_current_dir(original_cwd).unwrap();
+
+        let (config_path, config) = loaded.unwrap();
+        assert_eq!(config_path, test_dir.join("tests.toml"));
+        let Runtime::RepoPath(runtime_path) = config.runtime else {
+            panic!("expected a RepoPath runtime, got {:?}", config.runtime);
+        };
+        assert_eq!(runtime_path, root.join("package").join("kinode"));
+        assert_eq!(
+            config.tests[0].nodes[0].home,
+            test_dir.join("home").join("first"),
+        );
+    }
+
+    fn scripts(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn setup_scripts_in_order_runs_top_level_first() {
+        let config = scripts(&["config-a", "config-b"]);
+        let test = scripts(&["test-a"]);
+        assert_eq!(
+            setup_scripts_in_order(&config, &test),
+            ["config-a", "config-b", "test-a"],
+        );
+    }
+
+    #[test]
+    fn teardown_scripts_in_order_runs_test_first() {
+        let config = scripts(&["config-a"]);
+        let test = scripts(&["test-a", "test-b"]);
+        assert_eq!(
+            teardown_scripts_in_order(Some(&test), &config),
+            ["test-a", "test-b", "config-a"],
+        );
+        assert_eq!(teardown_scripts_in_order(None, &config), ["config-a"]);
+    }
+
+    #[tokio::test]
+    async fn teardown_scripts_run_in_order_with_script_env() {
+        let test_dir = tempfile::tempdir().unwrap();
+        fs::write(test_dir.path().join("log"), "").unwrap();
+        let script_env = ScriptEnv {
+            test_name: "chat",
+            kinode_port: Some(8080),
+            anvil_port: 8545,
+        };
+        let teardown_scripts = scripts(&[
+            "echo test $TEST_NAME $KINODE_PORT $ANVIL_PORT >> log",
+            "echo config >> log",
+        ]);
+        let teardown_scripts: Vec<&String> = teardown_scripts.iter().collect();
+        run_teardown_scripts(&teardown_scripts, test_dir.path(), None, &script_env)
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(test_dir.path().join("log")).unwrap(),
+            "test chat 8080 8545\nconfig\n",
+        );
+    }
+
+    #[test]
+    fn check_setup_scripts_fails_as_setup_failed() {
+        let test_dir = tempfile::tempdir().unwrap();
+        let script_env = ScriptEnv {
+            test_name: "chat",
+            kinode_port: None,
+            anvil_port: 8545,
+        };
+        let setup_scripts = scripts(&["true", "exit 3"]);
+        let setup_scripts: Vec<&String> = setup_scripts.iter().collect();
+        let mut children =
+            spawn_setup_scripts(&setup_scripts, test_dir.path(), &script_env).unwrap();
+        for (_, child) in children.iter_mut() {
+            child.wait().unwrap();
+        }
+        let error = check_setup_scripts(&mut children).unwrap_err();
+        assert!(error.downcast_ref::<TestSetupFailed>().is_some());
+        assert!(format!("{error}").contains("exit 3"));
+    }
+}
@@ -0,0 +1,268 @@
+use std::net::TcpListener;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tracing::{debug, info, instrument, warn};
+
+use crate::build::run_command;
+
+use super::types::{NetworkPolicy, Node};
+
+const LOOPBACK_DEVICE: &str = "lo";
+const CLEANUP_COMMAND: &str = "tc qdisc del dev lo root";
+/// Bit of `CAP_NET_ADMIN` in the capability sets of `/proc/<pid>/status`
+const CAP_NET_ADMIN: u32 = 12;
+/// How our root qdisc appears in `tc qdisc show`, telling one left by an
+/// interrupted run, e.g. on `SIGKILL`, from anyone else's
+const KIT_ROOT_QDISC: &str = "qdisc prio 1: root refcnt 2 bands 4";
+
+/// Whether our root qdisc is on the loopback device: the device is shared
+/// with the whole host, so it must be removed however `kit` exits
+static APPLIED: AtomicBool = AtomicBool::new(false);
+
+/// Removes the network policy from the loopback device when dropped
+pub struct NetworkPolicyGuard;
+
+impl Drop for NetworkPolicyGuard {
+    fn drop(&mut self) {
+        remove_network_policy();
+    }
+}
+
+/// Remove the network policy, if applied, from the loopback device;
+/// called by the guard & by `cleanup`, e.g. on a signal
+pub fn remove_network_policy() {
+    if !APPLIED.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    let result = run_command(
+        Command::new("tc").args(["qdisc", "del", "dev", LOOPBACK_DEVICE, "root"]),
+        false,
+    );
+    match result {
+        Ok(_) => debug!("Removed network policy."),
+        Err(e) => warn!("Failed to remove network policy from {LOOPBACK_DEVICE}: {e}; remove it with `{CLEANUP_COMMAND}`"),
+    }
+}
+
+/// Whether the `CapEff` line of a `/proc/<pid>/status` grants `CAP_NET_ADMIN`,
+/// as it does for root
+fn has_net_admin(proc_status: &str) -> bool {
+    proc_status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|caps| u64::from_str_radix(caps.trim(), 16).ok())
+        .map(|caps| caps & (1 << CAP_NET_ADMIN) != 0)
+        .unwrap_or(false)
+}
+
+/// A non-default root qdisc in `tc qdisc show` output, if any: the default,
+/// e.g. `qdisc noqueue 0: root refcnt 2`, has handle `0:`
+fn find_root_qdisc(qdiscs: &str) -> Option<&str> {
+    qdiscs
+        .lines()
+        .find(|line| line.contains(" root") && !line.contains(" 0: root"))
+}
+
+/// Fail before any node boots unless kit may shape the loopback device:
+/// `tc` is installed, kit has `CAP_NET_ADMIN`, and `lo` has no root qdisc
+/// but one left by an interrupted run, which is removed
+#[instrument(level = "trace", skip_all)]
+pub fn check_network_policy_allowed() -> Result<()> {
+    let proc_status = fs::read_to_string("/proc/self/status").unwrap_or_default();
+    if !has_net_admin(&proc_status) {
+        return Err(eyre!(
+            "network_policy shapes the host's loopback device {LOOPBACK_DEVICE} with `tc`, which needs root or CAP_NET_ADMIN"
+        )
+        .with_suggestion(|| "Re-run `kit run-tests` as root, or remove `network_policy` from tests.toml."));
+    }
+    let (stdout, _) = run_command(
+        Command::new("tc").args(["qdisc", "show", "dev", LOOPBACK_DEVICE]),
+        false,
+    )
+    .with_suggestion(|| "network_policy requires `tc` (iproute2).")?
+    .unwrap_or_default();
+    match find_root_qdisc(&stdout) {
+        None => Ok(()),
+        Some(existing) if existing.trim_end() == KIT_ROOT_QDISC => {
+            warn!("Removing network_policy left on {LOOPBACK_DEVICE} by an interrupted `kit run-tests`.");
+            tc(&["qdisc", "del", "dev", LOOPBACK_DEVICE, "root"])
+        }
+        Some(existing) => Err(eyre!(
+            "Refusing to apply network_policy: {LOOPBACK_DEVICE} already has a root qdisc: {existing}"
+        )
+        .with_suggestion(|| format!("Remove it with `{CLEANUP_COMMAND}` if it is not needed."))),
+    }
+}
+
+/// Give each node without a `ws_port` a free one, so that the networking
+/// traffic between nodes can be told apart from other local traffic
+pub fn assign_ws_ports(nodes: &mut [Node]) -> Result<()> {
+    for node in nodes.iter_mut().filter(|n| n.ws_port.is_none()) {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        node.ws_port = Some(listener.local_addr()?.port());
+    }
+    Ok(())
+}
+
+fn tc(args: &[&str]) -> Result<()> {
+    run_command(Command::new("tc").args(args), false)
+        .map_err(|e| {
+            eyre!("Failed to apply network_policy: {e}").with_suggestion(|| {
+                "network_policy requires `tc` (iproute2) and permission to configure the loopback device (e.g., root or CAP_NET_ADMIN)."
+            })
+        })
+        .map(|_| ())
+}
+
+/// The `tc netem` impairments of `policy`, e.g. `["loss", "10%"]`
+fn make_netem_args(policy: &NetworkPolicy) -> Result<Vec<String>> {
+    let mut netem = vec![];
+    if let Some(drop_rate) = policy.drop_rate {
+        if !(0.0..=1.0).contains(&drop_rate) {
+            return Err(eyre!(
+                "network_policy drop_rate must be between 0 and 1; got {drop_rate}"
+            ));
+        }
+        netem.extend(["loss".to_string(), format!("{}%", drop_rate * 100.0)]);
+    }
+    if let Some(latency_ms) = policy.latency_ms {
+        netem.extend(["delay".to_string(), format!("{latency_ms}ms")]);
+    }
+    if netem.is_empty() {
+        return Err(eyre!(
+            "network_policy must set at least one of drop_rate, latency_ms"
+        ));
+    }
+    Ok(netem)
+}
+
+/// Drop and delay packets between nodes on their `ws_port`s using `tc netem`
+/// on the loopback device; since nodes network over TCP, a dropped packet
+/// is retransmitted, i.e. appears to processes as additional latency.
+/// Other loopback traffic, e.g. to anvil, is filtered into unimpaired bands;
+/// call `check_network_policy_allowed()` first
+#[instrument(level = "trace", skip_all)]
+pub fn apply_network_policy(policy: &NetworkPolicy, nodes: &[Node]) -> Result<NetworkPolicyGuard> {
+    let netem = make_netem_args(policy)?;
+    // band 1:4 holds the netem qdisc; the default prio bands 1:1-1:3 are untouched
+    tc(&[
+        "qdisc",
+        "add",
+        "dev",
+        LOOPBACK_DEVICE,
+        "root",
+        "handle",
+        "1:",
+        "prio",
+        "bands",
+        "4",
+    ])?;
+    APPLIED.store(true, Ordering::SeqCst);
+    let guard = NetworkPolicyGuard;
+    let mut args = vec![
+        "qdisc",
+        "add",
+        "dev",
+        LOOPBACK_DEVICE,
+        "parent",
+        "1:4",
+        "handle",
+        "40:",
+        "netem",
+    ];
+    args.extend(netem.iter().map(|a| a.as_str()));
+    tc(&args)?;
+
+    for node in nodes {
+        let Some(ws_port) = node.ws_port else {
+            continue;
+        };
+        let ws_port = ws_port.to_string();
+        for direction in ["sport", "dport"] {
+            tc(&[
+                "filter",
+                "add",
+                "dev",
+                LOOPBACK_DEVICE,
+                "parent",
+                "1:0",
+                "protocol",
+                "ip",
+                "prio",
+                "1",
+                "u32",
+                "match",
+                "ip",
+                direction,
+                &ws_port,
+                "0xffff",
+                "flowid",
+                "1:4",
+            ])?;
+        }
+    }
+
+    info!(
+        "Applied network_policy between nodes:{}{}; if kit is killed before cleaning up, remove it with `{CLEANUP_COMMAND}`",
+        policy
+            .drop_rate
+            .map(|d| format!(" drop_rate = {d}"))
+            .unwrap_or_default(),
+        policy
+            .latency_ms
+            .map(|l| format!(" latency_ms = {l}"))
+            .unwrap_or_default(),
+    );
+    Ok(guard)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn has_net_admin_reads_effective_capabilities() {
+        let root = "Name:\tkit\nCapInh:\t0000000000000000\nCapEff:\t000001ffffffffff\n";
+        assert!(has_net_admin(root));
+        let net_admin_only = "CapEff:\t0000000000001000\n";
+        assert!(has_net_admin(net_admin_only));
+        let unprivileged = "CapPrm:\t000001ffffffffff\nCapEff:\t0000000000000000\n";
+        assert!(!has_net_admin(unprivileged));
+        assert!(!has_net_admin(""));
+    }
+
+    #[test]
+    fn find_root_qdisc_ignores_default() {
+        assert_eq!(find_root_qdisc("qdisc noqueue 0: root refcnt 2 \n"), None);
+        let qdiscs = format!("{KIT_ROOT_QDISC} priomap 1 2 2 2 1 2 0 0 1 1 1 1 1 1 1 1\nqdisc netem 40: parent 1:4 limit 1000 loss 10%\n");
+        assert_eq!(
+            find_root_qdisc(&qdiscs).map(|q| q.starts_with(KIT_ROOT_QDISC)),
+            Some(true),
+        );
+    }
+
+    #[test]
+    fn make_netem_args_from_policy() {
+        let policy = NetworkPolicy {
+            drop_rate: Some(0.1),
+            latency_ms: Some(100),
+        };
+        assert_eq!(
+            make_netem_args(&policy).unwrap(),
+            ["loss", "10%", "delay", "100ms"],
+        );
+        let policy = NetworkPolicy {
+            drop_rate: Some(1.5),
+            latency_ms: None,
+        };
+        assert!(make_netem_args(&policy).is_err());
+        let policy = NetworkPolicy {
+            drop_rate: None,
+            latency_ms: None,
+        };
+        assert!(make_netem_args(&policy).is_err());
+    }
+}
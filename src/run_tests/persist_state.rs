@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+
+use color_eyre::Result;
+use fs_err as fs;
+use tracing::{info, instrument};
+
+use crate::build::copy_dir;
+
+use super::types::Node;
+
+/// `<state_dir>/<test_index>/<fake_node_name>/vfs`
+fn node_vfs_state_dir(state_dir: &Path, test_index: usize, node: &Node) -> PathBuf {
+    state_dir
+        .join(test_index.to_string())
+        .join(&node.fake_node_name)
+        .join("vfs")
+}
+
+/// Clear the persisted state
+#[instrument(level = "trace", skip_all)]
+pub fn reset_state(state_dir: &Path) -> Result<()> {
+    if state_dir.exists() {
+        fs::remove_dir_all(state_dir)?;
+        info!("Cleared persisted test state in {state_dir:?}.");
+    }
+    Ok(())
+}
+
+/// Load a node's persisted VFS, if any, into its (freshly cleared) home
+#[instrument(level = "trace", skip_all)]
+pub fn restore_node_state(
+    state_dir: &Path,
+    test_index: usize,
+    node: &Node,
+    node_home: &Path,
+) -> Result<()> {
+    let vfs_state_dir = node_vfs_state_dir(state_dir, test_index, node);
+    if !vfs_state_dir.exists() {
+        return Ok(());
+    }
+    copy_dir(&vfs_state_dir, node_home.join("vfs"))?;
+    info!(
+        "Restored {} VFS state from {vfs_state_dir:?}.",
+        node.fake_node_name
+    );
+    Ok(())
+}
+
+/// Save each node's VFS, replacing the previously persisted state
+#[instrument(level = "trace", skip_all)]
+pub fn save_state(state_dir: &Path, test_index: usize, nodes: &[Node]) -> Result<()> {
+    for node in nodes {
+        let vfs_dir = node.home.join("vfs");
+        if !vfs_dir.exists() {
+            continue;
+        }
+        let vfs_state_dir = node_vfs_state_dir(state_dir, test_index, node);
+        if vfs_state_dir.exists() {
+            fs::remove_dir_all(&vfs_state_dir)?;
+        }
+        copy_dir(&vfs_dir, &vfs_state_dir)?;
+    }
+    info!("Saved test state to {state_dir:?}.");
+    Ok(())
+}
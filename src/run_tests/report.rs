@@ -0,0 +1,145 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use color_eyre::{eyre::Report, Result};
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+use crate::run_tests::types::Test;
+
+/// Output the test nodes print while a test runs
+#[derive(Debug, Default)]
+pub struct CapturedOutput {
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub type NodeOutput = Arc<Mutex<CapturedOutput>>;
+
+/// A test's tests did not finish within its `timeout_secs`
+#[derive(Debug, thiserror::Error)]
+#[error("TIMEOUT: tests did not finish within the expected {timeout_secs}s")]
+pub struct TestTimeout {
+    pub timeout_secs: u64,
+}
+
+/// One of a test's `setup_scripts` failed, so its tests were not run
+#[derive(Debug, thiserror::Error)]
+#[error("SETUP_FAILED: {reason}")]
+pub struct TestSetupFailed {
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestStatus {
+    Pass,
+    Fail,
+    Timeout,
+    #[serde(rename = "setup_failed")]
+    SetupFailed,
+    Skipped,
+}
+
+impl TestStatus {
+    pub fn from_result(result: &Result<(), Report>) -> Self {
+        match result {
+            Ok(()) => TestStatus::Pass,
+            Err(e) if e.downcast_ref::<TestTimeout>().is_some() => TestStatus::Timeout,
+            Err(e) if e.downcast_ref::<TestSetupFailed>().is_some() => TestStatus::SetupFailed,
+            Err(_) => TestStatus::Fail,
+        }
+    }
+}
+
+/// One line of `kit run-tests --output json`
+#[derive(Debug, Serialize)]
+pub struct TestRecord {
+    pub test: String,
+    pub status: TestStatus,
+    pub duration_ms: u128,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Last line of `kit run-tests --output json`
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub total: usize,
+    pub passed: usize,
+    pub failed: usize,
+    pub timed_out: usize,
+    pub setup_failed: usize,
+    pub skipped: usize,
+}
+
+impl Summary {
+    pub fn add(&mut self, status: TestStatus) {
+        self.total += 1;
+        match status {
+            TestStatus::Pass => self.passed += 1,
+            TestStatus::Fail => self.failed += 1,
+            TestStatus::Timeout => self.timed_out += 1,
+            TestStatus::SetupFailed => self.setup_failed += 1,
+            TestStatus::Skipped => self.skipped += 1,
+        }
+    }
+}
+
+/// The test's `name`, else `test 0 (path/to/test-a, path/to/test-b)`
+pub fn test_name(test_index: usize, test: &Test) -> String {
+    if let Some(ref name) = test.name {
+        return name.clone();
+    }
+    let test_packages: Vec<String> = test
+        .test_package_paths
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect();
+    format!("test {test_index} ({})", test_packages.join(", "))
+}
+
+/// Glob match if `filter` contains `*` or `?`, else substring match
+pub fn matches_filter(name: &str, filter: &str) -> Result<bool> {
+    if filter.contains(['*', '?']) {
+        Ok(glob::Pattern::new(filter)?.matches(name))
+    } else {
+        Ok(name.contains(filter))
+    }
+}
+
+/// A record of a test not run since its name doesn't match `--filter`
+pub fn make_skipped_record(test: String) -> TestRecord {
+    TestRecord {
+        test,
+        status: TestStatus::Skipped,
+        duration_ms: 0,
+        stdout: String::new(),
+        stderr: String::new(),
+    }
+}
+
+/// A record of a finished test; its error, if any, follows the nodes' stderr
+pub async fn make_record(
+    test: String,
+    result: &Result<(), Report>,
+    duration: Duration,
+    output: &NodeOutput,
+) -> TestRecord {
+    let output = output.lock().await;
+    let mut stderr = output.stderr.clone();
+    if let Err(e) = result {
+        stderr.push_str(&format!("{e}\n"));
+    }
+    TestRecord {
+        test,
+        status: TestStatus::from_result(result),
+        duration_ms: duration.as_millis(),
+        stdout: output.stdout.clone(),
+        stderr,
+    }
+}
+
+pub fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string(value).unwrap());
+}
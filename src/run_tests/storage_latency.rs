@@ -0,0 +1,54 @@
+use std::process::Command;
+
+use color_eyre::{eyre::eyre, Result, Section};
+use fs_err as fs;
+use tokio::time::{sleep, Duration};
+
+use crate::build::run_command;
+
+/// Syscalls that act on files only: those that name a path, plus those on
+/// the fds of open files, i.e. not the `read`s & `write`s nodes network with
+const STORAGE_SYSCALLS: &str =
+    "%file,pread64,pwrite64,preadv,pwritev,lseek,fsync,fdatasync,ftruncate,fallocate";
+const TRACED_PID_POLLS: u64 = 20;
+const TRACED_PID_POLL_INTERVAL_MS: u64 = 50;
+
+/// Command to run a node under so that each of its storage syscalls,
+/// & so each VFS, kv & sqlite operation, is delayed by `storage_latency_ms`,
+/// using `strace` syscall tampering
+pub fn make_storage_latency_wrapper(storage_latency_ms: u64) -> Result<Vec<String>> {
+    run_command(Command::new("strace").arg("-V"), false).map_err(|e| {
+        eyre!("Failed to apply storage_latency_ms: {e}")
+            .with_suggestion(|| "storage_latency_ms requires `strace` (Linux only).")
+    })?;
+    Ok(vec![
+        "strace".into(),
+        "-f".into(),
+        "-qq".into(),
+        "-o".into(),
+        "/dev/null".into(),
+        "-e".into(),
+        format!("trace={STORAGE_SYSCALLS}"),
+        "-e".into(),
+        format!(
+            "inject={STORAGE_SYSCALLS}:delay_enter={}",
+            storage_latency_ms * 1000,
+        ),
+    ])
+}
+
+/// The pid of the node a storage latency wrapper with pid `wrapper_pid` runs,
+/// so that it is the node, not `strace`, that is measured & cleaned up
+pub async fn get_traced_pid(wrapper_pid: u32) -> Result<i32> {
+    let children_path = format!("/proc/{wrapper_pid}/task/{wrapper_pid}/children");
+    for _ in 0..TRACED_PID_POLLS {
+        let children = fs::read_to_string(&children_path)?;
+        if let Some(pid) = children.split_whitespace().next() {
+            return Ok(pid.parse()?);
+        }
+        sleep(Duration::from_millis(TRACED_PID_POLL_INTERVAL_MS)).await;
+    }
+    Err(eyre!(
+        "storage latency wrapper {wrapper_pid} did not start the node"
+    ))
+}
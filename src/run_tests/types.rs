@@ -13,6 +13,34 @@ pub struct Config {
     pub runtime_build_release: bool,
     pub persist_home: bool,
     pub always_print_node_output: bool,
+    /// Whether `teardown_scripts` run when a test fails (default: `true`)
+    pub teardown_on_failure: Option<bool>,
+    /// Kill `teardown_scripts` still running after this long (default: no limit)
+    pub teardown_timeout_seconds: Option<u64>,
+    /// Write an HTML report of which WIT variant cases of the setup packages
+    /// are sent or received while the tests run to `target/wit-coverage/`,
+    /// read from the message log of nodes run at runtime verbosity 3
+    /// (default: `false`)
+    pub wit_coverage: Option<bool>,
+    /// Kill any test node whose RSS exceeds this many MB, failing the test
+    /// (default: no limit)
+    pub max_memory_mb: Option<u64>,
+    /// Seconds each test may run before it is reported as `TIMEOUT`, for
+    /// tests that set no `timeout_secs` of their own
+    pub timeout_secs: Option<u64>,
+    /// Delay each filesystem operation of the test nodes by this many ms,
+    /// to surface processes that block on VFS, kv or sqlite; requires
+    /// `strace` (default: `0`)
+    pub storage_latency_ms: Option<u64>,
+    /// Scripts spawned for each test before its own `setup_scripts`
+    #[serde(default)]
+    pub setup_scripts: Vec<String>,
+    /// Scripts run for each test after its own `teardown_scripts`
+    #[serde(default)]
+    pub teardown_scripts: Vec<String>,
+    /// Fail before running any test if the runtime is older than this,
+    /// e.g. `"v0.8.0"` (default: any version)
+    pub min_runtime_version: Option<String>,
     pub tests: Vec<Test>,
 }
 
@@ -24,27 +52,151 @@ pub enum Runtime {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Test {
+    /// Name to report the test by & match `--filter` against
+    /// (default: `test <index> (<test_package_paths>)`)
+    pub name: Option<String>,
     pub dependency_package_paths: Vec<PathBuf>,
     pub setup_packages: Vec<SetupPackage>,
+    /// Package zips, relative to this file & named `<package>:<publisher>.zip`
+    /// as `kit build` writes them, installed on each node in order once it
+    /// is up & before `setup_packages`
+    #[serde(default)]
+    pub pre_install: Vec<PathBuf>,
+    /// Scripts spawned in order, without waiting, before the fakechain
+    /// boots, with `TEST_NAME`, `KINODE_PORT` (the first node's) &
+    /// `ANVIL_PORT` set; one that has exited non-zero by the time the nodes
+    /// are up fails the test as `SETUP_FAILED`, after `teardown_scripts` run
     pub setup_scripts: Vec<String>,
     pub test_package_paths: Vec<PathBuf>,
     pub test_scripts: Vec<String>,
-    pub timeout_secs: u64,
+    /// Exit code each of `test_scripts` must exit with to pass (default: `0`)
+    pub expected_exit_code: Option<i32>,
+    /// Scripts run in order after the test, each waited for, with the same
+    /// environment as `setup_scripts`
+    pub teardown_scripts: Option<Vec<String>>,
+    /// Overrides the top-level `teardown_on_failure` for this test
+    pub teardown_on_failure: Option<bool>,
+    /// Overrides the top-level `teardown_timeout_seconds` for this test
+    pub teardown_timeout_seconds: Option<u64>,
+    /// Overrides the top-level `max_memory_mb` for this test
+    pub max_memory_mb: Option<u64>,
+    /// Relative CPU share of each node, as cgroup v1 `cpu.shares`
+    /// (`2` to `262144`; the default share is `1024`); Linux only
+    pub cpu_shares: Option<u64>,
+    /// Memory each node may use before the kernel OOM-kills it, failing
+    /// the test; Linux only
+    pub memory_limit_mb: Option<u64>,
+    /// Fail the test if any node writes more than this many MB to disk
+    /// while the tests run; implies `--measure-io` (default: no limit)
+    pub max_write_mb: Option<u64>,
+    /// Seconds to let setup & test processes initialize after they are
+    /// loaded and before the tests start (default: `0`)
+    pub warm_up_seconds: Option<u64>,
+    /// Overrides the top-level `timeout_secs` for this test
+    pub timeout_secs: Option<u64>,
+    /// Overrides the top-level `storage_latency_ms` for this test
+    pub storage_latency_ms: Option<u64>,
     pub fakechain_router: u16,
+    /// Seconds between the blocks the fakechain mines; `0` mines a block
+    /// per transaction (default: `0`)
+    pub block_time: Option<f64>,
+    /// Capabilities granted to each test process on top of those
+    /// requested in its `manifest.json`
+    pub capabilities: Option<Vec<TestCapability>>,
+    /// Capabilities withheld from each test process even if requested,
+    /// e.g. `"http_client"` or `"eth:distro:sys"`, to test how it handles
+    /// being denied
+    pub deny_capabilities: Option<Vec<String>>,
+    /// Expected values of the metrics test processes emit by printing
+    /// `METRIC: name=<name> value=<value>`, checked after the tests run
+    pub metrics_assertions: Option<Vec<MetricAssertion>>,
+    /// WebSocket to connect to after the test processes pass
+    pub websocket_connect: Option<WebSocketConnect>,
+    /// Drop and/or delay packets between nodes while the tests run, using
+    /// `tc` on the host's loopback device: Linux only, needs root or
+    /// CAP_NET_ADMIN, and refuses to run if `lo` already has a root qdisc
+    pub network_policy: Option<NetworkPolicy>,
     pub nodes: Vec<Node>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricAssertion {
+    pub name: String,
+    /// Inclusive lower bound on the last value emitted (default: none)
+    pub min: Option<f64>,
+    /// Inclusive upper bound on the last value emitted (default: none)
+    pub max: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkPolicy {
+    /// Fraction of packets between nodes to drop, from `0` to `1`
+    pub drop_rate: Option<f64>,
+    /// Latency added to each packet between nodes
+    pub latency_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestCapability {
+    /// `"messaging"`, or any other kind together with its `params`
+    pub kind: String,
+    /// Process that issues the capability, e.g. `"vfs:distro:sys"`
+    pub target: String,
+    /// JSON-encoded capability params; required for kinds other than `"messaging"`
+    pub params: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebSocketConnect {
+    pub url: String,
+    /// Extra handshake headers, e.g. a `Cookie` for authenticated paths
+    pub headers: Option<std::collections::HashMap<String, String>>,
+    /// Frames to send and expect, in order
+    pub steps: Vec<WebSocketStep>,
+    /// Timeout for connecting & for each expected frame (default: 5)
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum WebSocketStep {
+    Send { send: String },
+    Expect { expect: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SetupPackageConfig")]
 pub struct SetupPackage {
     pub path: PathBuf,
     pub run: bool,
 }
 
+/// `setup_packages` entries may be given either as a bare path, in which
+/// case the package is built and installed, or as `{ path, run }`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum SetupPackageConfig {
+    Path(PathBuf),
+    Full { path: PathBuf, run: bool },
+}
+
+impl From<SetupPackageConfig> for SetupPackage {
+    fn from(config: SetupPackageConfig) -> Self {
+        match config {
+            SetupPackageConfig::Path(path) => SetupPackage { path, run: true },
+            SetupPackageConfig::Full { path, run } => SetupPackage { path, run },
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     pub port: u16,
     pub home: PathBuf,
     pub fake_node_name: String,
+    /// Port for node-to-node networking; assigned automatically if a
+    /// `network_policy` is set
+    pub ws_port: Option<u16>,
     pub password: Option<String>,
     pub rpc: Option<String>,
     pub runtime_verbosity: Option<u8>,
@@ -0,0 +1,288 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use color_eyre::Result;
+use fs_err as fs;
+use regex::Regex;
+use serde_json::Value;
+use tracing::{info, instrument, warn};
+use walkdir::WalkDir;
+
+const REPORT_DIR: &str = "wit-coverage";
+/// Runtime verbosity at which a node logs every message it sends or receives
+pub const MESSAGE_LOG_VERBOSITY: u8 = 3;
+
+#[derive(Debug)]
+struct VariantCase {
+    interface: String,
+    variant: String,
+    case: String,
+    hit: bool,
+}
+
+/// `send-request` -> `SendRequest`, as generated by `wit_bindgen`
+fn to_upper_camel_case(kebab: &str) -> String {
+    kebab
+        .split('-')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Return the body of the `{ ... }` block opening at or after `start`
+fn block_body(content: &str, start: usize) -> Option<&str> {
+    let open = start + content[start..].find('{')?;
+    let mut depth = 0;
+    for (i, c) in content[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&content[open + 1..open + i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse every `variant` of every `interface` in the given WIT source
+fn parse_variant_cases(wit: &str) -> Vec<VariantCase> {
+    let comment_re = Regex::new(r"//[^\n]*").unwrap();
+    let wit = comment_re.replace_all(wit, "");
+    let interface_re = Regex::new(r"\binterface\s+([\w\-]+)").unwrap();
+    let variant_re = Regex::new(r"\bvariant\s+([\w\-]+)").unwrap();
+    let case_re = Regex::new(r"^\s*%?([\w\-]+)").unwrap();
+
+    let mut cases = vec![];
+    for interface in interface_re.captures_iter(&wit) {
+        let interface_name = interface[1].to_string();
+        let Some(interface_body) = block_body(&wit, interface.get(0).unwrap().end()) else {
+            continue;
+        };
+        for variant in variant_re.captures_iter(interface_body) {
+            let Some(variant_body) = block_body(interface_body, variant.get(0).unwrap().end())
+            else {
+                continue;
+            };
+            // split on top-level commas only: payloads may be e.g. `tuple<a, b>`
+            let mut depth = 0;
+            let mut current = String::new();
+            let mut raw_cases = vec![];
+            for c in variant_body.chars() {
+                match c {
+                    '(' | '<' => depth += 1,
+                    ')' | '>' => depth -= 1,
+                    ',' if depth == 0 => {
+                        raw_cases.push(std::mem::take(&mut current));
+                        continue;
+                    }
+                    _ => {}
+                }
+                current.push(c);
+            }
+            raw_cases.push(current);
+            for raw_case in raw_cases {
+                if let Some(case) = case_re.captures(&raw_case) {
+                    cases.push(VariantCase {
+                        interface: interface_name.clone(),
+                        variant: variant[1].to_string(),
+                        case: case[1].to_string(),
+                        hit: false,
+                    });
+                }
+            }
+        }
+    }
+    cases
+}
+
+fn read_files_with_extension(dir: &Path, extension: &str) -> Vec<String> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|e| e.file_name() != "target")
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().and_then(|e| e.to_str()) == Some(extension))
+        .filter_map(|e| fs::read_to_string(e.path()).ok())
+        .collect()
+}
+
+/// Collect the variant tags in `value`, a message body serialized by serde
+/// as externally tagged: `{"Case": payload}`, or `"Case"` for a case
+/// without a payload
+fn collect_tags(value: &Value, tags: &mut HashSet<String>) {
+    match value {
+        Value::String(s) if is_tag(s) => {
+            tags.insert(s.clone());
+        }
+        Value::Object(object) => {
+            if object.len() == 1 {
+                let (key, _) = object.iter().next().unwrap();
+                if is_tag(key) {
+                    tags.insert(key.clone());
+                }
+            }
+            object.values().for_each(|v| collect_tags(v, tags));
+        }
+        Value::Array(array) => array.iter().for_each(|v| collect_tags(v, tags)),
+        _ => {}
+    }
+}
+
+/// `wit_bindgen` names cases in `UpperCamelCase`
+fn is_tag(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_uppercase()) && chars.all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Variant tags of the JSON message bodies in the nodes' output, which
+/// logs every message at `MESSAGE_LOG_VERBOSITY`: bodies appear either as
+/// JSON or as a list of bytes
+fn observed_tags(node_log: &str) -> HashSet<String> {
+    let bytes_re = Regex::new(r"\[(\d{1,3}(?:,\s*\d{1,3})*)\]").unwrap();
+    let mut tags = HashSet::new();
+    for line in node_log.lines() {
+        for bytes in bytes_re.captures_iter(line) {
+            let Ok(bytes) = bytes[1]
+                .split(',')
+                .map(|b| b.trim().parse::<u8>())
+                .collect::<Result<Vec<u8>, _>>()
+            else {
+                continue;
+            };
+            if let Ok(value) = serde_json::from_slice::<Value>(&bytes) {
+                collect_tags(&value, &mut tags);
+            }
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find('{') {
+            rest = &rest[start..];
+            let mut values = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+            match values.next() {
+                Some(Ok(value)) => {
+                    collect_tags(&value, &mut tags);
+                    rest = &rest[values.byte_offset()..];
+                }
+                _ => rest = &rest[1..],
+            }
+        }
+    }
+    tags
+}
+
+/// Mark the cases whose tag the nodes logged in a message body, sent or
+/// received; cases with the same name in different variants are not told apart
+fn mark_hit_cases(cases: &mut [VariantCase], tags: &HashSet<String>) {
+    for case in cases.iter_mut() {
+        case.hit = tags.contains(&to_upper_camel_case(&case.case));
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn make_html_report(title: &str, cases: &[VariantCase]) -> String {
+    let hit = cases.iter().filter(|c| c.hit).count();
+    let percent = 100.0 * hit as f64 / cases.len().max(1) as f64;
+    let mut rows = String::new();
+    for case in cases {
+        rows.push_str(&format!(
+            "      <tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            if case.hit { "hit" } else { "miss" },
+            escape_html(&case.interface),
+            escape_html(&case.variant),
+            escape_html(&case.case),
+            if case.hit { "observed" } else { "not observed" },
+        ));
+    }
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+  <head>
+    <meta charset="utf-8">
+    <title>WIT coverage: {title}</title>
+    <style>
+      body {{ font-family: sans-serif; }}
+      table {{ border-collapse: collapse; }}
+      td, th {{ border: 1px solid #ccc; padding: 4px 8px; text-align: left; }}
+      tr.hit {{ background: #dfd; }}
+      tr.miss {{ background: #fdd; }}
+    </style>
+  </head>
+  <body>
+    <h1>WIT coverage: {title}</h1>
+    <p>{hit} of {total} variant cases observed in messages between processes ({percent:.1}%)</p>
+    <table>
+      <tr><th>interface</th><th>variant</th><th>case</th><th>status</th></tr>
+{rows}    </table>
+  </body>
+</html>
+"#,
+        title = escape_html(title),
+        total = cases.len(),
+    )
+}
+
+/// Write an HTML report of which request/response variant cases of the
+/// packages under test were sent or received while the tests ran, per
+/// `node_log`, the nodes' output
+#[instrument(level = "trace", skip_all)]
+pub fn write_wit_coverage_report(
+    test_dir_path: &Path,
+    package_paths: &[PathBuf],
+    test_package_paths: &[PathBuf],
+    node_log: &str,
+) -> Result<()> {
+    let mut cases = vec![];
+    for package_path in package_paths {
+        let api_dir = package_path.join("api");
+        if !api_dir.exists() {
+            continue;
+        }
+        for wit in read_files_with_extension(&api_dir, "wit") {
+            cases.extend(parse_variant_cases(&wit));
+        }
+    }
+    if cases.is_empty() {
+        info!("No WIT variants found in packages under test: skipping WIT coverage report.");
+        return Ok(());
+    }
+
+    let tags = observed_tags(node_log);
+    if tags.is_empty() {
+        warn!("No JSON message bodies found in node output: WIT coverage will be empty.");
+    }
+    mark_hit_cases(&mut cases, &tags);
+
+    let title = test_package_paths
+        .iter()
+        .filter_map(|p| p.file_name().and_then(|f| f.to_str()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let report_dir = test_dir_path.join("target").join(REPORT_DIR);
+    fs::create_dir_all(&report_dir)?;
+    let report_path = report_dir.join(format!(
+        "{}.html",
+        if title.is_empty() { "tests" } else { &title }.replace(", ", "_"),
+    ));
+    fs::write(&report_path, make_html_report(&title, &cases))?;
+
+    let hit = cases.iter().filter(|c| c.hit).count();
+    info!(
+        "WIT coverage: {}/{} variant cases observed; report at {:?}",
+        hit,
+        cases.len(),
+        report_path,
+    );
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use color_eyre::{eyre::eyre, Result, Section};
+use futures_util::{SinkExt, StreamExt};
+use tokio::time::{timeout, Duration};
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, http::HeaderValue, Message};
+use tracing::{debug, info, instrument};
+
+use super::types::{WebSocketConnect, WebSocketStep};
+
+const DEFAULT_WEBSOCKET_TIMEOUT_SECS: u64 = 5;
+
+/// Frames match if equal as text or, if both parse as JSON, equal as JSON
+fn is_expected_frame(expected: &str, received: &str) -> bool {
+    if expected == received {
+        return true;
+    }
+    match (
+        serde_json::from_str::<serde_json::Value>(expected),
+        serde_json::from_str::<serde_json::Value>(received),
+    ) {
+        (Ok(expected), Ok(received)) => expected == received,
+        _ => false,
+    }
+}
+
+/// Connect to `websocket_connect.url` and run its `steps` in order:
+/// send each `send` frame and assert each `expect`ed frame is received next
+#[instrument(level = "trace", skip_all)]
+pub async fn run_websocket_steps(websocket_connect: &WebSocketConnect) -> Result<()> {
+    let WebSocketConnect {
+        ref url,
+        ref headers,
+        ref steps,
+        timeout_secs,
+    } = websocket_connect;
+    let timeout_duration =
+        Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_WEBSOCKET_TIMEOUT_SECS));
+
+    let mut request = url.as_str().into_client_request()?;
+    for (key, value) in headers.iter().flatten() {
+        let key: tokio_tungstenite::tungstenite::http::HeaderName = key.parse()?;
+        request
+            .headers_mut()
+            .insert(key, HeaderValue::from_str(value)?);
+    }
+
+    info!("Connecting to WebSocket {url}...");
+    let (mut ws, _) = timeout(timeout_duration, tokio_tungstenite::connect_async(request))
+        .await
+        .map_err(|_| eyre!("Timed out connecting to WebSocket {url}"))?
+        .map_err(|e| {
+            eyre!("Failed to connect to WebSocket {url}: {e}")
+                .with_suggestion(|| "Is the WebSocket path bound by a process under test?")
+        })?;
+
+    for (i, step) in steps.iter().enumerate() {
+        match step {
+            WebSocketStep::Send { send } => {
+                debug!("ws_assert: send {send}");
+                ws.send(Message::text(send.clone())).await?;
+            }
+            WebSocketStep::Expect { expect } => {
+                let received = loop {
+                    let frame = timeout(timeout_duration, ws.next())
+                        .await
+                        .map_err(|_| eyre!("WebSocket step {i}: timed out waiting for {expect}"))?
+                        .ok_or_else(|| {
+                            eyre!("WebSocket step {i}: connection closed waiting for {expect}")
+                        })??;
+                    match frame {
+                        Message::Text(text) => break text.to_string(),
+                        Message::Binary(bytes) => {
+                            break String::from_utf8_lossy(&bytes).to_string()
+                        }
+                        Message::Close(_) => {
+                            return Err(eyre!(
+                                "WebSocket step {i}: connection closed waiting for {expect}"
+                            ));
+                        }
+                        // ping, pong & raw frames are not assertable
+                        _ => continue,
+                    }
+                };
+                debug!("ws_assert: received {received}");
+                if !is_expected_frame(expect, &received) {
+                    return Err(eyre!(
+                        "WebSocket step {i}: expected frame\n  {expect}\nbut received\n  {received}"
+                    ));
+                }
+            }
+        }
+    }
+
+    let _ = ws.close(None).await;
+    info!("WebSocket {url}: all {} step(s) passed.", steps.len());
+    Ok(())
+}
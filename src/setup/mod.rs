@@ -449,6 +449,18 @@ pub fn check_rust_deps() -> Result<Vec<Dependency>> {
     Ok(missing_deps)
 }
 
+/// Check for `cargo-component`, which processes that configure its WIT
+/// `target` or `dependencies` need to build
+#[instrument(level = "trace", skip_all)]
+pub fn check_cargo_component_deps() -> Result<()> {
+    if !is_command_installed("cargo-component")? {
+        return Err(eyre!(
+            "This package requires cargo-component. Install it with: cargo install cargo-component"
+        ));
+    }
+    Ok(())
+}
+
 // Check for Foundry deps, returning a Vec of not found: can be automatically fetched?
 #[instrument(level = "trace", skip_all)]
 pub fn check_docker_deps() -> Result<Vec<Dependency>> {
@@ -1,19 +1,30 @@
 use std::path::Path;
 
-use color_eyre::{eyre::eyre, Result, Section};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result, Section,
+};
 use fs_err as fs;
 use serde_json::json;
-use tracing::{debug, info, instrument};
+use tokio::time::{sleep, Duration};
+use tracing::{debug, info, instrument, warn};
+use zip::read::ZipArchive;
 
 use kinode_process_lib::kernel_types::{Erc721Metadata, PackageManifestEntry};
 
-use crate::build::{hash_zip_pkg, make_pkg_publisher, make_zip_filename, read_and_update_metadata};
+use crate::build::{
+    hash_zip_pkg, make_pkg_publisher, make_zip_filename, read_and_update_metadata, read_metadata,
+};
 use crate::new::is_kimap_safe;
 use crate::publish::{make_local_file_link_path, make_remote_link};
 use crate::{inject_message, KIT_LOG_PATH_DEFAULT};
 
+/// Attempts `install_zip` makes past the first, e.g. while the node's app-store starts
+pub const DEFAULT_INSTALL_RETRIES: u32 = 3;
+const INSTALL_RETRY_DELAY_SECS: u64 = 2;
+
 #[instrument(level = "trace", skip_all)]
-fn new_package(
+pub fn new_package(
     node: Option<&str>,
     package_name: &str,
     publisher_node: &str,
@@ -37,7 +48,7 @@ fn new_package(
 }
 
 #[instrument(level = "trace", skip_all)]
-fn install(
+pub fn install(
     node: Option<&str>,
     hash_string: &str,
     metadata: &Erc721Metadata,
@@ -203,8 +214,6 @@ pub async fn execute(package_dir: &Path, url: &str) -> Result<()> {
     }
     let pkg_dir = package_dir.join("pkg").canonicalize()?;
     let metadata = read_and_update_metadata(package_dir)?;
-    let package_name = metadata.properties.package_name.as_str();
-    let publisher = metadata.properties.publisher.as_str();
     let pkg_publisher = make_pkg_publisher(&metadata);
     let zip_filename = make_zip_filename(package_dir, &pkg_publisher);
 
@@ -220,6 +229,21 @@ pub async fn execute(package_dir: &Path, url: &str) -> Result<()> {
     info!("{}", pkg_publisher);
     let hash_string = hash_zip_pkg(&zip_filename)?;
 
+    add_and_install(url, &zip_filename, &hash_string, &metadata).await
+}
+
+/// Add the package zip to the node's app-store & install it
+#[instrument(level = "trace", skip_all)]
+async fn add_and_install(
+    url: &str,
+    zip_filename: &Path,
+    hash_string: &str,
+    metadata: &Erc721Metadata,
+) -> Result<()> {
+    let package_name = metadata.properties.package_name.as_str();
+    let publisher = metadata.properties.publisher.as_str();
+    let pkg_publisher = make_pkg_publisher(metadata);
+
     // Create and send new package request
     let new_pkg_request = new_package(
         None,
@@ -249,7 +273,7 @@ pub async fn execute(package_dir: &Path, url: &str) -> Result<()> {
         ));
     }
 
-    let install_request = install(None, &hash_string, &metadata)?;
+    let install_request = install(None, hash_string, metadata)?;
     let response = inject_message::send_request(url, install_request).await?;
     let inject_message::Response { ref body, .. } =
         inject_message::parse_response(response).await?;
@@ -270,3 +294,99 @@ pub async fn execute(package_dir: &Path, url: &str) -> Result<()> {
 
     Ok(())
 }
+
+/// Version given a package installed from a bare zip, without `metadata.json`
+const ZIP_ONLY_VERSION: &str = "0.1.0";
+
+/// Metadata of the package zip at `zip_path`, named `<package>:<publisher>.zip`:
+/// the `metadata.json` of the package dir above `target/` if `kit build` wrote
+/// it there; else a `metadata.json` inside the zip; else minimal metadata
+/// from the zip name
+#[instrument(level = "trace", skip_all)]
+fn read_zip_metadata(zip_path: &Path, hash_string: &str) -> Result<Erc721Metadata> {
+    let pkg_publisher = zip_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    let Some((package_name, publisher)) = pkg_publisher.split_once(':') else {
+        return Err(
+            eyre!("Package zip {zip_path:?} is not named `<package>:<publisher>.zip`")
+                .with_suggestion(|| "Rename it, e.g., to `my-package:my-name.os.zip`."),
+        );
+    };
+    let matches = |metadata: &Erc721Metadata| make_pkg_publisher(metadata) == pkg_publisher;
+
+    if let Some(package_dir) = zip_path
+        .parent()
+        .filter(|dir| dir.file_name().and_then(|n| n.to_str()) == Some("target"))
+        .and_then(|target| target.parent())
+    {
+        if let Ok(metadata) = read_metadata(package_dir) {
+            if matches(&metadata) {
+                return Ok(metadata);
+            }
+        }
+    }
+
+    let mut archive = ZipArchive::new(fs::File::open(zip_path)?)?;
+    if let Ok(file) = archive.by_name("metadata.json") {
+        let metadata: Erc721Metadata = serde_json::from_reader(file)
+            .wrap_err_with(|| format!("Failed to parse metadata.json in {zip_path:?}"))?;
+        if !matches(&metadata) {
+            return Err(eyre!(
+                "Package zip {zip_path:?} is named for {pkg_publisher} but its metadata.json describes {}",
+                make_pkg_publisher(&metadata),
+            ));
+        }
+        return Ok(metadata);
+    }
+
+    debug!("No metadata.json for {zip_path:?}; installing as {pkg_publisher} {ZIP_ONLY_VERSION}");
+    Ok(serde_json::from_value(json!({
+        "name": package_name,
+        "description": "",
+        "image": "",
+        "external_url": "",
+        "animation_url": "",
+        "properties": {
+            "package_name": package_name,
+            "publisher": publisher,
+            "current_version": ZIP_ONLY_VERSION,
+            "mirrors": [],
+            "code_hashes": {ZIP_ONLY_VERSION: hash_string},
+            "wit_version": 1,
+            "dependencies": [],
+        },
+    }))?)
+}
+
+/// Install the package zip at `zip_path`, named `<package>:<publisher>.zip`
+/// as `kit build` writes it to `<package>/target/`, retrying up to `retries`
+/// times; see `read_zip_metadata` for where its metadata comes from
+#[instrument(level = "trace", skip_all)]
+pub async fn install_zip(zip_path: &Path, url: &str, retries: u32) -> Result<()> {
+    let zip_path = zip_path
+        .canonicalize()
+        .wrap_err_with(|| format!("Package zip {zip_path:?} not found"))?;
+    let hash_string = hash_zip_pkg(&zip_path)?;
+    let metadata = read_zip_metadata(&zip_path, &hash_string)?;
+    let pkg_publisher = make_pkg_publisher(&metadata);
+
+    let mut attempt = 0;
+    loop {
+        match add_and_install(url, &zip_path, &hash_string, &metadata).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < retries => {
+                attempt += 1;
+                warn!("Failed to install {pkg_publisher} (attempt {attempt} of {}): {e}; retrying in {INSTALL_RETRY_DELAY_SECS}s...", retries + 1);
+                sleep(Duration::from_secs(INSTALL_RETRY_DELAY_SECS)).await;
+            }
+            Err(e) => {
+                return Err(e.wrap_err(format!(
+                    "Failed to install {pkg_publisher} after {} attempts",
+                    retries + 1,
+                )))
+            }
+        }
+    }
+}
@@ -7,15 +7,34 @@ use tracing::instrument;
 use crate::build::run_command;
 use crate::KIT_CACHE;
 
+/// Which kit to `cargo install`
+pub enum GitRef {
+    Branch(String),
+    Tag(String),
+    Rev(String),
+}
+
+impl GitRef {
+    /// `cargo install --git` args selecting this ref
+    fn to_args(&self) -> [&str; 2] {
+        match self {
+            GitRef::Branch(branch) => ["--branch", branch],
+            GitRef::Tag(tag) => ["--tag", tag],
+            GitRef::Rev(rev) => ["--rev", rev],
+        }
+    }
+}
+
 #[instrument(level = "trace", skip_all)]
-pub fn execute(mut user_args: Vec<String>, branch: &str) -> Result<()> {
+pub fn execute(mut user_args: Vec<String>, git_ref: GitRef) -> Result<()> {
+    let [ref_flag, ref_value] = git_ref.to_args();
     let mut args: Vec<String> = vec![
         "install",
         "--git",
         "https://github.com/kinode-dao/kit",
         "--locked",
-        "--branch",
-        branch,
+        ref_flag,
+        ref_value,
         "--color=always",
     ]
     .iter()
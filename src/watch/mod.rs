@@ -0,0 +1,232 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use color_eyre::{eyre::eyre, Result};
+use tracing::{error, info, instrument};
+use walkdir::WalkDir;
+
+use crate::build::{self, BuildOptions};
+use crate::start_package;
+
+/// Dirs whose contents trigger a rebuild: process & UI source, WIT
+const WATCHED_DIRS: &[&str] = &["src", "wit", "api"];
+/// Build outputs & installed JS packages: never trigger a rebuild & are
+/// not walked, nor are hidden dirs such as `.git/`
+const SKIP_DIRS: &[&str] = &["target", "node_modules", "pkg"];
+/// Each poll walks the package, so poll only as often as a human saves
+const POLL_INTERVAL_MS: u64 = 500;
+
+type Snapshot = BTreeMap<PathBuf, SystemTime>;
+
+fn is_watched(package_dir: &Path, path: &Path) -> bool {
+    path.strip_prefix(package_dir)
+        .unwrap_or(path)
+        .components()
+        .any(|c| WATCHED_DIRS.iter().any(|w| c.as_os_str() == *w))
+}
+
+/// Modification time of every watched file in the package
+fn snapshot(package_dir: &Path) -> Result<Snapshot> {
+    let mut snapshot = BTreeMap::new();
+    for entry in WalkDir::new(package_dir).into_iter().filter_entry(|e| {
+        !(e.depth() > 0
+            && e.file_type().is_dir()
+            && e.file_name()
+                .to_str()
+                .map(|n| SKIP_DIRS.contains(&n) || n.starts_with('.'))
+                .unwrap_or(false))
+    }) {
+        let entry = entry?;
+        if entry.file_type().is_file() && is_watched(package_dir, entry.path()) {
+            snapshot.insert(entry.path().to_path_buf(), entry.metadata()?.modified()?);
+        }
+    }
+    Ok(snapshot)
+}
+
+fn changed_paths<'a>(old: &'a Snapshot, new: &'a Snapshot) -> Vec<&'a Path> {
+    new.iter()
+        .filter(|(path, modified)| old.get(*path) != Some(modified))
+        .map(|(path, _)| path.as_path())
+        .chain(
+            old.keys()
+                .filter(|path| !new.contains_key(*path))
+                .map(|path| path.as_path()),
+        )
+        .collect()
+}
+
+/// Block until a watched file changes & then no further changes
+/// arrive for `debounce`; returns the new snapshot & what changed
+async fn wait_for_changes(
+    package_dir: &Path,
+    last: &Snapshot,
+    debounce: Duration,
+) -> Result<(Snapshot, Vec<PathBuf>)> {
+    let poll_interval = Duration::from_millis(POLL_INTERVAL_MS);
+    let mut current = loop {
+        tokio::time::sleep(poll_interval).await;
+        let current = snapshot(package_dir)?;
+        if current != *last {
+            break current;
+        }
+    };
+    // batch rapid successive saves
+    let mut quiet_since = Instant::now();
+    while quiet_since.elapsed() < debounce {
+        tokio::time::sleep(poll_interval.min(debounce)).await;
+        let next = snapshot(package_dir)?;
+        if next != current {
+            current = next;
+            quiet_since = Instant::now();
+        }
+    }
+    let changed = changed_paths(last, &current)
+        .into_iter()
+        .map(|p| p.to_path_buf())
+        .collect();
+    Ok((current, changed))
+}
+
+async fn build_and_reload(
+    package_dir: &Path,
+    url: &str,
+    no_ui: bool,
+    features: &str,
+    verbose: bool,
+) -> Result<()> {
+    build::execute(
+        package_dir,
+        &BuildOptions {
+            no_ui,
+            features: features.to_string(),
+            url: Some(url.into()),
+            verbose,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let start = Instant::now();
+    start_package::execute(package_dir, url).await?;
+    info!(
+        "Reloaded {:?} on {url} in {}ms.",
+        package_dir,
+        start.elapsed().as_millis(),
+    );
+    Ok(())
+}
+
+/// kit watch: rebuild the package when its source or WIT changes &
+/// reinstall it on the node at `url`; build & install errors are
+/// reported but do not stop watching
+#[instrument(level = "trace", skip_all)]
+pub async fn execute(
+    package_dir: &Path,
+    url: &str,
+    debounce_ms: u64,
+    no_ui: bool,
+    features: &str,
+    verbose: bool,
+) -> Result<()> {
+    if !package_dir.join("pkg").exists() {
+        return Err(eyre!(
+            "Required `pkg/` dir not found within given input dir {:?} (or cwd, if none given). Please re-run targeting a package.",
+            package_dir,
+        ));
+    }
+    let package_dir = package_dir.canonicalize()?;
+    let debounce = Duration::from_millis(debounce_ms);
+
+    let mut last = snapshot(&package_dir)?;
+    if let Err(e) = build_and_reload(&package_dir, url, no_ui, features, verbose).await {
+        error!("{e:?}");
+    }
+    info!(
+        "Watching {:?} for changes in {} dirs...",
+        package_dir,
+        WATCHED_DIRS.join("/, ") + "/",
+    );
+
+    loop {
+        let (current, changed) = wait_for_changes(&package_dir, &last, debounce).await?;
+        last = current;
+        info!(
+            "Changed: {}; rebuilding...",
+            changed
+                .iter()
+                .map(|p| p
+                    .strip_prefix(&package_dir)
+                    .unwrap_or(p)
+                    .display()
+                    .to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        if let Err(e) = build_and_reload(&package_dir, url, no_ui, features, verbose).await {
+            error!("{e:?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fs_err as fs;
+
+    #[test]
+    fn snapshot_skips_build_outputs_and_hidden_dirs() {
+        let package_dir = tempfile::tempdir().unwrap();
+        let package_dir = package_dir.path();
+        for path in [
+            "process/src/lib.rs",
+            "api/process.wit",
+            "process/target/wit/process.wit",
+            "ui/node_modules/dep/src/index.js",
+            "pkg/ui/src/index.js",
+            ".git/src/HEAD",
+            "process/Cargo.toml",
+        ] {
+            let path = package_dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+        let snapshot = snapshot(package_dir).unwrap();
+        let watched: Vec<_> = snapshot
+            .keys()
+            .map(|p| p.strip_prefix(package_dir).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            watched,
+            [
+                PathBuf::from("api/process.wit"),
+                PathBuf::from("process/src/lib.rs")
+            ],
+        );
+    }
+
+    #[test]
+    fn changed_paths_reports_modified_added_and_removed() {
+        let t0 = SystemTime::UNIX_EPOCH;
+        let t1 = t0 + Duration::from_secs(1);
+        let old = Snapshot::from([
+            (PathBuf::from("modified"), t0),
+            (PathBuf::from("removed"), t0),
+            (PathBuf::from("unchanged"), t0),
+        ]);
+        let new = Snapshot::from([
+            (PathBuf::from("added"), t0),
+            (PathBuf::from("modified"), t1),
+            (PathBuf::from("unchanged"), t0),
+        ]);
+        assert_eq!(
+            changed_paths(&old, &new),
+            [
+                Path::new("added"),
+                Path::new("modified"),
+                Path::new("removed")
+            ],
+        );
+    }
+}